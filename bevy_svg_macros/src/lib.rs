@@ -0,0 +1,228 @@
+//! Procedural macros of `bevy_svg`, use them through `bevy_svg` with its `macros` feature.
+
+// rustc
+#![deny(future_incompatible, nonstandard_style)]
+#![warn(missing_docs, rust_2018_idioms, unused)]
+
+use std::path::{Path, PathBuf};
+
+use lyon_tessellation::{
+    math::{point, Point},
+    path::Path as LyonPath,
+    BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex, LineCap, LineJoin,
+    StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+use usvg::NodeExt;
+
+
+/// Tolerance of fills, the same as `bevy_svg` uses at runtime.
+const FILL_TOLERANCE: f32 = 0.001;
+/// Tolerance of strokes, the same as `bevy_svg` uses at runtime.
+const STROKE_TOLERANCE: f32 = 0.01;
+
+/// Parses and tessellates an SVG file at compile time and expands to a `StaticSvg`, whose
+/// vertices and indices are stored in the binary.
+///
+/// The path is relative to the directory of the `Cargo.toml` of the crate that uses the macro.
+/// Only solid fills and strokes are drawn, dashes, gradients, patterns, clip paths, masks and
+/// filters are ignored, and the opacity of groups is applied to each of their paths.
+///
+/// ```ignore
+/// static ICON: StaticSvg = svg!("assets/icon.svg");
+///
+/// let svg = Svg::from_static(&ICON, &mut meshes);
+/// ```
+#[proc_macro]
+pub fn svg(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    let full_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join(path.value());
+    let baked = match bake(&full_path) {
+        Ok(baked) => baked,
+        Err(message) => return syn::Error::new(path.span(), message).to_compile_error().into(),
+    };
+
+    let Baked { size: [width, height], view_box, content_rect, vertices, indices } = baked;
+    let name = full_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    // Including the file makes the compiler rebuild the crate when it changes.
+    let file = full_path.to_string_lossy().into_owned();
+    let positions = vertices.iter().map(|vertex| {
+        let [x, y] = vertex.position;
+        quote!([#x, #y, 0.0])
+    });
+    let colors = vertices.iter().map(|vertex| vertex.color);
+    let expanded = quote! {
+        {
+            const _: &[u8] = include_bytes!(#file);
+            ::bevy_svg::prelude::StaticSvg {
+                name: #name,
+                size: [#width, #height],
+                view_box: [#(#view_box),*],
+                content_rect: [#(#content_rect),*],
+                positions: &[#(#positions),*],
+                colors: &[#(#colors),*],
+                indices: &[#(#indices),*],
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// A vertex with its position, with the y-axis of Bevy, and its color in linear RGBA.
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 2],
+    color: u32,
+}
+
+/// A tessellated SVG file.
+struct Baked {
+    size: [f32; 2],
+    view_box: [f64; 4],
+    /// The area that the view box covers, as x, y, width and height.
+    content_rect: [f32; 4],
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+/// Parses and tessellates the SVG file at `path`.
+fn bake(path: &Path) -> Result<Baked, String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+    let mut options = usvg::Options { resources_dir: path.parent().map(Path::to_owned), ..Default::default() };
+    options.fontdb.load_system_fonts();
+    let tree = usvg::Tree::from_data(&bytes, &options.to_ref())
+        .map_err(|err| format!("could not parse `{}`: {}", path.display(), err))?;
+
+    let svg = tree.svg_node();
+    // The view box is fitted into the size of the SVG, like a browser does.
+    let view_box_transform = usvg::utils::view_box_to_transform(svg.view_box.rect, svg.view_box.aspect, svg.size);
+    let content_rect = svg.view_box.rect.transform(&view_box_transform).unwrap_or(svg.view_box.rect);
+
+    let mut buffers: VertexBuffers<Vertex, u32> = VertexBuffers::new();
+    let mut fill_tess = FillTessellator::new();
+    let mut stroke_tess = StrokeTessellator::new();
+    for node in tree.root().descendants() {
+        let path = match *node.borrow() {
+            usvg::NodeKind::Path(ref path) if path.visibility == usvg::Visibility::Visible => path.clone(),
+            _ => continue,
+        };
+        // Content of `defs` is only drawn where it is referenced.
+        if node.ancestors().any(|ancestor| matches!(*ancestor.borrow(), usvg::NodeKind::Defs)) {
+            continue;
+        }
+        let mut transform = view_box_transform;
+        transform.append(&node.abs_transform());
+        transform.append(&path.transform);
+        let opacity: f64 = node.ancestors()
+            .filter_map(|ancestor| match *ancestor.borrow() {
+                usvg::NodeKind::Group(ref group) => Some(group.opacity.value()),
+                _ => None,
+            })
+            .product();
+        let lyon_path = lyon_path(&path.data, &transform);
+
+        if let Some(fill) = &path.fill {
+            if let usvg::Paint::Color(color) = fill.paint {
+                let color = linear_rgba(color, fill.opacity.value() * opacity);
+                let options = FillOptions::tolerance(FILL_TOLERANCE).with_fill_rule(match fill.rule {
+                    usvg::FillRule::NonZero => FillRule::NonZero,
+                    usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+                });
+                fill_tess.tessellate_path(
+                    &lyon_path,
+                    &options,
+                    &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex<'_>| flipped(vertex.position(), color)),
+                ).map_err(|err| format!("failed to tessellate a fill of `{}`: {:?}", path.id, err))?;
+            }
+        }
+        if let Some(stroke) = &path.stroke {
+            if let usvg::Paint::Color(color) = stroke.paint {
+                let color = linear_rgba(color, stroke.opacity.value() * opacity);
+                // The path is already transformed, so the width is scaled the same way.
+                let (scale_x, scale_y) = transform.get_scale();
+                let options = StrokeOptions::tolerance(STROKE_TOLERANCE)
+                    .with_line_width((stroke.width.value() * (scale_x * scale_y).sqrt()) as f32)
+                    .with_miter_limit(stroke.miterlimit.value() as f32)
+                    .with_line_cap(match stroke.linecap {
+                        usvg::LineCap::Butt => LineCap::Butt,
+                        usvg::LineCap::Round => LineCap::Round,
+                        usvg::LineCap::Square => LineCap::Square,
+                    })
+                    .with_line_join(match stroke.linejoin {
+                        usvg::LineJoin::Round => LineJoin::Round,
+                        usvg::LineJoin::Bevel => LineJoin::Bevel,
+                        _ => LineJoin::Miter,
+                    });
+                stroke_tess.tessellate_path(
+                    &lyon_path,
+                    &options,
+                    &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex<'_, '_>| flipped(vertex.position(), color)),
+                ).map_err(|err| format!("failed to tessellate a stroke of `{}`: {:?}", path.id, err))?;
+            }
+        }
+    }
+
+    Ok(Baked {
+        size: [svg.size.width() as f32, svg.size.height() as f32],
+        view_box: [svg.view_box.rect.x(), svg.view_box.rect.y(), svg.view_box.rect.width(), svg.view_box.rect.height()],
+        content_rect: [content_rect.x() as f32, content_rect.y() as f32, content_rect.width() as f32, content_rect.height() as f32],
+        vertices: buffers.vertices,
+        indices: buffers.indices,
+    })
+}
+
+/// Converts the segments of a `usvg` path into a lyon path, transformed by `transform`.
+fn lyon_path(data: &usvg::PathData, transform: &usvg::Transform) -> LyonPath {
+    let to_point = |x: f64, y: f64| {
+        let (x, y) = transform.apply(x, y);
+        point(x as f32, y as f32)
+    };
+    let mut builder = LyonPath::builder();
+    let mut open = false;
+    for segment in data.iter() {
+        match *segment {
+            usvg::PathSegment::MoveTo { x, y } => {
+                if open {
+                    builder.end(false);
+                }
+                builder.begin(to_point(x, y));
+                open = true;
+            },
+            usvg::PathSegment::LineTo { x, y } => {
+                builder.line_to(to_point(x, y));
+            },
+            usvg::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                builder.cubic_bezier_to(to_point(x1, y1), to_point(x2, y2), to_point(x, y));
+            },
+            usvg::PathSegment::ClosePath => {
+                if open {
+                    builder.end(true);
+                    open = false;
+                }
+            },
+        }
+    }
+    if open {
+        builder.end(false);
+    }
+    builder.build()
+}
+
+/// Creates a vertex with the y-axis of Bevy, which points up instead of down.
+fn flipped(position: Point, color: u32) -> Vertex {
+    Vertex { position: [position.x, -position.y], color }
+}
+
+/// Converts an sRGB color and its opacity into linear RGBA, packed like the vertex colors of
+/// `bevy_svg`.
+fn linear_rgba(color: usvg::Color, opacity: f64) -> u32 {
+    let linear = |channel: u8| {
+        let channel = f32::from(channel) / 255.0;
+        let linear = if channel <= 0.04045 { channel / 12.92 } else { ((channel + 0.055) / 1.055).powf(2.4) };
+        (linear * 255.0) as u8
+    };
+    let alpha = (opacity.clamp(0.0, 1.0) * 255.0) as u8;
+    u32::from_le_bytes([linear(color.red), linear(color.green), linear(color.blue), alpha])
+}
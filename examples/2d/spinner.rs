@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use bevy_svg::prelude::*;
+
+fn main() {
+    App::new()
+        .insert_resource(Msaa { samples: 4 })
+        .insert_resource(WindowDescriptor {
+            title: "spinner".to_string(),
+            width: 400.0,
+            height: 400.0,
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(bevy_svg::prelude::SvgPlugin)
+        .add_startup_system(setup)
+        .add_system(toggle_playback)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let svg = asset_server.load("spinner.svg");
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.spawn_bundle(Svg2dBundle {
+        svg,
+        origin: Origin::Center,
+        ..Default::default()
+    })
+    .insert(SvgAnimationPlayer::new());
+}
+
+/// Pauses and resumes the animations with the space bar.
+fn toggle_playback(keys: Res<Input<KeyCode>>, mut query: Query<&mut SvgAnimationPlayer>) {
+    if keys.just_pressed(KeyCode::Space) {
+        for mut player in query.iter_mut() {
+            if player.paused {
+                player.play();
+            } else {
+                player.pause();
+            }
+        }
+    }
+}
@@ -22,9 +22,5 @@ fn setup(
 ) {
     let svg = asset_server.load("neutron_star.svg");
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-    commands.spawn_bundle(Svg2dBundle {
-        svg,
-        origin: Origin::Center,
-        ..Default::default()
-    });
+    commands.spawn_bundle(Svg2dBundle::builder(svg).origin(Origin::Center).build());
 }
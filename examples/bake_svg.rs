@@ -0,0 +1,50 @@
+//! Bakes SVG files into the `.svgmesh` format, which loads without parsing or tessellating.
+//!
+//! ```sh
+//! cargo run --example bake_svg -- assets/neutron_star.svg assets/neutron_star.svgmesh
+//! ```
+//!
+//! Given a folder, every SVG file in it and its subfolders is baked into a `.svgmesh` file next
+//! to it. The asset loader loads those instead of the SVG files, until the SVG files change.
+//!
+//! ```sh
+//! cargo run --example bake_svg -- assets
+//! ```
+
+use std::path::Path;
+
+use bevy_svg::prelude::*;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let fonts = SvgFontDatabase::new(&SvgFonts::default());
+    match args.as_slice() {
+        [input, output] => bake(&fonts, Path::new(input), Path::new(output)),
+        [folder] if Path::new(folder).is_dir() => bake_folder(&fonts, Path::new(folder)),
+        _ => {
+            eprintln!("Usage: bake_svg <input.svg> <output.svgmesh>");
+            eprintln!("       bake_svg <folder>");
+            std::process::exit(2);
+        },
+    }
+}
+
+fn bake(fonts: &SvgFontDatabase, input: &Path, output: &Path) {
+    let bytes = std::fs::read(input).unwrap_or_else(|err| panic!("Could not read {}: {}", input.display(), err));
+    let baked = Svg::bake(&bytes, fonts).unwrap_or_else(|err| panic!("Could not bake {}: {}", input.display(), err));
+    std::fs::write(output, baked).unwrap_or_else(|err| panic!("Could not write {}: {}", output.display(), err));
+}
+
+fn bake_folder(fonts: &SvgFontDatabase, folder: &Path) {
+    let entries = std::fs::read_dir(folder).unwrap_or_else(|err| panic!("Could not read {}: {}", folder.display(), err));
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_svg = path.extension().map_or(false, |extension| extension == "svg" || extension == "svgz");
+        if path.is_dir() {
+            bake_folder(fonts, &path);
+        } else if is_svg {
+            println!("Baking {}", path.display());
+            bake(fonts, &path, &path.with_extension("svgmesh"));
+        }
+    }
+}
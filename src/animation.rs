@@ -0,0 +1,746 @@
+//! SMIL animations, `<animate>`, `<set>`, `<animateTransform>` and `<animateMotion>` in SVG.
+//!
+//! The animations are read while the file is loaded and stored with the [`Svg`](crate::svg::Svg).
+//! An entity with a [`SvgAnimationPlayer`] evaluates them at its own time and gets its own mesh,
+//! like with the components of [`overrides`](crate::overrides).
+//!
+//! Only properties that don't change the geometry of the shapes can be animated: `opacity`, the
+//! `fill` and `stroke` color and opacity of shapes and the transform of elements.
+
+use std::str::FromStr;
+
+use bevy::{
+    core::Time,
+    ecs::{component::Component, reflect::ReflectComponent, system::{Query, Res}},
+    log::warn,
+    reflect::Reflect,
+    render::color::Color,
+};
+use lyon_geom::{euclid::{default::Transform2D, Angle}, CubicBezierSegment, LineSegment};
+use lyon_svg::path::{iterator::PathIterator, Path, PathEvent};
+use lyon_tessellation::math::{point, Point};
+
+use crate::overrides::Overrides;
+
+
+/// Tolerance that is used to flatten motion paths.
+const FLATTEN_TOLERANCE: f32 = 0.01;
+
+/// Plays the animations of the [`Svg`](crate::svg::Svg) of an entity.
+///
+/// The animations start when the component is added. Removing it shows the SVG without
+/// animations again.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgAnimationPlayer {
+    /// Time in seconds since the animations started, this is the time that they are evaluated at.
+    pub elapsed: f32,
+    /// How fast the time advances, `1.0` is real time.
+    pub speed: f32,
+    /// Whether the time stands still.
+    pub paused: bool,
+}
+
+impl Default for SvgAnimationPlayer {
+    fn default() -> Self {
+        Self { elapsed: 0.0, speed: 1.0, paused: false }
+    }
+}
+
+impl SvgAnimationPlayer {
+    /// Creates a player that starts playing right away, in real time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues playing the animations.
+    pub fn play(&mut self) {
+        self.paused = false;
+    }
+
+    /// Stops the time, the animations keep their current state.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Jumps to `seconds` after the start of the animations.
+    pub fn seek(&mut self, seconds: f32) {
+        self.elapsed = seconds.max(0.0);
+    }
+}
+
+/// Advances the time of all [`SvgAnimationPlayer`]s that are playing.
+pub(crate) fn advance_animation_players(time: Res<Time>, mut query: Query<&mut SvgAnimationPlayer>) {
+    for mut player in query.iter_mut() {
+        if !player.paused && player.speed != 0.0 {
+            player.elapsed = (player.elapsed + player.speed * time.delta_seconds()).max(0.0);
+        }
+    }
+}
+
+/// A single animation of an element.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Animation {
+    /// ID of the animated element.
+    pub(crate) target: String,
+    pub(crate) property: Property,
+    pub(crate) timing: Timing,
+    /// The values that are interpolated, each one a list of numbers.
+    pub(crate) values: Vec<Vec<f32>>,
+    /// When the values are reached, as fraction of the simple duration.
+    pub(crate) key_times: Vec<f32>,
+    /// Easing of each interval between two values, only used with `spline` interpolation.
+    pub(crate) easings: Vec<Easing>,
+    pub(crate) calc_mode: CalcMode,
+    /// Whether the animation is added to the underlying value, `additive="sum"`.
+    pub(crate) additive: bool,
+    /// The `transform` attribute of the animated element.
+    pub(crate) element_transform: Transform2D<f32>,
+}
+
+/// The property of the element that an [`Animation`] changes.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Property {
+    Opacity,
+    /// The `fill-opacity` of a shape.
+    FillOpacity,
+    /// The `stroke-opacity` of a shape.
+    StrokeOpacity,
+    /// The fill or stroke color of a shape, which is drawn as if it came from the custom property
+    /// with this name.
+    Color(String),
+    /// A list of transform functions, the values hold the parameters of all of them, see
+    /// [`TransformKind::parameters`].
+    Transform(Vec<TransformKind>),
+    /// Moves the element along a path, each subpath is a polyline.
+    Motion { path: Vec<Vec<Point>>, rotate: MotionRotation },
+}
+
+/// A transform function, like the type of an `animateTransform`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum TransformKind {
+    Translate,
+    Scale,
+    Rotate,
+    SkewX,
+    SkewY,
+    Matrix,
+}
+
+impl TransformKind {
+    /// Returns the parameters of the function that don't change anything:
+    /// `translate(tx ty)`, `scale(sx sy)`, `rotate(angle cx cy)`, `skewX(angle)`, `skewY(angle)`
+    /// and `matrix(a b c d e f)`.
+    pub(crate) fn identity(self) -> &'static [f32] {
+        match self {
+            Self::Translate => &[0.0, 0.0],
+            Self::Scale => &[1.0, 1.0],
+            Self::Rotate => &[0.0, 0.0, 0.0],
+            Self::SkewX | Self::SkewY => &[0.0],
+            Self::Matrix => &[1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        }
+    }
+
+    /// Returns the number of parameters of the function.
+    pub(crate) fn parameters(self) -> usize {
+        self.identity().len()
+    }
+}
+
+/// How the progress between two values is eased.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Easing {
+    Linear,
+    /// A curve from `(0, 0)` to `(1, 1)`, like `keySplines` or `cubic-bezier()` in CSS.
+    CubicBezier(CubicBezierSegment<f32>),
+    /// Jumps between a number of steps, `steps()` in CSS.
+    Steps { count: u32, position: StepPosition },
+}
+
+/// Where the jumps of [`Easing::Steps`] happen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum StepPosition {
+    Start,
+    End,
+    None,
+    Both,
+}
+
+impl Easing {
+    /// Returns the eased progress at `t`, from `0.0` to `1.0`.
+    pub(crate) fn apply(&self, t: f32) -> f32 {
+        match *self {
+            Self::Linear => t,
+            Self::CubicBezier(ref spline) => ease(spline, t),
+            Self::Steps { count, position } => {
+                let count = count.max(1) as f32;
+                let mut step = (t * count).floor();
+                if matches!(position, StepPosition::Start | StepPosition::Both) {
+                    step += 1.0;
+                }
+                let jumps = match position {
+                    StepPosition::Start | StepPosition::End => count,
+                    StepPosition::None => (count - 1.0).max(1.0),
+                    StepPosition::Both => count + 1.0,
+                };
+                step.clamp(0.0, jumps) / jumps
+            },
+        }
+    }
+}
+
+/// The order in which the repetitions of an animation play, `animation-direction` in CSS.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Direction {
+    Normal,
+    Reverse,
+    /// Every second repetition plays backwards.
+    Alternate,
+    /// Every first repetition plays backwards.
+    AlternateReverse,
+}
+
+/// How an element is turned while it moves along a motion path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum MotionRotation {
+    /// Fixed angle in degrees.
+    Angle(f32),
+    /// Follows the direction of the path.
+    Auto,
+    /// Follows the opposite direction of the path.
+    AutoReverse,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum CalcMode {
+    Discrete,
+    Linear,
+    Paced,
+    Spline,
+}
+
+/// When an [`Animation`] is active, all times are in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Timing {
+    pub(crate) begin: f32,
+    /// Duration of a single repetition, infinite if it is `indefinite`.
+    pub(crate) duration: f32,
+    /// Duration of all repetitions together, infinite if the animation repeats forever.
+    pub(crate) active_duration: f32,
+    /// Whether the last value is kept after the animation ended, `fill="freeze"`.
+    pub(crate) freeze: bool,
+    /// Whether the first value is shown before the animation begins, only used by CSS.
+    pub(crate) fill_backwards: bool,
+    pub(crate) direction: Direction,
+}
+
+impl Timing {
+    /// Returns how far the current repetition of the animation is at `time`, from `0.0` to `1.0`.
+    /// `None` if the animation isn't active and doesn't keep its first or last value.
+    fn progress(&self, time: f32) -> Option<f32> {
+        let local = time - self.begin;
+        if local < 0.0 {
+            return self.fill_backwards.then(|| self.directed(0.0, 0));
+        }
+        if !self.duration.is_finite() {
+            return Some(self.directed(0.0, 0));
+        }
+        if local >= self.active_duration {
+            if !self.freeze {
+                return None;
+            }
+            // Frozen at the end of the last, possibly partial, repetition.
+            let repetitions = self.active_duration / self.duration;
+            return Some(match self.active_duration % self.duration {
+                end if end == 0.0 => self.directed(1.0, (repetitions as u32).saturating_sub(1)),
+                end => self.directed(end / self.duration, repetitions as u32),
+            });
+        }
+        Some(self.directed((local % self.duration) / self.duration, (local / self.duration) as u32))
+    }
+
+    /// Turns the `progress` of the given repetition around, if it plays backwards.
+    fn directed(&self, progress: f32, repetition: u32) -> f32 {
+        let backwards = match self.direction {
+            Direction::Normal => false,
+            Direction::Reverse => true,
+            Direction::Alternate => repetition % 2 == 1,
+            Direction::AlternateReverse => repetition % 2 == 0,
+        };
+        if backwards { 1.0 - progress } else { progress }
+    }
+}
+
+/// The animated transform of an element.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct AnimatedTransform {
+    /// The `transform` attribute of the element.
+    pub(crate) own: Transform2D<f32>,
+    /// Replaces the transform of the element, if an animation doesn't add to it.
+    pub(crate) base: Option<Transform2D<f32>>,
+    /// Applied before `base` or the transform of the element.
+    pub(crate) added: Transform2D<f32>,
+    /// Motion along a path, applied after the transform of the element.
+    pub(crate) motion: Transform2D<f32>,
+}
+
+impl AnimatedTransform {
+    pub(crate) fn new(own: Transform2D<f32>) -> Self {
+        Self { own, base: None, added: Transform2D::identity(), motion: Transform2D::identity() }
+    }
+
+    /// Returns the transform that is applied before the `transform` attribute of the element, to
+    /// turn it into the animated one.
+    ///
+    /// `usvg` merges the transforms of removed groups into their children, so the transform of
+    /// the element can't be replaced directly.
+    pub(crate) fn correction(&self) -> Option<Transform2D<f32>> {
+        let animated = self.added.then(self.base.as_ref().unwrap_or(&self.own)).then(&self.motion);
+        Some(animated.then(&self.own.inverse()?))
+    }
+}
+
+/// Evaluates `animations` at `time` and stores the animated values in `overrides`.
+/// Later animations of the same property take precedence.
+pub(crate) fn apply(animations: &[Animation], time: f32, overrides: &mut Overrides) {
+    for animation in animations {
+        let progress = match animation.timing.progress(time) {
+            Some(progress) => progress,
+            None => continue,
+        };
+        match animation.property {
+            Property::Opacity | Property::FillOpacity | Property::StrokeOpacity => {
+                let opacities = match animation.property {
+                    Property::FillOpacity => &mut overrides.fill_opacities,
+                    Property::StrokeOpacity => &mut overrides.stroke_opacities,
+                    _ => &mut overrides.opacities,
+                };
+                if let Some(value) = animation.value_at(progress) {
+                    let opacity = value.first().copied().unwrap_or(1.0).clamp(0.0, 1.0);
+                    let opacity = match (animation.additive, opacities.get(&animation.target)) {
+                        (true, Some(previous)) => (previous + opacity).clamp(0.0, 1.0),
+                        _ => opacity,
+                    };
+                    opacities.insert(animation.target.clone(), opacity);
+                }
+            },
+            Property::Color(ref variable) => {
+                if let Some(&[r, g, b]) = animation.value_at(progress).as_deref() {
+                    overrides.css_variables.insert(variable.clone(), Color::rgb(r, g, b));
+                }
+            },
+            Property::Transform(ref kinds) => {
+                if let Some(value) = animation.value_at(progress) {
+                    let transform = transform(kinds, &value);
+                    let animated = overrides.transforms.entry(animation.target.clone())
+                        .or_insert_with(|| AnimatedTransform::new(animation.element_transform));
+                    if animation.additive {
+                        animated.added = transform.then(&animated.added);
+                    } else {
+                        animated.base = Some(transform);
+                        animated.added = Transform2D::identity();
+                    }
+                }
+            },
+            Property::Motion { ref path, rotate } => {
+                if let Some(motion) = motion_at(path, rotate, progress) {
+                    overrides.transforms.entry(animation.target.clone())
+                        .or_insert_with(|| AnimatedTransform::new(animation.element_transform))
+                        .motion = motion;
+                }
+            },
+        }
+    }
+}
+
+impl Animation {
+    /// Interpolates the values at `progress` into the simple duration.
+    fn value_at(&self, progress: f32) -> Option<Vec<f32>> {
+        let values = &self.values;
+        match values.len() {
+            0 => return None,
+            1 => return Some(values[0].clone()),
+            _ => {},
+        }
+
+        if self.calc_mode == CalcMode::Discrete {
+            let index = match self.key_times.len() == values.len() {
+                true => self.key_times.iter().rposition(|&key_time| key_time <= progress).unwrap_or(0),
+                false => ((progress * values.len() as f32) as usize).min(values.len() - 1),
+            };
+            return Some(values[index].clone());
+        }
+
+        let key_times = match self.key_times.len() == values.len() {
+            true => self.key_times.clone(),
+            false => (0..values.len()).map(|i| i as f32 / (values.len() - 1) as f32).collect(),
+        };
+        let interval = key_times.windows(2)
+            .position(|window| progress < window[1])
+            .unwrap_or(values.len() - 2);
+        let (start, end) = (key_times[interval], key_times[interval + 1]);
+        let mut t = if end > start { ((progress - start) / (end - start)).clamp(0.0, 1.0) } else { 1.0 };
+        if self.calc_mode == CalcMode::Spline {
+            if let Some(easing) = self.easings.get(interval) {
+                t = easing.apply(t);
+            }
+        }
+
+        let (from, to) = (&values[interval], &values[interval + 1]);
+        Some(from.iter().zip(to).map(|(from, to)| from + (to - from) * t).collect())
+    }
+}
+
+/// Returns the progress of the easing curve `spline`, which goes from `(0, 0)` to `(1, 1)`,
+/// at `t` on the x axis.
+fn ease(spline: &CubicBezierSegment<f32>, t: f32) -> f32 {
+    // The x coordinate grows monotonically, so bisection always finds it.
+    let (mut low, mut high) = (0.0_f32, 1.0_f32);
+    for _ in 0..32 {
+        let middle = (low + high) * 0.5;
+        if spline.x(middle) < t {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    spline.y((low + high) * 0.5)
+}
+
+/// Builds the transform of a list of transform functions from their animated `value`. Like in
+/// the `transform` attribute, the last function is applied first.
+pub(crate) fn transform(kinds: &[TransformKind], value: &[f32]) -> Transform2D<f32> {
+    let mut transform = Transform2D::identity();
+    let mut parameters = value;
+    for &kind in kinds {
+        let (own, rest) = parameters.split_at(kind.parameters().min(parameters.len()));
+        let at = |i: usize| own.get(i).copied().unwrap_or(kind.identity()[i]);
+        let function = match kind {
+            TransformKind::Translate => Transform2D::translation(at(0), at(1)),
+            TransformKind::Scale => Transform2D::scale(at(0), at(1)),
+            TransformKind::Rotate => Transform2D::translation(-at(1), -at(2))
+                .then_rotate(Angle::degrees(at(0)))
+                .then_translate((at(1), at(2)).into()),
+            TransformKind::SkewX => Transform2D::new(1.0, 0.0, at(0).to_radians().tan(), 1.0, 0.0, 0.0),
+            TransformKind::SkewY => Transform2D::new(1.0, at(0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+            TransformKind::Matrix => Transform2D::new(at(0), at(1), at(2), at(3), at(4), at(5)),
+        };
+        transform = function.then(&transform);
+        parameters = rest;
+    }
+    transform
+}
+
+/// Returns the transform that moves an element to `progress` along the length of `path`.
+fn motion_at(path: &[Vec<Point>], rotate: MotionRotation, progress: f32) -> Option<Transform2D<f32>> {
+    let segments: Vec<LineSegment<f32>> = path.iter()
+        .flat_map(|points| points.windows(2).map(|window| LineSegment { from: window[0], to: window[1] }))
+        .collect();
+    let total: f32 = segments.iter().map(LineSegment::length).sum();
+    let (position, direction) = match segments.first() {
+        Some(_) if total > 0.0 => {
+            let mut remaining = progress.clamp(0.0, 1.0) * total;
+            let mut found = *segments.last()?;
+            let mut t = 1.0;
+            for segment in &segments {
+                let length = segment.length();
+                if remaining <= length && length > 0.0 {
+                    found = *segment;
+                    t = remaining / length;
+                    break;
+                }
+                remaining -= length;
+            }
+            (found.sample(t), found.to_vector())
+        },
+        // A path without length keeps the element at its start.
+        _ => (*path.first()?.first()?, lyon_tessellation::math::vector(1.0, 0.0)),
+    };
+
+    let angle = match rotate {
+        MotionRotation::Angle(degrees) => Angle::degrees(degrees),
+        MotionRotation::Auto => direction.angle_from_x_axis(),
+        MotionRotation::AutoReverse => direction.angle_from_x_axis() + Angle::pi(),
+    };
+    Some(Transform2D::rotation(angle).then_translate(position.to_vector()))
+}
+
+/// Reads an animation element of the raw document. `element` is the animated element, `target`
+/// its ID and `color_variable` the name under which an animated color of the element is drawn,
+/// if it is a shape.
+///
+/// Returns `None` for animations that can't be played, after a warning.
+pub(crate) fn parse(
+    node: &roxmltree::Node<'_, '_>,
+    element: &roxmltree::Node<'_, '_>,
+    target: String,
+    color_variable: impl Fn(&str) -> Option<String>,
+) -> Option<Animation> {
+    let attribute_name = node.attribute("attributeName").unwrap_or_default();
+    let (property, calc_mode) = match node.tag_name().name() {
+        "animateTransform" => {
+            let kind = match node.attribute("type").unwrap_or("translate") {
+                "translate" => TransformKind::Translate,
+                "scale" => TransformKind::Scale,
+                "rotate" => TransformKind::Rotate,
+                "skewX" => TransformKind::SkewX,
+                "skewY" => TransformKind::SkewY,
+                kind => {
+                    warn!("Unknown transform type `{}` of an `animateTransform`, skipping it.", kind);
+                    return None;
+                },
+            };
+            (Property::Transform(vec![kind]), CalcMode::Linear)
+        },
+        "animateMotion" => {
+            let rotate = match node.attribute("rotate").map(str::trim) {
+                Some("auto") => MotionRotation::Auto,
+                Some("auto-reverse") => MotionRotation::AutoReverse,
+                angle => MotionRotation::Angle(angle.and_then(|angle| angle.parse().ok()).unwrap_or(0.0)),
+            };
+            (Property::Motion { path: motion_path(node)?, rotate }, CalcMode::Paced)
+        },
+        "animate" | "set" => {
+            let property = match attribute_name {
+                "opacity" => Property::Opacity,
+                "fill" | "stroke" | "fill-opacity" | "stroke-opacity" => match color_variable(attribute_name) {
+                    Some(_) if attribute_name == "fill-opacity" => Property::FillOpacity,
+                    Some(_) if attribute_name == "stroke-opacity" => Property::StrokeOpacity,
+                    Some(variable) => Property::Color(variable),
+                    None => {
+                        warn!("Only the `{}` of shapes can be animated, skipping the animation of `{}`.", attribute_name, target);
+                        return None;
+                    },
+                },
+                name => {
+                    warn!("Animating `{}` is not supported, skipping the animation of `{}`.", name, target);
+                    return None;
+                },
+            };
+            (property, if node.has_tag_name("set") { CalcMode::Discrete } else { CalcMode::Linear })
+        },
+        _ => return None,
+    };
+
+    let calc_mode = match node.attribute("calcMode") {
+        _ if node.has_tag_name("set") => CalcMode::Discrete,
+        Some("discrete") => CalcMode::Discrete,
+        Some("linear") => CalcMode::Linear,
+        Some("paced") => CalcMode::Paced,
+        Some("spline") => CalcMode::Spline,
+        _ => calc_mode,
+    };
+
+    let parse_value = |value: &str| -> Option<Vec<f32>> {
+        match property {
+            Property::Color(_) => color(value),
+            // Missing parameters are filled in, so all values can be interpolated with each other.
+            Property::Transform(ref kinds) => {
+                let mut value = numbers(value)?;
+                let kind = kinds[0];
+                if kind == TransformKind::Scale && value.len() == 1 {
+                    value.push(value[0]);
+                }
+                value.extend(kind.identity().iter().skip(value.len()));
+                value.truncate(kind.parameters());
+                Some(value)
+            },
+            _ => numbers(value),
+        }
+    };
+    let values = if let Property::Motion { .. } = property {
+        Vec::new()
+    } else if node.has_tag_name("set") {
+        vec![parse_value(node.attribute("to")?)?]
+    } else if let Some(values) = node.attribute("values") {
+        values.split(';').filter(|value| !value.trim().is_empty()).map(parse_value).collect::<Option<_>>()?
+    } else {
+        let from = node.attribute("from").and_then(parse_value);
+        let to = node.attribute("to").and_then(parse_value);
+        let by = node.attribute("by").and_then(parse_value);
+        match (from, to, by) {
+            (Some(from), Some(to), _) => vec![from, to],
+            (Some(from), None, Some(by)) => {
+                let to = from.iter().zip(&by).map(|(from, by)| from + by).collect();
+                vec![from, to]
+            },
+            // Without a start value, the animation starts from the value of the element.
+            // That value isn't known here, so the animation jumps right to the end.
+            (None, Some(to), _) => vec![to],
+            _ => {
+                warn!("The animation of `{}` has no values, skipping it.", target);
+                return None;
+            },
+        }
+    };
+
+    let key_times = node.attribute("keyTimes").map(|key_times| {
+        key_times.split(';').filter_map(|key_time| key_time.trim().parse().ok()).collect()
+    }).unwrap_or_default();
+    let easings = node.attribute("keySplines").map(|key_splines| {
+        key_splines.split(';')
+            .filter_map(|spline| match numbers(spline)?[..] {
+                [x1, y1, x2, y2] => Some(cubic_bezier(x1, y1, x2, y2)),
+                _ => None,
+            })
+            .collect()
+    }).unwrap_or_default();
+
+    Some(Animation {
+        timing: timing(node, &target)?,
+        target,
+        property,
+        values,
+        key_times,
+        easings,
+        calc_mode,
+        additive: node.attribute("additive") == Some("sum"),
+        element_transform: element_transform(element),
+    })
+}
+
+/// Returns the `transform` attribute of `element`.
+pub(crate) fn element_transform(element: &roxmltree::Node<'_, '_>) -> Transform2D<f32> {
+    element.attribute("transform")
+        .and_then(|transform| lyon_svg::parser::Transform::from_str(transform).ok())
+        .map_or_else(Transform2D::identity, |t| Transform2D::new(t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32))
+}
+
+/// Parses a color into its red, green and blue channels.
+pub(crate) fn color(value: &str) -> Option<Vec<f32>> {
+    let color = lyon_svg::parser::Color::from_str(value.trim()).ok()?;
+    Some([color.red, color.green, color.blue].iter().map(|&channel| f32::from(channel) / 255.0).collect())
+}
+
+/// Returns an easing curve from `(0, 0)` to `(1, 1)` with the given control points.
+pub(crate) fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32) -> Easing {
+    Easing::CubicBezier(CubicBezierSegment {
+        from: point(0.0, 0.0),
+        ctrl1: point(x1, y1),
+        ctrl2: point(x2, y2),
+        to: point(1.0, 1.0),
+    })
+}
+
+/// Reads when the animation `node` is active.
+fn timing(node: &roxmltree::Node<'_, '_>, target: &str) -> Option<Timing> {
+    // Only the first begin time is used, and there is no user interaction.
+    let begin = match node.attribute("begin").and_then(|begin| begin.split(';').next()) {
+        Some(begin) => match clock_value(begin) {
+            Some(begin) => begin,
+            None => {
+                warn!("The animation of `{}` begins with `{}`, which is not supported, skipping it.", target, begin.trim());
+                return None;
+            },
+        },
+        None => 0.0,
+    };
+    let duration = node.attribute("dur").and_then(clock_value).filter(|duration| *duration > 0.0).unwrap_or(f32::INFINITY);
+    let repeat_count = match node.attribute("repeatCount").map(str::trim) {
+        Some("indefinite") => f32::INFINITY,
+        Some(count) => count.parse().ok().filter(|count: &f32| *count > 0.0).unwrap_or(1.0),
+        None => 1.0,
+    };
+    let repeat_duration = match node.attribute("repeatDur").map(str::trim) {
+        Some("indefinite") => Some(f32::INFINITY),
+        Some(duration) => clock_value(duration),
+        None => None,
+    };
+    let active_duration = match repeat_duration {
+        Some(repeat_duration) if node.attribute("repeatCount").is_some() => (duration * repeat_count).min(repeat_duration),
+        Some(repeat_duration) => repeat_duration,
+        None => duration * repeat_count,
+    };
+
+    Some(Timing {
+        begin,
+        duration,
+        active_duration,
+        freeze: node.attribute("fill") == Some("freeze"),
+        fill_backwards: false,
+        direction: Direction::Normal,
+    })
+}
+
+/// Parses a clock value, like `2s`, `500ms` or `00:01.5`, into seconds.
+fn clock_value(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let units = [("ms", 0.001), ("min", 60.0), ("h", 3600.0), ("s", 1.0)];
+    if let Some((number, factor)) = units.iter().find_map(|(unit, factor)| Some((value.strip_suffix(unit)?, factor))) {
+        return number.trim().parse::<f32>().ok().map(|number| number * factor);
+    }
+    // Full or partial clock values, the seconds can have a fraction.
+    value.split(':')
+        .try_fold(0.0, |seconds, part| part.parse::<f32>().ok().map(|part| seconds * 60.0 + part))
+}
+
+/// Parses a list of numbers, separated by whitespace or commas.
+pub(crate) fn numbers(value: &str) -> Option<Vec<f32>> {
+    value.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse().ok())
+        .collect()
+}
+
+/// Reads the motion path of an `animateMotion`, from an `mpath` child, the `path` attribute or
+/// the points in `values`, `from` and `to`.
+fn motion_path(node: &roxmltree::Node<'_, '_>) -> Option<Vec<Vec<Point>>> {
+    let mpath = node.children()
+        .find(|child| child.has_tag_name("mpath"))
+        .and_then(|mpath| href(&mpath))
+        .and_then(|id| node.document().descendants().find(|other| other.attribute("id") == Some(id)))
+        .and_then(|path| path.attribute("d"));
+    let data = match (mpath, node.attribute("path")) {
+        (Some(data), _) | (None, Some(data)) => data.to_owned(),
+        (None, None) => {
+            let points: Vec<&str> = match node.attribute("values") {
+                Some(values) => values.split(';').filter(|value| !value.trim().is_empty()).collect(),
+                None => node.attribute("from").into_iter().chain(node.attribute("to")).collect(),
+            };
+            if points.is_empty() {
+                warn!("`animateMotion` without a path, skipping it.");
+                return None;
+            }
+            format!("M {}", points.join(" L "))
+        },
+    };
+
+    let path = match lyon_svg::path_utils::build_path(Path::svg_builder(), &data) {
+        Ok(path) => path,
+        Err(_) => {
+            warn!("Could not parse the motion path `{}`, skipping it.", data);
+            return None;
+        },
+    };
+    let mut polylines: Vec<Vec<Point>> = Vec::new();
+    for event in path.iter().flattened(FLATTEN_TOLERANCE) {
+        match event {
+            PathEvent::Begin { at } => polylines.push(vec![at]),
+            PathEvent::Line { to, .. } => polylines.last_mut()?.push(to),
+            PathEvent::End { first, close: true, .. } => polylines.last_mut()?.push(first),
+            _ => {},
+        }
+    }
+    Some(polylines)
+}
+
+/// Returns the ID that the `href` or `xlink:href` of `node` refers to.
+pub(crate) fn href<'a>(node: &roxmltree::Node<'a, '_>) -> Option<&'a str> {
+    node.attribute(("http://www.w3.org/1999/xlink", "href"))
+        .or_else(|| node.attribute("href"))
+        .and_then(|href| href.trim().strip_prefix('#'))
+}
+
+/// Returns true for the elements that [`parse`] reads.
+pub(crate) fn is_animation(node: &roxmltree::Node<'_, '_>) -> bool {
+    matches!(node.tag_name().name(), "animate" | "set" | "animateTransform" | "animateMotion")
+}
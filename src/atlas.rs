@@ -0,0 +1,191 @@
+//! Bakes many small SVG icons into a single texture atlas, so they are drawn as batched sprites
+//! instead of one mesh each.
+
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{Assets, Handle},
+    math::Vec2,
+    render::{
+        mesh::Mesh,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::Image,
+    },
+    sprite::{Rect, TextureAtlas},
+};
+
+use crate::{icons::SvgIconRegistry, svg::Svg};
+
+
+/// Collects SVG icons with the size in pixels they are rasterized at, and bakes them into a
+/// [`TextureAtlas`] with [`SvgAtlasBuilder::build`]. The icons are packed in rows, separated by
+/// transparent padding so that neighbours don't bleed into each other when the atlas is filtered.
+///
+/// ```ignore
+/// fn bake_icons(
+///     mut atlas: Local<Option<SvgIconAtlas>>,
+///     icons: Res<SvgIconRegistry>,
+///     svgs: Res<Assets<Svg>>,
+///     meshes: Res<Assets<Mesh>>,
+///     mut images: ResMut<Assets<Image>>,
+///     mut atlases: ResMut<Assets<TextureAtlas>>,
+/// ) {
+///     if atlas.is_none() {
+///         // `None` until all icons are loaded.
+///         *atlas = SvgAtlasBuilder::new()
+///             .registry(&icons, 32, 32)
+///             .build(&svgs, &meshes, &mut images, &mut atlases);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SvgAtlasBuilder {
+    icons: Vec<(String, Handle<Svg>, u32, u32)>,
+    padding: u32,
+}
+
+impl Default for SvgAtlasBuilder {
+    fn default() -> Self {
+        Self { icons: Vec::new(), padding: 1 }
+    }
+}
+
+impl SvgAtlasBuilder {
+    /// Creates a builder without icons and with one pixel of padding.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `svg` rasterized at `width` x `height` pixels under `name`. The same SVG can be added
+    /// at several sizes under different names, like `settings` and `settings@2x`.
+    #[must_use]
+    pub fn icon(mut self, name: impl Into<String>, svg: Handle<Svg>, width: u32, height: u32) -> Self {
+        self.icons.push((name.into(), svg, width, height));
+        self
+    }
+
+    /// Adds all icons of `registry` under their names, rasterized at `width` x `height` pixels.
+    #[must_use]
+    pub fn registry(mut self, registry: &SvgIconRegistry, width: u32, height: u32) -> Self {
+        let mut icons: Vec<_> = registry.iter().collect();
+        // The order of the registry changes from run to run, the atlas shouldn't.
+        icons.sort_unstable_by_key(|(name, _)| *name);
+        for (name, svg) in icons {
+            self.icons.push((name.to_owned(), svg.clone(), width, height));
+        }
+        self
+    }
+
+    /// Sets the transparent space in pixels around every icon.
+    #[must_use]
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Rasterizes the icons, packs them into one image that is added to `images` and adds a
+    /// [`TextureAtlas`] with their regions to `atlases`.
+    ///
+    /// Returns `None` while an icon or its mesh isn't loaded yet. Icons of zero size are left out.
+    pub fn build(
+        &self,
+        svgs: &Assets<Svg>,
+        meshes: &Assets<Mesh>,
+        images: &mut Assets<Image>,
+        atlases: &mut Assets<TextureAtlas>,
+    ) -> Option<SvgIconAtlas> {
+        let mut rasterized = Vec::with_capacity(self.icons.len());
+        for (name, handle, width, height) in &self.icons {
+            let svg = svgs.get(handle)?;
+            meshes.get(&svg.mesh)?;
+            if let Some(image) = svg.rasterize(*width, *height, meshes) {
+                rasterized.push((name, image));
+            }
+        }
+
+        let sizes: Vec<_> = rasterized.iter()
+            .map(|(_, image)| (image.texture_descriptor.size.width, image.texture_descriptor.size.height))
+            .collect();
+        let (positions, atlas_width, atlas_height) = pack(&sizes, self.padding);
+
+        let mut data = vec![0; atlas_width as usize * atlas_height as usize * 4];
+        let row_length = atlas_width as usize * 4;
+        for ((_, image), &(x, y)) in rasterized.iter().zip(&positions) {
+            let width = image.texture_descriptor.size.width as usize * 4;
+            for (row, src) in image.data.chunks_exact(width).enumerate() {
+                let start = (y as usize + row) * row_length + x as usize * 4;
+                data[start..start + width].copy_from_slice(src);
+            }
+        }
+        let image = images.add(Image::new(
+            Extent3d { width: atlas_width, height: atlas_height, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        ));
+
+        let mut atlas = TextureAtlas::new_empty(image, Vec2::new(atlas_width as f32, atlas_height as f32));
+        let mut indices = HashMap::with_capacity(rasterized.len());
+        for ((name, _), (&(x, y), &(width, height))) in rasterized.iter().zip(positions.iter().zip(&sizes)) {
+            let min = Vec2::new(x as f32, y as f32);
+            let index = atlas.add_texture(Rect { min, max: min + Vec2::new(width as f32, height as f32) });
+            indices.insert((*name).clone(), index);
+        }
+
+        Some(SvgIconAtlas { atlas: atlases.add(atlas), indices })
+    }
+}
+
+/// Icons that were baked into a [`TextureAtlas`] by a [`SvgAtlasBuilder`], drawn with a
+/// `SpriteSheetBundle` whose `TextureAtlasSprite` has the index of the icon.
+#[derive(Clone, Debug)]
+pub struct SvgIconAtlas {
+    atlas: Handle<TextureAtlas>,
+    indices: HashMap<String, usize>,
+}
+
+impl SvgIconAtlas {
+    /// Returns the texture atlas that contains the icons.
+    pub fn atlas(&self) -> &Handle<TextureAtlas> {
+        &self.atlas
+    }
+
+    /// Returns the index of the icon with the name `name` in the texture atlas.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+
+    /// Iterates over the names of all icons and their indices in the texture atlas.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.indices.iter().map(|(name, index)| (name.as_str(), *index))
+    }
+}
+
+/// Places rectangles of `sizes` in rows, the tallest first, with `padding` around every one.
+/// Returns the top left corners of the rectangles in the order of `sizes`, and the size of the
+/// image they fit in, which is about square.
+pub(crate) fn pack(sizes: &[(u32, u32)], padding: u32) -> (Vec<(u32, u32)>, u32, u32) {
+    let padded = |size: u32| size + padding * 2;
+    let area: u64 = sizes.iter().map(|&(width, height)| u64::from(padded(width)) * u64::from(padded(height))).sum();
+    let widest = sizes.iter().map(|&(width, _)| padded(width)).max().unwrap_or(1);
+    let atlas_width = ((area as f64).sqrt().ceil() as u32).max(widest).next_power_of_two();
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for i in order {
+        let (width, height) = sizes[i];
+        if x + padded(width) > atlas_width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        positions[i] = (x + padding, y + padding);
+        x += padded(width);
+        row_height = row_height.max(padded(height));
+    }
+
+    (positions, atlas_width, (y + row_height).max(1))
+}
@@ -0,0 +1,276 @@
+//! The `.svgmesh` format, which stores an already tessellated [`Svg`], so that shipped builds
+//! don't need to parse and tessellate the file while loading it.
+//!
+//! Files are created with [`Svg::bake`]. All numbers are stored in little endian:
+//!
+//! | Content      | Encoding                                                      |
+//! |--------------|---------------------------------------------------------------|
+//! | Magic        | `SVGMESH` followed by a zero byte                             |
+//! | Version      | `u32`                                                         |
+//! | Source hash  | `u64` FNV-1a hash of the SVG file that was baked              |
+//! | Name         | `u32` length and UTF-8 bytes                                  |
+//! | Size         | 2 × `f32`                                                     |
+//! | View box     | 4 × `f64`, x, y, width and height                             |
+//! | Content rect | 4 × `f32`, x, y, width and height                             |
+//! | Vertices     | `u32` count, then 3 × `f32` position and `u32` color for each |
+//! | Indices      | `u32` count, then a `u32` for each                            |
+
+use anyhow;
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    log::debug,
+    math::Vec2,
+    render::mesh::{Indices, Mesh, VertexAttributeValues},
+};
+use lyon_svg::parser::ViewBox;
+
+use crate::{diagnostics::LoadFailures, loader::{FileSvgError, SvgLoadError}, render::vertex_buffer::{mesh, view_box_uv}, svg::Svg};
+
+
+/// Marks the start of every `.svgmesh` file.
+const MAGIC: &[u8; 8] = b"SVGMESH\0";
+/// Version of the format, increased whenever the layout changes.
+const VERSION: u32 = 2;
+
+/// An SVG that was tessellated at compile time by the `svg!` macro of the `macros` feature, turned
+/// into an [`Svg`] with [`Svg::from_static`].
+///
+/// Like baked files, it is drawn as it is: runtime changes like
+/// [`SvgColor`](crate::prelude::SvgColor) have no effect on it.
+#[derive(Clone, Copy, Debug)]
+pub struct StaticSvg {
+    /// The name of the file.
+    pub name: &'static str,
+    /// Size of the SVG.
+    pub size: [f32; 2],
+    /// View box of the SVG, x, y, width and height.
+    pub view_box: [f64; 4],
+    /// The area that the view box covers, x, y, width and height.
+    pub content_rect: [f32; 4],
+    /// Positions of the vertices, with the y-axis of Bevy.
+    pub positions: &'static [[f32; 3]],
+    /// Colors of the vertices in linear RGBA, one byte per channel.
+    pub colors: &'static [u32],
+    /// Indices of the triangles.
+    pub indices: &'static [u32],
+}
+
+impl StaticSvg {
+    /// Creates the mesh of the SVG.
+    pub fn mesh(&self) -> Mesh {
+        let uvs = self.positions.iter().map(|position| view_box_uv(*position, Vec2::from(self.size))).collect();
+        mesh(self.positions.to_vec(), self.colors.to_vec(), uvs, self.indices.to_vec())
+    }
+
+    /// Creates the [`Svg`] without a mesh.
+    pub(crate) fn svg(&self) -> Svg {
+        let [x, y, w, h] = self.view_box;
+        let [rect_x, rect_y, rect_width, rect_height] = self.content_rect;
+        drawn_as_is(
+            self.name.to_owned(),
+            Vec2::from(self.size),
+            ViewBox { x, y, w, h },
+            lyon_geom::rect(rect_x, rect_y, rect_width, rect_height),
+        )
+    }
+}
+
+/// Loads baked `.svgmesh` files as [`Svg`]s.
+pub struct SvgMeshLoader {
+    /// Collects the errors of failed loads, which are sent as
+    /// [`SvgLoadFailed`](crate::prelude::SvgLoadFailed) events.
+    pub(crate) failures: LoadFailures,
+}
+
+impl AssetLoader for SvgMeshLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            debug!("Loading baked SVG: {} ...", load_context.path().display());
+            let (mut svg, mesh) = decode(bytes).map_err(|error| {
+                self.failures.push(load_context.path(), error.clone());
+                FileSvgError { error, path: format!("{}", load_context.path().display()) }
+            })?;
+            svg.mesh = load_context.set_labeled_asset("mesh", LoadedAsset::new(mesh));
+            load_context.set_default_asset(LoadedAsset::new(svg));
+            debug!("Loading baked SVG: {} ... Done", load_context.path().display());
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svgmesh"]
+    }
+}
+
+/// Returns the FNV-1a hash of `bytes`, which is stable between builds, unlike the hashers of the
+/// standard library.
+pub(crate) fn source_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Returns whether the `.svgmesh` file `baked` was baked from the SVG file `source`, so it can be
+/// loaded instead of it.
+pub(crate) fn is_baked_from(baked: &[u8], source: &[u8]) -> bool {
+    let mut reader = Reader { bytes: baked };
+    reader.take(MAGIC.len()).ok() == Some(&MAGIC[..])
+        && reader.u32().ok() == Some(VERSION)
+        && reader.u64().ok() == Some(source_hash(source))
+}
+
+/// Writes `svg` with its tessellated `mesh` in the `.svgmesh` format, `source` is the SVG file
+/// that it was parsed from.
+pub(crate) fn encode(svg: &Svg, mesh: &Mesh, source: &[u8]) -> Vec<u8> {
+    let (positions, colors, indices) = mesh_data(mesh);
+
+    let mut bytes = Vec::with_capacity(64 + svg.name.len() + positions.len() * 16 + indices.len() * 4);
+    bytes.extend_from_slice(MAGIC);
+    write_u32(&mut bytes, VERSION);
+    bytes.extend_from_slice(&source_hash(source).to_le_bytes());
+    write_u32(&mut bytes, svg.name.len() as u32);
+    bytes.extend_from_slice(svg.name.as_bytes());
+    for value in [svg.size.x, svg.size.y] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for value in [svg.view_box.x, svg.view_box.y, svg.view_box.w, svg.view_box.h] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    let rect = svg.content_rect;
+    for value in [rect.origin.x, rect.origin.y, rect.size.width, rect.size.height] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    write_u32(&mut bytes, positions.len() as u32);
+    for (position, color) in positions.iter().zip(colors) {
+        for value in position {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        write_u32(&mut bytes, *color);
+    }
+    write_u32(&mut bytes, indices.len() as u32);
+    for index in indices {
+        write_u32(&mut bytes, *index);
+    }
+
+    bytes
+}
+
+/// Reads an [`Svg`] and its mesh from the `.svgmesh` file `bytes`.
+///
+/// The [`Svg`] has no paths or animations, so runtime overrides like
+/// [`SvgColor`](crate::prelude::SvgColor) don't change how it is drawn.
+pub(crate) fn decode(bytes: &[u8]) -> Result<(Svg, Mesh), SvgLoadError> {
+    let mut reader = Reader { bytes };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(SvgLoadError::InvalidBakedMesh("not a `.svgmesh` file"));
+    }
+    if reader.u32()? != VERSION {
+        return Err(SvgLoadError::InvalidBakedMesh("unsupported version"));
+    }
+    reader.u64()?;
+    let name_len = reader.u32()? as usize;
+    let name = std::str::from_utf8(reader.take(name_len)?)
+        .map_err(|_| SvgLoadError::InvalidBakedMesh("name is not valid UTF-8"))?
+        .to_owned();
+    let size = Vec2::new(reader.f32()?, reader.f32()?);
+    let view_box = ViewBox { x: reader.f64()?, y: reader.f64()?, w: reader.f64()?, h: reader.f64()? };
+    let content_rect = lyon_geom::rect(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+
+    let vertex_count = reader.u32()? as usize;
+    let mut positions = Vec::with_capacity(vertex_count.min(reader.bytes.len() / 16));
+    let mut colors = Vec::with_capacity(positions.capacity());
+    for _ in 0..vertex_count {
+        positions.push([reader.f32()?, reader.f32()?, reader.f32()?]);
+        colors.push(reader.u32()?);
+    }
+    let index_count = reader.u32()? as usize;
+    let mut indices = Vec::with_capacity(index_count.min(reader.bytes.len() / 4));
+    for _ in 0..index_count {
+        let index = reader.u32()?;
+        if index as usize >= vertex_count {
+            return Err(SvgLoadError::InvalidBakedMesh("index out of bounds"));
+        }
+        indices.push(index);
+    }
+
+    let uvs = positions.iter().map(|position| view_box_uv(*position, size)).collect();
+    Ok((drawn_as_is(name, size, view_box, content_rect), mesh(positions, colors, uvs, indices)))
+}
+
+/// Returns the positions and colors of the vertices of a tessellated `mesh` and the indices of
+/// its triangles, empty if the mesh doesn't have them.
+pub(crate) fn mesh_data(mesh: &Mesh) -> (&[[f32; 3]], &[u32], &[u32]) {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions.as_slice(),
+        _ => &[],
+    };
+    let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Uint32(colors)) => colors.as_slice(),
+        _ => &[],
+    };
+    let indices = match mesh.indices() {
+        Some(Indices::U32(indices)) => indices.as_slice(),
+        _ => &[],
+    };
+    (positions, colors, indices)
+}
+
+/// Creates an [`Svg`] without paths, whose mesh is drawn as it is.
+fn drawn_as_is(name: String, size: Vec2, view_box: ViewBox, content_rect: lyon_tessellation::math::Rect) -> Svg {
+    Svg {
+        name,
+        size,
+        view_box,
+        content_rect,
+        paths: Vec::new(),
+        animations: Vec::new(),
+        transitions: Default::default(),
+        hidden: Default::default(),
+        skipped_features: Vec::new(),
+        dependencies: Vec::new(),
+        tolerance: None,
+        time_sliced: false,
+        texts: None,
+        modified_paths: Default::default(),
+        mesh: Default::default(),
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Reads little endian values from the front of a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SvgLoadError> {
+        if self.bytes.len() < len {
+            return Err(SvgLoadError::InvalidBakedMesh("unexpected end of file"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, SvgLoadError> {
+        self.take(4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SvgLoadError> {
+        self.take(8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, SvgLoadError> {
+        self.take(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64, SvgLoadError> {
+        self.take(8).map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
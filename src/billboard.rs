@@ -0,0 +1,68 @@
+//! Turns SVGs in a 3D scene toward the camera, for markers and labels in the world.
+
+use bevy::{
+    ecs::{
+        component::Component,
+        reflect::ReflectComponent,
+        system::{Query, Res},
+    },
+    hierarchy::Parent,
+    math::{Quat, Vec3},
+    reflect::Reflect,
+    render::camera::{ActiveCameras, CameraPlugin},
+    transform::components::{GlobalTransform, Transform},
+};
+
+
+/// Turns the entity of a [`Svg3dBundle`](crate::prelude::Svg3dBundle) toward the active 3D
+/// camera every frame, so the SVG is always seen from the front. The rotation of its
+/// [`Transform`] is replaced, the rotation of its parents is taken into account.
+///
+/// The SVG is turned parallel to the screen, so several of them line up like a HUD that is
+/// placed in the world.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgBillboard {
+    /// Only turns the SVG around the y-axis, so it stays upright like a sign when the camera looks
+    /// down on it.
+    pub lock_y: bool,
+}
+
+/// Bevy system which turns the entities with a [`SvgBillboard`] toward the active 3D camera.
+pub(crate) fn face_camera(
+    active_cameras: Res<ActiveCameras>,
+    global_transforms: Query<&GlobalTransform>,
+    mut billboards: Query<(&SvgBillboard, &mut Transform, Option<&Parent>)>,
+) {
+    let camera = active_cameras.get(CameraPlugin::CAMERA_3D)
+        .and_then(|camera| camera.entity)
+        .and_then(|entity| global_transforms.get(entity).ok());
+    let camera_rotation = match camera {
+        Some(camera) => camera.rotation,
+        None => return,
+    };
+    // The mesh is drawn in its xy-plane, with its front toward the z-axis, like the camera sees
+    // the world.
+    let locked_rotation = {
+        let back = camera_rotation * Vec3::Z;
+        // A camera that looks straight down gives no direction.
+        (back.x.abs() > f32::EPSILON || back.z.abs() > f32::EPSILON)
+            .then(|| Quat::from_rotation_y(back.x.atan2(back.z)))
+    };
+
+    for (billboard, mut transform, parent) in billboards.iter_mut() {
+        let rotation = match (billboard.lock_y, locked_rotation) {
+            (false, _) => camera_rotation,
+            (true, Some(locked_rotation)) => locked_rotation,
+            (true, None) => continue,
+        };
+        let parent_rotation = parent
+            .and_then(|parent| global_transforms.get(parent.0).ok())
+            .map_or(Quat::IDENTITY, |parent| parent.rotation);
+        let rotation = parent_rotation.inverse() * rotation;
+        // Keeps `Changed<Transform>` quiet while the camera doesn't turn.
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! The `Aabb` of SVG entities, which Bevy uses to skip drawing entities that are out of view.
+
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle, HandleId},
+    ecs::{
+        entity::Entity,
+        event::EventReader,
+        query::Without,
+        system::{Commands, Local, Query, Res},
+    },
+    math::Vec3,
+    render::{mesh::Mesh, primitives::Aabb},
+    sprite::Mesh2dHandle,
+    transform::components::GlobalTransform,
+};
+
+use crate::{
+    path_entities::{SvgPartOf, SvgPathEntities},
+    render::{placement_correction, Placement},
+    svg::{Svg, SvgYAxis},
+};
+
+
+/// Bevy system which gives every entity that draws an [`Svg`] an `Aabb` that encloses its mesh,
+/// strokes included, where it is drawn after it was placed with its origin, size and y-axis.
+///
+/// Bevy computes the `Aabb` of 3D meshes only once and from the mesh as it is, and none for 2D
+/// meshes, so SVGs were culled while they were still in view, or never.
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_svg_aabbs(
+    mut commands: Commands,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    // Center and half extents of the meshes, `None` for empty ones.
+    mut mesh_bounds: Local<HashMap<HandleId, Option<(Vec3, Vec3)>>>,
+    meshes: Res<Assets<Mesh>>,
+    svgs: Res<Assets<Svg>>,
+    default_y_axis: Res<SvgYAxis>,
+    mut query: Query<
+        (Entity, &Handle<Svg>, Option<&Mesh2dHandle>, Option<&Handle<Mesh>>, &GlobalTransform, Option<&SvgPartOf>, Option<&mut Aabb>),
+        // The root of path entities draws nothing itself.
+        Without<SvgPathEntities>,
+    >,
+    placements: Query<Placement>,
+) {
+    for event in mesh_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                mesh_bounds.remove(&handle.id);
+            },
+        }
+    }
+
+    for (entity, svg_handle, mesh_2d, mesh_3d, global_transform, part_of, aabb) in query.iter_mut() {
+        let mesh = match mesh_2d.map(|mesh_2d| &mesh_2d.0).or(mesh_3d) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        let bounds = match mesh_bounds.get(&mesh.id) {
+            Some(bounds) => *bounds,
+            // Wait until the mesh is loaded.
+            None => match meshes.get(mesh) {
+                Some(mesh_asset) => *mesh_bounds.entry(mesh.id)
+                    .or_insert_with(|| mesh_asset.compute_aabb().map(|aabb| (aabb.center, aabb.half_extents))),
+                None => continue,
+            },
+        };
+        // Path entities are placed like the whole SVG of their root entity.
+        let placement = placements.get(part_of.map_or(entity, |part_of| part_of.0));
+        let ((center, half_extents), svg, placement) = match (bounds, svgs.get(svg_handle), placement) {
+            (Some(bounds), Some(svg), Ok(placement)) => (bounds, svg, placement),
+            _ => continue,
+        };
+        let model = global_transform.compute_matrix();
+        if model.determinant() == 0.0 {
+            continue;
+        }
+
+        // The mesh is drawn with the corrected model matrix, while the `Aabb` is in the space of
+        // the entity.
+        let to_local = model.inverse() * placement_correction(svg, placement, *default_y_axis) * model;
+        let placed = Aabb {
+            center: to_local.transform_point3(center),
+            half_extents: to_local.x_axis.truncate().abs() * half_extents.x
+                + to_local.y_axis.truncate().abs() * half_extents.y
+                + to_local.z_axis.truncate().abs() * half_extents.z,
+        };
+        match aabb {
+            Some(mut aabb) => if aabb.center != placed.center || aabb.half_extents != placed.half_extents {
+                *aabb = placed;
+            },
+            None => {
+                commands.entity(entity).insert(placed);
+            },
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Builds an [`Svg`] from code out of `lyon` paths, for procedural vector graphics that are drawn
+//! like loaded files.
+
+use std::sync::Arc;
+
+use bevy::{
+    asset::Assets,
+    math::Vec2,
+    render::{color::Color, mesh::Mesh},
+    transform::components::Transform,
+};
+use lyon_geom::euclid::default::Transform2D;
+use lyon_svg::parser::ViewBox;
+use lyon_tessellation::{path::{Path, PathEvent}, FillOptions, FillTessellator, StrokeOptions, StrokeTessellator};
+
+use crate::{
+    filter::BlendMode,
+    overrides::Overrides,
+    paint::Paint,
+    render::{dash, tessellation},
+    svg::{DrawType, Group, PathDescriptor, Svg, FILL_TOLERANCE, STROKE_TOLERANCE},
+    Convert,
+};
+
+
+/// Builds an [`Svg`] from code, out of shapes and groups, which is tessellated and drawn like a
+/// loaded file. Runtime changes like [`SvgPaintOverrides`](crate::prelude::SvgPaintOverrides) or
+/// [`SvgNodeVisibility`](crate::prelude::SvgNodeVisibility) find the shapes and groups by their ID.
+///
+/// Everything is placed in the coordinate system of the SVG, where the y-axis points down from
+/// the top left corner.
+///
+/// ```ignore
+/// let svg = Svg::builder(100.0, 100.0)
+///     .shape(SvgShape::new(circle).id("dial").fill(Color::WHITE).stroke(Color::BLACK, 2.0))
+///     .group(SvgGroup::new().id("needle").transform(rotation), |needle| {
+///         needle.shape(SvgShape::new(line).stroke(Color::RED, 3.0))
+///     })
+///     .build(&mut meshes);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SvgBuilder {
+    size: Vec2,
+    paths: Vec<PathDescriptor>,
+    /// The open groups, the outermost first.
+    groups: Vec<Arc<Group>>,
+    /// Accumulated transform of the open groups.
+    transform: Transform2D<f32>,
+}
+
+impl Svg {
+    /// Starts building an [`Svg`] of the given size from code, see [`SvgBuilder`].
+    pub fn builder(width: f32, height: f32) -> SvgBuilder {
+        SvgBuilder::new(width, height)
+    }
+}
+
+impl SvgBuilder {
+    /// Starts building an empty [`Svg`] of the given size.
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            size: Vec2::new(width, height),
+            paths: Vec::new(),
+            groups: Vec::new(),
+            transform: Transform2D::identity(),
+        }
+    }
+
+    /// Adds a shape on top of everything that was added before.
+    #[must_use]
+    pub fn shape(mut self, shape: SvgShape) -> Self {
+        let transform = shape.transform.then(&self.transform);
+        let segments: Vec<PathEvent> = shape.path.iter().map(|event| event.transformed(&transform)).collect();
+        // The segments are already in the coordinate system of the SVG, so the width of a stroke
+        // is scaled with the shape.
+        let scale = transform.determinant().abs().sqrt();
+        let groups = self.groups.clone();
+        let descriptor = |paint, draw_type, length| PathDescriptor {
+            id: shape.id.clone(),
+            length,
+            segments: segments.clone(),
+            abs_transform: Transform::identity(),
+            transform: Transform2D::identity(),
+            paint: Paint::Color(paint),
+            paint_variable: None,
+            draw_type,
+            non_scaling_stroke: false,
+            dash: None,
+            groups: groups.clone(),
+        };
+        if let Some((color, options)) = shape.fill {
+            self.paths.push(descriptor(color, DrawType::Fill(options), 0.0));
+        }
+        if let Some((color, options)) = shape.stroke {
+            let draw_type = DrawType::Stroke(options).scale_stroke(scale);
+            self.paths.push(descriptor(color, draw_type, dash::length(&segments)));
+        }
+        self
+    }
+
+    /// Adds a group, whose content is added by `content`, on top of everything that was added
+    /// before.
+    #[must_use]
+    pub fn group(mut self, group: SvgGroup, content: impl FnOnce(Self) -> Self) -> Self {
+        let parent_transform = self.transform;
+        self.groups.push(Arc::new(Group {
+            id: group.id,
+            transform: group.transform,
+            parent_transform,
+            clip_path: None,
+            mask: None,
+            filters: Vec::new(),
+            opacity: group.opacity,
+            blend_mode: BlendMode::Normal,
+        }));
+        self.transform = group.transform.then(&parent_transform);
+
+        let mut builder = content(self);
+        builder.groups.pop();
+        builder.transform = parent_transform;
+        builder
+    }
+
+    /// Creates the [`Svg`] and adds its tessellated mesh to `meshes`.
+    pub fn build(self, meshes: &mut Assets<Mesh>) -> Svg {
+        let mut svg = Svg {
+            name: String::new(),
+            size: self.size,
+            view_box: ViewBox { x: 0.0, y: 0.0, w: f64::from(self.size.x), h: f64::from(self.size.y) },
+            content_rect: lyon_geom::rect(0.0, 0.0, self.size.x, self.size.y),
+            paths: self.paths,
+            animations: Vec::new(),
+            transitions: Default::default(),
+            hidden: Default::default(),
+            skipped_features: Vec::new(),
+            dependencies: Vec::new(),
+            tolerance: None,
+            time_sliced: false,
+            texts: None,
+            modified_paths: Default::default(),
+            mesh: Default::default(),
+        };
+        let buffer = tessellation::generate_buffer(&svg, &Overrides::default(), &mut FillTessellator::new(), &mut StrokeTessellator::new());
+        svg.mesh = meshes.add(buffer.convert());
+        svg
+    }
+}
+
+/// A shape of an [`SvgBuilder`], a path that is filled, stroked or both. The stroke is drawn on
+/// top of the fill.
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    path: Path,
+    id: String,
+    fill: Option<(Color, FillOptions)>,
+    stroke: Option<(Color, StrokeOptions)>,
+    transform: Transform2D<f32>,
+}
+
+impl SvgShape {
+    /// Creates a shape of `path` that is neither filled nor stroked.
+    pub fn new(path: Path) -> Self {
+        Self {
+            path,
+            id: String::new(),
+            fill: None,
+            stroke: None,
+            transform: Transform2D::identity(),
+        }
+    }
+
+    /// Sets the ID of the shape.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Fills the shape with `color`, with the non-zero fill rule.
+    #[must_use]
+    pub fn fill(self, color: Color) -> Self {
+        self.fill_with(color, FillOptions::tolerance(FILL_TOLERANCE))
+    }
+
+    /// Fills the shape with `color` and the given `options`.
+    #[must_use]
+    pub fn fill_with(mut self, color: Color, options: FillOptions) -> Self {
+        self.fill = Some((color, options));
+        self
+    }
+
+    /// Strokes the shape with `color` and a line of the given `width`.
+    #[must_use]
+    pub fn stroke(self, color: Color, width: f32) -> Self {
+        self.stroke_with(color, StrokeOptions::tolerance(STROKE_TOLERANCE).with_line_width(width))
+    }
+
+    /// Strokes the shape with `color` and the given `options`.
+    #[must_use]
+    pub fn stroke_with(mut self, color: Color, options: StrokeOptions) -> Self {
+        self.stroke = Some((color, options));
+        self
+    }
+
+    /// Sets the transform of the shape, relative to its group. The width of its stroke is scaled
+    /// along, non-uniform scales scale it by the geometric mean of both axes.
+    #[must_use]
+    pub fn transform(mut self, transform: Transform2D<f32>) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+/// A group of an [`SvgBuilder`], which transforms its content and draws it with an opacity as a
+/// whole. Groups with an ID can be changed at runtime, like with
+/// [`SvgGroupTransforms`](crate::prelude::SvgGroupTransforms).
+#[derive(Clone, Debug)]
+pub struct SvgGroup {
+    id: String,
+    transform: Transform2D<f32>,
+    opacity: f32,
+}
+
+impl Default for SvgGroup {
+    fn default() -> Self {
+        Self { id: String::new(), transform: Transform2D::identity(), opacity: 1.0 }
+    }
+}
+
+impl SvgGroup {
+    /// Creates a group without a transform, that is fully opaque.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the ID of the group.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Sets the transform of the group, relative to its parent.
+    #[must_use]
+    pub fn transform(mut self, transform: Transform2D<f32>) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Sets the opacity of the group, from `0.0` to `1.0`.
+    #[must_use]
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+}
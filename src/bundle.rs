@@ -2,8 +2,10 @@
 
 use bevy::{
     asset::Handle,
-    ecs::bundle::Bundle,
+    ecs::{bundle::Bundle, entity::Entity, system::Commands},
+    math::{Quat, Vec3},
     render::{
+        color::Color,
         mesh::Mesh,
         view::{ComputedVisibility, Visibility}
     },
@@ -11,7 +13,7 @@ use bevy::{
     transform::components::{GlobalTransform, Transform},
 };
 
-use crate::svg::{Origin, Svg};
+use crate::{overrides::SvgColor, path_entities::SvgPathEntities, svg::{Origin, Svg, SvgDoubleSided, SvgScaleMode, SvgSize}};
 
 
 /// A Bevy [`Bundle`] representing an SVG entity.
@@ -52,6 +54,8 @@ pub struct Svg3dBundle {
     pub mesh: Handle<Mesh>,
     /// [`Origin`] of the coordinate system and as such the origin for the Bevy position.
     pub origin: Origin,
+    /// Whether the back of the SVG is drawn as well, see [`SvgDoubleSided`].
+    pub double_sided: SvgDoubleSided,
     pub transform: Transform,
     pub global_transform: GlobalTransform,
     pub visibility: Visibility,
@@ -65,6 +69,7 @@ impl Default for Svg3dBundle {
             svg: Default::default(),
             mesh: Default::default(),
             origin: Default::default(),
+            double_sided: Default::default(),
             transform: Transform::default(),
             global_transform: GlobalTransform::default(),
             visibility: Visibility::default(),
@@ -72,3 +77,169 @@ impl Default for Svg3dBundle {
         }
     }
 }
+
+impl Svg2dBundle {
+    /// Starts building a [`Svg2dBundle`] that draws `svg`.
+    ///
+    /// ```ignore
+    /// Svg2dBundle::builder(svg)
+    ///     .origin(Origin::Center)
+    ///     .size(64.0, 64.0)
+    ///     .translation(Vec3::new(100.0, 0.0, 0.0))
+    ///     .color(Color::WHITE)
+    ///     .spawn(&mut commands);
+    /// ```
+    pub fn builder(svg: Handle<Svg>) -> Svg2dBundleBuilder {
+        Svg2dBundleBuilder { bundle: Self { svg, ..Default::default() }, color: None, size: None, scale_mode: None, path_entities: false }
+    }
+}
+
+impl Svg3dBundle {
+    /// Starts building a [`Svg3dBundle`] that draws `svg`.
+    pub fn builder(svg: Handle<Svg>) -> Svg3dBundleBuilder {
+        Svg3dBundleBuilder { bundle: Self { svg, ..Default::default() }, color: None, size: None, scale_mode: None, path_entities: false }
+    }
+}
+
+/// Implements the methods that both bundle builders share.
+macro_rules! impl_bundle_builder {
+    ($builder:ident, $bundle:ident) => {
+        impl $builder {
+            /// Sets the [`Origin`] that the SVG is placed at.
+            #[must_use]
+            pub fn origin(mut self, origin: Origin) -> Self {
+                self.bundle.origin = origin;
+                self
+            }
+
+            /// Sets the whole [`Transform`].
+            #[must_use]
+            pub fn transform(mut self, transform: Transform) -> Self {
+                self.bundle.transform = transform;
+                self
+            }
+
+            /// Sets the translation of the [`Transform`].
+            #[must_use]
+            pub fn translation(mut self, translation: Vec3) -> Self {
+                self.bundle.transform.translation = translation;
+                self
+            }
+
+            /// Sets the rotation of the [`Transform`].
+            #[must_use]
+            pub fn rotation(mut self, rotation: Quat) -> Self {
+                self.bundle.transform.rotation = rotation;
+                self
+            }
+
+            /// Sets the scale of the [`Transform`].
+            #[must_use]
+            pub fn scale(mut self, scale: Vec3) -> Self {
+                self.bundle.transform.scale = scale;
+                self
+            }
+
+            /// Sets whether the SVG is visible.
+            #[must_use]
+            pub fn visible(mut self, is_visible: bool) -> Self {
+                self.bundle.visibility = Visibility { is_visible };
+                self
+            }
+
+            /// Replaces the `currentColor` of the SVG with a [`SvgColor`].
+            ///
+            /// The color is a component of its own, so it is only inserted by
+            /// [`spawn`](Self::spawn), not part of the bundle returned by [`build`](Self::build).
+            #[must_use]
+            pub fn color(mut self, color: Color) -> Self {
+                self.color = Some(SvgColor(color));
+                self
+            }
+
+            /// Draws the SVG with a fixed size with a [`SvgSize`].
+            ///
+            /// The size is a component of its own, so it is only inserted by
+            /// [`spawn`](Self::spawn), not part of the bundle returned by [`build`](Self::build).
+            #[must_use]
+            pub fn size(mut self, width: f32, height: f32) -> Self {
+                self.size = Some(SvgSize::new(width, height));
+                self
+            }
+
+            /// Sets how the SVG is fitted into its [`size`](Self::size) with a [`SvgScaleMode`].
+            ///
+            /// Like the size, it is only inserted by [`spawn`](Self::spawn).
+            #[must_use]
+            pub fn scale_mode(mut self, scale_mode: SvgScaleMode) -> Self {
+                self.scale_mode = Some(scale_mode);
+                self
+            }
+
+            /// Draws every path as a child entity of its own with [`SvgPathEntities`].
+            ///
+            /// It is only inserted by [`spawn`](Self::spawn).
+            #[must_use]
+            pub fn path_entities(mut self) -> Self {
+                self.path_entities = true;
+                self
+            }
+
+            /// Returns the bundle.
+            pub fn build(self) -> $bundle {
+                self.bundle
+            }
+
+            /// Spawns an entity with the bundle and all other components that were set.
+            pub fn spawn(self, commands: &mut Commands) -> Entity {
+                let mut entity = commands.spawn_bundle(self.bundle);
+                if let Some(color) = self.color {
+                    entity.insert(color);
+                }
+                if let Some(size) = self.size {
+                    entity.insert(size);
+                }
+                if let Some(scale_mode) = self.scale_mode {
+                    entity.insert(scale_mode);
+                }
+                if self.path_entities {
+                    entity.insert(SvgPathEntities);
+                }
+                entity.id()
+            }
+        }
+    };
+}
+
+/// Builds a [`Svg2dBundle`], created with [`Svg2dBundle::builder`].
+#[must_use]
+pub struct Svg2dBundleBuilder {
+    bundle: Svg2dBundle,
+    color: Option<SvgColor>,
+    size: Option<SvgSize>,
+    scale_mode: Option<SvgScaleMode>,
+    path_entities: bool,
+}
+
+impl_bundle_builder!(Svg2dBundleBuilder, Svg2dBundle);
+
+/// Builds a [`Svg3dBundle`], created with [`Svg3dBundle::builder`].
+#[must_use]
+pub struct Svg3dBundleBuilder {
+    bundle: Svg3dBundle,
+    color: Option<SvgColor>,
+    size: Option<SvgSize>,
+    scale_mode: Option<SvgScaleMode>,
+    path_entities: bool,
+}
+
+impl_bundle_builder!(Svg3dBundleBuilder, Svg3dBundle);
+
+impl Svg3dBundleBuilder {
+    /// Sets whether the back of the SVG is drawn as well, see [`SvgDoubleSided`].
+    #[must_use]
+    pub fn double_sided(mut self, double_sided: bool) -> Self {
+        self.bundle.double_sided = SvgDoubleSided(double_sided);
+        self
+    }
+}
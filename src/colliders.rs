@@ -0,0 +1,228 @@
+//! Colliders of `bevy_rapier2d` that are generated from the paths of an SVG, so level geometry that
+//! is drawn in an editor like Inkscape is both the visual and the collision of a level.
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{Changed, Or, With},
+        reflect::ReflectComponent,
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::{Mat4, Vec2, Vec3},
+    reflect::Reflect,
+    transform::components::GlobalTransform,
+};
+use bevy_rapier2d::prelude::Collider;
+use lyon_svg::path::{iterator::PathIterator, PathEvent};
+use lyon_tessellation::{geometry_builder::Positions, math::Point, BuffersBuilder, FillTessellator, StrokeTessellator, VertexBuffers};
+
+use crate::{
+    render::{placement_correction, Placement},
+    svg::{document_transform, DrawType, Origin, PathDescriptor, Svg, SvgScaleMode, SvgSize, SvgYAxis},
+};
+
+
+/// Tolerance that is used to flatten curves into the lines of colliders.
+const FLATTEN_TOLERANCE: f32 = 0.1;
+
+/// Gives an entity with an [`Svg`] a `Collider` of `bevy_rapier2d`, which is generated from the
+/// paths of the SVG once it is loaded, and again when it or the placement of the entity changes.
+///
+/// The collider is placed like the SVG is drawn, with the [`Origin`], [`SvgSize`] and
+/// [`SvgYAxis`] of the entity and the scale that its `Transform` has when the collider is
+/// generated. Hidden elements are left out.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgColliders {
+    /// IDs of the elements whose paths become the collider, like the layers of Inkscape, which
+    /// are groups. All paths are used if there are none.
+    pub ids: Vec<String>,
+    /// The kind of collider that is generated.
+    pub shape: SvgColliderShape,
+}
+
+impl SvgColliders {
+    /// Creates a collider of the `shape` from all paths of the SVG.
+    pub fn new(shape: SvgColliderShape) -> Self {
+        Self { ids: Vec::new(), shape }
+    }
+
+    /// Adds the element with the ID `id` to the elements that the collider is generated from.
+    #[must_use]
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.ids.push(id.into());
+        self
+    }
+
+    /// Returns whether `path` belongs to one of the elements of the collider.
+    fn includes(&self, path: &PathDescriptor) -> bool {
+        self.ids.is_empty() || self.ids.iter().any(|id| {
+            path.id == *id || path.groups.iter().any(|group| group.id == *id)
+        })
+    }
+}
+
+/// The kind of collider that [`SvgColliders`] generates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect_value(PartialEq)]
+pub enum SvgColliderShape {
+    /// The outlines of the paths as lines without an area, for walls and terrain of static bodies.
+    Polyline,
+    /// The areas inside of the outlines, split into convex parts, which also works for dynamic
+    /// bodies.
+    ConvexDecomposition,
+    /// The triangles of the fills and strokes of the paths, strokes keep their width.
+    Trimesh,
+}
+
+impl Default for SvgColliderShape {
+    fn default() -> Self {
+        SvgColliderShape::Polyline
+    }
+}
+
+/// Bevy system which inserts the collider of entities with [`SvgColliders`], and replaces it when
+/// the [`Svg`] or the placement of the entity changes.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn generate_svg_colliders(
+    mut commands: Commands,
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    svgs: Res<Assets<Svg>>,
+    default_y_axis: Res<SvgYAxis>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    changed: Query<
+        Entity,
+        (With<SvgColliders>, Or<(Changed<SvgColliders>, Changed<Handle<Svg>>, Changed<Origin>, Changed<SvgSize>, Changed<SvgScaleMode>, Changed<SvgYAxis>)>),
+    >,
+    query: Query<(Entity, &SvgColliders, &Handle<Svg>, Placement)>,
+) {
+    let loaded: Vec<_> = svg_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.clone_weak()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    for (entity, colliders, handle, (origin, size, scale_mode, y_axis, global_transform)) in query.iter() {
+        if !changed.contains(entity) && !loaded.contains(handle) {
+            continue;
+        }
+        // Wait until the svg is loaded.
+        let svg = match svgs.get(handle) {
+            Some(svg) => svg,
+            None => continue,
+        };
+        // The collider is scaled with the entity, only the scale decides where the mesh is placed
+        // relative to it.
+        let scaled = GlobalTransform::from_scale(global_transform.scale);
+        let scale = scaled.compute_matrix();
+        if scale.determinant() == 0.0 {
+            continue;
+        }
+        let correction = placement_correction(svg, (origin, size, scale_mode, y_axis, &scaled), *default_y_axis);
+        // Bevy has a different y-axis origin, so we need to flip that axis.
+        let to_local = scale.inverse() * correction * scale * Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+
+        let paths: Vec<&PathDescriptor> = svg.paths.iter()
+            .filter(|path| !svg.is_hidden(path) && colliders.includes(path))
+            .collect();
+        let collider = match colliders.shape {
+            SvgColliderShape::Polyline | SvgColliderShape::ConvexDecomposition => {
+                let (vertices, indices) = outlines(&paths, to_local);
+                match colliders.shape {
+                    _ if indices.is_empty() => None,
+                    SvgColliderShape::Polyline => Some(Collider::polyline(vertices, Some(indices))),
+                    _ => Some(Collider::convex_decomposition(&vertices, &indices)),
+                }
+            },
+            SvgColliderShape::Trimesh => {
+                let (vertices, indices) = triangles(&paths, to_local, &mut fill_tess, &mut stroke_tess);
+                (!indices.is_empty()).then(|| Collider::trimesh(vertices, indices))
+            },
+        };
+        match collider {
+            Some(collider) => commands.entity(entity).insert(collider),
+            None => commands.entity(entity).remove::<Collider>(),
+        };
+    }
+}
+
+/// Returns the vertices and lines of the flattened outlines of `paths`, transformed from the
+/// coordinate system of the SVG with the y-axis of Bevy by `to_local`.
+fn outlines(paths: &[&PathDescriptor], to_local: Mat4) -> (Vec<Vec2>, Vec<[u32; 2]>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (index, path) in paths.iter().enumerate() {
+        // The fill and the stroke of a shape share their outline.
+        if index > 0 && !path.id.is_empty() && paths[index - 1].id == path.id {
+            continue;
+        }
+        let is_fill = matches!(path.draw_type, DrawType::Fill(_));
+        let to_document = document_transform(path);
+        let mut start = 0;
+        for event in path.segments.iter().copied().flattened(FLATTEN_TOLERANCE) {
+            match event {
+                PathEvent::Begin { at } => {
+                    start = vertices.len() as u32;
+                    vertices.push(local_vertex(to_document.transform_point(at), to_local));
+                },
+                PathEvent::Line { to, .. } => {
+                    vertices.push(local_vertex(to_document.transform_point(to), to_local));
+                    let end = vertices.len() as u32 - 1;
+                    indices.push([end - 1, end]);
+                },
+                // Outlines of fills are closed, even if their path isn't.
+                PathEvent::End { close, .. } if close || is_fill => {
+                    let end = vertices.len() as u32 - 1;
+                    if end > start {
+                        indices.push([end, start]);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    (vertices, indices)
+}
+
+/// Returns the vertices and triangles of the tessellated `paths`, transformed from the
+/// coordinate system of the SVG with the y-axis of Bevy by `to_local`.
+fn triangles(
+    paths: &[&PathDescriptor],
+    to_local: Mat4,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+) -> (Vec<Vec2>, Vec<[u32; 3]>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for path in paths {
+        let mut buffer: VertexBuffers<Point, u32> = VertexBuffers::new();
+        let result = match path.draw_type {
+            DrawType::Fill(ref options) => {
+                fill_tess.tessellate(path.segments.iter().copied(), options, &mut BuffersBuilder::new(&mut buffer, Positions))
+            },
+            DrawType::Stroke(ref options) => {
+                stroke_tess.tessellate(path.segments.iter().copied(), options, &mut BuffersBuilder::new(&mut buffer, Positions))
+            },
+        };
+        // Paths that can't be tessellated already failed to load, or are left out of the mesh.
+        if result.is_err() {
+            continue;
+        }
+        let offset = vertices.len() as u32;
+        let to_document = document_transform(path);
+        vertices.extend(buffer.vertices.iter().map(|vertex| local_vertex(to_document.transform_point(*vertex), to_local)));
+        indices.extend(buffer.indices.chunks_exact(3).map(|triangle| {
+            [triangle[0] + offset, triangle[1] + offset, triangle[2] + offset]
+        }));
+    }
+    (vertices, indices)
+}
+
+/// Transforms `point` from the coordinate system of the SVG into the one of the entity.
+fn local_vertex(point: Point, to_local: Mat4) -> Vec2 {
+    to_local.transform_point3(Vec3::new(point.x, point.y, 0.0)).truncate()
+}
@@ -0,0 +1,835 @@
+//! CSS animations and transitions, mapped onto the [`Animation`]s of SMIL.
+//!
+//! `@keyframes` rules are read from the style sheets, and every element with an `animation`
+//! gets one [`Animation`] per animated property, which the
+//! [`SvgAnimationPlayer`](crate::animation::SvgAnimationPlayer) plays like any other.
+//!
+//! Transitions need a change of a property to start, which only happens when the color of a
+//! custom property is changed with [`SvgCssVariables`](crate::overrides::SvgCssVariables). So only
+//! the `fill` and `stroke` colors that come from a custom property can transition.
+
+use std::{borrow::Cow, collections::HashMap};
+
+use bevy::{log::warn, render::color::Color};
+use lyon_geom::euclid::default::{Box2D, Transform2D};
+use lyon_svg::path::{iterator::PathIterator, Path, PathEvent};
+use lyon_tessellation::math::{point, Point, Rect};
+
+use crate::animation::{self, Animation, CalcMode, Direction, Easing, Property, StepPosition, Timing, TransformKind};
+
+
+/// Tolerance that is used to flatten paths, when their bounding box is calculated.
+const FLATTEN_TOLERANCE: f32 = 0.1;
+
+/// Prefixes of `@keyframes` rules, including the ones of older browsers.
+const KEYFRAMES_RULES: &[&str] = &["keyframes", "-webkit-keyframes", "-moz-keyframes", "-o-keyframes"];
+
+/// A `@keyframes` rule.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Keyframes<'a> {
+    frames: Vec<Keyframe<'a>>,
+}
+
+/// A single offset of a `@keyframes` rule, with the declarations of its block.
+#[derive(Clone, Debug)]
+struct Keyframe<'a> {
+    offset: f32,
+    declarations: Vec<simplecss::Declaration<'a>>,
+}
+
+impl<'a> Keyframes<'a> {
+    /// Returns the names of all properties that are animated, without duplicates.
+    pub(crate) fn properties(&self) -> Vec<&'a str> {
+        let mut properties = Vec::new();
+        for declaration in self.frames.iter().flat_map(|frame| &frame.declarations) {
+            if declaration.name != "animation-timing-function" && !properties.contains(&declaration.name) {
+                properties.push(declaration.name);
+            }
+        }
+        properties
+    }
+}
+
+/// An `animation` of an element, with the `@keyframes` rule that it plays.
+#[derive(Clone, Debug)]
+pub(crate) struct CssAnimation<'k> {
+    name: String,
+    keyframes: &'k Keyframes<'k>,
+    timing: Timing,
+    easing: Easing,
+}
+
+impl<'k> CssAnimation<'k> {
+    /// Returns the names of all properties that are animated.
+    pub(crate) fn properties(&self) -> Vec<&'k str> {
+        self.keyframes.properties()
+    }
+}
+
+/// A `transition` of a property.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Transition {
+    pub(crate) duration: f32,
+    pub(crate) delay: f32,
+    pub(crate) easing: Easing,
+}
+
+/// A transition of the color of a custom property that is running or has ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RunningTransition {
+    from: Color,
+    to: Color,
+    /// Time the transition started, in seconds since startup.
+    start: f64,
+}
+
+/// The state of all color transitions of an entity, keyed by the name of the custom property.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ColorTransitions {
+    running: HashMap<String, RunningTransition>,
+}
+
+impl ColorTransitions {
+    /// Returns the colors of the custom properties at `now`. Colors of custom properties with a
+    /// `transition` move from their current color to the one in `targets`, or back to the color
+    /// in the file if they aren't in `targets` anymore. All other colors are returned as they are.
+    pub(crate) fn update(
+        &mut self,
+        transitions: &HashMap<String, Transition>,
+        initial: impl Fn(&str) -> Option<Color>,
+        targets: &HashMap<String, Color>,
+        now: f64,
+    ) -> HashMap<String, Color> {
+        let mut colors = targets.clone();
+        for (name, transition) in transitions {
+            let target = match targets.get(name).copied().or_else(|| initial(name)) {
+                Some(target) => target,
+                None => continue,
+            };
+            let from = match self.running.get(name) {
+                Some(running) if running.to == target => None,
+                Some(running) => Some(running.color(transition, now)),
+                // Without a known start, the first color is used right away.
+                None => initial(name).filter(|initial| *initial != target),
+            };
+            if let Some(from) = from {
+                self.running.insert(name.clone(), RunningTransition { from, to: target, start: now });
+            }
+            if let Some(running) = self.running.get(name) {
+                colors.insert(name.clone(), running.color(transition, now));
+            }
+        }
+        colors
+    }
+
+    /// Returns true if any of the transitions hasn't ended at `now`.
+    pub(crate) fn is_running(&self, transitions: &HashMap<String, Transition>, now: f64) -> bool {
+        self.running.iter().any(|(name, running)| {
+            transitions.get(name).map_or(false, |transition| {
+                now < running.start + f64::from(transition.delay + transition.duration)
+            })
+        })
+    }
+}
+
+impl RunningTransition {
+    fn color(&self, transition: &Transition, now: f64) -> Color {
+        let elapsed = (now - self.start) as f32 - transition.delay;
+        let t = match transition.duration {
+            duration if duration > 0.0 => (elapsed / duration).clamp(0.0, 1.0),
+            _ if elapsed >= 0.0 => 1.0,
+            _ => 0.0,
+        };
+        let t = transition.easing.apply(t);
+        let (from, to) = (self.from.as_rgba_f32(), self.to.as_rgba_f32());
+        let [r, g, b, a] = [0, 1, 2, 3].map(|i| from[i] + (to[i] - from[i]) * t);
+        Color::rgba(r, g, b, a)
+    }
+}
+
+/// Reads all `@keyframes` rules of the style sheets, keyed by their name. Later rules replace
+/// earlier ones with the same name.
+pub(crate) fn keyframes(style_sheets: &[String]) -> HashMap<&str, Keyframes<'_>> {
+    let mut keyframes = HashMap::new();
+    for css in style_sheets {
+        let mut rest = css.as_str();
+        while let Some(start) = rest.find('@') {
+            let rule = &rest[start + 1..];
+            let open = match rule.find(|c| c == '{' || c == ';') {
+                Some(open) if rule[open..].starts_with('{') => open,
+                Some(end) => {
+                    rest = &rule[end + 1..];
+                    continue;
+                },
+                None => break,
+            };
+            let end = closing_brace(&rule[open + 1..]).map_or(rule.len(), |end| open + 1 + end);
+            let (keyword, name) = rule[..open].trim().split_once(char::is_whitespace).unwrap_or((rule[..open].trim(), ""));
+            if KEYFRAMES_RULES.contains(&keyword) {
+                let name = name.trim().trim_matches(|c| c == '"' || c == '\'');
+                keyframes.insert(name, parse_keyframes(&rule[open + 1..end]));
+            }
+            rest = rule.get(end + 1..).unwrap_or_default();
+        }
+    }
+    keyframes
+}
+
+/// Parses the content of a `@keyframes` block.
+fn parse_keyframes(block: &str) -> Keyframes<'_> {
+    let mut frames = Vec::new();
+    let mut rest = block;
+    while let Some(open) = rest.find('{') {
+        let close = match rest[open..].find('}') {
+            Some(close) => open + close,
+            None => break,
+        };
+        let declarations: Vec<simplecss::Declaration<'_>> = simplecss::DeclarationTokenizer::from(&rest[open + 1..close])
+            // Important declarations are ignored in keyframes.
+            .filter(|declaration| !declaration.important)
+            .collect();
+        for selector in rest[..open].split(',') {
+            let offset = match selector.trim() {
+                "from" => Some(0.0),
+                "to" => Some(1.0),
+                selector => selector.strip_suffix('%').and_then(|percent| percent.trim().parse::<f32>().ok()).map(|percent| percent / 100.0),
+            };
+            match offset {
+                Some(offset) if (0.0..=1.0).contains(&offset) => frames.push(Keyframe { offset, declarations: declarations.clone() }),
+                _ => warn!("Invalid keyframe selector `{}`, skipping it.", selector.trim()),
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+    // Keyframes with the same offset keep their order, so later declarations win.
+    frames.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    Keyframes { frames }
+}
+
+/// Returns the position of the brace that closes the already opened one.
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// The values of the `animation` properties of an element, one entry per animation.
+#[derive(Default)]
+struct AnimationLists {
+    names: Vec<String>,
+    durations: Vec<f32>,
+    easings: Vec<Easing>,
+    delays: Vec<f32>,
+    iteration_counts: Vec<f32>,
+    directions: Vec<Direction>,
+    fill_modes: Vec<(bool, bool)>,
+}
+
+/// Reads the animations of an element from its resolved `style`. Animations whose `@keyframes`
+/// rule doesn't exist are skipped.
+pub(crate) fn animations_of<'k>(style: &[(&str, Cow<'_, str>)], keyframes: &'k HashMap<&str, Keyframes<'k>>) -> Vec<CssAnimation<'k>> {
+    let mut lists = AnimationLists::default();
+    // The style is in the order of the cascade, so a later longhand can override the shorthand.
+    for (name, value) in style {
+        match *name {
+            "animation" => {
+                lists = AnimationLists::default();
+                for item in split_top_level(value, ',') {
+                    shorthand_item(item, &mut lists);
+                }
+            },
+            "animation-name" => lists.names = split_top_level(value, ',').into_iter().map(unquote).collect(),
+            "animation-duration" => lists.durations = split_top_level(value, ',').into_iter().map(|value| time(value).unwrap_or(0.0)).collect(),
+            "animation-timing-function" => lists.easings = split_top_level(value, ',').into_iter().map(|value| easing(value).unwrap_or(EASE)).collect(),
+            "animation-delay" => lists.delays = split_top_level(value, ',').into_iter().map(|value| time(value).unwrap_or(0.0)).collect(),
+            "animation-iteration-count" => lists.iteration_counts = split_top_level(value, ',').into_iter().map(|value| iteration_count(value).unwrap_or(1.0)).collect(),
+            "animation-direction" => lists.directions = split_top_level(value, ',').into_iter().map(|value| direction(value).unwrap_or(Direction::Normal)).collect(),
+            "animation-fill-mode" => lists.fill_modes = split_top_level(value, ',').into_iter().map(|value| fill_mode(value).unwrap_or((false, false))).collect(),
+            _ => {},
+        }
+    }
+
+    // Lists that are shorter than the names are repeated.
+    fn nth<T: Copy>(list: &[T], i: usize, default: T) -> T {
+        match list.len() {
+            0 => default,
+            len => list[i % len],
+        }
+    }
+
+    let mut animations = Vec::new();
+    for (i, name) in lists.names.iter().enumerate() {
+        if name == "none" {
+            continue;
+        }
+        let keyframes = match keyframes.get(name.as_str()) {
+            Some(keyframes) => keyframes,
+            None => {
+                warn!("There are no keyframes named `{}`, skipping the animation.", name);
+                continue;
+            },
+        };
+        let duration = nth(&lists.durations, i, 0.0);
+        if duration <= 0.0 {
+            continue;
+        }
+        let (freeze, fill_backwards) = nth(&lists.fill_modes, i, (false, false));
+        animations.push(CssAnimation {
+            name: name.clone(),
+            keyframes,
+            timing: Timing {
+                begin: nth(&lists.delays, i, 0.0),
+                duration,
+                active_duration: duration * nth(&lists.iteration_counts, i, 1.0),
+                freeze,
+                fill_backwards,
+                direction: nth(&lists.directions, i, Direction::Normal),
+            },
+            easing: nth(&lists.easings, i, EASE),
+        });
+    }
+    animations
+}
+
+/// Reads one animation of the `animation` shorthand into `lists`.
+fn shorthand_item(item: &str, lists: &mut AnimationLists) {
+    let (mut name, mut duration, mut delay, mut easing_function) = (None, None, None, None);
+    let (mut iteration_count_value, mut direction_value, mut fill_mode_value) = (None, None, None);
+    for token in split_top_level(item, ' ') {
+        if let Some(time) = time(token) {
+            if duration.is_none() {
+                duration = Some(time);
+            } else {
+                delay = Some(time);
+            }
+        } else if let (None, Some(value)) = (easing_function, easing(token)) {
+            easing_function = Some(value);
+        } else if let (None, Some(value)) = (iteration_count_value, iteration_count(token)) {
+            iteration_count_value = Some(value);
+        } else if let (None, Some(value)) = (direction_value, direction(token)) {
+            direction_value = Some(value);
+        } else if let (None, Some(value)) = (fill_mode_value, fill_mode(token)) {
+            fill_mode_value = Some(value);
+        } else if matches!(token, "running" | "paused") {
+            // There is no user interaction that could pause an animation, so it always runs.
+        } else {
+            name = Some(unquote(token));
+        }
+    }
+    lists.names.push(name.unwrap_or_else(|| "none".to_owned()));
+    lists.durations.push(duration.unwrap_or(0.0));
+    lists.easings.push(easing_function.unwrap_or(EASE));
+    lists.delays.push(delay.unwrap_or(0.0));
+    lists.iteration_counts.push(iteration_count_value.unwrap_or(1.0));
+    lists.directions.push(direction_value.unwrap_or(Direction::Normal));
+    lists.fill_modes.push(fill_mode_value.unwrap_or((false, false)));
+}
+
+/// Reads the transitions of an element from its resolved `style`, keyed by the name of the
+/// property, or `all`.
+pub(crate) fn transitions_of(style: &[(&str, Cow<'_, str>)]) -> HashMap<String, Transition> {
+    let (mut properties, mut durations, mut delays, mut easings) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (name, value) in style {
+        match *name {
+            "transition" => {
+                properties.clear();
+                durations.clear();
+                delays.clear();
+                easings.clear();
+                for item in split_top_level(value, ',') {
+                    let (mut property, mut duration, mut delay, mut easing_function) = (None, None, None, None);
+                    for token in split_top_level(item, ' ') {
+                        match (time(token), easing(token)) {
+                            (Some(time), _) if duration.is_none() => duration = Some(time),
+                            (Some(time), _) => delay = Some(time),
+                            (None, Some(value)) => easing_function = Some(value),
+                            (None, None) => property = Some(token.to_owned()),
+                        }
+                    }
+                    properties.push(property.unwrap_or_else(|| "all".to_owned()));
+                    durations.push(duration.unwrap_or(0.0));
+                    delays.push(delay.unwrap_or(0.0));
+                    easings.push(easing_function.unwrap_or(EASE));
+                }
+            },
+            "transition-property" => properties = split_top_level(value, ',').into_iter().map(str::to_owned).collect(),
+            "transition-duration" => durations = split_top_level(value, ',').into_iter().map(|value| time(value).unwrap_or(0.0)).collect(),
+            "transition-delay" => delays = split_top_level(value, ',').into_iter().map(|value| time(value).unwrap_or(0.0)).collect(),
+            "transition-timing-function" => easings = split_top_level(value, ',').into_iter().map(|value| easing(value).unwrap_or(EASE)).collect(),
+            _ => {},
+        }
+    }
+
+    let mut transitions = HashMap::new();
+    for (i, property) in properties.into_iter().enumerate() {
+        let transition = Transition {
+            duration: durations.get(i % durations.len().max(1)).copied().unwrap_or(0.0),
+            delay: delays.get(i % delays.len().max(1)).copied().unwrap_or(0.0),
+            easing: easings.get(i % easings.len().max(1)).copied().unwrap_or(EASE),
+        };
+        if property != "none" && transition.duration + transition.delay > 0.0 {
+            transitions.insert(property, transition);
+        }
+    }
+    transitions
+}
+
+/// Turns the `animation` of `node` into one [`Animation`] per animated property.
+///
+/// `property` returns the resolved value of a property of the element, which is used where the
+/// keyframes don't declare a value at their start or end. `color_variable` returns the name
+/// under which an animated color of the element is drawn, if it is a shape. Physical lengths
+/// are converted into pixels with `dpi`.
+pub(crate) fn animations<'a>(
+    node: &roxmltree::Node<'_, '_>,
+    css_animation: &CssAnimation<'_>,
+    target: &str,
+    property: impl Fn(&str) -> Option<&'a str>,
+    color_variable: impl Fn(&str) -> Option<String>,
+    dpi: f32,
+) -> Vec<Animation> {
+    let units = Units::new(dpi, property("font-size"));
+    let element_transform = animation::element_transform(node);
+    let reference_box = match property("transform-box") {
+        None | Some("view-box") => view_box(node, &units),
+        // Without a CSS layout box, all other boxes are the one of the fill, the stroke is ignored.
+        Some(_) => fill_box(node, &units).unwrap_or_else(|| view_box(node, &units)),
+    };
+    let origin = transform_origin(property("transform-origin"), &reference_box, &units);
+
+    let mut animations = Vec::new();
+    for name in css_animation.properties() {
+        let mut frames: Vec<(f32, &str, Easing)> = Vec::new();
+        for frame in &css_animation.keyframes.frames {
+            let easing = frame.declarations.iter()
+                .rfind(|declaration| declaration.name == "animation-timing-function")
+                .and_then(|declaration| easing(declaration.value))
+                .unwrap_or(css_animation.easing);
+            if let Some(declaration) = frame.declarations.iter().rfind(|declaration| declaration.name == name) {
+                // Of several keyframes with the same offset, the last one wins.
+                if frames.last().map_or(false, |(offset, _, _)| *offset == frame.offset) {
+                    frames.pop();
+                }
+                frames.push((frame.offset, declaration.value, easing));
+            }
+        }
+
+        let property_of_animation = match name {
+            "opacity" => Property::Opacity,
+            "fill" | "stroke" | "fill-opacity" | "stroke-opacity" => match color_variable(name) {
+                Some(_) if name == "fill-opacity" => Property::FillOpacity,
+                Some(_) if name == "stroke-opacity" => Property::StrokeOpacity,
+                Some(variable) => Property::Color(variable),
+                None => {
+                    warn!("Only the `{}` of shapes can be animated, skipping it in the animation `{}` of `{}`.", name, css_animation.name, target);
+                    continue;
+                },
+            },
+            // The functions are only known after all values are parsed.
+            "transform" => Property::Transform(Vec::new()),
+            _ => {
+                warn!("Animating `{}` is not supported, skipping it in the animation `{}` of `{}`.", name, css_animation.name, target);
+                continue;
+            },
+        };
+
+        // Without a value at the start or the end, the value of the element is used there.
+        let underlying = match property_of_animation {
+            Property::Transform(_) if element_transform == Transform2D::identity() => Some("none".to_owned()),
+            Property::Transform(_) => {
+                let t = element_transform;
+                Some(format!("matrix({} {} {} {} {} {})", t.m11, t.m12, t.m21, t.m22, t.m31, t.m32))
+            },
+            Property::Opacity | Property::FillOpacity | Property::StrokeOpacity => Some(property(name).unwrap_or("1").to_owned()),
+            _ => property(name).map(str::to_owned),
+        };
+        let underlying = underlying.as_deref().unwrap_or_else(|| frames.first().map_or("", |frame| frame.1));
+        if frames.first().map_or(true, |(offset, _, _)| *offset > 0.0) {
+            frames.insert(0, (0.0, underlying, css_animation.easing));
+        }
+        if frames.last().map_or(true, |(offset, _, _)| *offset < 1.0) {
+            frames.push((1.0, underlying, css_animation.easing));
+        }
+
+        let (property_of_animation, values) = match property_of_animation {
+            Property::Transform(_) => {
+                let lists: Option<Vec<_>> = frames.iter().map(|(_, value, _)| transform_list(value, &reference_box, &units)).collect();
+                match lists {
+                    Some(lists) => {
+                        let (kinds, values) = interpolable_transforms(&lists, origin);
+                        (Property::Transform(kinds), values)
+                    },
+                    None => {
+                        warn!("Could not parse the transforms of the animation `{}` of `{}`, skipping them.", css_animation.name, target);
+                        continue;
+                    },
+                }
+            },
+            property_of_animation => match frames.iter().map(|(_, value, _)| match property_of_animation {
+                Property::Opacity | Property::FillOpacity | Property::StrokeOpacity => opacity(value).map(|opacity| vec![opacity]),
+                _ => animation::color(value),
+            }).collect::<Option<Vec<_>>>() {
+                Some(values) => (property_of_animation, values),
+                None => {
+                    warn!("Could not parse the values of `{}` in the animation `{}` of `{}`, skipping them.", name, css_animation.name, target);
+                    continue;
+                },
+            },
+        };
+
+        animations.push(Animation {
+            target: target.to_owned(),
+            property: property_of_animation,
+            timing: css_animation.timing,
+            values,
+            key_times: frames.iter().map(|(offset, _, _)| *offset).collect(),
+            easings: frames.iter().map(|(_, _, easing)| *easing).collect(),
+            calc_mode: CalcMode::Spline,
+            additive: false,
+            element_transform,
+        });
+    }
+    animations
+}
+
+/// Brings the transform function lists of all keyframes into the same shape, so their
+/// parameters can be interpolated, and adds the `transform-origin`.
+///
+/// Lists that start with the same functions are interpolated function by function, shorter lists
+/// are filled up with functions that don't change anything. All other lists are interpolated as
+/// matrices.
+fn interpolable_transforms(lists: &[Vec<(TransformKind, Vec<f32>)>], origin: Point) -> (Vec<TransformKind>, Vec<Vec<f32>>) {
+    let longest: Vec<TransformKind> = lists.iter()
+        .max_by_key(|list| list.len())
+        .map(|list| list.iter().map(|(kind, _)| *kind).collect())
+        .unwrap_or_default();
+    let matching = lists.iter().all(|list| list.iter().zip(&longest).all(|((kind, _), longest)| kind == longest));
+
+    let mut kinds = vec![TransformKind::Translate];
+    let mut values = Vec::new();
+    for list in lists {
+        let mut value = vec![origin.x, origin.y];
+        if matching {
+            for (i, kind) in longest.iter().enumerate() {
+                value.extend_from_slice(list.get(i).map_or(kind.identity(), |(_, parameters)| parameters));
+            }
+        } else {
+            let list_kinds: Vec<TransformKind> = list.iter().map(|(kind, _)| *kind).collect();
+            let list_values: Vec<f32> = list.iter().flat_map(|(_, parameters)| parameters.iter().copied()).collect();
+            let t = animation::transform(&list_kinds, &list_values);
+            value.extend_from_slice(&[t.m11, t.m12, t.m21, t.m22, t.m31, t.m32]);
+        }
+        value.extend_from_slice(&[-origin.x, -origin.y]);
+        values.push(value);
+    }
+    if matching {
+        kinds.extend(longest);
+    } else {
+        kinds.push(TransformKind::Matrix);
+    }
+    kinds.push(TransformKind::Translate);
+    (kinds, values)
+}
+
+/// Parses the value of the CSS `transform` property into a list of functions with their
+/// parameters. Percentages refer to the `reference_box`.
+fn transform_list(value: &str, reference_box: &Rect, units: &Units) -> Option<Vec<(TransformKind, Vec<f32>)>> {
+    let mut functions = Vec::new();
+    let value = value.trim();
+    if value != "none" {
+        let mut rest = value;
+        while !rest.trim().is_empty() {
+            let open = rest.find('(')?;
+            let close = open + rest[open..].find(')')?;
+            let name = rest[..open].trim();
+            let arguments: Vec<&str> = rest[open + 1..close].split(|c: char| c == ',' || c.is_whitespace()).filter(|argument| !argument.is_empty()).collect();
+            let length = |i: usize, size: f32| arguments.get(i).map_or(Some(0.0), |argument| units.length(argument, size));
+            let angle = |i: usize| arguments.get(i).map_or(Some(0.0), |argument| css_angle(argument));
+            let number = |i: usize| arguments.get(i).and_then(|argument| css_number(argument));
+            let function = match name {
+                "translate" => (TransformKind::Translate, vec![length(0, reference_box.width())?, length(1, reference_box.height())?]),
+                "translateX" => (TransformKind::Translate, vec![length(0, reference_box.width())?, 0.0]),
+                "translateY" => (TransformKind::Translate, vec![0.0, length(0, reference_box.height())?]),
+                "scale" => {
+                    let x = number(0)?;
+                    (TransformKind::Scale, vec![x, number(1).unwrap_or(x)])
+                },
+                "scaleX" => (TransformKind::Scale, vec![number(0)?, 1.0]),
+                "scaleY" => (TransformKind::Scale, vec![1.0, number(0)?]),
+                "rotate" | "rotateZ" => (TransformKind::Rotate, vec![angle(0)?, 0.0, 0.0]),
+                "skewX" => (TransformKind::SkewX, vec![angle(0)?]),
+                "skewY" => (TransformKind::SkewY, vec![angle(0)?]),
+                "skew" if arguments.len() < 2 => (TransformKind::SkewX, vec![angle(0)?]),
+                "skew" => (TransformKind::Matrix, vec![1.0, angle(1)?.to_radians().tan(), angle(0)?.to_radians().tan(), 1.0, 0.0, 0.0]),
+                "matrix" => (TransformKind::Matrix, (0..6).map(number).collect::<Option<_>>()?),
+                _ => {
+                    warn!("The transform function `{}` is not supported.", name);
+                    return None;
+                },
+            };
+            functions.push(function);
+            rest = &rest[close + 1..];
+        }
+    }
+    Some(functions)
+}
+
+/// The `transform-origin` of an element, relative to the `reference_box`.
+fn transform_origin(value: Option<&str>, reference_box: &Rect, units: &Units) -> Point {
+    // The initial value for SVG elements is `0 0`.
+    let mut tokens: Vec<&str> = value.map_or_else(Vec::new, |value| value.split_whitespace().collect());
+    if tokens.len() == 1 {
+        tokens.push("center");
+    }
+    // Keywords can come in any order, `top left` is the same as `left top`.
+    if tokens.len() >= 2 && (matches!(tokens[0], "top" | "bottom") || matches!(tokens[1], "left" | "right")) {
+        tokens.swap(0, 1);
+    }
+    let coordinate = |token: Option<&&str>, start: f32, size: f32| match token.copied() {
+        Some("left" | "top") => start,
+        Some("center") => start + size * 0.5,
+        Some("right" | "bottom") => start + size,
+        Some(token) => start + units.length(token, size).unwrap_or(0.0),
+        None => start,
+    };
+    point(
+        coordinate(tokens.get(0), reference_box.min_x(), reference_box.width()),
+        coordinate(tokens.get(1), reference_box.min_y(), reference_box.height()),
+    )
+}
+
+/// Returns the reference box of the nearest viewport of `node`, its view box at the origin.
+fn view_box(node: &roxmltree::Node<'_, '_>, units: &Units) -> Rect {
+    let viewport = match node.ancestors().skip(1).find(|ancestor| ancestor.has_tag_name("svg")) {
+        Some(viewport) => viewport,
+        None => return Rect::zero(),
+    };
+    let size = viewport.attribute("viewBox")
+        .and_then(animation::numbers)
+        .and_then(|numbers| Some((*numbers.get(2)?, *numbers.get(3)?)))
+        .unwrap_or((length_attribute(&viewport, "width", 100.0, units), length_attribute(&viewport, "height", 100.0, units)));
+    Rect::new(Point::zero(), size.into())
+}
+
+/// Returns the bounding box of the geometry of `node` in its own coordinate system, `fill-box`
+/// in CSS.
+fn fill_box(node: &roxmltree::Node<'_, '_>, units: &Units) -> Option<Rect> {
+    let length = |name: &str| length_attribute(node, name, 0.0, units);
+    let points: Vec<Point> = match node.tag_name().name() {
+        "rect" => vec![point(length("x"), length("y")), point(length("x") + length("width"), length("y") + length("height"))],
+        "circle" => {
+            let (cx, cy, r) = (length("cx"), length("cy"), length("r"));
+            vec![point(cx - r, cy - r), point(cx + r, cy + r)]
+        },
+        "ellipse" => {
+            let (cx, cy, rx, ry) = (length("cx"), length("cy"), length("rx"), length("ry"));
+            vec![point(cx - rx, cy - ry), point(cx + rx, cy + ry)]
+        },
+        "line" => vec![point(length("x1"), length("y1")), point(length("x2"), length("y2"))],
+        "polyline" | "polygon" => node.attribute("points")
+            .and_then(animation::numbers)
+            .map(|numbers| numbers.chunks_exact(2).map(|pair| point(pair[0], pair[1])).collect())
+            .unwrap_or_default(),
+        "path" => {
+            let path = lyon_svg::path_utils::build_path(Path::svg_builder(), node.attribute("d")?).ok()?;
+            path.iter().flattened(FLATTEN_TOLERANCE)
+                .filter_map(|event| match event {
+                    PathEvent::Begin { at } => Some(at),
+                    PathEvent::Line { to, .. } => Some(to),
+                    _ => None,
+                })
+                .collect()
+        },
+        "g" | "a" | "switch" => {
+            return node.children()
+                .filter(roxmltree::Node::is_element)
+                .filter_map(|child| Some(animation::element_transform(&child).outer_transformed_rect(&fill_box(&child, units)?)))
+                .reduce(|a, b| a.union(&b));
+        },
+        _ => return None,
+    };
+    (!points.is_empty()).then(|| Box2D::from_points(points).to_rect())
+}
+
+/// Returns the value of an attribute of `node` that is a number or a length, in pixels.
+fn length_attribute(node: &roxmltree::Node<'_, '_>, name: &str, default: f32, units: &Units) -> f32 {
+    node.attribute(name).and_then(|value| units.length(value, 0.0)).unwrap_or(default)
+}
+
+/// Converts lengths with units into pixels.
+struct Units {
+    /// Pixels per inch.
+    dpi: f32,
+    /// Font size of the element in pixels, which `em` and `ex` refer to.
+    font_size: f32,
+}
+
+impl Units {
+    /// Creates the units of an element with the given `font-size`.
+    fn new(dpi: f32, font_size: Option<&str>) -> Self {
+        let default_font_size = usvg::Options::default().font_size as f32;
+        let units = Self { dpi, font_size: default_font_size };
+        let font_size = font_size.and_then(|font_size| units.length(font_size, default_font_size));
+        Self { font_size: font_size.unwrap_or(default_font_size), ..units }
+    }
+
+    /// Parses a length into pixels, or a percentage of `size`.
+    fn length(&self, value: &str, size: f32) -> Option<f32> {
+        let value = value.trim();
+        let units = [
+            ("%", size / 100.0),
+            ("px", 1.0),
+            ("in", self.dpi),
+            ("cm", self.dpi / 2.54),
+            ("mm", self.dpi / 25.4),
+            ("pt", self.dpi / 72.0),
+            ("pc", self.dpi / 6.0),
+            ("em", self.font_size),
+            // Without the metrics of the font, an `ex` is half an `em`, like in `usvg`.
+            ("ex", self.font_size / 2.0),
+        ];
+        match units.iter().find_map(|(unit, factor)| Some((value.strip_suffix(unit)?, factor))) {
+            Some((number, factor)) => number.trim_end().parse::<f32>().ok().map(|number| number * factor),
+            None => value.parse().ok(),
+        }
+    }
+}
+
+/// Parses an angle into degrees.
+fn css_angle(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let units = [("deg", 1.0), ("grad", 0.9), ("rad", 180.0 / std::f32::consts::PI), ("turn", 360.0)];
+    match units.iter().find_map(|(unit, factor)| Some((value.strip_suffix(unit)?, factor))) {
+        Some((number, factor)) => number.parse::<f32>().ok().map(|number| number * factor),
+        // Only zero can be written without a unit.
+        None => value.parse::<f32>().ok().filter(|number| *number == 0.0),
+    }
+}
+
+/// Parses a plain number.
+fn css_number(value: &str) -> Option<f32> {
+    value.trim().parse().ok()
+}
+
+/// Parses an opacity, a number or a percentage.
+fn opacity(value: &str) -> Option<f32> {
+    let value = value.trim();
+    match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().ok().map(|percent| percent / 100.0),
+        None => value.parse().ok(),
+    }
+}
+
+/// The default timing function, `ease`.
+const EASE: Easing = Easing::CubicBezier(lyon_geom::CubicBezierSegment {
+    from: Point::new(0.0, 0.0),
+    ctrl1: Point::new(0.25, 0.1),
+    ctrl2: Point::new(0.25, 1.0),
+    to: Point::new(1.0, 1.0),
+});
+
+/// Parses a timing function.
+fn easing(value: &str) -> Option<Easing> {
+    let value = value.trim();
+    let steps = |count: u32, position: StepPosition| Easing::Steps { count, position };
+    match value {
+        "linear" => return Some(Easing::Linear),
+        "ease" => return Some(EASE),
+        "ease-in" => return Some(animation::cubic_bezier(0.42, 0.0, 1.0, 1.0)),
+        "ease-out" => return Some(animation::cubic_bezier(0.0, 0.0, 0.58, 1.0)),
+        "ease-in-out" => return Some(animation::cubic_bezier(0.42, 0.0, 0.58, 1.0)),
+        "step-start" => return Some(steps(1, StepPosition::Start)),
+        "step-end" => return Some(steps(1, StepPosition::End)),
+        _ => {},
+    }
+    let (name, arguments) = value.strip_suffix(')')?.split_once('(')?;
+    let arguments: Vec<&str> = arguments.split(',').map(str::trim).collect();
+    match (name.trim(), &arguments[..]) {
+        ("cubic-bezier", &[x1, y1, x2, y2]) => {
+            let [x1, y1, x2, y2] = [x1, y1, x2, y2].map(|number| number.parse::<f32>().ok());
+            Some(animation::cubic_bezier(x1?.clamp(0.0, 1.0), y1?, x2?.clamp(0.0, 1.0), y2?))
+        },
+        ("steps", &[count, ref position @ ..]) => {
+            let position = match position.first().copied() {
+                None | Some("end" | "jump-end") => StepPosition::End,
+                Some("start" | "jump-start") => StepPosition::Start,
+                Some("jump-none") => StepPosition::None,
+                Some("jump-both") => StepPosition::Both,
+                Some(_) => return None,
+            };
+            Some(steps(count.parse().ok().filter(|count| *count > 0)?, position))
+        },
+        _ => None,
+    }
+}
+
+/// Parses a CSS time, like `2s` or `500ms`, into seconds. Unlike SMIL, the unit is required.
+fn time(value: &str) -> Option<f32> {
+    let value = value.trim();
+    match value.strip_suffix("ms") {
+        Some(milliseconds) => milliseconds.parse::<f32>().ok().map(|milliseconds| milliseconds / 1000.0),
+        None => value.strip_suffix('s')?.parse().ok(),
+    }
+}
+
+fn iteration_count(value: &str) -> Option<f32> {
+    match value.trim() {
+        "infinite" => Some(f32::INFINITY),
+        count => count.parse().ok().filter(|count: &f32| *count >= 0.0),
+    }
+}
+
+fn direction(value: &str) -> Option<Direction> {
+    match value.trim() {
+        "normal" => Some(Direction::Normal),
+        "reverse" => Some(Direction::Reverse),
+        "alternate" => Some(Direction::Alternate),
+        "alternate-reverse" => Some(Direction::AlternateReverse),
+        _ => None,
+    }
+}
+
+/// Parses an `animation-fill-mode` into whether the animation keeps its last value and whether
+/// it shows its first value before it begins.
+fn fill_mode(value: &str) -> Option<(bool, bool)> {
+    match value.trim() {
+        "none" => Some((false, false)),
+        "forwards" => Some((true, false)),
+        "backwards" => Some((false, true)),
+        "both" => Some((true, true)),
+        _ => None,
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_owned()
+}
+
+/// Splits `value` at every `separator` that isn't inside of parentheses. With a space as
+/// separator, it splits at any whitespace.
+fn split_top_level(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 && (c == separator || separator == ' ' && c.is_whitespace()) => {
+                parts.push(&value[start..i]);
+                start = i + c.len_utf8();
+            },
+            _ => {},
+        }
+    }
+    parts.push(&value[start..]);
+    parts.into_iter().map(str::trim).filter(|part| !part.is_empty()).collect()
+}
@@ -0,0 +1,98 @@
+//! Reports content of an SVG that is skipped while loading, so it is visible why an asset looks
+//! incomplete, and SVGs that fail to load.
+
+use std::{path::{Path, PathBuf}, sync::{Arc, Mutex}};
+
+use bevy::{
+    asset::{AssetEvent, AssetPath, Assets, Handle},
+    ecs::{event::{EventReader, EventWriter}, system::Res},
+};
+
+use crate::{loader::SvgLoadError, svg::Svg};
+
+
+/// Content of an SVG that can't be drawn and is skipped while loading.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SkippedFeature {
+    /// The name of the feature, the tag name for skipped elements, like `foreignObject`.
+    pub feature: String,
+    /// The ID of the element that uses the feature, if it has one.
+    pub element_id: Option<String>,
+}
+
+impl std::fmt::Display for SkippedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.element_id {
+            Some(ref id) => write!(f, "`{}` of `{}`", self.feature, id),
+            None => write!(f, "`{}`", self.feature),
+        }
+    }
+}
+
+/// Sent when an [`Svg`] is loaded or reloaded that contains content which is skipped.
+#[derive(Clone, Debug)]
+pub struct SvgLoadWarning {
+    /// The SVG that was loaded.
+    pub svg: Handle<Svg>,
+    /// The name of the file.
+    pub name: String,
+    /// Everything that was skipped, in document order.
+    pub skipped: Vec<SkippedFeature>,
+}
+
+/// Sends a [`SvgLoadWarning`] for every loaded [`Svg`] with skipped features.
+pub(crate) fn report_skipped_features(
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    mut warnings: EventWriter<SvgLoadWarning>,
+    svgs: Res<Assets<Svg>>,
+) {
+    for event in svg_events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        match svgs.get(handle) {
+            Some(svg) if !svg.skipped_features.is_empty() => warnings.send(SvgLoadWarning {
+                svg: handle.clone_weak(),
+                name: svg.name.clone(),
+                skipped: svg.skipped_features.clone(),
+            }),
+            _ => {},
+        }
+    }
+}
+
+/// Sent when an [`Svg`] fails to load, so that fallback art can be shown instead.
+#[derive(Clone, Debug)]
+pub struct SvgLoadFailed {
+    /// The SVG that failed to load.
+    pub svg: Handle<Svg>,
+    /// The path of the file inside of the asset folder.
+    pub path: PathBuf,
+    /// Why loading failed.
+    pub error: SvgLoadError,
+}
+
+/// The errors of failed loads, shared between the asset loaders and [`report_failed_loads`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LoadFailures(Arc<Mutex<Vec<(PathBuf, SvgLoadError)>>>);
+
+impl LoadFailures {
+    /// Records that loading the file at `path` failed with `error`.
+    pub(crate) fn push(&self, path: &Path, error: SvgLoadError) {
+        self.0.lock().unwrap().push((path.to_owned(), error));
+    }
+}
+
+/// Sends a [`SvgLoadFailed`] for every [`Svg`] that failed to load since the last frame.
+pub(crate) fn report_failed_loads(failures: Res<LoadFailures>, mut events: EventWriter<SvgLoadFailed>) {
+    let failed = std::mem::take(&mut *failures.0.lock().unwrap());
+    for (path, error) in failed {
+        events.send(SvgLoadFailed {
+            svg: Handle::weak(AssetPath::new_ref(&path, None).into()),
+            path,
+            error,
+        });
+    }
+}
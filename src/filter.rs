@@ -0,0 +1,381 @@
+//! Filters that get applied to the content of a group, `filter` in SVG.
+//!
+//! There is no way to express most filter effects with vector geometry, so the content of a
+//! filtered group gets rasterized on the CPU while tessellating. The filter primitives are then
+//! applied to that raster, which is afterwards turned back into a mesh.
+//!
+//! Primitives that are not supported are skipped with a warning, so their consumers get the
+//! result of the previous primitive instead.
+
+use bevy::{log::warn, math::Vec2, render::color::Color};
+use lyon_geom::euclid::default::Transform2D;
+use lyon_tessellation::math::Rect;
+
+use crate::{svg::node_bbox, Convert};
+
+
+/// A filter, `filter` in SVG.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Filter {
+    /// The filter region in the user space of the filtered group, nothing outside of it is visible.
+    pub region: Rect,
+    /// Maps the user space of the filtered group into the coordinate system of its paths.
+    pub transform: Transform2D<f32>,
+    /// All filter primitives, applied in order.
+    pub primitives: Vec<Primitive>,
+}
+
+/// A single filter primitive, like `feGaussianBlur`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Primitive {
+    /// The primitive subregion in the user space of the filtered group, the result of the
+    /// primitive is transparent outside of it.
+    pub region: Rect,
+    /// Name of the result, which can be referenced by the following primitives.
+    pub result: String,
+    /// What the primitive does.
+    pub kind: PrimitiveKind,
+}
+
+/// The supported filter primitives.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrimitiveKind {
+    /// Blurs the input, `feGaussianBlur` in SVG.
+    GaussianBlur {
+        /// The input of the primitive.
+        input: Input,
+        /// Standard deviation of the blur in user space.
+        std_dev: Vec2,
+    },
+    /// Draws a blurred, offset and colored copy of the input below it, `feDropShadow` in SVG.
+    DropShadow {
+        /// The input of the primitive.
+        input: Input,
+        /// Offset of the shadow in user space.
+        offset: Vec2,
+        /// Standard deviation of the blur in user space.
+        std_dev: Vec2,
+        /// Color of the shadow, already multiplied with the flood opacity.
+        color: Color,
+    },
+    /// Transforms the colors of the input, `feColorMatrix` in SVG.
+    ColorMatrix {
+        /// The input of the primitive.
+        input: Input,
+        /// The 4x5 matrix in row-major order, which is applied to the unpremultiplied RGBA color.
+        /// `saturate`, `hueRotate` and `luminanceToAlpha` are converted into an equivalent matrix.
+        matrix: [f32; 20],
+    },
+    /// Moves the input, `feOffset` in SVG.
+    Offset {
+        /// The input of the primitive.
+        input: Input,
+        /// Offset in user space.
+        offset: Vec2,
+    },
+    /// Fills the primitive subregion with a color, `feFlood` in SVG.
+    Flood {
+        /// The flood color, already multiplied with the flood opacity.
+        color: Color,
+    },
+    /// Composites all inputs on top of each other, `feMerge` in SVG.
+    Merge {
+        /// The inputs, from bottom to top.
+        inputs: Vec<Input>,
+    },
+    /// Blends two inputs, `feBlend` in SVG.
+    Blend {
+        /// The top layer, `in` in SVG.
+        input1: Input,
+        /// The bottom layer, `in2` in SVG.
+        input2: Input,
+        /// How the colors are blended.
+        mode: BlendMode,
+    },
+    /// Combines two inputs with a Porter-Duff operator, `feComposite` in SVG.
+    Composite {
+        /// The source, `in` in SVG.
+        input1: Input,
+        /// The destination, `in2` in SVG.
+        input2: Input,
+        /// How the inputs are combined.
+        operator: CompositeOperator,
+    },
+}
+
+/// Blend modes of [`PrimitiveKind::Blend`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+}
+
+/// Operators of [`PrimitiveKind::Composite`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum CompositeOperator {
+    Over,
+    In,
+    Out,
+    Atop,
+    Xor,
+    /// `k1 * source * destination + k2 * source + k3 * destination + k4`
+    Arithmetic { k1: f32, k2: f32, k3: f32, k4: f32 },
+}
+
+/// The input of a filter primitive.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Input {
+    /// The content of the filtered group.
+    SourceGraphic,
+    /// Only the alpha channel of the filtered group.
+    SourceAlpha,
+    /// The result of a previous primitive.
+    Reference(String),
+}
+
+/// Returns the element name of a filter primitive that is not supported, `None` for the
+/// supported ones.
+pub(crate) fn unsupported_primitive(kind: &usvg::filter::Kind) -> Option<&'static str> {
+    use usvg::filter::Kind;
+    match kind {
+        Kind::GaussianBlur(_) | Kind::DropShadow(_) | Kind::ColorMatrix(_) | Kind::Offset(_)
+        | Kind::Flood(_) | Kind::Merge(_) | Kind::Blend(_) | Kind::Composite(_) => None,
+        Kind::ComponentTransfer(_) => Some("feComponentTransfer"),
+        Kind::ConvolveMatrix(_) => Some("feConvolveMatrix"),
+        Kind::DiffuseLighting(_) => Some("feDiffuseLighting"),
+        Kind::DisplacementMap(_) => Some("feDisplacementMap"),
+        Kind::Image(_) => Some("feImage"),
+        Kind::Morphology(_) => Some("feMorphology"),
+        Kind::SpecularLighting(_) => Some("feSpecularLighting"),
+        Kind::Tile(_) => Some("feTile"),
+        Kind::Turbulence(_) => Some("feTurbulence"),
+    }
+}
+
+/// Resolves the filter with the given `id`, which is referenced by the group `node`.
+/// `transform` is the accumulated transform of the group.
+pub(crate) fn resolve_filter(tree: &usvg::Tree, node: &usvg::Node, id: &str, transform: &usvg::Transform) -> Option<Filter> {
+    let filter_node = tree.defs_by_id(id)?;
+    let filter = match *filter_node.borrow() {
+        usvg::NodeKind::Filter(ref filter) => filter.clone(),
+        _ => return None,
+    };
+
+    let bbox = node_bbox(node, usvg::Transform::default()).and_then(|bbox| bbox.to_rect());
+    let region = match filter.units {
+        usvg::Units::UserSpaceOnUse => filter.rect,
+        usvg::Units::ObjectBoundingBox => filter.rect.bbox_transform(bbox?),
+    };
+    // Lengths of the primitives are either in user space or relative to the bounding box.
+    let primitive_scale = match filter.primitive_units {
+        usvg::Units::UserSpaceOnUse => Vec2::ONE,
+        usvg::Units::ObjectBoundingBox => bbox.map(|bbox| Vec2::new(bbox.width() as f32, bbox.height() as f32))?,
+    };
+
+    let mut primitives = Vec::with_capacity(filter.primitives.len());
+    for primitive in &filter.primitives {
+        let kind = match primitive.kind {
+            usvg::filter::Kind::GaussianBlur(ref blur) => PrimitiveKind::GaussianBlur {
+                input: blur.input.convert(),
+                std_dev: Vec2::new(blur.std_dev_x.value() as f32, blur.std_dev_y.value() as f32) * primitive_scale,
+            },
+            usvg::filter::Kind::DropShadow(ref shadow) => PrimitiveKind::DropShadow {
+                input: shadow.input.convert(),
+                offset: Vec2::new(shadow.dx as f32, shadow.dy as f32) * primitive_scale,
+                std_dev: Vec2::new(shadow.std_dev_x.value() as f32, shadow.std_dev_y.value() as f32) * primitive_scale,
+                color: Color::rgba_u8(shadow.color.red, shadow.color.green, shadow.color.blue, shadow.opacity.to_u8()),
+            },
+            usvg::filter::Kind::ColorMatrix(ref color_matrix) => PrimitiveKind::ColorMatrix {
+                input: color_matrix.input.convert(),
+                matrix: (&color_matrix.kind).convert(),
+            },
+            usvg::filter::Kind::Offset(ref offset) => PrimitiveKind::Offset {
+                input: offset.input.convert(),
+                offset: Vec2::new(offset.dx as f32, offset.dy as f32) * primitive_scale,
+            },
+            usvg::filter::Kind::Flood(ref flood) => PrimitiveKind::Flood {
+                color: Color::rgba_u8(flood.color.red, flood.color.green, flood.color.blue, flood.opacity.to_u8()),
+            },
+            usvg::filter::Kind::Merge(ref merge) => PrimitiveKind::Merge {
+                inputs: merge.inputs.iter().map(Convert::convert).collect(),
+            },
+            usvg::filter::Kind::Blend(ref blend) => PrimitiveKind::Blend {
+                input1: blend.input1.convert(),
+                input2: blend.input2.convert(),
+                mode: blend.mode.convert(),
+            },
+            usvg::filter::Kind::Composite(ref composite) => PrimitiveKind::Composite {
+                input1: composite.input1.convert(),
+                input2: composite.input2.convert(),
+                operator: composite.operator.convert(),
+            },
+            ref kind => {
+                warn!("Filter primitive {:?} of filter `{}` is not supported, skipping it.", kind, id);
+                continue;
+            },
+        };
+
+        // Missing attributes of the subregion default to the filter region.
+        let subregion = match filter.primitive_units {
+            usvg::Units::UserSpaceOnUse => usvg::Rect::new(
+                primitive.x.unwrap_or_else(|| region.x()),
+                primitive.y.unwrap_or_else(|| region.y()),
+                primitive.width.unwrap_or_else(|| region.width()),
+                primitive.height.unwrap_or_else(|| region.height()),
+            ),
+            usvg::Units::ObjectBoundingBox => {
+                let bbox = bbox?;
+                usvg::Rect::new(
+                    primitive.x.map_or_else(|| region.x(), |x| bbox.x() + x * bbox.width()),
+                    primitive.y.map_or_else(|| region.y(), |y| bbox.y() + y * bbox.height()),
+                    primitive.width.map_or_else(|| region.width(), |width| width * bbox.width()),
+                    primitive.height.map_or_else(|| region.height(), |height| height * bbox.height()),
+                )
+            },
+        };
+        let subregion = match subregion {
+            Some(subregion) => subregion.convert(),
+            None => {
+                warn!("Filter primitive of filter `{}` has an invalid subregion, skipping it.", id);
+                continue;
+            },
+        };
+        primitives.push(Primitive { region: subregion, result: primitive.result.clone(), kind });
+    }
+
+    Some(Filter {
+        region: region.convert(),
+        transform: transform.convert(),
+        primitives,
+    })
+}
+
+impl Convert<Input> for &usvg::filter::Input {
+    fn convert(self) -> Input {
+        match self {
+            usvg::filter::Input::SourceGraphic => Input::SourceGraphic,
+            usvg::filter::Input::SourceAlpha => Input::SourceAlpha,
+            usvg::filter::Input::Reference(name) => Input::Reference(name.clone()),
+            input => {
+                warn!("Filter input {:?} is not supported, using `SourceGraphic` instead.", input);
+                Input::SourceGraphic
+            },
+        }
+    }
+}
+
+impl Convert<[f32; 20]> for &usvg::filter::ColorMatrixKind {
+    fn convert(self) -> [f32; 20] {
+        use usvg::filter::ColorMatrixKind;
+
+        match self {
+            ColorMatrixKind::Matrix(values) => {
+                let mut matrix = [0.0; 20];
+                for (value, converted) in values.iter().zip(matrix.iter_mut()) {
+                    *converted = *value as f32;
+                }
+                matrix
+            },
+            ColorMatrixKind::Saturate(value) => saturate_matrix(value.value() as f32),
+            ColorMatrixKind::HueRotate(angle) => hue_rotate_matrix(*angle as f32),
+            ColorMatrixKind::LuminanceToAlpha => [
+                0.0, 0.0, 0.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 0.0, 0.0,
+                0.2125, 0.7154, 0.0721, 0.0, 0.0,
+            ],
+        }
+    }
+}
+
+/// Returns the color matrix of `feColorMatrix type="saturate"`, `0.0` is grey and `1.0` keeps
+/// the colors.
+pub(crate) fn saturate_matrix(s: f32) -> [f32; 20] {
+    [
+        0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+        0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+        0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s, 0.0, 0.0,
+        0.0, 0.0, 0.0, 1.0, 0.0,
+    ]
+}
+
+/// Returns the color matrix of `feColorMatrix type="hueRotate"`, which rotates the hue by
+/// `degrees`.
+pub(crate) fn hue_rotate_matrix(degrees: f32) -> [f32; 20] {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    [
+        0.213 + cos * 0.787 - sin * 0.213, 0.715 - cos * 0.715 - sin * 0.715, 0.072 - cos * 0.072 + sin * 0.928, 0.0, 0.0,
+        0.213 - cos * 0.213 + sin * 0.143, 0.715 + cos * 0.285 + sin * 0.140, 0.072 - cos * 0.072 - sin * 0.283, 0.0, 0.0,
+        0.213 - cos * 0.213 - sin * 0.787, 0.715 - cos * 0.715 + sin * 0.715, 0.072 + cos * 0.928 + sin * 0.072, 0.0, 0.0,
+        0.0, 0.0, 0.0, 1.0, 0.0,
+    ]
+}
+
+impl Convert<Rect> for usvg::Rect {
+    fn convert(self) -> Rect {
+        lyon_geom::rect(self.x() as f32, self.y() as f32, self.width() as f32, self.height() as f32)
+    }
+}
+
+impl Convert<BlendMode> for usvg::filter::BlendMode {
+    fn convert(self) -> BlendMode {
+        use usvg::filter::BlendMode as Mode;
+
+        match self {
+            Mode::Normal => BlendMode::Normal,
+            Mode::Multiply => BlendMode::Multiply,
+            Mode::Screen => BlendMode::Screen,
+            Mode::Overlay => BlendMode::Overlay,
+            Mode::Darken => BlendMode::Darken,
+            Mode::Lighten => BlendMode::Lighten,
+            Mode::ColorDodge => BlendMode::ColorDodge,
+            Mode::ColorBurn => BlendMode::ColorBurn,
+            Mode::HardLight => BlendMode::HardLight,
+            Mode::SoftLight => BlendMode::SoftLight,
+            Mode::Difference => BlendMode::Difference,
+            Mode::Exclusion => BlendMode::Exclusion,
+            mode => {
+                warn!("Blend mode {:?} is not supported, using `normal` instead.", mode);
+                BlendMode::Normal
+            },
+        }
+    }
+}
+
+impl Convert<CompositeOperator> for usvg::filter::CompositeOperator {
+    fn convert(self) -> CompositeOperator {
+        use usvg::filter::CompositeOperator as Operator;
+
+        match self {
+            Operator::Over => CompositeOperator::Over,
+            Operator::In => CompositeOperator::In,
+            Operator::Out => CompositeOperator::Out,
+            Operator::Atop => CompositeOperator::Atop,
+            Operator::Xor => CompositeOperator::Xor,
+            Operator::Arithmetic { k1, k2, k3, k4 } => CompositeOperator::Arithmetic {
+                k1: k1 as f32,
+                k2: k2 as f32,
+                k3: k3 as f32,
+                k4: k4 as f32,
+            },
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Fonts that are used to convert the `text` elements of an SVG into paths.
+
+use std::{path::{Path, PathBuf}, sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard}};
+
+use bevy::log::{debug, warn};
+use usvg::fontdb;
+
+
+/// Fonts that are loaded when the [`SvgPlugin`](crate::prelude::SvgPlugin) is added.
+///
+/// Insert this resource before adding the plugin to change which fonts get loaded initially.
+/// Fonts can also be added later through the [`SvgFontDatabase`] resource.
+#[derive(Clone, Debug)]
+pub struct SvgFonts {
+    /// Whether the fonts that are installed on the system are loaded.
+    pub system_fonts: bool,
+    /// Directories whose fonts are all loaded, relative to the working directory.
+    pub font_dirs: Vec<PathBuf>,
+    /// Single font files to load, relative to the working directory.
+    pub font_files: Vec<PathBuf>,
+    /// Font family that is used when a `text` element doesn't specify one.
+    pub default_font_family: String,
+}
+
+impl Default for SvgFonts {
+    fn default() -> Self {
+        Self {
+            system_fonts: true,
+            font_dirs: vec![PathBuf::from("./assets")],
+            font_files: Vec::new(),
+            default_font_family: usvg::Options::default().font_family,
+        }
+    }
+}
+
+/// The font database that is used by the SVG asset loader, inserted by the
+/// [`SvgPlugin`](crate::prelude::SvgPlugin).
+///
+/// Fonts that are registered here are available to all SVG files that are loaded afterwards.
+/// Files that were already loaded need to be reloaded to pick them up.
+#[derive(Clone, Debug)]
+pub struct SvgFontDatabase {
+    fontdb: Arc<RwLock<fontdb::Database>>,
+    default_font_family: Arc<RwLock<String>>,
+}
+
+impl SvgFontDatabase {
+    /// Creates a new database containing the fonts described by `fonts`.
+    pub fn new(fonts: &SvgFonts) -> Self {
+        let mut fontdb = fontdb::Database::new();
+        if fonts.system_fonts {
+            fontdb.load_system_fonts();
+        }
+        let database = Self {
+            fontdb: Arc::new(RwLock::new(fontdb)),
+            default_font_family: Arc::new(RwLock::new(fonts.default_font_family.clone())),
+        };
+        for dir in &fonts.font_dirs {
+            database.load_fonts_dir(dir);
+        }
+        for file in &fonts.font_files {
+            if let Err(err) = database.load_font_file(file) {
+                warn!("Could not load font {}: {}", file.display(), err);
+            }
+        }
+        debug!("Loaded {} font faces for SVG text", database.len());
+
+        database
+    }
+
+    /// Loads a single font file, like a `.ttf` or `.otf`.
+    pub fn load_font_file<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        self.write().load_font_file(path)
+    }
+
+    /// Loads all fonts inside of `dir` and its subdirectories.
+    pub fn load_fonts_dir<P: AsRef<Path>>(&self, dir: P) {
+        self.write().load_fonts_dir(dir);
+    }
+
+    /// Loads a font from memory, e.g. one that was embedded with `include_bytes!`.
+    pub fn load_font_data(&self, data: Vec<u8>) {
+        self.write().load_font_data(data);
+    }
+
+    /// Sets the font family that is used when a `text` element doesn't specify one.
+    pub fn set_default_font_family(&self, family: impl Into<String>) {
+        *self.default_font_family.write().unwrap_or_else(|err| err.into_inner()) = family.into();
+    }
+
+    /// Returns the font family that is used when a `text` element doesn't specify one.
+    pub fn default_font_family(&self) -> String {
+        self.default_font_family.read().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Returns the number of loaded font faces.
+    pub fn len(&self) -> usize {
+        self.read().faces().len()
+    }
+
+    /// Returns `true` if no font face is loaded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, fontdb::Database> {
+        // A panic while loading a font doesn't leave the database in an invalid state.
+        self.fontdb.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, fontdb::Database> {
+        self.fontdb.write().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,72 @@
+//! A registry of icons that are loaded from a folder and looked up by name.
+
+use std::{collections::HashMap, path::Path};
+
+use bevy::asset::{AssetServer, AssetServerError, Handle};
+
+use crate::svg::Svg;
+
+
+/// The [`Svg`]s of icon packs, keyed by the stem of their file name, inserted by the
+/// [`SvgPlugin`](crate::prelude::SvgPlugin).
+///
+/// ```ignore
+/// # use bevy::prelude::*;
+/// # use bevy_svg::prelude::*;
+/// fn load_icons(asset_server: Res<AssetServer>, mut icons: ResMut<SvgIconRegistry>) {
+///     icons.load_folder(&asset_server, "icons").unwrap();
+/// }
+///
+/// fn show_icon(mut commands: Commands, icons: Res<SvgIconRegistry>) {
+///     if let Some(svg) = icons.get("settings") {
+///         commands.spawn_bundle(Svg2dBundle { svg: svg.clone(), ..Default::default() });
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SvgIconRegistry {
+    icons: HashMap<String, Handle<Svg>>,
+}
+
+impl SvgIconRegistry {
+    /// Loads every SVG in the folder at `path` inside of the asset folder and registers it under
+    /// the stem of its file name, so `icons/arrow-left.svg` is registered as `arrow-left`.
+    ///
+    /// Icons with the same name replace the ones that were registered before, other files in the
+    /// folder are ignored. Folders can only be loaded from the file system, not on `wasm32` or
+    /// Android.
+    pub fn load_folder<P: AsRef<Path>>(&mut self, asset_server: &AssetServer, path: P) -> Result<(), AssetServerError> {
+        for handle in asset_server.load_folder(path)? {
+            let path = match asset_server.get_handle_path(&handle) {
+                Some(path) => path,
+                None => continue,
+            };
+            let is_svg = path.path().extension()
+                .map_or(false, |extension| ["svg", "svgz", "svgmesh"].iter().any(|svg| extension.eq_ignore_ascii_case(svg)));
+            if let (true, Some(name)) = (is_svg, path.path().file_stem()) {
+                self.icons.insert(name.to_string_lossy().into_owned(), handle.typed());
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers `svg` under `name`, replacing the icon with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, svg: Handle<Svg>) {
+        self.icons.insert(name.into(), svg);
+    }
+
+    /// Returns the icon with the name `name`.
+    pub fn get(&self, name: &str) -> Option<&Handle<Svg>> {
+        self.icons.get(name)
+    }
+
+    /// Removes the icon with the name `name` and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<Handle<Svg>> {
+        self.icons.remove(name)
+    }
+
+    /// Iterates over the names and [`Svg`]s of all icons.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Handle<Svg>)> {
+        self.icons.iter().map(|(name, svg)| (name.as_str(), svg))
+    }
+}
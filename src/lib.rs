@@ -26,19 +26,69 @@
     clippy::cargo
 )]
 
+mod animation;
+#[cfg(feature = "2d")]
+mod atlas;
+mod baked;
+#[cfg(feature = "3d")]
+mod billboard;
+#[cfg(any(feature = "2d", feature = "3d"))]
+mod bounds;
+mod builder;
 mod bundle;
+#[cfg(feature = "rapier")]
+mod colliders;
+mod css_animation;
+mod diagnostics;
+mod filter;
+mod font;
+mod icons;
 mod loader;
+#[cfg(feature = "lottie")]
+mod lottie;
+#[cfg(feature = "2d")]
+mod material;
+mod morph;
+mod overrides;
+mod paint;
+mod path_entities;
+#[cfg(feature = "3d")]
+mod pbr;
+#[cfg(feature = "picking")]
+mod picking;
 mod plugin;
+mod progress;
+#[cfg(feature = "prototype_lyon")]
+mod prototype_lyon;
 mod render;
+#[cfg(feature = "2d")]
+mod sdf;
+#[cfg(feature = "serde")]
+mod serialization;
+mod source;
 mod svg;
+mod text;
+mod time_slicing;
+#[cfg(feature = "ui")]
+mod ui;
 
 /// Import this module as `use bevy_svg::prelude::*` to get convenient imports.
 pub mod prelude {
-    pub use crate::{plugin::SvgPlugin, svg::{Svg, Origin}};
+    pub use crate::{animation::SvgAnimationPlayer, baked::StaticSvg, builder::{SvgBuilder, SvgGroup, SvgShape}, diagnostics::{SkippedFeature, SvgLoadFailed, SvgLoadWarning}, font::{SvgFontDatabase, SvgFonts}, icons::SvgIconRegistry, loader::{SvgDpi, SvgLanguages, SvgLoadError, SvgLoadSettings, SvgParserOptions, SvgSettings, SvgStyleSheet}, morph::SvgMorph, overrides::{SvgColor, SvgColorMatrix, SvgCssVariables, SvgDashOffset, SvgDrawOn, SvgExtrusion, SvgFillRule, SvgGroupTransforms, SvgNineSlice, SvgNodeVisibility, SvgPaintOverrides, SvgTextOverrides, SvgTiling, SvgTint}, path_entities::{SvgGroupEntities, SvgGroupId, SvgPathEntities, SvgPathId}, plugin::{Stage, SvgMeshReady, SvgPlugin, SvgSystem}, progress::{SvgLoadProgress, SvgLoadStage, SvgProgress}, svg::{Svg, SvgBatching, SvgDoubleSided, SvgInstancing, SvgNode, SvgNodeKind, SvgScaleMode, SvgSize, SvgYAxis, Origin}, time_slicing::SvgTimeSlicing};
+    #[cfg(feature = "macros")]
+    pub use bevy_svg_macros::svg;
+    #[cfg(feature = "picking")]
+    pub use crate::picking::{SvgClicked, SvgHovered, SvgPick, SvgPickable};
+    #[cfg(feature = "rapier")]
+    pub use crate::colliders::{SvgColliderShape, SvgColliders};
     #[cfg(feature = "2d")]
-    pub use crate::bundle::Svg2dBundle;
+    pub use crate::{atlas::{SvgAtlasBuilder, SvgIconAtlas}, bundle::{Svg2dBundle, Svg2dBundleBuilder}, material::SvgMaterial2dPlugin, sdf::SvgSdf};
     #[cfg(feature = "3d")]
-    pub use crate::bundle::Svg3dBundle;
+    pub use crate::{billboard::SvgBillboard, bundle::{Svg3dBundle, Svg3dBundleBuilder}, pbr::SvgStandardMaterial};
+    #[cfg(feature = "serde")]
+    pub use crate::serialization::SerializedSvg;
+    #[cfg(feature = "ui")]
+    pub use crate::ui::{SvgUiBundle, SvgUiNode};
     pub use lyon_tessellation::{
         FillOptions, FillRule, LineCap, LineJoin, Orientation, StrokeOptions,
     };
@@ -1,13 +1,33 @@
+use std::{collections::{hash_map::DefaultHasher, HashMap}, hash::{Hash, Hasher}, path::{Component, Path, PathBuf}, sync::{Arc, Mutex, RwLock}};
+
 use anyhow;
-use bevy::{asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset}, log::debug, render::mesh::Mesh};
+use bevy::{asset::{AssetLoader, AssetPath, AssetServer, BoxedFuture, Handle, HandleId, LoadContext, LoadState, LoadedAsset}, log::{debug, warn}, math::Vec2, reflect::TypeUuid, render::mesh::Mesh, tasks::AsyncComputeTaskPool};
 use lyon_tessellation::{FillTessellator, StrokeTessellator};
 use thiserror::Error;
 
-use crate::{svg::Svg, Convert, render::tessellation};
+use crate::{baked, diagnostics::{LoadFailures, SkippedFeature}, progress::{SvgLoadProgress, SvgLoadStage, SvgProgress}, font::SvgFontDatabase, overrides::Overrides, source::{self, Source}, svg::{fragment_ids, Svg}, text::TextSource, time_slicing::SvgTimeSlicing, Convert, render::{tessellation, vertex_buffer::VertexBuffers}};
 
 
-#[derive(Default)]
-pub struct SvgAssetLoader;
+pub struct SvgAssetLoader {
+    pub(crate) fonts: SvgFontDatabase,
+    pub(crate) dpi: SvgDpi,
+    pub(crate) languages: SvgLanguages,
+    pub(crate) parser: SvgParserOptions,
+    pub(crate) settings: SvgLoadSettings,
+    pub(crate) time_slicing: SvgTimeSlicing,
+    /// Meshes of loaded files by the hash of their content, so that identical files share one.
+    pub(crate) shared_meshes: SharedMeshes,
+    /// How far the files that are loading are, shared with the app as a resource.
+    pub(crate) progress: SvgLoadProgress,
+    /// Task pool that files are parsed and tessellated on, if it exists.
+    pub(crate) compute_pool: Option<AsyncComputeTaskPool>,
+    /// Collects the errors of failed loads, which are sent as
+    /// [`SvgLoadFailed`](crate::prelude::SvgLoadFailed) events.
+    pub(crate) failures: LoadFailures,
+    /// Directory of the assets on the file system, relative `image` references of an SVG are
+    /// resolved from there.
+    pub(crate) asset_root: Option<PathBuf>,
+}
 
 impl AssetLoader for SvgAssetLoader {
     fn load<'a>(
@@ -16,67 +36,576 @@ impl AssetLoader for SvgAssetLoader {
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut opts = usvg::Options::default();
-            opts.fontdb.load_system_fonts();
-            opts.fontdb.load_fonts_dir("./assets");
-
-            debug!("Parsing SVG: {} ...", load_context.path().display());
-            let svg_tree = usvg::Tree::from_data(&bytes, &opts.to_ref()).map_err(|err| {
-                FileSvgError {
-                    error: err.into(),
-                    path: format!("{}", load_context.path().display()),
-                }
-            })?;
-
-            let mut svg = Svg::from_tree(svg_tree);
-            let name = &load_context.path().file_name().ok_or_else(||
-                    FileSvgError {
-                        error: SvgError::InvalidFileName(load_context.path().display().to_string()),
-                        path: format!("{}", load_context.path().display()),
-                    }
-                )?.to_string_lossy();
-            svg.name = name.to_string();
-            debug!("Parsing SVG: {} ... Done", load_context.path().display());
-
-            debug!("Tessellating SVG: {} ...", load_context.path().display());
-            let buffer = tessellation::generate_buffer(&svg, &mut FillTessellator::new(), &mut StrokeTessellator::new());
-            debug!("Tessellating SVG: {} ... Done", load_context.path().display());
-            let mesh: Mesh = buffer.convert();
-            let mesh_handle = load_context.set_labeled_asset("mesh", LoadedAsset::new(mesh));
-            svg.mesh = mesh_handle;
-
-            load_context.set_default_asset(LoadedAsset::new(svg));
+            self.load_svg(bytes, load_context).await.map_err(|error| {
+                self.progress.remove(load_context.path());
+                self.failures.push(load_context.path(), error.clone());
+                FileSvgError { error, path: format!("{}", load_context.path().display()) }.into()
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg", "svgz"]
+    }
+}
+
+impl SvgAssetLoader {
+    async fn load_svg(&self, bytes: &[u8], load_context: &mut LoadContext<'_>) -> Result<(), SvgLoadError> {
+        if self.load_baked(bytes, load_context).await {
+            return Ok(());
+        }
+
+        debug!("Parsing SVG: {} ...", load_context.path().display());
+        self.progress.set(load_context.path(), SvgProgress { stage: SvgLoadStage::Parsing, tessellated_paths: 0, total_paths: 0 });
+        let text = source::decode(bytes)?;
+
+        let mut dependencies = Vec::new();
+        let mut documents = HashMap::new();
+        for file in source::external_references(&text) {
+            if file.contains(':') {
+                warn!("Reference to `{}` of {} is not a path to an asset, skipping it.", file, load_context.path().display());
+                continue;
+            }
+            let path = resolve_path(load_context.path(), &file);
+            match load_context.read_asset_bytes(&path).await.map_err(anyhow::Error::from).and_then(|bytes| Ok(source::decode(&bytes)?)) {
+                Ok(document) => {
+                    documents.insert(file, document);
+                    dependencies.push(AssetPath::new(path, None));
+                },
+                Err(err) => warn!("Could not load `{}`, which {} references: {}", file, load_context.path().display(), err),
+            }
+        }
+        let text = source::inline_external_references(text, &documents);
+
+        let mut style_sheets = Vec::new();
+        for href in source::style_sheet_links(&text) {
+            if href.contains(':') {
+                warn!("Style sheet `{}` of {} is not a path to an asset, skipping it.", href, load_context.path().display());
+                continue;
+            }
+            let path = resolve_path(load_context.path(), &href);
+            match load_context.read_asset_bytes(&path).await {
+                Ok(bytes) => {
+                    style_sheets.push(String::from_utf8_lossy(&bytes).into_owned());
+                    dependencies.push(AssetPath::new(path, None));
+                },
+                Err(err) => warn!("Could not load style sheet `{}` of {}: {}", href, load_context.path().display(), err),
+            }
+        }
+
+        let name = load_context.path().file_name()
+            .ok_or_else(|| SvgLoadError::InvalidFileName(load_context.path().display().to_string()))?
+            .to_string_lossy().into_owned();
+        let settings = self.settings.get(load_context.path()).unwrap_or_default();
+        let handle_id = HandleId::from(AssetPath::new_ref(load_context.path(), None));
+        // The mesh of this file is replaced, so files with the old content can't share it anymore.
+        self.shared_meshes.remove_owner(handle_id);
+        // Referenced files may change independently of this one, so only self-contained files
+        // are shared.
+        let content_hash = dependencies.is_empty().then(|| {
+            let mut hasher = DefaultHasher::new();
+            bytes.hash(&mut hasher);
+            settings.tolerance.map(f32::to_bits).hash(&mut hasher);
+            settings.dpi.unwrap_or(self.dpi).0.to_bits().hash(&mut hasher);
+            settings.default_size.map(|size| (size.x.to_bits(), size.y.to_bits())).hash(&mut hasher);
+            hasher.finish()
+        });
+        let resources_dir = self.parser.resources_dir.clone().or_else(|| self.asset_root.as_ref()
+            .map(|root| root.join(load_context.path().parent().unwrap_or_else(|| "".as_ref()))));
+        let (fonts, dpi, languages) = (self.fonts.clone(), settings.dpi.unwrap_or(self.dpi), self.languages.clone());
+        let parser = self.parser.clone();
+        let time_sliced_above = self.time_slicing.min_paths;
+        let (progress, path) = (self.progress.clone(), load_context.path().to_owned());
+        let parse_task = async move {
+            let report = |current: SvgProgress| progress.set(&path, current);
+            let options = ParseOptions {
+                fonts: &fonts,
+                dpi,
+                languages: &languages,
+                parser: &parser,
+                tolerance: settings.tolerance,
+                default_size: settings.default_size,
+                time_sliced_above,
+                fragments: true,
+                resources_dir,
+                progress: Some(&report),
+            };
+            parse(text, name, &style_sheets, &options)
+        };
+        // Parsing and tessellating complex files takes a while, which would block other
+        // assets from loading on the IO threads.
+        let result = match &self.compute_pool {
+            Some(compute_pool) => compute_pool.spawn(parse_task).await,
+            None => parse_task.await,
+        };
+        let (mut svg, mesh, fragments) = result?;
+        debug!("Parsing SVG: {} ... Done", load_context.path().display());
+        // Time-sliced files are still being tessellated.
+        if !svg.time_sliced {
+            self.progress.remove(load_context.path());
+        }
+        for Fragment { id, mut svg, mesh } in fragments {
+            // The label is taken by the mesh of the whole file.
+            if id == "mesh" {
+                continue;
+            }
+            svg.mesh = load_context.set_labeled_asset(&format!("{}/mesh", id), LoadedAsset::new(mesh));
+            load_context.set_labeled_asset(&id, LoadedAsset::new(svg));
+        }
+        // Time-sliced meshes are filled in per file, so they can't be shared.
+        let shared_mesh = content_hash.filter(|_| !svg.time_sliced)
+            .and_then(|hash| self.shared_meshes.get_or_insert(hash, handle_id, load_context.path()));
+        svg.dependencies = dependencies.clone();
+        match shared_mesh {
+            Some(shared) => {
+                debug!("{} has the same content as {}, sharing its mesh.", load_context.path().display(), shared.path().display());
+                svg.mesh = load_context.get_handle(shared.clone());
+                // Files that share the mesh are reloaded when its file changes.
+                svg.dependencies.push(AssetPath::new(shared.path().to_owned(), None));
+            },
+            None => svg.mesh = load_context.set_labeled_asset("mesh", LoadedAsset::new(mesh)),
+        }
+
+        load_context.set_default_asset(LoadedAsset::new(svg).with_dependencies(dependencies));
+
+        Ok(())
+    }
+}
+
+impl SvgAssetLoader {
+    /// Loads the baked `.svgmesh` file next to the SVG file instead of it, if there is one that
+    /// was baked from the same `bytes`. Returns whether it was loaded.
+    ///
+    /// Files with [`SvgSettings`] are always parsed, because their settings may differ from the
+    /// ones that the file was baked with.
+    async fn load_baked(&self, bytes: &[u8], load_context: &mut LoadContext<'_>) -> bool {
+        if self.settings.get(load_context.path()).is_some() {
+            return false;
+        }
+        let baked_path = load_context.path().with_extension("svgmesh");
+        let baked = match load_context.read_asset_bytes(&baked_path).await {
+            Ok(baked) if baked::is_baked_from(&baked, bytes) => baked,
+            _ => return false,
+        };
+        match baked::decode(&baked) {
+            Ok((mut svg, mesh)) => {
+                debug!("Loading SVG {} from {}", load_context.path().display(), baked_path.display());
+                svg.name = load_context.path().file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                svg.mesh = load_context.set_labeled_asset("mesh", LoadedAsset::new(mesh));
+                load_context.set_default_asset(LoadedAsset::new(svg));
+                true
+            },
+            Err(err) => {
+                warn!("Could not load {}, parsing {} instead: {}", baked_path.display(), load_context.path().display(), err);
+                false
+            },
+        }
+    }
+}
+
+/// The meshes of loaded SVG files by the hash of their content and settings, shared between the
+/// [`SvgAssetLoader`] and the [`SvgPlugin`](crate::prelude::SvgPlugin).
+///
+/// Files with the same content use the mesh of the first one that was loaded, instead of
+/// uploading the same buffers again.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SharedMeshes(Arc<Mutex<HashMap<u64, (HandleId, AssetPath<'static>)>>>);
+
+impl SharedMeshes {
+    /// Returns the path of the mesh that files with the content `hash` share, or registers the
+    /// mesh of the file at `path` if there is none.
+    fn get_or_insert(&self, hash: u64, owner: HandleId, path: &Path) -> Option<AssetPath<'static>> {
+        let mut meshes = self.0.lock().unwrap();
+        match meshes.get(&hash) {
+            Some((_, mesh)) => Some(mesh.clone()),
+            None => {
+                meshes.insert(hash, (owner, AssetPath::new(path.to_owned(), Some("mesh".to_owned()))));
+                None
+            },
+        }
+    }
+
+    /// Stops sharing the mesh of the file `owner`, because it changes or is unloaded.
+    pub(crate) fn remove_owner(&self, owner: HandleId) {
+        self.0.lock().unwrap().retain(|_, (id, _)| *id != owner);
+    }
+}
+
+/// Everything besides the document itself that decides how an SVG is parsed.
+pub(crate) struct ParseOptions<'a> {
+    pub(crate) fonts: &'a SvgFontDatabase,
+    pub(crate) dpi: SvgDpi,
+    pub(crate) languages: &'a SvgLanguages,
+    pub(crate) parser: &'a SvgParserOptions,
+    pub(crate) tolerance: Option<f32>,
+    pub(crate) default_size: Option<Vec2>,
+    /// Files with more paths are tessellated over several frames instead of right away.
+    pub(crate) time_sliced_above: Option<usize>,
+    /// Whether the drawn elements with an ID are also turned into [`Fragment`]s.
+    pub(crate) fragments: bool,
+    /// Directory that relative `image` references are resolved from.
+    pub(crate) resources_dir: Option<PathBuf>,
+    /// Reports how far the file is tessellated.
+    pub(crate) progress: Option<&'a dyn Fn(SvgProgress)>,
+}
+
+/// An element of a file that is loaded on its own, as `file.svg#id`.
+pub(crate) struct Fragment {
+    pub(crate) id: String,
+    pub(crate) svg: Svg,
+    pub(crate) mesh: Mesh,
+}
+
+/// Parses the document `text` with the external `style_sheets` into an [`Svg`] called `name`,
+/// and tessellates its mesh.
+pub(crate) fn parse(text: String, name: String, style_sheets: &[String], options: &ParseOptions<'_>) -> Result<(Svg, Mesh, Vec<Fragment>), SvgLoadError> {
+    let texts = TextSource::new(&text, style_sheets, options).map(Arc::new);
+    let (mut svg, svg_tree, source) = parse_tree(text, style_sheets, options)?;
+    if options.parser.strict && !svg.skipped_features.is_empty() {
+        return Err(SvgLoadError::UnsupportedFeatures(svg.skipped_features));
+    }
+    svg.name = name;
+    svg.tolerance = options.tolerance;
+    svg.texts = texts;
+    if options.time_sliced_above.map_or(false, |min_paths| svg.paths.len() > min_paths) {
+        debug!("Tessellating SVG: {} over several frames", svg.name);
+        svg.time_sliced = true;
+        if let Some(report) = options.progress {
+            report(SvgProgress { stage: SvgLoadStage::Tessellating, tessellated_paths: 0, total_paths: svg.paths.len() });
+        }
+        return Ok((svg, VertexBuffers::new().convert(), Vec::new()));
+    }
+
+    let total_paths = svg.paths.len();
+    let report_tessellated = options.progress.map(|report| move |tessellated_paths: usize| {
+        report(SvgProgress { stage: SvgLoadStage::Tessellating, tessellated_paths, total_paths });
+    });
+    if let Some(report) = &report_tessellated {
+        report(0);
+    }
+    let buffer = tessellation::try_generate_buffer(
+        &svg,
+        &Overrides::default(),
+        &mut FillTessellator::new(),
+        &mut StrokeTessellator::new(),
+        report_tessellated.as_ref().map(|report| report as &dyn Fn(usize)),
+    )?;
+    let fragments = match options.fragments {
+        true => fragment_ids(&svg_tree).iter()
+            .filter_map(|id| fragment(&svg_tree, &source, id, id, None, &svg))
+            .chain(source.symbols.iter().filter_map(|symbol| {
+                let frame = symbol.size.and_then(|(width, height)| usvg::Size::new(width, height));
+                fragment(&svg_tree, &source, &symbol.use_id, &symbol.id, frame, &svg)
+            }))
+            .collect(),
+        false => Vec::new(),
+    };
+    Ok((svg, buffer.convert(), fragments))
+}
+
+/// Parses the document `text` with the external `style_sheets` into an [`Svg`], without
+/// tessellating it. Also returns the tree of `usvg` and the [`Source`] that it was collected from.
+pub(crate) fn parse_tree(text: String, style_sheets: &[String], options: &ParseOptions<'_>) -> Result<(Svg, usvg::Tree, Source), SvgLoadError> {
+    let opts = usvg::Options {
+        // Named groups may carry properties that are only known to `source`.
+        keep_named_groups: options.parser.keep_named_groups,
+        font_family: options.fonts.default_font_family(),
+        font_size: f64::from(options.parser.font_size),
+        dpi: f64::from(options.dpi.0),
+        languages: options.languages.0.clone(),
+        resources_dir: options.resources_dir.clone(),
+        ..Default::default()
+    };
+    let opts = match options.default_size.and_then(|size| usvg::Size::new(f64::from(size.x), f64::from(size.y))) {
+        Some(default_size) => usvg::Options { default_size, ..opts },
+        None => opts,
+    };
+    // The fonts are shared between all loads, instead of loading them for every file.
+    let fontdb = options.fonts.read();
+    let opts = usvg::OptionsRef {
+        fontdb: &fontdb,
+        ..opts.to_ref()
+    };
+
+    let (text, source) = source::preprocess(text, style_sheets, options.dpi.0, &options.languages.0);
+    let svg_tree = usvg::Tree::from_str(&text, &opts)?;
+    let svg = Svg::from_tree(&svg_tree, &source);
+    Ok((svg, svg_tree, source))
+}
+
+/// Creates the [`Fragment`] called `label` of the element with the ID `id` of `svg`. It is sized to
+/// the `frame` at the origin of the document, or to the bounds of the element without one.
+/// Returns `None` if the element draws nothing.
+fn fragment(tree: &usvg::Tree, source: &Source, id: &str, label: &str, frame: Option<usvg::Size>, svg: &Svg) -> Option<Fragment> {
+    let (mut fill_tess, mut stroke_tess) = (FillTessellator::new(), StrokeTessellator::new());
+    let (offset, size) = match frame {
+        Some(frame) => {
+            let view_box = tree.svg_node().view_box;
+            let transform = usvg::utils::view_box_to_transform(view_box.rect, view_box.aspect, tree.svg_node().size);
+            let (x, y) = transform.apply(0.0, 0.0);
+            let (right, bottom) = transform.apply(frame.width(), frame.height());
+            (Vec2::new(-x as f32, -y as f32), Vec2::new((right - x) as f32, (bottom - y) as f32))
+        },
+        None => {
+            // The bounds are only known after tessellating the element once where it is.
+            let mut fragment = Svg::fragment_from_tree(tree, source, id, Vec2::ZERO, svg.size)?;
+            fragment.tolerance = svg.tolerance;
+            let bounds = tessellation::bounds(&tessellation::generate_buffer(&fragment, &Overrides::default(), &mut fill_tess, &mut stroke_tess))?;
+            // The y-axis of the buffer is flipped.
+            (Vec2::new(-bounds.min_x(), bounds.max_y()), Vec2::new(bounds.width(), bounds.height()))
+        },
+    };
+    let mut fragment = Svg::fragment_from_tree(tree, source, id, offset, size)?;
+    fragment.name = format!("{}#{}", svg.name, label);
+    fragment.tolerance = svg.tolerance;
+    let mesh = tessellation::generate_buffer(&fragment, &Overrides::default(), &mut fill_tess, &mut stroke_tess).convert();
+
+    Some(Fragment { id: label.to_owned(), svg: fragment, mesh })
+}
 
+/// Resolves `href`, which is referenced by the asset at `path`, into the path of another asset.
+/// Absolute references start at the root of the asset folder.
+fn resolve_path(path: &Path, href: &str) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    let base = if href.starts_with('/') { Path::new("") } else { path.parent().unwrap_or_else(|| "".as_ref()) };
+    for component in base.join(href).components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::ParentDir => { resolved.pop(); },
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {},
+        }
+    }
+    resolved
+}
+
+/// The resolution that physical lengths of SVGs, like `mm`, `in` or `pt`, are converted with,
+/// in pixels per inch.
+///
+/// Insert this resource before adding the [`SvgPlugin`](crate::prelude::SvgPlugin) to change it,
+/// it defaults to the CSS resolution of 96 pixels per inch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgDpi(pub f32);
+
+impl Default for SvgDpi {
+    fn default() -> Self {
+        Self(usvg::Options::default().dpi as f32)
+    }
+}
+
+/// Options of the parser that SVG files are read with.
+///
+/// Insert this resource before adding the [`SvgPlugin`](crate::prelude::SvgPlugin) to change them.
+/// The font family that texts use by default is set with [`SvgFonts`](crate::prelude::SvgFonts).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgParserOptions {
+    /// Whether groups with an ID are kept, even if they have no effect on how their content is
+    /// drawn. Turning it off makes tessellating a bit faster, but groups that are merged into
+    /// their content can't be animated, hidden at runtime or loaded as fragments. Defaults to
+    /// `true`.
+    pub keep_named_groups: bool,
+    /// The font size of texts that don't specify one, in pixels. Defaults to `12`.
+    pub font_size: f32,
+    /// The directory that relative references of `image` elements are resolved from, relative to
+    /// the working directory. Defaults to `None`, which resolves them from the directory of the
+    /// SVG inside of the asset folder.
+    pub resources_dir: Option<PathBuf>,
+    /// Whether files with content that can't be drawn fail to load with
+    /// [`SvgLoadError::UnsupportedFeatures`], instead of loading without it. This finds files
+    /// that need to be fixed before shipping. Defaults to `false`.
+    pub strict: bool,
+}
+
+impl Default for SvgParserOptions {
+    fn default() -> Self {
+        Self {
+            keep_named_groups: true,
+            font_size: usvg::Options::default().font_size as f32,
+            resources_dir: None,
+            strict: false,
+        }
+    }
+}
+
+/// The preferred languages of the user, in order of preference, like `en` or `de-CH`.
+///
+/// Children of a `switch` element are drawn if their `systemLanguage` contains one of these
+/// languages, or a region of one, so `en` matches `en-US`. Insert this resource before adding the
+/// [`SvgPlugin`](crate::prelude::SvgPlugin) to change it, it defaults to English.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgLanguages(pub Vec<String>);
+
+impl Default for SvgLanguages {
+    fn default() -> Self {
+        Self(usvg::Options::default().languages)
+    }
+}
+
+/// Settings of a single SVG file, which replace the global ones when it is loaded. They are
+/// registered with [`SvgLoadSettings`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SvgSettings {
+    /// The maximum distance between curves and the line segments they are approximated with, in
+    /// user units of the file. Small icons that are scaled up need a lower tolerance than large
+    /// maps. Defaults to `0.001` for fills and `0.01` for strokes.
+    pub tolerance: Option<f32>,
+    /// The resolution that physical lengths are converted with, instead of the [`SvgDpi`] resource.
+    pub dpi: Option<SvgDpi>,
+    /// The size of SVGs without a `viewBox` whose `width` or `height` are relative, defaults to
+    /// 100x100.
+    pub default_size: Option<Vec2>,
+}
+
+/// The [`SvgSettings`] of individual files, keyed by their path inside of the asset folder,
+/// inserted by the [`SvgPlugin`](crate::prelude::SvgPlugin).
+///
+/// Settings need to be registered before a file is loaded, files that were already loaded need to
+/// be reloaded to pick them up. [`SvgLoadSettings::load`] does both.
+#[derive(Clone, Debug, Default)]
+pub struct SvgLoadSettings {
+    settings: Arc<RwLock<HashMap<PathBuf, SvgSettings>>>,
+}
+
+impl SvgLoadSettings {
+    /// Registers the `settings` of the file at `path`, and returns the settings that were
+    /// registered before.
+    pub fn insert<P: Into<PathBuf>>(&self, path: P, settings: SvgSettings) -> Option<SvgSettings> {
+        self.settings.write().unwrap().insert(path.into(), settings)
+    }
+
+    /// Returns the settings of the file at `path`.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<SvgSettings> {
+        self.settings.read().unwrap().get(path.as_ref()).copied()
+    }
+
+    /// Removes the settings of the file at `path`, it uses the global settings when it is loaded
+    /// the next time.
+    pub fn remove<P: AsRef<Path>>(&self, path: P) -> Option<SvgSettings> {
+        self.settings.write().unwrap().remove(path.as_ref())
+    }
+
+    /// Loads the file at `path` with `settings`. If the file was already loaded with other
+    /// settings, it is reloaded.
+    pub fn load<P: AsRef<Path>>(&self, asset_server: &AssetServer, path: P, settings: SvgSettings) -> Handle<Svg> {
+        let path = path.as_ref();
+        let changed = self.insert(path, settings) != Some(settings);
+        let handle = asset_server.load(path);
+        if changed && asset_server.get_load_state(&handle) == LoadState::Loaded {
+            asset_server.reload_asset(path);
+        }
+        handle
+    }
+}
+
+/// A CSS file that is referenced by an SVG as external style sheet.
+///
+/// The style sheet is applied while the SVG is loaded, this asset only exists so that
+/// the file can be tracked as a dependency of the SVG.
+#[derive(Debug, TypeUuid)]
+#[uuid = "6e5a7d3c-2f0b-4c8e-9a61-d4b2e8f17c35"]
+pub struct SvgStyleSheet {
+    /// The content of the file.
+    pub css: String,
+}
+
+/// Loads `.css` files as [`SvgStyleSheet`]s.
+pub struct SvgStyleSheetLoader;
+
+impl AssetLoader for SvgStyleSheetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let css = String::from_utf8_lossy(bytes).into_owned();
+            load_context.set_default_asset(LoadedAsset::new(SvgStyleSheet { css }));
             Ok(())
         })
     }
 
     fn extensions(&self) -> &[&str] {
-        &["svg", "svgz"]
+        &["css"]
     }
 }
 
-/// An error that occurs when loading a texture
-#[derive(Error, Debug)]
-pub enum SvgError {
-    #[error("invalid file name")]
+/// An error that occurs when loading an SVG.
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum SvgLoadError {
+    /// The path of the file has no file name.
+    #[error("invalid file name `{0}`")]
     InvalidFileName(String),
-    #[error("failed to load an SVG: {0}")]
-    SvgError(#[from] usvg::Error),
+    /// The file is neither UTF-8 text nor gzip compressed UTF-8 text.
+    #[error("the file is not valid UTF-8")]
+    NotUtf8,
+    /// The file starts like a gzip file, but can't be decompressed.
+    #[error("the file is not valid gzip")]
+    MalformedGzip,
+    /// The document is not well-formed XML.
+    #[error("invalid XML: {message}")]
+    Xml {
+        /// Description of the error, including its position.
+        message: String,
+        /// The line of the error, starting at 1.
+        line: u32,
+        /// The column of the error, starting at 1.
+        column: u32,
+    },
+    /// The `width`, `height` or `viewBox` of the root element is invalid.
+    #[error("the SVG has an invalid size")]
+    InvalidSize,
+    /// The document has more elements than `usvg` allows, which is meant to stop billion laughs
+    /// attacks.
+    #[error("the SVG has too many elements")]
+    TooManyElements,
+    /// A path couldn't be tessellated.
+    #[error("failed to tessellate {}: {message}", .element_id.as_deref().map_or_else(|| "a path without ID".to_owned(), |id| format!("`{}`", id)))]
+    Tessellation {
+        /// The ID of the shape that the path belongs to, if it has one.
+        element_id: Option<String>,
+        /// The error of the tessellator.
+        message: String,
+    },
+    /// The file has content that can't be drawn, only with [`SvgParserOptions::strict`].
+    #[error("unsupported features: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    UnsupportedFeatures(Vec<SkippedFeature>),
+    /// A baked `.svgmesh` file is truncated or was baked by an incompatible version.
+    #[error("invalid baked SVG mesh: {0}")]
+    InvalidBakedMesh(&'static str),
+    /// A [`SerializedSvg`](crate::prelude::SerializedSvg) references content that it doesn't
+    /// contain or was serialized by an incompatible version.
+    #[cfg(feature = "serde")]
+    #[error("invalid serialized SVG: {0}")]
+    InvalidSerializedSvg(&'static str),
+    /// A Lottie animation is not valid JSON or misses required fields.
+    #[cfg(feature = "lottie")]
+    #[error("invalid Lottie animation: {0}")]
+    InvalidLottie(String),
+}
+
+impl From<usvg::Error> for SvgLoadError {
+    fn from(error: usvg::Error) -> Self {
+        match error {
+            usvg::Error::NotAnUtf8Str => SvgLoadError::NotUtf8,
+            usvg::Error::MalformedGZip => SvgLoadError::MalformedGzip,
+            usvg::Error::ElementsLimitReached => SvgLoadError::TooManyElements,
+            usvg::Error::InvalidSize => SvgLoadError::InvalidSize,
+            usvg::Error::ParsingFailed(error) => SvgLoadError::Xml {
+                message: error.to_string(),
+                line: error.pos().row,
+                column: error.pos().col,
+            },
+        }
+    }
 }
 
-/// An error that occurs when loading a texture from a file.
+/// An error that occurs when loading an SVG from a file.
 #[derive(Error, Debug)]
 pub struct FileSvgError {
-    error: SvgError,
-    path: String,
+    pub(crate) error: SvgLoadError,
+    pub(crate) path: String,
 }
 impl std::fmt::Display for FileSvgError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(
-            f,
-            "Error reading SVG file {}: {}, this is an error in `bevy_svg`.",
-            self.path, self.error
-        )
+        write!(f, "Error reading SVG file {}: {}", self.path, self.error)
     }
 }
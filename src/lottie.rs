@@ -0,0 +1,566 @@
+//! Lottie animations, which are converted into an SVG document with SMIL animations while they
+//! are loaded, so that they are tessellated like any other [`Svg`](crate::svg::Svg) and played by
+//! a [`SvgAnimationPlayer`](crate::prelude::SvgAnimationPlayer).
+//!
+//! Supported are shape, solid and null layers with their parents, groups, paths, rectangles,
+//! ellipses, solid fills and strokes, and animated positions, rotations, scales, anchors,
+//! opacities and colors. Paints apply to the shapes of their own group, and to nested groups that
+//! have none of their own. Animated shapes show their first keyframe, because the geometry can't
+//! be animated. Precompositions, images, texts, gradients, masks, mattes and other shape
+//! modifiers are skipped.
+
+use std::fmt::Write;
+
+use anyhow;
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    log::{debug, warn},
+};
+use serde_json::Value;
+
+use crate::{
+    diagnostics::LoadFailures,
+    font::SvgFontDatabase,
+    loader::{self, FileSvgError, ParseOptions, SvgDpi, SvgLanguages, SvgLoadError, SvgParserOptions},
+};
+
+
+/// Easing of keyframes that don't specify one.
+const LINEAR: [f64; 4] = [0.0, 0.0, 1.0, 1.0];
+/// How long before the next keyframe the value of a hold keyframe jumps, as fraction of the
+/// whole animation.
+const HOLD_GAP: f64 = 1e-6;
+
+/// Loads Lottie animations, `.lottie.json` files, as [`Svg`](crate::svg::Svg)s.
+pub struct LottieLoader {
+    pub(crate) fonts: SvgFontDatabase,
+    pub(crate) parser: SvgParserOptions,
+    /// Collects the errors of failed loads, which are sent as
+    /// [`SvgLoadFailed`](crate::prelude::SvgLoadFailed) events.
+    pub(crate) failures: LoadFailures,
+}
+
+impl AssetLoader for LottieLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            self.load_lottie(bytes, load_context).map_err(|error| {
+                self.failures.push(load_context.path(), error.clone());
+                FileSvgError { error, path: format!("{}", load_context.path().display()) }.into()
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lottie.json"]
+    }
+}
+
+impl LottieLoader {
+    fn load_lottie(&self, bytes: &[u8], load_context: &mut LoadContext<'_>) -> Result<(), SvgLoadError> {
+        debug!("Converting Lottie animation: {} ...", load_context.path().display());
+        let animation: Value = serde_json::from_slice(bytes)
+            .map_err(|error| SvgLoadError::InvalidLottie(error.to_string()))?;
+        let text = to_svg(&animation)?;
+
+        let name = load_context.path().file_name()
+            .ok_or_else(|| SvgLoadError::InvalidFileName(load_context.path().display().to_string()))?
+            .to_string_lossy().into_owned();
+        // Lottie has neither physical units nor `switch` elements.
+        let options = ParseOptions {
+            fonts: &self.fonts,
+            dpi: SvgDpi::default(),
+            languages: &SvgLanguages::default(),
+            parser: &self.parser,
+            tolerance: None,
+            default_size: None,
+            time_sliced_above: None,
+            fragments: false,
+            resources_dir: None,
+            progress: None,
+        };
+        let (mut svg, mesh, _) = loader::parse(text, name, &[], &options)?;
+        svg.mesh = load_context.set_labeled_asset("mesh", LoadedAsset::new(mesh));
+        load_context.set_default_asset(LoadedAsset::new(svg));
+        debug!("Converting Lottie animation: {} ... Done", load_context.path().display());
+
+        Ok(())
+    }
+}
+
+/// The frames of the whole animation, which all animations are played in a loop over.
+struct Timeline {
+    start: f64,
+    end: f64,
+    frame_rate: f64,
+}
+
+impl Timeline {
+    /// Returns the duration of the animation in seconds.
+    fn duration(&self) -> f64 {
+        (self.end - self.start) / self.frame_rate
+    }
+
+    /// Returns how far into the animation `frame` is, from `0.0` to `1.0`.
+    fn key_time(&self, frame: f64) -> f64 {
+        ((frame - self.start) / (self.end - self.start)).clamp(0.0, 1.0)
+    }
+}
+
+/// A keyframe of an animated property.
+struct Keyframe {
+    time: f64,
+    value: Vec<f64>,
+    /// Easing towards the next keyframe as `x1 y1 x2 y2`, `None` for hold keyframes that keep
+    /// their value until the next one.
+    easing: Option<[f64; 4]>,
+}
+
+/// Converts the Lottie animation `animation` into an SVG document with SMIL animations.
+fn to_svg(animation: &Value) -> Result<String, SvgLoadError> {
+    let invalid = |message: &str| SvgLoadError::InvalidLottie(message.to_owned());
+    let width = animation["w"].as_f64().ok_or_else(|| invalid("missing width `w`"))?;
+    let height = animation["h"].as_f64().ok_or_else(|| invalid("missing height `h`"))?;
+    let timeline = Timeline {
+        start: animation["ip"].as_f64().unwrap_or(0.0),
+        end: animation["op"].as_f64().ok_or_else(|| invalid("missing out point `op`"))?,
+        frame_rate: animation["fr"].as_f64().filter(|rate| *rate > 0.0).ok_or_else(|| invalid("missing frame rate `fr`"))?,
+    };
+    if timeline.end <= timeline.start {
+        return Err(invalid("the out point `op` is not after the in point `ip`"));
+    }
+    let layers = animation["layers"].as_array().ok_or_else(|| invalid("missing `layers`"))?;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        width, height,
+    );
+    // The first layer is drawn on top.
+    for layer in layers.iter().rev() {
+        write_layer(&mut svg, layer, layers, &timeline);
+    }
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+/// Writes a layer with the transforms of its parents.
+fn write_layer(out: &mut String, layer: &Value, layers: &[Value], timeline: &Timeline) {
+    let name = layer["nm"].as_str().unwrap_or_default();
+    if layer["hd"].as_bool() == Some(true) {
+        return;
+    }
+    if layer["td"].as_i64() == Some(1) {
+        warn!("Lottie layer `{}` is a matte, which is not supported, skipping it.", name);
+        return;
+    }
+    let kind = layer["ty"].as_i64();
+    match kind {
+        Some(1) | Some(4) => {},
+        // Null layers only move their children.
+        Some(3) => return,
+        _ => {
+            warn!("Lottie layer `{}` of type {} is not supported, skipping it.", name, kind.unwrap_or(-1));
+            return;
+        },
+    }
+
+    let mut opened = open_visibility(out, layer, timeline) as usize;
+    let mut parents = Vec::new();
+    let mut parent = &layer["parent"];
+    // The length check stops parents that refer to each other.
+    while let (Some(index), true) = (parent.as_i64(), parents.len() < layers.len()) {
+        match layers.iter().find(|other| other["ind"].as_i64() == Some(index)) {
+            Some(other) => {
+                parents.push(other);
+                parent = &other["parent"];
+            },
+            None => break,
+        }
+    }
+    for parent in parents.iter().rev() {
+        opened += open_transform(out, &parent["ks"], timeline, false);
+    }
+    // `ln` is the ID that the layer gets in SVG.
+    match layer["ln"].as_str().filter(|id| !id.is_empty() && id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')) {
+        Some(id) => write!(out, r#"<g id="{}">"#, id).unwrap(),
+        None => out.push_str("<g>"),
+    }
+    opened += 1;
+    opened += open_transform(out, &layer["ks"], timeline, true);
+
+    match kind {
+        Some(1) => {
+            let color = layer["sc"].as_str()
+                .filter(|color| color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit()))
+                .unwrap_or("#000000");
+            write!(
+                out,
+                r#"<rect width="{}" height="{}" fill="{}"/>"#,
+                layer["sw"].as_f64().unwrap_or(0.0), layer["sh"].as_f64().unwrap_or(0.0), color,
+            ).unwrap();
+        },
+        _ => {
+            let shapes = layer["shapes"].as_array().map_or(&[][..], Vec::as_slice);
+            write_shapes(out, shapes, &[], timeline);
+        },
+    }
+    for _ in 0..opened {
+        out.push_str("</g>");
+    }
+}
+
+/// Opens a group that hides the layer outside of its in and out point, if they differ from
+/// the ones of the animation. Returns whether a group was opened.
+fn open_visibility(out: &mut String, layer: &Value, timeline: &Timeline) -> bool {
+    let in_point = timeline.key_time(layer["ip"].as_f64().unwrap_or(timeline.start));
+    let out_point = timeline.key_time(layer["op"].as_f64().unwrap_or(timeline.end));
+    if in_point <= 0.0 && out_point >= 1.0 {
+        return false;
+    }
+    write!(
+        out,
+        r#"<g><animate attributeName="opacity" calcMode="discrete" dur="{}s" repeatCount="indefinite" keyTimes="0;{};{}" values="0;1;0"/>"#,
+        timeline.duration(), in_point, out_point,
+    ).unwrap();
+    true
+}
+
+/// How a property is applied to a group.
+#[derive(Clone, Copy)]
+enum Channel {
+    /// A transform function, `translate`, `rotate` or `scale`.
+    Transform(&'static str),
+    Opacity,
+}
+
+/// Opens the groups that apply a Lottie transform, the `ks` of a layer or a `tr` shape.
+/// Returns the number of opened groups.
+fn open_transform(out: &mut String, transform: &Value, timeline: &Timeline, opacity: bool) -> usize {
+    let mut opened = 0;
+    let position = &transform["p"];
+    if position["s"].as_bool() == Some(true) {
+        opened += open_group(out, Channel::Transform("translate"), &position["x"], |v| vec![at(v, 0), 0.0], timeline) as usize;
+        opened += open_group(out, Channel::Transform("translate"), &position["y"], |v| vec![0.0, at(v, 0)], timeline) as usize;
+    } else {
+        opened += open_group(out, Channel::Transform("translate"), position, |v| vec![at(v, 0), at(v, 1)], timeline) as usize;
+    }
+    let rotation = if transform["r"].is_null() { &transform["rz"] } else { &transform["r"] };
+    opened += open_group(out, Channel::Transform("rotate"), rotation, |v| vec![at(v, 0)], timeline) as usize;
+    let scale = |v: &[f64]| vec![v.get(0).map_or(1.0, |x| x / 100.0), v.get(1).or_else(|| v.get(0)).map_or(1.0, |y| y / 100.0)];
+    opened += open_group(out, Channel::Transform("scale"), &transform["s"], scale, timeline) as usize;
+    opened += open_group(out, Channel::Transform("translate"), &transform["a"], |v| vec![-at(v, 0), -at(v, 1)], timeline) as usize;
+    if opacity {
+        opened += open_group(out, Channel::Opacity, &transform["o"], |v| vec![v.get(0).map_or(1.0, |o| o / 100.0)], timeline) as usize;
+    }
+    opened
+}
+
+/// Opens a group that applies `property`, converted with `convert`, unless it never changes
+/// anything. Returns whether a group was opened.
+fn open_group(out: &mut String, channel: Channel, property: &Value, convert: impl Fn(&[f64]) -> Vec<f64>, timeline: &Timeline) -> bool {
+    if let Some(keyframes) = keyframes(property) {
+        out.push_str("<g>");
+        match channel {
+            Channel::Transform(kind) => {
+                let attributes = format!(r#" attributeName="transform" type="{}""#, kind);
+                write_animation(out, "animateTransform", &attributes, &keyframes, |v| join(&convert(v), " "), timeline);
+            },
+            Channel::Opacity => {
+                write_animation(out, "animate", r#" attributeName="opacity""#, &keyframes, |v| join(&convert(v), " "), timeline);
+            },
+        }
+        return true;
+    }
+    let value = match initial_value(property) {
+        Some(value) => convert(&value),
+        None => return false,
+    };
+    match channel {
+        Channel::Transform(kind) => {
+            let identity = match kind {
+                "scale" => 1.0,
+                _ => 0.0,
+            };
+            if value.iter().all(|v| (v - identity).abs() < f64::EPSILON) {
+                return false;
+            }
+            write!(out, r#"<g transform="{}({})">"#, kind, join(&value, " ")).unwrap();
+        },
+        Channel::Opacity => {
+            if at(&value, 0) >= 1.0 {
+                return false;
+            }
+            write!(out, r#"<g opacity="{}">"#, at(&value, 0)).unwrap();
+        },
+    }
+    true
+}
+
+/// Writes the shape items of a layer or group, the first item is drawn on top.
+///
+/// `inherited_paints` are the fills and strokes of the enclosing group, which are used if the
+/// items have none of their own.
+fn write_shapes(out: &mut String, items: &[Value], inherited_paints: &[&Value], timeline: &Timeline) {
+    let items: Vec<&Value> = items.iter().filter(|item| item["hd"].as_bool() != Some(true)).collect();
+    let data = items.iter().filter_map(|item| path_data(item)).collect::<Vec<_>>().join(" ");
+    let own_paints: Vec<&Value> = items.iter().copied()
+        .filter(|item| matches!(item["ty"].as_str(), Some("fl") | Some("st")))
+        .collect();
+    let paints = if own_paints.is_empty() { inherited_paints.to_vec() } else { own_paints.clone() };
+
+    if own_paints.is_empty() && !data.is_empty() {
+        for paint in inherited_paints.iter().rev() {
+            write_paint(out, paint, &data, timeline);
+        }
+    }
+    for item in items.iter().rev() {
+        match item["ty"].as_str() {
+            Some("gr") => {
+                let group_items = item["it"].as_array().map_or(&[][..], Vec::as_slice);
+                let opened = group_items.iter()
+                    .find(|item| item["ty"].as_str() == Some("tr"))
+                    .map_or(0, |transform| open_transform(out, transform, timeline, true));
+                write_shapes(out, group_items, &paints, timeline);
+                for _ in 0..opened {
+                    out.push_str("</g>");
+                }
+            },
+            Some("fl") | Some("st") if !data.is_empty() => write_paint(out, item, &data, timeline),
+            Some("sh") | Some("rc") | Some("el") | Some("tr") | Some("fl") | Some("st") | None => {},
+            Some(kind) => warn!(
+                "Lottie shape `{}` of type `{}` is not supported, skipping it.",
+                item["nm"].as_str().unwrap_or_default(), kind,
+            ),
+        }
+    }
+}
+
+/// Writes a path with the geometry `data` and the fill or stroke `paint`.
+fn write_paint(out: &mut String, paint: &Value, data: &str, timeline: &Timeline) {
+    let is_stroke = paint["ty"].as_str() == Some("st");
+    let (color_name, opacity_name) = if is_stroke { ("stroke", "stroke-opacity") } else { ("fill", "fill-opacity") };
+    write!(out, r#"<path d="{}""#, data).unwrap();
+    if is_stroke {
+        let line_cap = match paint["lc"].as_i64() {
+            Some(2) => "round",
+            Some(3) => "square",
+            _ => "butt",
+        };
+        let line_join = match paint["lj"].as_i64() {
+            Some(2) => "round",
+            Some(3) => "bevel",
+            _ => "miter",
+        };
+        write!(
+            out,
+            r#" fill="none" stroke-width="{}" stroke-linecap="{}" stroke-linejoin="{}" stroke-miterlimit="{}""#,
+            initial_value(&paint["w"]).map_or(1.0, |width| at(&width, 0)), line_cap, line_join,
+            paint["ml"].as_f64().unwrap_or(4.0),
+        ).unwrap();
+    } else if paint["r"].as_i64() == Some(2) {
+        out.push_str(r#" fill-rule="evenodd""#);
+    }
+    let color = initial_value(&paint["c"]).unwrap_or_default();
+    let opacity = initial_value(&paint["o"]).map_or(1.0, |opacity| at(&opacity, 0) / 100.0);
+    write!(out, r#" {}="{}" {}="{}">"#, color_name, hex(&color), opacity_name, opacity).unwrap();
+    if let Some(keyframes) = keyframes(&paint["c"]) {
+        let attributes = format!(r#" attributeName="{}""#, color_name);
+        write_animation(out, "animate", &attributes, &keyframes, hex, timeline);
+    }
+    if let Some(keyframes) = keyframes(&paint["o"]) {
+        let attributes = format!(r#" attributeName="{}""#, opacity_name);
+        write_animation(out, "animate", &attributes, &keyframes, |v| (at(v, 0) / 100.0).to_string(), timeline);
+    }
+    out.push_str("</path>");
+}
+
+/// Returns the path data of a `sh`, `rc` or `el` shape item, at its first keyframe.
+fn path_data(item: &Value) -> Option<String> {
+    match item["ty"].as_str()? {
+        "sh" => {
+            let shape = match &item["ks"]["k"] {
+                Value::Array(keyframes) => {
+                    warn!("Animated Lottie path `{}` is not supported, showing its first keyframe.", item["nm"].as_str().unwrap_or_default());
+                    let start = &keyframes.first()?["s"];
+                    start.get(0).unwrap_or(start)
+                },
+                shape => shape,
+            };
+            bezier_data(shape)
+        },
+        "rc" => {
+            let (center, size) = (initial_value(&item["p"])?, initial_value(&item["s"])?);
+            let (width, height) = (at(&size, 0), at(&size, 1));
+            let (left, top) = (at(&center, 0) - width / 2.0, at(&center, 1) - height / 2.0);
+            let (right, bottom) = (left + width, top + height);
+            let radius = initial_value(&item["r"]).map_or(0.0, |radius| at(&radius, 0)).min(width / 2.0).min(height / 2.0);
+            Some(match radius > 0.0 {
+                true => format!(
+                    "M {} {} H {} A {r} {r} 0 0 1 {} {} V {} A {r} {r} 0 0 1 {} {} H {} A {r} {r} 0 0 1 {} {} V {} A {r} {r} 0 0 1 {} {} Z",
+                    left + radius, top, right - radius, right, top + radius, bottom - radius, right - radius, bottom,
+                    left + radius, left, bottom - radius, top + radius, left + radius, top, r = radius,
+                ),
+                false => format!("M {} {} H {} V {} H {} Z", left, top, right, bottom, left),
+            })
+        },
+        "el" => {
+            let (center, size) = (initial_value(&item["p"])?, initial_value(&item["s"])?);
+            let (rx, ry) = (at(&size, 0) / 2.0, at(&size, 1) / 2.0);
+            let (cx, cy) = (at(&center, 0), at(&center, 1));
+            Some(format!(
+                "M {} {} A {rx} {ry} 0 1 0 {} {} A {rx} {ry} 0 1 0 {} {} Z",
+                cx - rx, cy, cx + rx, cy, cx - rx, cy, rx = rx, ry = ry,
+            ))
+        },
+        _ => None,
+    }
+}
+
+/// Returns the path data of a Lottie bezier shape, whose tangents `i` and `o` are relative to
+/// their vertex in `v`.
+fn bezier_data(shape: &Value) -> Option<String> {
+    let point = |list: &str, index: usize| {
+        let point = numbers(&shape[list][index]).unwrap_or_default();
+        (at(&point, 0), at(&point, 1))
+    };
+    let count = shape["v"].as_array()?.len();
+    if count == 0 {
+        return None;
+    }
+    let segment = |data: &mut String, from: usize, to: usize| {
+        let ((x, y), (out_x, out_y)) = (point("v", from), point("o", from));
+        let ((to_x, to_y), (in_x, in_y)) = (point("v", to), point("i", to));
+        write!(data, " C {} {} {} {} {} {}", x + out_x, y + out_y, to_x + in_x, to_y + in_y, to_x, to_y).unwrap();
+    };
+    let (x, y) = point("v", 0);
+    let mut data = format!("M {} {}", x, y);
+    for index in 1..count {
+        segment(&mut data, index - 1, index);
+    }
+    if shape["c"].as_bool() == Some(true) {
+        segment(&mut data, count - 1, 0);
+        data.push_str(" Z");
+    }
+    Some(data)
+}
+
+/// Writes an animation element that plays `keyframes` in a loop over the whole `timeline`, each
+/// value formatted with `format`.
+fn write_animation(
+    out: &mut String,
+    tag: &str,
+    attributes: &str,
+    keyframes: &[Keyframe],
+    format: impl Fn(&[f64]) -> String,
+    timeline: &Timeline,
+) {
+    let mut points = Vec::new();
+    let mut splines = Vec::new();
+    let (first, last) = match (keyframes.first(), keyframes.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return,
+    };
+    if timeline.key_time(first.time) > 0.0 {
+        points.push((0.0, format(&first.value)));
+        splines.push(LINEAR);
+    }
+    for (index, keyframe) in keyframes.iter().enumerate() {
+        let time = timeline.key_time(keyframe.time);
+        points.push((time, format(&keyframe.value)));
+        if let Some(next) = keyframes.get(index + 1) {
+            match keyframe.easing {
+                Some(easing) => splines.push(easing),
+                // Hold keyframes keep their value until right before the next one.
+                None => {
+                    points.push(((timeline.key_time(next.time) - HOLD_GAP).max(time), format(&keyframe.value)));
+                    splines.extend([LINEAR, LINEAR]);
+                },
+            }
+        }
+    }
+    if timeline.key_time(last.time) < 1.0 {
+        points.push((1.0, format(&last.value)));
+        splines.push(LINEAR);
+    }
+
+    let key_times = points.iter().map(|(time, _)| time.to_string()).collect::<Vec<_>>().join(";");
+    let values = points.into_iter().map(|(_, value)| value).collect::<Vec<_>>().join(";");
+    let key_splines = splines.iter().map(|spline| join(spline, " ")).collect::<Vec<_>>().join(";");
+    write!(
+        out,
+        r#"<{}{} calcMode="spline" dur="{}s" repeatCount="indefinite" keyTimes="{}" values="{}" keySplines="{}"/>"#,
+        tag, attributes, timeline.duration(), key_times, values, key_splines,
+    ).unwrap();
+}
+
+/// Returns the keyframes of an animated property, `None` if it has less than two.
+fn keyframes(property: &Value) -> Option<Vec<Keyframe>> {
+    let frames = property["k"].as_array()?;
+    if !frames.first()?.is_object() {
+        return None;
+    }
+    let mut keyframes = Vec::new();
+    // Older files store the end value of each keyframe, instead of the start of the next one.
+    let mut previous_end = None;
+    for frame in frames {
+        let time = match frame["t"].as_f64() {
+            Some(time) => time,
+            None => continue,
+        };
+        let value = match numbers(&frame["s"]).or_else(|| previous_end.take()) {
+            Some(value) => value,
+            None => continue,
+        };
+        previous_end = numbers(&frame["e"]);
+        keyframes.push(Keyframe { time, value, easing: easing(frame) });
+    }
+    (keyframes.len() > 1).then(|| keyframes)
+}
+
+/// Returns the easing of a keyframe towards the next one, `None` for hold keyframes.
+fn easing(keyframe: &Value) -> Option<[f64; 4]> {
+    if keyframe["h"].as_i64() == Some(1) || keyframe["h"].as_bool() == Some(true) {
+        return None;
+    }
+    // Tangents are given per dimension, only the first one is used.
+    let first = |value: &Value| value.as_f64().or_else(|| value.get(0)?.as_f64());
+    Some(match (first(&keyframe["o"]["x"]), first(&keyframe["o"]["y"]), first(&keyframe["i"]["x"]), first(&keyframe["i"]["y"])) {
+        (Some(x1), Some(y1), Some(x2), Some(y2)) => [x1.clamp(0.0, 1.0), y1, x2.clamp(0.0, 1.0), y2],
+        _ => LINEAR,
+    })
+}
+
+/// Returns the value of a static property, or the first keyframe of an animated one.
+fn initial_value(property: &Value) -> Option<Vec<f64>> {
+    match &property["k"] {
+        Value::Array(frames) if frames.first().map_or(false, Value::is_object) => numbers(&frames[0]["s"]),
+        value => numbers(value),
+    }
+}
+
+/// Reads a number or a list of numbers.
+fn numbers(value: &Value) -> Option<Vec<f64>> {
+    match value {
+        Value::Number(number) => Some(vec![number.as_f64()?]),
+        Value::Array(values) => values.iter().map(Value::as_f64).collect(),
+        _ => None,
+    }
+}
+
+/// Returns the value at `index`, or zero if there is none.
+fn at(values: &[f64], index: usize) -> f64 {
+    values.get(index).copied().unwrap_or(0.0)
+}
+
+fn join(values: &[f64], separator: &str) -> String {
+    values.iter().map(f64::to_string).collect::<Vec<_>>().join(separator)
+}
+
+/// Formats a color with channels from `0.0` to `1.0` as `#rrggbb`.
+fn hex(color: &[f64]) -> String {
+    let channel = |index: usize| (at(color, index).clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(0), channel(1), channel(2))
+}
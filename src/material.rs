@@ -0,0 +1,212 @@
+//! Draws 2D SVGs with a [`Material2d`](bevy::sprite::Material2d) of the user instead of the
+//! built-in pipeline, for effects like outlines, dissolves or palette swaps.
+
+use std::marker::PhantomData;
+
+use bevy::{
+    app::{App, CoreStage, Plugin},
+    asset::{AddAsset, Handle},
+    core::FloatOrd,
+    core_pipeline::Transparent2d,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{Commands, Local, Query, RemovedComponents, Res, ResMut},
+        world::{FromWorld, World},
+    },
+    render::{
+        mesh::Mesh,
+        render_asset::{RenderAssetPlugin, RenderAssets},
+        render_phase::{AddRenderCommand, DrawFunctions, RenderPhase, SetItemPipeline},
+        render_resource::{
+            PipelineCache, RenderPipelineDescriptor, SpecializedRenderPipeline, SpecializedRenderPipelines,
+            VertexBufferLayout,
+        },
+        view::{ComputedVisibility, Msaa},
+        RenderApp, RenderStage,
+    },
+    sprite::{
+        DrawMesh2d, Material2dPipeline, Mesh2dHandle, Mesh2dPipelineKey, Mesh2dUniform, SetMaterial2dBindGroup,
+        SetMesh2dBindGroup, SetMesh2dViewBindGroup, SpecializedMaterial2d,
+    },
+};
+
+use crate::{render::vertex_buffer::vertex_buffer_layout, svg::Svg};
+
+
+/// Draws the [`Svg`]s of 2D entities that have a `Handle<M>` with the material `M`, whose shaders
+/// get the mesh of the SVG instead of the built-in SVG shader. Add it instead of bevy's
+/// `Material2dPlugin::<M>`, with [`SvgPlugin`](crate::prelude::SvgPlugin).
+///
+/// The SVG mesh has the vertex attributes of bevy's 2D meshes at the same locations, the position
+/// at 0, the normal at 1, the uv at 2 and the tangent at 3, so the vertex shader of bevy works.
+/// The color of the vertex is at location 4, as linear RGBA with 8 bits per channel packed into a
+/// `u32`, with red in the lowest bits. The mesh is placed with the [`Origin`](crate::prelude::Origin)
+/// and size of the SVG like any other.
+///
+/// Entities with the material aren't drawn with [`SvgTint`](crate::prelude::SvgTint),
+/// [`SvgSdf`](crate::prelude::SvgSdf), [`SvgInstancing`](crate::prelude::SvgInstancing) or
+/// [`SvgBatching`](crate::prelude::SvgBatching). Other entities with a `Handle<M>` aren't drawn
+/// by this plugin.
+///
+/// ```ignore
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .add_plugin(SvgPlugin)
+///     .add_plugin(SvgMaterial2dPlugin::<OutlineMaterial>::default())
+///     .run();
+/// ```
+pub struct SvgMaterial2dPlugin<M: SpecializedMaterial2d>(PhantomData<M>);
+
+impl<M: SpecializedMaterial2d> Default for SvgMaterial2dPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: SpecializedMaterial2d> Plugin for SvgMaterial2dPlugin<M> {
+    fn build(&self, app: &mut App) {
+        app
+            .add_asset::<M>()
+            .add_plugin(RenderAssetPlugin::<M>::default())
+            .add_system_to_stage(CoreStage::PostUpdate, mark_svg_materials::<M>);
+        app.get_sub_app_mut(RenderApp).unwrap()
+            .add_render_command::<Transparent2d, DrawSvgMaterial2d<M>>()
+            .init_resource::<SvgMaterial2dPipeline<M>>()
+            .init_resource::<SpecializedRenderPipelines<SvgMaterial2dPipeline<M>>>()
+            .add_system_to_stage(RenderStage::Extract, extract_svg_materials::<M>)
+            .add_system_to_stage(RenderStage::Queue, queue_svg_materials::<M>);
+    }
+}
+
+/// Marks SVG entities that are drawn with the material of a [`SvgMaterial2dPlugin`], which the
+/// built-in pipeline leaves out.
+#[derive(Component)]
+pub(crate) struct SvgMaterialMesh;
+
+/// Bevy system which marks the SVG entities that got a `Handle<M>`, and unmarks the ones that lost
+/// it.
+#[allow(clippy::type_complexity)]
+fn mark_svg_materials<M: SpecializedMaterial2d>(
+    mut commands: Commands,
+    added: Query<Entity, (With<Handle<M>>, With<Handle<Svg>>, Without<SvgMaterialMesh>)>,
+    removed: RemovedComponents<Handle<M>>,
+    marked: Query<(), With<SvgMaterialMesh>>,
+) {
+    for entity in added.iter() {
+        commands.entity(entity).insert(SvgMaterialMesh);
+    }
+    for entity in removed.iter() {
+        // Despawned entities aren't found anymore.
+        if marked.get(entity).is_ok() {
+            commands.entity(entity).remove::<SvgMaterialMesh>();
+        }
+    }
+}
+
+/// Extract the `Handle<M>` of every visible SVG entity with the material into [`RenderWorld`](bevy::render::RenderWorld).
+fn extract_svg_materials<M: SpecializedMaterial2d>(
+    mut commands: Commands,
+    mut previous_len: Local<usize>,
+    query: Query<(Entity, &ComputedVisibility, &Handle<M>), With<SvgMaterialMesh>>,
+) {
+    let mut values = Vec::with_capacity(*previous_len);
+    for (entity, computed_visibility, handle) in query.iter() {
+        if computed_visibility.is_visible {
+            values.push((entity, (handle.clone_weak(),)));
+        }
+    }
+    *previous_len = values.len();
+    commands.insert_or_spawn_batch(values);
+}
+
+/// Queue all extracted SVG entities with the material `M` for rendering with the
+/// [`SvgMaterial2dPipeline`] of `M`.
+#[allow(clippy::too_many_arguments)]
+fn queue_svg_materials<M: SpecializedMaterial2d>(
+    transparent_draw_functions: Res<DrawFunctions<Transparent2d>>,
+    material_pipeline: Res<SvgMaterial2dPipeline<M>>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<SvgMaterial2dPipeline<M>>>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    msaa: Res<Msaa>,
+    render_meshes: Res<RenderAssets<Mesh>>,
+    render_materials: Res<RenderAssets<M>>,
+    svgs: Query<(Entity, &Handle<M>, &Mesh2dHandle, &Mesh2dUniform)>,
+    mut views: Query<&mut RenderPhase<Transparent2d>>,
+) {
+    if svgs.is_empty() {
+        return;
+    }
+    let mesh_key = Mesh2dPipelineKey::from_msaa_samples(msaa.samples);
+    let draw_svg_material = transparent_draw_functions
+        .read()
+        .get_id::<DrawSvgMaterial2d<M>>()
+        .unwrap();
+
+    for mut transparent_phase in views.iter_mut() {
+        for (entity, material_handle, mesh2d_handle, mesh2d_uniform) in svgs.iter() {
+            // Drawn as soon as the material is on the GPU.
+            let material = match render_materials.get(material_handle) {
+                Some(material) => material,
+                None => continue,
+            };
+            let mut mesh2d_key = mesh_key;
+            if let Some(mesh) = render_meshes.get(&mesh2d_handle.0) {
+                mesh2d_key |= Mesh2dPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            }
+            let key = (mesh2d_key | Mesh2dPipelineKey::VERTEX_TANGENTS, M::key(material));
+            let pipeline_id = pipelines.specialize(&mut pipeline_cache, &material_pipeline, key);
+            transparent_phase.add(Transparent2d {
+                entity,
+                draw_function: draw_svg_material,
+                pipeline: pipeline_id,
+                // The transform of the mesh already has the placement of the SVG.
+                sort_key: FloatOrd(mesh2d_uniform.transform.w_axis.z),
+                batch_range: None,
+            });
+        }
+    }
+}
+
+/// The pipeline of the material `M`, specialized for the vertex buffer of SVG meshes.
+pub struct SvgMaterial2dPipeline<M: SpecializedMaterial2d> {
+    material2d_pipeline: Material2dPipeline<M>,
+}
+
+impl<M: SpecializedMaterial2d> FromWorld for SvgMaterial2dPipeline<M> {
+    fn from_world(world: &mut World) -> Self {
+        Self { material2d_pipeline: Material2dPipeline::from_world(world) }
+    }
+}
+
+impl<M: SpecializedMaterial2d> SpecializedRenderPipeline for SvgMaterial2dPipeline<M> {
+    type Key = (Mesh2dPipelineKey, M::Key);
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut descriptor = self.material2d_pipeline.specialize(key);
+        descriptor.vertex.buffers = vec![svg_vertex_layout()];
+        descriptor
+    }
+}
+
+/// The layout of the vertex buffer of SVG meshes, with the attributes at the locations of bevy's
+/// 2D meshes and the color after them.
+fn svg_vertex_layout() -> VertexBufferLayout {
+    vertex_buffer_layout(&[
+        Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+        Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+        Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+        Mesh::ATTRIBUTE_TANGENT.at_shader_location(3),
+        Mesh::ATTRIBUTE_COLOR.at_shader_location(4),
+    ])
+}
+
+/// Render commands that draw the mesh of an SVG entity with the material `M`.
+type DrawSvgMaterial2d<M> = (
+    SetItemPipeline,
+    SetMesh2dViewBindGroup<0>,
+    SetMaterial2dBindGroup<M, 1>,
+    SetMesh2dBindGroup<2>,
+    DrawMesh2d,
+);
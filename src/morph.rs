@@ -0,0 +1,367 @@
+//! Morphing between two [`Svg`]s whose elements have matching IDs, for transitions between the
+//! states of an icon, like a play button that turns into a pause button.
+
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::Without,
+        reflect::ReflectComponent,
+        system::{Commands, Query, Res, ResMut},
+    },
+    reflect::Reflect,
+    render::{color::Color, mesh::Mesh},
+    sprite::Mesh2dHandle,
+    transform::components::Transform,
+};
+use lyon_geom::euclid::default::Transform2D;
+use lyon_svg::path::{iterator::PathIterator, PathEvent};
+use lyon_tessellation::{math::Point, FillTessellator, StrokeTessellator};
+
+use crate::{
+    overrides::Overrides,
+    paint::Paint,
+    render::{dash, tessellation},
+    svg::{document_transform, paint_color, DrawType, PathDescriptor, Svg},
+    Convert,
+};
+
+
+/// Tolerance that is used to flatten curves before the paths are resampled.
+const MORPH_TOLERANCE: f32 = 0.1;
+
+/// Draws an entity as a morph between the shapes of two [`Svg`]s, for transitions between the
+/// states of an icon.
+///
+/// Shapes with the same ID and both a fill or both a stroke are resampled to the same number of
+/// points, which move from one shape to the other while `t` goes from zero to one. Their colors
+/// and stroke widths are blended too. Shapes without a counterpart fade out or in, and are
+/// swapped halfway if their paint isn't a plain color.
+///
+/// While morphing, the shapes are drawn in solid colors and without the effects of their groups,
+/// like clip paths, filters or dashes. At zero and one, the meshes of `from` and `to` are drawn
+/// as they are. The entity is placed like its own `Handle<Svg>`, which is usually `from`.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgMorph {
+    /// The SVG at the start of the morph.
+    pub from: Handle<Svg>,
+    /// The SVG at the end of the morph.
+    pub to: Handle<Svg>,
+    /// How far the shapes moved from `from` to `to`, from zero to one.
+    pub t: f32,
+}
+
+impl SvgMorph {
+    /// Creates a morph from `from` to `to` that is at its start.
+    pub fn new(from: Handle<Svg>, to: Handle<Svg>) -> Self {
+        Self { from, to, t: 0.0 }
+    }
+}
+
+/// The own mesh of an entity with a [`SvgMorph`].
+#[derive(Component)]
+pub(crate) struct MorphedMesh {
+    morph: SvgMorph,
+    mesh: Handle<Mesh>,
+}
+
+/// Bevy system which tessellates the mesh of every entity with a [`SvgMorph`] again when the morph
+/// or one of its [`Svg`]s changed, and switches entities whose [`SvgMorph`] was removed back to
+/// the shared mesh of their [`Svg`].
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn update_morphed_meshes(
+    mut commands: Commands,
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    svgs: Res<Assets<Svg>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    mut query: Query<(Entity, &SvgMorph, Option<&mut MorphedMesh>, Option<&mut Mesh2dHandle>, Option<&mut Handle<Mesh>>)>,
+    mut removed: Query<(Entity, &MorphedMesh, Option<&Handle<Svg>>, Option<&mut Mesh2dHandle>, Option<&mut Handle<Mesh>>), Without<SvgMorph>>,
+) {
+    let modified: Vec<_> = svg_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, morph, morphed, mesh_2d, mesh_3d) in query.iter_mut() {
+        // Wait until both svgs are loaded.
+        let (from, to) = match (svgs.get(&morph.from), svgs.get(&morph.to)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => continue,
+        };
+        let mesh = match morphed {
+            _ if morph.t <= 0.0 => from.mesh.clone(),
+            _ if morph.t >= 1.0 => to.mesh.clone(),
+            // Baked SVGs have no paths that could be morphed.
+            _ if from.paths.is_empty() || to.paths.is_empty() => match morph.t < 0.5 {
+                true => from.mesh.clone(),
+                false => to.mesh.clone(),
+            },
+            Some(mut morphed) => {
+                let outdated = morphed.morph != *morph || modified.contains(&morph.from) || modified.contains(&morph.to);
+                if outdated || !meshes.contains(&morphed.mesh) {
+                    let mesh = generate_mesh(from, to, morph.t, &mut fill_tess, &mut stroke_tess);
+                    match meshes.get_mut(&morphed.mesh) {
+                        Some(old_mesh) => *old_mesh = mesh,
+                        None => morphed.mesh = meshes.add(mesh),
+                    }
+                    morphed.morph = morph.clone();
+                }
+                morphed.mesh.clone()
+            },
+            None => {
+                let mesh = meshes.add(generate_mesh(from, to, morph.t, &mut fill_tess, &mut stroke_tess));
+                commands.entity(entity).insert(MorphedMesh { morph: morph.clone(), mesh: mesh.clone() });
+                mesh
+            },
+        };
+
+        if let Some(mut mesh_2d) = mesh_2d {
+            if mesh_2d.0 != mesh {
+                mesh_2d.0 = mesh.clone();
+            }
+        }
+        if let Some(mut mesh_3d) = mesh_3d {
+            if *mesh_3d != mesh {
+                *mesh_3d = mesh;
+            }
+        }
+    }
+
+    // The morph was removed, go back to the shared mesh.
+    for (entity, morphed, handle, mesh_2d, mesh_3d) in removed.iter_mut() {
+        meshes.remove(&morphed.mesh);
+        commands.entity(entity).remove::<MorphedMesh>();
+        let svg = match handle.and_then(|handle| svgs.get(handle)) {
+            Some(svg) => svg,
+            None => continue,
+        };
+        if let Some(mut mesh_2d) = mesh_2d {
+            mesh_2d.0 = svg.mesh.clone();
+        }
+        if let Some(mut mesh_3d) = mesh_3d {
+            *mesh_3d = svg.mesh.clone();
+        }
+    }
+}
+
+/// Tessellates the morph from `from` to `to` at `t`.
+fn generate_mesh(from: &Svg, to: &Svg, t: f32, fill_tess: &mut FillTessellator, stroke_tess: &mut StrokeTessellator) -> Mesh {
+    let morphed = Svg {
+        name: format!("{} -> {}", from.name, to.name),
+        size: from.size.lerp(to.size, t),
+        view_box: from.view_box,
+        content_rect: from.content_rect,
+        paths: morph_paths(from, to, t),
+        animations: Vec::new(),
+        transitions: Default::default(),
+        hidden: Default::default(),
+        skipped_features: Vec::new(),
+        dependencies: Vec::new(),
+        tolerance: from.tolerance,
+        time_sliced: false,
+        texts: None,
+        modified_paths: Default::default(),
+        mesh: Default::default(),
+    };
+    tessellation::generate_buffer(&morphed, &Overrides::default(), fill_tess, stroke_tess).convert()
+}
+
+/// Returns the visible paths of `from` morphed towards their counterparts in `to` by `t`, in the
+/// coordinate system of the SVG. Paths without a counterpart fade out or in.
+fn morph_paths(from: &Svg, to: &Svg, t: f32) -> Vec<PathDescriptor> {
+    let key = |path: &PathDescriptor| (path.id.clone(), matches!(path.draw_type, DrawType::Fill(_)));
+    // Counterparts are matched in the order of the paths, the first one is taken first.
+    let mut counterparts: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, path) in to.paths.iter().enumerate().rev() {
+        if !path.id.is_empty() && !to.is_hidden(path) {
+            counterparts.entry(key(path)).or_default().push(index);
+        }
+    }
+
+    let mut matched = vec![false; to.paths.len()];
+    let mut paths = Vec::new();
+    for path in from.paths.iter().filter(|path| !from.is_hidden(path)) {
+        let counterpart = match path.id.is_empty() {
+            true => None,
+            false => counterparts.get_mut(&key(path)).and_then(Vec::pop),
+        };
+        match counterpart {
+            Some(index) => {
+                matched[index] = true;
+                paths.push(morph_path(path, &to.paths[index], t));
+            },
+            None => paths.extend(fade_path(path, 1.0 - t, t < 0.5)),
+        }
+    }
+    for (index, path) in to.paths.iter().enumerate() {
+        if !matched[index] && !to.is_hidden(path) {
+            paths.extend(fade_path(path, t, t >= 0.5));
+        }
+    }
+    paths
+}
+
+/// A flattened subpath in the coordinate system of the SVG.
+struct Subpath {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+/// Morphs `from` towards `to` by `t`, their subpaths are paired in order.
+fn morph_path(from: &PathDescriptor, to: &PathDescriptor, t: f32) -> PathDescriptor {
+    let (mut from_subpaths, mut to_subpaths) = (subpaths(from), subpaths(to));
+    // Subpaths without a counterpart grow out of, or shrink into, their center.
+    while from_subpaths.len() < to_subpaths.len() {
+        from_subpaths.push(collapsed(&to_subpaths[from_subpaths.len()]));
+    }
+    while to_subpaths.len() < from_subpaths.len() {
+        to_subpaths.push(collapsed(&from_subpaths[to_subpaths.len()]));
+    }
+    let subpaths = from_subpaths.iter().zip(&to_subpaths)
+        .map(|(start, end)| {
+            let count = start.points.len().max(end.points.len()).max(2);
+            let points = resample(start, count).into_iter()
+                .zip(resample(end, count))
+                .map(|(start, end)| start.lerp(end, t))
+                .collect();
+            Subpath { points, closed: if t < 0.5 { start.closed } else { end.closed } }
+        })
+        .collect();
+
+    let draw_type = match (document_draw_type(from), document_draw_type(to)) {
+        (DrawType::Stroke(start), DrawType::Stroke(end)) => {
+            let mut options = if t < 0.5 { start } else { end };
+            options.line_width = start.line_width + (end.line_width - start.line_width) * t;
+            DrawType::Stroke(options)
+        },
+        (start, end) => if t < 0.5 { start } else { end },
+    };
+    descriptor(from.id.clone(), subpaths, lerp_color(paint_color(from), paint_color(to), t), draw_type)
+}
+
+/// Returns `path` with its opacity multiplied by `opacity`. Paths whose paint isn't a plain color
+/// are only drawn if they are `shown`.
+fn fade_path(path: &PathDescriptor, opacity: f32, shown: bool) -> Option<PathDescriptor> {
+    let mut color = paint_color(path);
+    match path.paint {
+        Paint::Color(_) if opacity <= 0.0 => return None,
+        Paint::Color(_) => {
+            color.set_a(color.a() * opacity);
+        },
+        _ if !shown => return None,
+        _ => {},
+    }
+    Some(descriptor(path.id.clone(), subpaths(path), color, document_draw_type(path)))
+}
+
+/// Returns the flattened subpaths of `path` in the coordinate system of the SVG.
+fn subpaths(path: &PathDescriptor) -> Vec<Subpath> {
+    let to_document = document_transform(path);
+    let mut subpaths: Vec<Subpath> = Vec::new();
+    for event in path.segments.iter().map(|event| event.transformed(&to_document)).flattened(MORPH_TOLERANCE) {
+        match event {
+            PathEvent::Begin { at } => subpaths.push(Subpath { points: vec![at], closed: false }),
+            PathEvent::Line { to, .. } => subpaths.last_mut().into_iter().for_each(|subpath| subpath.points.push(to)),
+            PathEvent::End { close, .. } => subpaths.last_mut().into_iter().for_each(|subpath| subpath.closed = close),
+            _ => {},
+        }
+    }
+    subpaths
+}
+
+/// Returns `subpath` collapsed into the center of its points.
+fn collapsed(subpath: &Subpath) -> Subpath {
+    let sum = subpath.points.iter().fold(Point::zero(), |sum, point| sum + point.to_vector());
+    let center = sum / subpath.points.len().max(1) as f32;
+    Subpath { points: vec![center], closed: subpath.closed }
+}
+
+/// Returns `count` points that are evenly spaced along `subpath`. Closed subpaths don't repeat
+/// their first point at the end.
+fn resample(subpath: &Subpath, count: usize) -> Vec<Point> {
+    let mut points = subpath.points.clone();
+    if subpath.closed {
+        points.push(points[0]);
+    }
+    let mut lengths = vec![0.0];
+    for pair in points.windows(2) {
+        lengths.push(lengths[lengths.len() - 1] + (pair[1] - pair[0]).length());
+    }
+    let total = lengths[lengths.len() - 1];
+    if total <= 0.0 {
+        return vec![points[0]; count];
+    }
+
+    let step = match subpath.closed {
+        true => total / count as f32,
+        false => total / (count - 1) as f32,
+    };
+    let mut segment = 0;
+    (0..count)
+        .map(|index| {
+            let distance = (index as f32 * step).min(total);
+            while segment + 2 < points.len() && lengths[segment + 1] < distance {
+                segment += 1;
+            }
+            let (start, end) = (lengths[segment], lengths[segment + 1]);
+            let along = if end > start { (distance - start) / (end - start) } else { 0.0 };
+            points[segment].lerp(points[segment + 1], along)
+        })
+        .collect()
+}
+
+/// Returns the draw type of `path` with a stroke width in the coordinate system of the SVG.
+fn document_draw_type(path: &PathDescriptor) -> DrawType {
+    match path.draw_type {
+        DrawType::Stroke(mut options) => {
+            options.line_width *= document_transform(path).determinant().abs().sqrt();
+            DrawType::Stroke(options)
+        },
+        ref fill => fill.clone(),
+    }
+}
+
+/// Blends the colors `start` and `end` by `t`.
+fn lerp_color(start: Color, end: Color, t: f32) -> Color {
+    let mut channels = start.as_rgba_f32();
+    for (channel, end) in channels.iter_mut().zip(end.as_rgba_f32()) {
+        *channel += (end - *channel) * t;
+    }
+    Color::rgba(channels[0], channels[1], channels[2], channels[3])
+}
+
+/// Creates a path with the `subpaths` in the coordinate system of the SVG.
+fn descriptor(id: String, subpaths: Vec<Subpath>, color: Color, draw_type: DrawType) -> PathDescriptor {
+    let mut segments = Vec::new();
+    for Subpath { points, closed } in subpaths {
+        let (first, last) = (points[0], points[points.len() - 1]);
+        segments.push(PathEvent::Begin { at: first });
+        segments.extend(points.windows(2).map(|pair| PathEvent::Line { from: pair[0], to: pair[1] }));
+        segments.push(PathEvent::End { last, first, close: closed });
+    }
+    PathDescriptor {
+        id,
+        length: match draw_type {
+            DrawType::Stroke(_) => dash::length(&segments),
+            DrawType::Fill(_) => 0.0,
+        },
+        segments,
+        abs_transform: Transform::identity(),
+        transform: Transform2D::identity(),
+        paint: Paint::Color(color),
+        paint_variable: None,
+        draw_type,
+        non_scaling_stroke: false,
+        dash: None,
+        groups: Vec::new(),
+    }
+}
@@ -0,0 +1,857 @@
+//! Components that change how the [`Svg`] of a single entity is drawn at runtime.
+//!
+//! An entity with one of these components gets its own mesh, which is tessellated again whenever
+//! one of the components changes, apart from elements that are toggled with [`SvgNodeVisibility`].
+//! Removing all of them switches the entity back to the shared mesh of the [`Svg`].
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    core::Time,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{Or, With, Without},
+        reflect::ReflectComponent,
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::{Rect, Vec2},
+    reflect::Reflect,
+    render::{color::Color, mesh::{Indices, Mesh}},
+    sprite::Mesh2dHandle,
+    transform::components::{GlobalTransform, Transform},
+};
+use lyon_geom::euclid::default::Transform2D;
+use lyon_tessellation::{FillRule, FillTessellator, StrokeTessellator};
+
+use crate::{animation::{self, AnimatedTransform, SvgAnimationPlayer}, css_animation::ColorTransitions, filter, font::SvgFontDatabase, morph::SvgMorph, paint::Paint, path_entities::SvgPathId, render::{masking::ElementRanges, nine_slice::NineSlice, tessellation, vertex_buffer::{IndexType, Vertex, VertexBuffers}}, source::CURRENT_COLOR_VARIABLE, svg::{is_part_of, Svg, SvgScaleMode, SvgSize}, text::ShapedTexts, Convert};
+
+
+/// Moves the dash pattern of all dashed strokes, `stroke-dashoffset` in SVG.
+///
+/// The offset is added to the `stroke-dashoffset` of every dashed stroke. With a `speed`, the
+/// offset is animated, which makes for marching ants or filling progress rings.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgDashOffset {
+    /// The current offset, added to the dash offset of the SVG.
+    pub offset: f32,
+    /// Change of the offset per second.
+    pub speed: f32,
+}
+
+/// Reveals the strokes of the SVG along their length, for handwriting and signature effects.
+///
+/// Every stroke is drawn from its start up to `progress` of its length, where zero hides it and
+/// one draws all of it. Its subpaths are revealed one after another, and dashes follow along.
+/// Fills are drawn as they are, they can be hidden with [`SvgNodeVisibility`] until the strokes
+/// are done. The lengths are measured while loading, animating `progress` only cuts the strokes.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgDrawOn {
+    /// How much of every stroke is drawn, from zero to one.
+    pub progress: f32,
+}
+
+impl Default for SvgDrawOn {
+    fn default() -> Self {
+        Self { progress: 1.0 }
+    }
+}
+
+/// Replaces the values of CSS custom properties, like `--accent`, that the fill or stroke color
+/// of a shape comes from.
+///
+/// Only shapes whose `fill` or `stroke` is a single `var()` function are recolored, like
+/// `fill="var(--accent)"` or `style="stroke: var(--accent, black)"`. The fill and stroke opacity
+/// of the shapes still apply. If an element declares a `transition` of its `fill` or `stroke`,
+/// the color changes over time instead of right away.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgCssVariables {
+    #[reflect(ignore)]
+    variables: HashMap<String, Color>,
+}
+
+impl SvgCssVariables {
+    /// Creates an empty set of variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the color of the custom property `name`, the leading `--` is optional.
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, color: Color) -> Self {
+        self.set(name, color);
+        self
+    }
+
+    /// Sets the color of the custom property `name`, the leading `--` is optional.
+    pub fn set(&mut self, name: impl Into<String>, color: Color) {
+        self.variables.insert(property_name(name.into()), color);
+    }
+
+    /// Returns the color of the custom property `name`, if it is overridden.
+    pub fn get(&self, name: impl Into<String>) -> Option<Color> {
+        self.variables.get(&property_name(name.into())).copied()
+    }
+
+    /// Removes the override of the custom property `name`, so the value from the file is used again.
+    pub fn remove(&mut self, name: impl Into<String>) -> Option<Color> {
+        self.variables.remove(&property_name(name.into()))
+    }
+}
+
+fn property_name(name: String) -> String {
+    if name.starts_with("--") {
+        name
+    } else {
+        format!("--{}", name)
+    }
+}
+
+/// Replaces the `color` of the SVG, which all paints with `currentColor` use, like
+/// `fill="currentColor"`.
+///
+/// Elements that set their own `color` keep it, only the `color` of the root element is replaced.
+/// This makes it easy to use the same icon in different colors. The fill and stroke opacity of the
+/// shapes still apply.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgColor(pub Color);
+
+/// Multiplies the colors of the whole SVG with a color in the shader, so the same mesh can be drawn
+/// in different variants, like red, green or greyed out when disabled.
+///
+/// Unlike [`SvgColor`], the SVG isn't tessellated again, and the color is multiplied into every
+/// paint, including gradients and images. The alpha fades the whole SVG.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgTint(pub Color);
+
+impl Default for SvgTint {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
+
+/// Transforms the colors of the whole SVG with a color matrix in the shader, like a
+/// `feColorMatrix` filter on its root, so recolored variants of an icon, like a greyed out or hue
+/// shifted one, don't need assets of their own.
+///
+/// Like [`SvgTint`], the SVG isn't tessellated again when the matrix changes. The matrix is applied
+/// to the linear, unpremultiplied color of every pixel before the tint. Entities with the matrix
+/// aren't drawn with [`SvgInstancing`](crate::prelude::SvgInstancing) or
+/// [`SvgBatching`](crate::prelude::SvgBatching). `feColorMatrix` filters in the file are applied to
+/// their groups when the file is loaded.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgColorMatrix {
+    #[reflect(ignore)]
+    matrix: [f32; 20],
+}
+
+impl Default for SvgColorMatrix {
+    fn default() -> Self {
+        Self::new([
+            1.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ])
+    }
+}
+
+impl SvgColorMatrix {
+    /// Creates a color matrix from the values of `feColorMatrix type="matrix"`, 4 rows of 5 values
+    /// in row-major order, where the last column is added to the color.
+    pub fn new(matrix: [f32; 20]) -> Self {
+        Self { matrix }
+    }
+
+    /// Changes the saturation like `feColorMatrix type="saturate"`, `0.0` is grey and `1.0` keeps
+    /// the colors.
+    pub fn saturate(saturation: f32) -> Self {
+        Self::new(filter::saturate_matrix(saturation))
+    }
+
+    /// Rotates the hue by `degrees`, like `feColorMatrix type="hueRotate"`.
+    pub fn hue_rotate(degrees: f32) -> Self {
+        Self::new(filter::hue_rotate_matrix(degrees))
+    }
+
+    /// Returns the values of the matrix, 4 rows of 5 values in row-major order.
+    pub fn matrix(&self) -> &[f32; 20] {
+        &self.matrix
+    }
+}
+
+/// Replaces the fill or stroke of elements by their ID with a solid color, like the team color of a
+/// unit.
+///
+/// The color of a group applies to all shapes inside of it, unless a shape or a group closer to it
+/// has a color of its own. Gradients, patterns and images are replaced as well. The fill and stroke
+/// opacity of solid colors still apply.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgPaintOverrides {
+    #[reflect(ignore)]
+    fills: HashMap<String, Color>,
+    #[reflect(ignore)]
+    strokes: HashMap<String, Color>,
+}
+
+impl SvgPaintOverrides {
+    /// Creates an empty set of overrides, all elements are drawn like in the file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the fill of the element with the given `id` with `color`.
+    #[must_use]
+    pub fn with_fill(mut self, id: impl Into<String>, color: Color) -> Self {
+        self.set_fill(id, color);
+        self
+    }
+
+    /// Replaces the stroke of the element with the given `id` with `color`.
+    #[must_use]
+    pub fn with_stroke(mut self, id: impl Into<String>, color: Color) -> Self {
+        self.set_stroke(id, color);
+        self
+    }
+
+    /// Replaces the fill of the element with the given `id` with `color`.
+    pub fn set_fill(&mut self, id: impl Into<String>, color: Color) {
+        self.fills.insert(id.into(), color);
+    }
+
+    /// Replaces the stroke of the element with the given `id` with `color`.
+    pub fn set_stroke(&mut self, id: impl Into<String>, color: Color) {
+        self.strokes.insert(id.into(), color);
+    }
+
+    /// Returns the color that replaces the fill of the element with the given `id`, if it is
+    /// overridden.
+    pub fn fill(&self, id: &str) -> Option<Color> {
+        self.fills.get(id).copied()
+    }
+
+    /// Returns the color that replaces the stroke of the element with the given `id`, if it is
+    /// overridden.
+    pub fn stroke(&self, id: &str) -> Option<Color> {
+        self.strokes.get(id).copied()
+    }
+
+    /// Removes the override of the fill of the element with the given `id`.
+    pub fn remove_fill(&mut self, id: &str) -> Option<Color> {
+        self.fills.remove(id)
+    }
+
+    /// Removes the override of the stroke of the element with the given `id`.
+    pub fn remove_stroke(&mut self, id: &str) -> Option<Color> {
+        self.strokes.remove(id)
+    }
+}
+
+/// Replaces the `fill-rule` of all fills, including the shapes of clip paths.
+///
+/// Helps to find out why a path with holes is filled solid, or why parts of a path are missing.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct SvgFillRule(pub FillRule);
+
+/// Shows or hides elements of the SVG by their ID.
+///
+/// Elements with `display: none` in the file are hidden until they are shown here, which makes it
+/// easy to switch between states that are drawn in the same file, like the frames of a button.
+/// Hiding a group hides all of its content.
+///
+/// The mesh of the entity contains all elements that are set here, so toggling them only leaves
+/// out their triangles instead of tessellating the SVG again, like for a damage overlay that is
+/// turned on and off. Elements in groups with clip paths, masks, filters or blend modes, and those
+/// of an entity with [`SvgNineSlice`], [`SvgTiling`] or [`SvgExtrusion`], are still tessellated
+/// again.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgNodeVisibility {
+    #[reflect(ignore)]
+    visibility: HashMap<String, bool>,
+}
+
+impl SvgNodeVisibility {
+    /// Creates an empty set of visibilities, all elements are drawn like in the file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shows or hides the element with the given `id`.
+    #[must_use]
+    pub fn with(mut self, id: impl Into<String>, visible: bool) -> Self {
+        self.set(id, visible);
+        self
+    }
+
+    /// Shows or hides the element with the given `id`.
+    pub fn set(&mut self, id: impl Into<String>, visible: bool) {
+        self.visibility.insert(id.into(), visible);
+    }
+
+    /// Returns whether the element with the given `id` is shown, if it is overridden.
+    pub fn get(&self, id: &str) -> Option<bool> {
+        self.visibility.get(id).copied()
+    }
+
+    /// Removes the override of the element with the given `id`, so it is drawn like in the file again.
+    pub fn remove(&mut self, id: &str) -> Option<bool> {
+        self.visibility.remove(id)
+    }
+}
+
+/// Moves, rotates or scales groups of the SVG by their ID, on top of their transform in the file
+/// and their animations, like the hands of a clock or the arm of a character.
+///
+/// Each transform is applied in the coordinate system of its group, around its origin, with the
+/// y-axis of Bevy pointing up, so a positive rotation around the z-axis turns the group
+/// counterclockwise. Only the x and y axes are used.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgGroupTransforms {
+    #[reflect(ignore)]
+    transforms: HashMap<String, Transform>,
+}
+
+impl SvgGroupTransforms {
+    /// Creates an empty set of transforms, all groups are drawn like in the file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `transform` to the group with the given `id`.
+    #[must_use]
+    pub fn with(mut self, id: impl Into<String>, transform: Transform) -> Self {
+        self.set(id, transform);
+        self
+    }
+
+    /// Applies `transform` to the group with the given `id`.
+    pub fn set(&mut self, id: impl Into<String>, transform: Transform) {
+        self.transforms.insert(id.into(), transform);
+    }
+
+    /// Returns the transform of the group with the given `id`, if it has one.
+    pub fn get(&self, id: &str) -> Option<Transform> {
+        self.transforms.get(id).copied()
+    }
+
+    /// Removes the transform of the group with the given `id`, so it is drawn like in the file again.
+    pub fn remove(&mut self, id: &str) -> Option<Transform> {
+        self.transforms.remove(id)
+    }
+}
+
+/// Replaces the content of `text` elements of the SVG by their ID, like a score counter or a
+/// nameplate inside of an authored layout.
+///
+/// The new content is plain text, which is laid out with the position and style of the element
+/// in the file. Nested `tspan` elements are replaced as well. Only the replaced elements are laid
+/// out again, the fonts come from the [`SvgFontDatabase`](crate::prelude::SvgFontDatabase). Their
+/// triangles are swapped in the mesh of the entity, the rest of the SVG is only tessellated again
+/// if the text is in a group with an opacity, clip path, mask, filter or blend mode.
+/// Texts of baked or deserialized SVGs can't be replaced.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgTextOverrides {
+    #[reflect(ignore)]
+    texts: HashMap<String, String>,
+}
+
+impl SvgTextOverrides {
+    /// Creates an empty set of texts, all texts are drawn like in the file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the content of the `text` element with the given `id` with `text`.
+    #[must_use]
+    pub fn with(mut self, id: impl Into<String>, text: impl Into<String>) -> Self {
+        self.set(id, text);
+        self
+    }
+
+    /// Replaces the content of the `text` element with the given `id` with `text`.
+    pub fn set(&mut self, id: impl Into<String>, text: impl Into<String>) {
+        self.texts.insert(id.into(), text.into());
+    }
+
+    /// Returns the content of the `text` element with the given `id`, if it is replaced.
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.texts.get(id).map(String::as_str)
+    }
+
+    /// Removes the content of the `text` element with the given `id`, so it is drawn like in the
+    /// file again.
+    pub fn remove(&mut self, id: &str) -> Option<String> {
+        self.texts.remove(id)
+    }
+}
+
+/// Stretches the [`Svg`] of an entity to its [`SvgSize`] without distorting the corners, like the
+/// nine-slice scaling of UI panels and speech bubbles.
+///
+/// The SVG is split into nine regions at the `margins`, which are distances from its edges in the
+/// coordinate system of the SVG. The corners keep their size, the edges are stretched along them
+/// and the center in both directions. Margins that are larger than the size shrink. Without a
+/// [`SvgSize`], the SVG is drawn as it is.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgNineSlice {
+    /// Distances of the split lines from the edges of the SVG.
+    pub margins: Rect<f32>,
+}
+
+impl SvgNineSlice {
+    /// Splits the SVG at the same distance from all of its edges.
+    pub fn uniform(margin: f32) -> Self {
+        Self { margins: Rect { left: margin, right: margin, top: margin, bottom: margin } }
+    }
+}
+
+/// Repeats the [`Svg`] of an entity to fill a region, for vector backgrounds and repeating
+/// decorative borders.
+///
+/// The tiles are as large as the SVG, which itself is the tile at the origin. The region is given
+/// in the coordinate system of the SVG, where the y-axis points down, and everything outside of it
+/// is cut off. The [`Origin`](crate::prelude::Origin) still refers to the size of the SVG.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct SvgTiling {
+    /// The region that is filled with tiles.
+    #[reflect(ignore)]
+    pub rect: lyon_tessellation::math::Rect,
+    /// Whether every other tile is mirrored, so that the edges of neighboring tiles match.
+    pub mirror: bool,
+}
+
+impl SvgTiling {
+    /// Fills the region of the given `size` whose top left corner is at `min`.
+    pub fn new(min: Vec2, size: Vec2) -> Self {
+        Self { rect: lyon_geom::rect(min.x, min.y, size.x, size.y), mirror: false }
+    }
+
+    /// Mirrors every other tile.
+    #[must_use]
+    pub fn mirrored(mut self) -> Self {
+        self.mirror = true;
+        self
+    }
+}
+
+/// Extrudes the [`Svg`] of an entity into a solid that is `depth` deep, which turns a flat logo
+/// into a 3D mesh for a [`Svg3dBundle`](crate::prelude::Svg3dBundle).
+///
+/// The SVG stays the front, a copy of it `depth` behind is the back, and walls connect the
+/// outlines of both. The walls have the colors of the shapes, a bit darker, so the depth shows
+/// without lighting. Extruded SVGs are drawn with depth writes, so they hide what is behind them.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgExtrusion {
+    /// How deep the solid is, in the coordinate system of the SVG.
+    pub depth: f32,
+}
+
+/// All runtime changes that are applied while tessellating the [`Svg`] of an entity.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Overrides {
+    /// Added to the dash offset of every dashed stroke.
+    pub(crate) dash_offset: f32,
+    /// Share of the length of every stroke that is drawn, see [`SvgDrawOn`].
+    pub(crate) draw_on: Option<f32>,
+    /// Colors of CSS custom properties, keyed by their name including the leading `--`. The
+    /// [`SvgColor`] is stored as [`CURRENT_COLOR_VARIABLE`].
+    pub(crate) css_variables: HashMap<String, Color>,
+    /// Replaces the fill rule of every fill.
+    pub(crate) fill_rule: Option<FillRule>,
+    /// Colors that replace the fills of elements, keyed by their ID.
+    pub(crate) fills: HashMap<String, Color>,
+    /// Colors that replace the strokes of elements, keyed by their ID.
+    pub(crate) strokes: HashMap<String, Color>,
+    /// Animated opacities of groups, keyed by their ID.
+    pub(crate) opacities: HashMap<String, f32>,
+    /// Animated `fill-opacity` of shapes, keyed by their ID.
+    pub(crate) fill_opacities: HashMap<String, f32>,
+    /// Animated `stroke-opacity` of shapes, keyed by their ID.
+    pub(crate) stroke_opacities: HashMap<String, f32>,
+    /// Animated transforms of groups, keyed by their ID.
+    pub(crate) transforms: HashMap<String, AnimatedTransform>,
+    /// Whether elements are shown, keyed by their ID.
+    pub(crate) visibility: HashMap<String, bool>,
+    /// Content that replaces the content of `text` elements, keyed by their ID.
+    pub(crate) texts: HashMap<String, String>,
+    /// Scale of the entity, which the width of non-scaling strokes is divided by.
+    pub(crate) scale: Option<f32>,
+    /// Nine-slice scaling of the tessellated SVG.
+    pub(crate) nine_slice: Option<NineSlice>,
+    /// Repeats the tessellated SVG to fill a region, after the nine-slice scaling.
+    pub(crate) tiling: Option<SvgTiling>,
+    /// Depth that the tessellated SVG is extruded by, after the tiling.
+    pub(crate) extrusion: Option<f32>,
+}
+
+/// The own mesh of an entity, which was tessellated with [`Overrides`].
+#[derive(Component)]
+pub(crate) struct OverriddenMesh {
+    svg: Handle<Svg>,
+    overrides: Overrides,
+    /// The colors of custom properties that are transitioning, or were.
+    transitions: ColorTransitions,
+    mesh: Handle<Mesh>,
+    /// Where the elements of the [`SvgNodeVisibility`] are in the mesh, if they can be hidden by
+    /// leaving out their triangles.
+    masking: Option<Masking>,
+    /// The texts of the [`SvgTextOverrides`], laid out with their new content.
+    shaped: ShapedTexts,
+}
+
+/// All indices of a mesh that was tessellated with the elements of a [`SvgNodeVisibility`] shown,
+/// and where the triangles of the elements are in them.
+struct Masking {
+    indices: Vec<IndexType>,
+    /// The vertices of the mesh, only kept with [`SvgTextOverrides`], whose triangles are replaced
+    /// in them by [`replace_texts`].
+    vertices: Vec<Vertex>,
+    ranges: ElementRanges,
+}
+
+/// Advances the offset of all [`SvgDashOffset`]s with a speed.
+pub(crate) fn animate_dash_offset(time: Res<Time>, mut query: Query<&mut SvgDashOffset>) {
+    for mut dash_offset in query.iter_mut() {
+        if dash_offset.speed != 0.0 {
+            dash_offset.offset += dash_offset.speed * time.delta_seconds();
+        }
+    }
+}
+
+/// Tessellates the mesh of every entity with overrides again, when the overrides or the
+/// [`Svg`] changed.
+pub(crate) fn update_overridden_meshes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    fonts: Res<SvgFontDatabase>,
+    svgs: Res<Assets<Svg>>,
+    mut query: Query<
+        (
+            Entity,
+            &Handle<Svg>,
+            (Option<&SvgDashOffset>, Option<&SvgDrawOn>),
+            Option<&SvgCssVariables>,
+            Option<&SvgColor>,
+            Option<&SvgPaintOverrides>,
+            Option<&SvgFillRule>,
+            (Option<&SvgNodeVisibility>, Option<&SvgGroupTransforms>, Option<&SvgTextOverrides>),
+            Option<&SvgAnimationPlayer>,
+            (Option<&SvgNineSlice>, Option<&SvgSize>, Option<&SvgScaleMode>, Option<&SvgTiling>, Option<&SvgExtrusion>),
+            Option<&GlobalTransform>,
+            Option<&mut OverriddenMesh>,
+            Option<&mut Mesh2dHandle>,
+            Option<&mut Handle<Mesh>>,
+        ),
+        // Every entity can be scaled, which changes the mesh if the SVG has non-scaling strokes.
+        // Path entities and morphs have meshes of their own.
+        (Or<(With<SvgDashOffset>, With<SvgDrawOn>, With<SvgCssVariables>, With<SvgColor>, With<SvgPaintOverrides>, With<SvgFillRule>, With<SvgNodeVisibility>, With<SvgGroupTransforms>, With<SvgTextOverrides>, With<SvgAnimationPlayer>, With<SvgNineSlice>, With<SvgTiling>, With<SvgExtrusion>, With<OverriddenMesh>, With<GlobalTransform>)>, Without<SvgPathId>, Without<SvgMorph>),
+    >,
+) {
+    let modified: Vec<_> = svg_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, handle, (dash_offset, draw_on), css_variables, color, paints, fill_rule, (visibility, group_transforms, text_overrides), player, (nine_slice, size, scale_mode, tiling, extrusion), global_transform, overridden, mesh_2d, mesh_3d) in query.iter_mut() {
+        let svg = match svgs.get(handle) {
+            // Baked SVGs have no paths that could be tessellated again.
+            Some(svg) if svg.paths.is_empty() => continue,
+            Some(svg) => svg,
+            // Wait until the svg is loaded.
+            None => continue,
+        };
+        // Non-uniform scales are approximated with the geometric mean of both axes.
+        let scale = global_transform
+            .map(|global_transform| (global_transform.scale.x * global_transform.scale.y).abs().sqrt())
+            .filter(|scale| *scale > 0.0 && (scale - 1.0).abs() > f32::EPSILON)
+            .filter(|_| svg.paths.iter().any(|path| path.non_scaling_stroke));
+        let nine_slice = match (nine_slice, size) {
+            (Some(nine_slice), Some(size)) => {
+                let (mesh_scale, _) = size.layout(svg.size, scale_mode.copied().unwrap_or_default());
+                Some(NineSlice::new(nine_slice.margins, svg.size, mesh_scale))
+            },
+            _ => None,
+        };
+        let now = time.seconds_since_startup();
+        let mut transitions = overridden.as_ref().map(|overridden| overridden.transitions.clone()).unwrap_or_default();
+        let mut targets = css_variables.map(|css_variables| css_variables.variables.clone()).unwrap_or_default();
+        if let Some(color) = color {
+            targets.insert(CURRENT_COLOR_VARIABLE.to_owned(), color.0);
+        }
+        let colors = transitions.update(&svg.transitions, |name| initial_color(svg, name), &targets, now);
+        // A transition back to the colors of the file keeps the own mesh until it ended.
+        let transitioning = transitions.is_running(&svg.transitions, now);
+        let overrides = if dash_offset.is_some() || draw_on.is_some() || css_variables.is_some() || color.is_some() || paints.is_some() || fill_rule.is_some() || visibility.is_some() || group_transforms.is_some() || text_overrides.is_some() || player.is_some() || transitioning || scale.is_some() || nine_slice.is_some() || tiling.is_some() || extrusion.is_some() {
+            let mut overrides = Overrides {
+                dash_offset: dash_offset.map_or(0.0, |dash_offset| dash_offset.offset),
+                // A complete reveal is the same as drawing the strokes as they are.
+                draw_on: draw_on.map(|draw_on| draw_on.progress).filter(|progress| *progress < 1.0),
+                css_variables: colors,
+                fill_rule: fill_rule.map(|fill_rule| fill_rule.0),
+                fills: paints.map(|paints| paints.fills.clone()).unwrap_or_default(),
+                strokes: paints.map(|paints| paints.strokes.clone()).unwrap_or_default(),
+                visibility: visibility.map(|visibility| visibility.visibility.clone()).unwrap_or_default(),
+                texts: text_overrides.map(|text_overrides| text_overrides.texts.clone()).unwrap_or_default(),
+                scale,
+                nine_slice,
+                tiling: tiling.copied(),
+                extrusion: extrusion.map(|extrusion| extrusion.depth),
+                ..Default::default()
+            };
+            if let Some(player) = player {
+                animation::apply(&svg.animations, player.elapsed, &mut overrides);
+            }
+            if let Some(group_transforms) = group_transforms {
+                apply_group_transforms(svg, group_transforms, &mut overrides);
+            }
+            Some(overrides)
+        } else {
+            None
+        };
+
+        let mesh = match (overrides, overridden) {
+            // All overrides were removed, go back to the shared mesh.
+            (None, Some(overridden)) => {
+                meshes.remove(&overridden.mesh);
+                commands.entity(entity).remove::<OverriddenMesh>();
+                svg.mesh.clone()
+            },
+            (Some(overrides), Some(mut overridden)) => {
+                let outdated = overridden.overrides != overrides || overridden.svg != *handle || modified.contains(handle);
+                if outdated || !meshes.contains(&overridden.mesh) {
+                    let only_visibility = overridden.svg == *handle && !modified.contains(handle)
+                        && Overrides { visibility: overridden.overrides.visibility.clone(), ..overrides.clone() } == overridden.overrides;
+                    let only_texts = overridden.svg == *handle && !modified.contains(handle)
+                        && Overrides { visibility: overridden.overrides.visibility.clone(), texts: overridden.overrides.texts.clone(), ..overrides.clone() } == overridden.overrides;
+                    let masked = overridden.masking.as_ref()
+                        .filter(|_| only_visibility)
+                        .and_then(|masking| masking.ranges.mask(&masking.indices, &overrides.visibility, &svg.hidden));
+                    let replaced = match masked {
+                        Some(_) => None,
+                        None => {
+                            let OverriddenMesh { overrides: previous, masking, shaped, .. } = &mut *overridden;
+                            masking.as_mut()
+                                .filter(|_| only_texts)
+                                .and_then(|masking| replace_texts(svg, &previous.texts, &overrides, masking, shaped, &fonts, &mut fill_tess, &mut stroke_tess))
+                        },
+                    };
+                    match (masked, replaced, meshes.get_mut(&overridden.mesh)) {
+                        // Only elements were shown or hidden, which are already part of the mesh.
+                        (Some(indices), _, Some(mesh)) => mesh.set_indices(Some(Indices::U32(indices))),
+                        // Only the triangles of the replaced texts changed.
+                        (_, Some(replaced), Some(mesh)) => *mesh = replaced,
+                        (_, _, old_mesh) => {
+                            if overridden.svg != *handle || modified.contains(handle) {
+                                overridden.shaped = ShapedTexts::default();
+                            }
+                            let (mesh, masking) = generate_mesh(svg, &overrides, &mut overridden.shaped, &fonts, &mut fill_tess, &mut stroke_tess);
+                            match old_mesh {
+                                Some(old_mesh) => *old_mesh = mesh,
+                                None => overridden.mesh = meshes.add(mesh),
+                            }
+                            overridden.masking = masking;
+                        },
+                    }
+                    overridden.svg = handle.clone();
+                    overridden.overrides = overrides;
+                }
+                if overridden.transitions != transitions {
+                    overridden.transitions = transitions;
+                }
+                overridden.mesh.clone()
+            },
+            (Some(overrides), None) => {
+                let mut shaped = ShapedTexts::default();
+                let (mesh, masking) = generate_mesh(svg, &overrides, &mut shaped, &fonts, &mut fill_tess, &mut stroke_tess);
+                let mesh = meshes.add(mesh);
+                commands.entity(entity).insert(OverriddenMesh { svg: handle.clone(), overrides, transitions, mesh: mesh.clone(), masking, shaped });
+                mesh
+            },
+            (None, None) => continue,
+        };
+
+        if let Some(mut mesh_2d) = mesh_2d {
+            if mesh_2d.0 != mesh {
+                mesh_2d.0 = mesh.clone();
+            }
+        }
+        if let Some(mut mesh_3d) = mesh_3d {
+            if *mesh_3d != mesh {
+                *mesh_3d = mesh;
+            }
+        }
+    }
+}
+
+/// Applies the [`SvgGroupTransforms`] before the animated transforms in `overrides`.
+fn apply_group_transforms(svg: &Svg, group_transforms: &SvgGroupTransforms, overrides: &mut Overrides) {
+    for (id, transform) in &group_transforms.transforms {
+        let group = svg.paths.iter()
+            .flat_map(|path| path.groups.iter())
+            .find(|group| group.id == *id);
+        let group = match group {
+            Some(group) => group,
+            None => continue,
+        };
+        // Bevy has a different y-axis origin, so the transform is mirrored along that axis.
+        let matrix = transform.compute_matrix();
+        let transform = Transform2D::new(
+            matrix.x_axis.x, -matrix.x_axis.y,
+            -matrix.y_axis.x, matrix.y_axis.y,
+            matrix.w_axis.x, -matrix.w_axis.y,
+        );
+        let animated = overrides.transforms.entry(id.clone())
+            .or_insert_with(|| AnimatedTransform::new(group.transform));
+        animated.added = transform.then(&animated.added);
+    }
+}
+
+/// Tessellates the mesh of an entity with `overrides`. With elements whose visibility is set, all
+/// of them are tessellated and the hidden ones are left out of the indices, so they can be toggled
+/// later on without tessellating again. Replaced texts are laid out with the `fonts`, the ones
+/// that are already in `shaped` are reused, and where their triangles are is kept as well, so
+/// [`replace_texts`] can swap them when their content changes.
+fn generate_mesh(
+    svg: &Svg,
+    overrides: &Overrides,
+    shaped: &mut ShapedTexts,
+    fonts: &SvgFontDatabase,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+) -> (Mesh, Option<Masking>) {
+    let substituted = shaped.substitute(svg, &overrides.texts, fonts);
+    let svg = substituted.as_ref().unwrap_or(svg);
+    let maskable = !overrides.visibility.is_empty() || !overrides.texts.is_empty();
+    if maskable && overrides.nine_slice.is_none() && overrides.tiling.is_none() && overrides.extrusion.is_none() {
+        let (buffer, ranges) = tessellation::generate_maskable_buffer(svg, overrides, fill_tess, stroke_tess);
+        if let Some(indices) = ranges.mask(&buffer.indices, &overrides.visibility, &svg.hidden) {
+            let vertices = match overrides.texts.is_empty() {
+                true => Vec::new(),
+                false => buffer.vertices.clone(),
+            };
+            let mesh = VertexBuffers { vertices: buffer.vertices, indices }.convert();
+            return (mesh, Some(Masking { indices: buffer.indices, vertices, ranges }));
+        }
+    }
+    (tessellation::generate_buffer(svg, overrides, fill_tess, stroke_tess).convert(), None)
+}
+
+/// Replaces the triangles of the texts whose content changed since `previous` in the buffer of
+/// `masking`, without tessellating the rest of `svg` again. Returns the new mesh, or `None` if a
+/// text can't be replaced on its own, like one whose triangles were merged with others by the
+/// effects of a group, and the whole SVG needs to be tessellated again.
+#[allow(clippy::too_many_arguments)]
+fn replace_texts(
+    svg: &Svg,
+    previous: &HashMap<String, String>,
+    overrides: &Overrides,
+    masking: &mut Masking,
+    shaped: &mut ShapedTexts,
+    fonts: &SvgFontDatabase,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+) -> Option<Mesh> {
+    if masking.vertices.is_empty() {
+        return None;
+    }
+    let changed: HashSet<&String> = previous.keys()
+        .chain(overrides.texts.keys())
+        .filter(|id| previous.get(*id) != overrides.texts.get(*id))
+        .collect();
+    let substituted = shaped.substitute(svg, &overrides.texts, fonts);
+    let svg = substituted.as_ref().unwrap_or(svg);
+
+    // Everything is checked before the buffer is changed, so it can still be tessellated as a whole.
+    let mut replacements = Vec::with_capacity(changed.len());
+    for id in changed {
+        let old = match masking.ranges.ranges.get(id).map(Vec::as_slice) {
+            // An empty range has no position among the ranges of its siblings.
+            Some([old]) if !old.is_empty() && !masking.ranges.unmaskable.contains(id) => old.clone(),
+            _ => return None,
+        };
+        let start = svg.paths.iter().position(|path| is_part_of(path, id)).unwrap_or(0);
+        let end = start + svg.paths[start..].iter().take_while(|path| is_part_of(path, id)).count();
+        if svg.paths[end..].iter().any(|path| is_part_of(path, id)) {
+            return None;
+        }
+        // The groups around the text are applied to its triangles alone, which is only the same
+        // as applying them to everything with an opacity of 1.
+        let ancestors: HashSet<&str> = svg.paths[start..end].iter()
+            .flat_map(|path| path.groups.iter().take_while(|group| group.id != *id))
+            .map(|group| {
+                let opacity = overrides.opacities.get(&group.id).copied().unwrap_or(group.opacity);
+                (opacity >= 1.0).then(|| group.id.as_str())
+            })
+            .collect::<Option<_>>()?;
+        let (buffer, ranges) = tessellation::generate_maskable_paths(svg, start..end, overrides, fill_tess, stroke_tess);
+        replacements.push((id, old, ancestors, buffer, ranges));
+    }
+
+    for (id, old, ancestors, buffer, ranges) in replacements {
+        let old = masking.ranges.ranges.get(id).and_then(|ranges| ranges.first()).cloned().unwrap_or(old);
+        let offset = masking.vertices.len() as IndexType;
+        let new_len = buffer.indices.len();
+        masking.vertices.extend(buffer.vertices);
+        masking.indices.splice(old.clone(), buffer.indices.iter().map(|index| index + offset));
+
+        // The ranges inside of the text are taken from the new triangles.
+        masking.ranges.replace(old.clone(), new_len);
+        for (inner, inner_ranges) in ranges.ranges {
+            if inner != *id && !ancestors.contains(inner.as_str()) {
+                let shifted = inner_ranges.into_iter().map(|range| range.start + old.start..range.end + old.start);
+                masking.ranges.ranges.entry(inner).or_default().extend(shifted);
+            }
+        }
+        masking.ranges.unmaskable.extend(ranges.unmaskable);
+    }
+
+    // The vertices of the old texts aren't used anymore.
+    let mut remap = vec![IndexType::MAX; masking.vertices.len()];
+    let mut vertices = Vec::with_capacity(masking.vertices.len());
+    for index in &mut masking.indices {
+        let new_index = &mut remap[*index as usize];
+        if *new_index == IndexType::MAX {
+            *new_index = vertices.len() as IndexType;
+            vertices.push(masking.vertices[*index as usize]);
+        }
+        *index = *new_index;
+    }
+    masking.vertices = vertices;
+
+    let indices = masking.ranges.mask(&masking.indices, &overrides.visibility, &svg.hidden)?;
+    Some(VertexBuffers { vertices: masking.vertices.clone(), indices }.convert())
+}
+
+/// Returns the color in the file of the shapes whose paint comes from the custom property `name`.
+fn initial_color(svg: &Svg, name: &str) -> Option<Color> {
+    svg.paths.iter()
+        .find(|path| path.paint_variable.as_deref() == Some(name))
+        .and_then(|path| match path.paint {
+            // The alpha is the fill or stroke opacity, which is applied separately.
+            Paint::Color(mut color) => {
+                color.set_a(1.0);
+                Some(color)
+            },
+            _ => None,
+        })
+}
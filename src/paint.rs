@@ -0,0 +1,405 @@
+//! Paint servers used to fill or stroke the paths of an [`Svg`](crate::svg::Svg).
+//!
+//! Gradients are resolved when the file is loaded and then baked into the vertex colors during
+//! tessellation, so the render pipelines only ever have to deal with per-vertex colors.
+
+use std::sync::Arc;
+
+use bevy::render::color::Color;
+use lyon_geom::euclid::default::Transform2D;
+use lyon_tessellation::math::{Point, Rect};
+
+use crate::{svg::PathDescriptor, Convert};
+
+
+/// Describes how a path gets colored.
+#[derive(Clone, Debug)]
+pub enum Paint {
+    /// A single flat color.
+    Color(Color),
+    /// A linear gradient, `linearGradient` in SVG.
+    LinearGradient(LinearGradient),
+    /// A radial gradient, `radialGradient` in SVG.
+    RadialGradient(RadialGradient),
+    /// A pattern, `pattern` in SVG.
+    Pattern(Pattern),
+    /// A raster image, `image` in SVG.
+    Image(RasterImage),
+}
+
+impl Default for Paint {
+    fn default() -> Self {
+        Paint::Color(Color::default())
+    }
+}
+
+impl Paint {
+    /// Computes the color of the paint at `point`, which is given in the coordinate
+    /// system of the tessellated path.
+    pub fn color_at(&self, point: Point) -> Color {
+        match self {
+            Paint::Color(color) => *color,
+            Paint::LinearGradient(gradient) => gradient.color_at(point),
+            Paint::RadialGradient(gradient) => gradient.color_at(point),
+            // Patterns and images are tessellated on their own, the color is never sampled.
+            Paint::Pattern(_) | Paint::Image(_) => Color::NONE,
+        }
+    }
+}
+
+/// A color stop of a gradient.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GradientStop {
+    /// Position of the stop along the gradient, in the range `0.0..=1.0`.
+    pub offset: f32,
+    /// Color of the stop, already multiplied with the stop and paint opacity.
+    pub color: Color,
+}
+
+/// How a gradient continues before its first and after its last stop, `spreadMethod` in SVG.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpreadMethod {
+    /// The colors of the first and last stop extend to the edges.
+    Pad,
+    /// The gradient is mirrored at each end, back and forth.
+    Reflect,
+    /// The gradient starts over at each end.
+    Repeat,
+}
+
+impl SpreadMethod {
+    /// Maps the offset `t` of a gradient into the range of the stops, `0.0..=1.0`.
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            SpreadMethod::Pad => t,
+            SpreadMethod::Reflect => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            },
+            SpreadMethod::Repeat => t.rem_euclid(1.0),
+        }
+    }
+}
+
+/// A linear gradient along the line from `start` to `end`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinearGradient {
+    /// Start of the gradient vector in gradient space.
+    pub start: Point,
+    /// End of the gradient vector in gradient space.
+    pub end: Point,
+    /// All color stops, sorted by their offset.
+    pub stops: Vec<GradientStop>,
+    /// How the gradient continues outside of the stops.
+    pub spread: SpreadMethod,
+    /// Maps a point from path space into gradient space.
+    pub transform: Transform2D<f32>,
+}
+
+impl LinearGradient {
+    /// Computes the color of the gradient at `point`, given in path space.
+    pub fn color_at(&self, point: Point) -> Color {
+        sample_stops(&self.stops, self.spread.apply(self.offset_at(point)))
+    }
+
+    /// Computes the gradient offset `t` for `point`, given in path space, which is the position of
+    /// the point projected onto the gradient vector.
+    pub(crate) fn offset_at(&self, point: Point) -> f32 {
+        let point = self.transform.transform_point(point);
+        let direction = self.end - self.start;
+        let length_sq = direction.square_length();
+        if length_sq > 0.0 {
+            (point - self.start).dot(direction) / length_sq
+        } else {
+            // A zero-length gradient vector is painted with the color of the last stop.
+            1.0
+        }
+    }
+
+    /// Computes the color of a padded gradient at `offset` in the range from `from` to `to`, which
+    /// has no stop inside of it. At a hard edge on either end of the range, where two stops share
+    /// the offset, `offset` takes the color of the side inside of the range.
+    pub(crate) fn color_between(&self, from: f32, to: f32, offset: f32) -> Color {
+        sample_piece(&self.stops, (from + to) / 2.0, offset)
+    }
+}
+
+/// A radial gradient, spreading from the `focal` point to the circle around `center`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RadialGradient {
+    /// Center of the end circle in gradient space.
+    pub center: Point,
+    /// Radius of the end circle in gradient space.
+    pub radius: f32,
+    /// Focal point, where the gradient starts, in gradient space.
+    pub focal: Point,
+    /// All color stops, sorted by their offset.
+    pub stops: Vec<GradientStop>,
+    /// How the gradient continues outside of the stops.
+    pub spread: SpreadMethod,
+    /// Maps a point from path space into gradient space.
+    pub transform: Transform2D<f32>,
+}
+
+impl RadialGradient {
+    /// Computes the color of the gradient at `point`, given in path space.
+    pub fn color_at(&self, point: Point) -> Color {
+        let point = self.transform.transform_point(point);
+        sample_stops(&self.stops, self.spread.apply(self.offset_at(point)))
+    }
+
+    /// Computes the gradient offset `t` for `point`, which is the solution of
+    /// `|point - focal - t * (center - focal)| = t * radius` with the largest `t`.
+    fn offset_at(&self, point: Point) -> f32 {
+        let d = self.center - self.focal;
+        let q = point - self.focal;
+        let a = d.square_length() - self.radius * self.radius;
+        let b = q.dot(d);
+        let c = q.square_length();
+
+        if a.abs() <= f32::EPSILON {
+            // The focal point lies on the end circle, which leaves only a single solution.
+            return if b.abs() > f32::EPSILON { c / (2.0 * b) } else { 0.0 };
+        }
+
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return 1.0;
+        }
+        let root = discriminant.sqrt();
+        ((b + root) / a).max((b - root) / a)
+    }
+}
+
+/// A pattern whose tile gets repeated across the painted area.
+///
+/// The tile is tessellated on its own, repeated to cover the whole path and then clipped
+/// to the shape of the filled or stroked path.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    /// All paths that make up a single tile.
+    pub tile: Vec<PathDescriptor>,
+    /// Maps the content of the tile at the origin into pattern space.
+    pub content_transform: Transform2D<f32>,
+    /// Rectangle of the tile at the origin in pattern space.
+    pub rect: Rect,
+    /// Opacity of the fill or stroke that uses this pattern.
+    pub opacity: f32,
+    /// Maps a point from pattern space into path space.
+    pub transform: Transform2D<f32>,
+}
+
+/// A decoded raster image, which gets drawn inside of the shape of its path.
+///
+/// The pixels are turned into a grid of colored vertices during tessellation, which is then
+/// clipped to the shape of the path.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RasterImage {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// The pixels in sRGB RGBA8, row by row.
+    pub data: Arc<Vec<u8>>,
+    /// Maps a point from image space, where a pixel has a size of 1, into path space.
+    pub transform: Transform2D<f32>,
+}
+
+/// Samples the color at offset `t` of a list of sorted gradient stops.
+fn sample_stops(stops: &[GradientStop], t: f32) -> Color {
+    sample_piece(stops, t, t)
+}
+
+/// Samples the color at offset `t` on the piece of the sorted gradient stops that contains the
+/// offset `within`, so `t` takes the color of the side of `within` at a hard edge.
+fn sample_piece(stops: &[GradientStop], within: f32, t: f32) -> Color {
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Color::NONE,
+    };
+    if within <= first.offset {
+        return first.color;
+    }
+    if within >= last.offset {
+        return last.color;
+    }
+
+    for pair in stops.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        if within <= to.offset {
+            let range = to.offset - from.offset;
+            let factor = if range > 0.0 { ((t - from.offset) / range).clamp(0.0, 1.0) } else { 1.0 };
+            return lerp_color(from.color, to.color, factor);
+        }
+    }
+
+    last.color
+}
+
+/// Linearly interpolates between two colors in sRGB space, as SVG does by default.
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let from = from.as_rgba_f32();
+    let to = to.as_rgba_f32();
+    Color::rgba(
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+        from[3] + (to[3] - from[3]) * t,
+    )
+}
+
+impl Convert<Transform2D<f32>> for usvg::Transform {
+    fn convert(self) -> Transform2D<f32> {
+        Transform2D::new(
+            self.a as f32, self.b as f32,
+            self.c as f32, self.d as f32,
+            self.e as f32, self.f as f32,
+        )
+    }
+}
+
+impl Convert<SpreadMethod> for usvg::SpreadMethod {
+    fn convert(self) -> SpreadMethod {
+        match self {
+            usvg::SpreadMethod::Pad => SpreadMethod::Pad,
+            usvg::SpreadMethod::Reflect => SpreadMethod::Reflect,
+            usvg::SpreadMethod::Repeat => SpreadMethod::Repeat,
+        }
+    }
+}
+
+impl Convert<Vec<GradientStop>> for (&[usvg::Stop], usvg::Opacity) {
+    fn convert(self) -> Vec<GradientStop> {
+        let (stops, opacity) = self;
+        let mut converted: Vec<GradientStop> = stops.iter()
+            .map(|stop| GradientStop {
+                offset: stop.offset.value() as f32,
+                color: Color::rgba_u8(
+                    stop.color.red,
+                    stop.color.green,
+                    stop.color.blue,
+                    (stop.opacity * opacity).to_u8(),
+                ),
+            })
+            .collect();
+        // `usvg` already guarantees ascending offsets, but sorting keeps `sample_stops` honest.
+        converted.sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap_or(std::cmp::Ordering::Equal));
+        converted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon_tessellation::math::point;
+
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-5, "{} is not {}", actual, expected);
+    }
+
+    fn stops(colors: &[(f32, Color)]) -> Vec<GradientStop> {
+        colors.iter().map(|&(offset, color)| GradientStop { offset, color }).collect()
+    }
+
+    fn radial(center: Point, radius: f32, focal: Point) -> RadialGradient {
+        RadialGradient {
+            center,
+            radius,
+            focal,
+            stops: stops(&[(0.0, Color::BLACK), (1.0, Color::WHITE)]),
+            spread: SpreadMethod::Pad,
+            transform: Transform2D::identity(),
+        }
+    }
+
+    #[test]
+    fn pad_keeps_offsets() {
+        assert_close(SpreadMethod::Pad.apply(-0.5), -0.5);
+        assert_close(SpreadMethod::Pad.apply(1.5), 1.5);
+    }
+
+    #[test]
+    fn reflect_mirrors_offsets() {
+        assert_close(SpreadMethod::Reflect.apply(0.25), 0.25);
+        assert_close(SpreadMethod::Reflect.apply(1.25), 0.75);
+        assert_close(SpreadMethod::Reflect.apply(2.5), 0.5);
+        assert_close(SpreadMethod::Reflect.apply(-0.25), 0.25);
+        assert_close(SpreadMethod::Reflect.apply(-1.25), 0.75);
+    }
+
+    #[test]
+    fn repeat_wraps_offsets() {
+        assert_close(SpreadMethod::Repeat.apply(1.25), 0.25);
+        assert_close(SpreadMethod::Repeat.apply(-0.25), 0.75);
+        assert_close(SpreadMethod::Repeat.apply(3.0), 0.0);
+    }
+
+    #[test]
+    fn centered_radial_offset_is_distance_over_radius() {
+        let gradient = radial(point(0.0, 0.0), 10.0, point(0.0, 0.0));
+        assert_close(gradient.offset_at(point(0.0, 0.0)), 0.0);
+        assert_close(gradient.offset_at(point(5.0, 0.0)), 0.5);
+        assert_close(gradient.offset_at(point(0.0, -10.0)), 1.0);
+        assert_close(gradient.offset_at(point(20.0, 0.0)), 2.0);
+    }
+
+    #[test]
+    fn radial_offset_starts_at_focal_point() {
+        let gradient = radial(point(0.0, 0.0), 10.0, point(5.0, 0.0));
+        assert_close(gradient.offset_at(point(5.0, 0.0)), 0.0);
+        assert_close(gradient.offset_at(point(-10.0, 0.0)), 1.0);
+        assert_close(gradient.offset_at(point(10.0, 0.0)), 1.0);
+        assert_close(gradient.offset_at(point(0.0, 10.0)), 1.0);
+    }
+
+    #[test]
+    fn radial_offset_with_focal_point_on_circle() {
+        let gradient = radial(point(0.0, 0.0), 10.0, point(10.0, 0.0));
+        assert_close(gradient.offset_at(point(10.0, 0.0)), 0.0);
+        assert_close(gradient.offset_at(point(0.0, 0.0)), 0.5);
+        assert_close(gradient.offset_at(point(-10.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn radial_offset_outside_of_cone_is_one() {
+        // With the focal point outside of the circle, points beside the cone have no solution.
+        let gradient = radial(point(0.0, 0.0), 1.0, point(10.0, 0.0));
+        assert_close(gradient.offset_at(point(10.0, 5.0)), 1.0);
+    }
+
+    #[test]
+    fn linear_offset_projects_onto_gradient_vector() {
+        let gradient = LinearGradient {
+            start: point(0.0, 0.0),
+            end: point(10.0, 0.0),
+            stops: stops(&[(0.0, Color::BLACK), (1.0, Color::WHITE)]),
+            spread: SpreadMethod::Pad,
+            transform: Transform2D::translation(-10.0, 0.0),
+        };
+        assert_close(gradient.offset_at(point(15.0, 7.0)), 0.5);
+        assert_close(gradient.offset_at(point(0.0, 0.0)), -1.0);
+    }
+
+    #[test]
+    fn stops_are_interpolated() {
+        let stops = stops(&[(0.0, Color::BLACK), (0.5, Color::WHITE), (1.0, Color::BLACK)]);
+        assert_close(sample_stops(&stops, 0.25).r(), 0.5);
+        assert_close(sample_stops(&stops, 0.5).r(), 1.0);
+        assert_close(sample_stops(&stops, 0.75).r(), 0.5);
+        assert_close(sample_stops(&stops, -1.0).r(), 0.0);
+        assert_close(sample_stops(&stops, 2.0).r(), 0.0);
+    }
+
+    #[test]
+    fn hard_edge_takes_color_of_side() {
+        let stops = stops(&[(0.0, Color::BLACK), (0.5, Color::BLACK), (0.5, Color::WHITE), (1.0, Color::WHITE)]);
+        assert_close(sample_piece(&stops, 0.25, 0.5).r(), 0.0);
+        assert_close(sample_piece(&stops, 0.75, 0.5).r(), 1.0);
+    }
+}
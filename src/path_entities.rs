@@ -0,0 +1,232 @@
+//! Entities for every path of an SVG, and optionally for its groups, so that its parts can be
+//! transformed, hidden, tinted or animated independently.
+
+use std::{collections::HashMap, sync::Arc};
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    core::Name,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{ChangeTrackers, With},
+        reflect::ReflectComponent,
+        system::{Commands, Query, RemovedComponents, Res, ResMut},
+    },
+    hierarchy::{BuildChildren, DespawnRecursiveExt},
+    math::{Mat4, Vec2, Vec4},
+    reflect::Reflect,
+    render::{
+        mesh::Mesh,
+        view::{ComputedVisibility, Visibility},
+    },
+    sprite::Mesh2dHandle,
+    transform::components::{GlobalTransform, Transform},
+};
+use lyon_geom::euclid::default::Transform2D;
+use lyon_tessellation::{FillTessellator, StrokeTessellator};
+
+use crate::{
+    overrides::Overrides,
+    render::tessellation,
+    svg::{Group, PathDescriptor, Svg},
+    Convert,
+};
+
+
+/// Draws every path of the [`Svg`] of an entity as a child entity of its own, instead of drawing
+/// the whole SVG as one mesh.
+///
+/// Each child has its own mesh and a [`SvgPathId`], and can be moved, hidden with its
+/// `Visibility` or tinted with a [`SvgTint`](crate::prelude::SvgTint) on its own. The children are
+/// spawned once the [`Svg`] is loaded and spawned again when it changes. They are placed with the
+/// [`Origin`](crate::prelude::Origin), [`SvgSize`](crate::prelude::SvgSize) and
+/// [`SvgYAxis`](crate::prelude::SvgYAxis) of this entity. Overrides like
+/// [`SvgColor`](crate::prelude::SvgColor) don't apply to them.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgPathEntities;
+
+/// Nests the path entities of an entity with [`SvgPathEntities`] in entities for the groups of
+/// the SVG, which have a [`SvgGroupId`]. Those are the `g` elements with an ID, and groups that
+/// hold effects like an opacity or a clip path.
+///
+/// The `Transform` of a group entity is the transform of the group relative to its parent group,
+/// so rotating or moving it moves all of its content, like the limbs of a character. Groups with a
+/// skewed transform keep it in the meshes of their paths and get no `Transform` of their own.
+/// Group entities aren't drawn, and as the `Visibility` of Bevy isn't inherited, hiding a group
+/// means hiding its path entities.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgGroupEntities;
+
+/// Identifies the path that a child entity of an entity with [`SvgPathEntities`] draws.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgPathId {
+    /// The ID of the shape that the path belongs to, can be empty. A shape with both a fill and
+    /// a stroke has a path for each.
+    pub id: String,
+    /// Index of the path in the [`Svg`], in drawing order.
+    pub index: usize,
+}
+
+/// Identifies the group that an entity of [`SvgGroupEntities`] stands for.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgGroupId {
+    /// The ID of the group, can be empty for groups that only hold effects, like an opacity.
+    pub id: String,
+}
+
+/// The entity with [`SvgPathEntities`] that a path entity belongs to, which decides where it is
+/// drawn.
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct SvgPartOf(pub(crate) Entity);
+
+/// The path and group entities that were spawned as children of an entity.
+#[derive(Component)]
+pub(crate) struct SvgPathChildren {
+    children: Vec<Entity>,
+    /// Whether the paths are nested in group entities.
+    grouped: bool,
+}
+
+/// Bevy system which spawns a child entity for every path of the [`Svg`] of entities with
+/// [`SvgPathEntities`], and removes them again when the component is removed.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn spawn_path_entities(
+    mut commands: Commands,
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    svgs: Res<Assets<Svg>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    removed: RemovedComponents<SvgPathEntities>,
+    parents: Query<
+        (Entity, &Handle<Svg>, ChangeTrackers<Handle<Svg>>, Option<&SvgPathChildren>, Option<&SvgGroupEntities>, Option<&Mesh2dHandle>),
+        With<SvgPathEntities>,
+    >,
+    all_children: Query<&SvgPathChildren>,
+) {
+    for entity in removed.iter() {
+        if let Ok(path_children) = all_children.get(entity) {
+            for child in &path_children.children {
+                commands.entity(*child).despawn_recursive();
+            }
+            commands.entity(entity).remove::<SvgPathChildren>();
+        }
+    }
+
+    let loaded: Vec<_> = svg_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.clone_weak()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    for (entity, handle, handle_tracker, path_children, grouped, mesh_2d) in parents.iter() {
+        let grouped = grouped.is_some();
+        let up_to_date = path_children.map_or(false, |path_children| path_children.grouped == grouped);
+        if up_to_date && !handle_tracker.is_changed() && !loaded.contains(handle) {
+            continue;
+        }
+        // Wait until the svg is loaded.
+        let svg = match svgs.get(handle) {
+            Some(svg) => svg,
+            None => continue,
+        };
+        for child in path_children.iter().flat_map(|path_children| path_children.children.iter()) {
+            commands.entity(*child).despawn_recursive();
+        }
+
+        let mut children = Vec::new();
+        // The entities of the groups with their accumulated transforms, keyed by their address.
+        let mut groups: HashMap<*const Group, (Entity, Transform)> = HashMap::new();
+        for (index, path) in svg.paths.iter().enumerate() {
+            let mut parent = None;
+            let mut accumulated = Transform::identity();
+            for group in path.groups.iter().filter(|_| grouped) {
+                let (group_entity, group_accumulated) = match groups.get(&Arc::as_ptr(group)) {
+                    Some(existing) => *existing,
+                    None => {
+                        let transform = group_transform(&group.transform).unwrap_or_default();
+                        let mut group_commands = commands.spawn_bundle((
+                            SvgGroupId { id: group.id.clone() },
+                            transform,
+                            GlobalTransform::default(),
+                        ));
+                        if !group.id.is_empty() {
+                            group_commands.insert(Name::new(group.id.clone()));
+                        }
+                        let group_entity = group_commands.id();
+                        match parent {
+                            Some(parent) => {
+                                commands.entity(parent).push_children(&[group_entity]);
+                            },
+                            None => children.push(group_entity),
+                        }
+                        let spawned = (group_entity, accumulated.mul_transform(transform));
+                        groups.insert(Arc::as_ptr(group), spawned);
+                        spawned
+                    },
+                };
+                parent = Some(group_entity);
+                accumulated = group_accumulated;
+            }
+
+            // The mesh is in the coordinate system of its innermost group entity.
+            let to_local = accumulated.compute_matrix().inverse();
+            let buffer = tessellation::generate_path_buffer(svg, index, to_local, &Overrides::default(), &mut fill_tess, &mut stroke_tess);
+            let mesh = meshes.add(buffer.convert());
+            let mut child = commands.spawn_bundle((
+                handle.clone(),
+                SvgPathId { id: path.id.clone(), index },
+                SvgPartOf(entity),
+                Transform::default(),
+                GlobalTransform::default(),
+                Visibility::default(),
+                ComputedVisibility::default(),
+            ));
+            match mesh_2d {
+                Some(_) => child.insert(Mesh2dHandle(mesh)),
+                None => child.insert(mesh),
+            };
+            let child = child.id();
+            match parent {
+                Some(parent) => {
+                    commands.entity(parent).push_children(&[child]);
+                },
+                None => children.push(child),
+            }
+        }
+        commands.entity(entity).push_children(&children).insert(SvgPathChildren { children, grouped });
+    }
+}
+
+/// Returns the transform of the mesh of the path entity of `path` relative to its root entity, the
+/// accumulated transform of its group entities if the paths are `grouped`.
+pub(crate) fn path_mesh_transform(path: &PathDescriptor, grouped: bool) -> Transform {
+    path.groups.iter()
+        .filter(|_| grouped)
+        .fold(Transform::identity(), |accumulated, group| {
+            accumulated.mul_transform(group_transform(&group.transform).unwrap_or_default())
+        })
+}
+
+/// Converts the transform of a group relative to its parent into a [`Transform`] with the y-axis
+/// of Bevy. Returns `None` for skewed transforms, which a [`Transform`] can't hold.
+fn group_transform(transform: &Transform2D<f32>) -> Option<Transform> {
+    let x_axis = Vec2::new(transform.m11, -transform.m12);
+    let y_axis = Vec2::new(-transform.m21, transform.m22);
+    let lengths = x_axis.length() * y_axis.length();
+    if lengths == 0.0 || x_axis.dot(y_axis).abs() > 1e-4 * lengths {
+        return None;
+    }
+    Some(Transform::from_matrix(Mat4::from_cols(
+        x_axis.extend(0.0).extend(0.0),
+        y_axis.extend(0.0).extend(0.0),
+        Vec4::Z,
+        Vec4::new(transform.m31, -transform.m32, 0.0, 1.0),
+    )))
+}
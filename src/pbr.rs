@@ -0,0 +1,146 @@
+//! Draws 3D SVGs with bevy's [`StandardMaterial`], so vector decals and signs are lit like the
+//! rest of the scene.
+
+use std::collections::HashSet;
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle, HandleId},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::{ChangeTrackers, Without},
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::Vec2,
+    pbr::{AlphaMode, StandardMaterial},
+    render::{mesh::Mesh, texture::Image},
+};
+
+use crate::{render::raster::Raster, svg::Svg};
+
+
+/// Upper limit for the width and height of the texture of a SVG in pixels.
+const MAX_TEXTURE_SIZE: f32 = 2048.0;
+
+/// Draws the [`Svg`] of a 3D entity with bevy's [`StandardMaterial`] instead of the unlit SVG
+/// pipeline, so it is lit by the lights of the scene and has the metallic, roughness and emissive
+/// of `material`.
+///
+/// bevy's materials have no vertex colors, so the SVG is rasterized into the
+/// `base_color_texture` of the material, with `resolution` pixels per unit of the SVG, and again
+/// whenever the mesh of the entity changes. The texture is drawn on that mesh, so the outline of
+/// the shapes stays sharp, and runtime overrides like [`SvgColor`](crate::prelude::SvgColor)
+/// apply. `base_color` tints the SVG, [`SvgTint`](crate::prelude::SvgTint) and
+/// [`SvgDoubleSided`](crate::prelude::SvgDoubleSided) don't apply.
+///
+/// ```ignore
+/// commands
+///     .spawn_bundle(Svg3dBundle { svg: asset_server.load("exit_sign.svg"), ..Default::default() })
+///     .insert(SvgStandardMaterial {
+///         material: StandardMaterial { emissive: Color::rgb(0.0, 0.4, 0.0), ..Default::default() },
+///         ..Default::default()
+///     });
+/// ```
+#[derive(Component, Clone, Debug)]
+pub struct SvgStandardMaterial {
+    /// The material the SVG is drawn with, its `base_color_texture` is replaced by the SVG.
+    pub material: StandardMaterial,
+    /// Pixels of the texture per unit of the SVG.
+    pub resolution: f32,
+}
+
+impl Default for SvgStandardMaterial {
+    fn default() -> Self {
+        Self {
+            // The texture is transparent around the shapes and where they are.
+            material: StandardMaterial { alpha_mode: AlphaMode::Blend, ..Default::default() },
+            resolution: 4.0,
+        }
+    }
+}
+
+/// The texture and material of an entity with a [`SvgStandardMaterial`].
+#[derive(Component)]
+pub(crate) struct PbrSvg {
+    material: Handle<StandardMaterial>,
+    image: Handle<Image>,
+    /// Size of the image in pixels.
+    size: (u32, u32),
+    /// The mesh that was rasterized into the image.
+    mesh: HandleId,
+}
+
+/// Bevy system which rasterizes the mesh of every entity with a [`SvgStandardMaterial`] again
+/// when it or the resolution changed, updates their materials, and switches entities whose
+/// [`SvgStandardMaterial`] was removed back to the SVG pipeline.
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_pbr_svgs(
+    mut commands: Commands,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    svgs: Res<Assets<Svg>>,
+    meshes: Res<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut query: Query<(Entity, &Handle<Svg>, &Handle<Mesh>, &SvgStandardMaterial, ChangeTrackers<SvgStandardMaterial>, Option<&mut PbrSvg>)>,
+    removed: Query<(Entity, &PbrSvg), Without<SvgStandardMaterial>>,
+) {
+    let changed_meshes: HashSet<_> = mesh_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+
+    for (entity, svg_handle, mesh_handle, svg_material, tracker, pbr_svg) in query.iter_mut() {
+        let (svg, mesh) = match (svgs.get(svg_handle), meshes.get(mesh_handle)) {
+            (Some(svg), Some(mesh)) if svg.size.min_element() > 0.0 => (svg, mesh),
+            _ => continue,
+        };
+        let pixels = (svg.size * svg_material.resolution).round().clamp(Vec2::ONE, Vec2::splat(MAX_TEXTURE_SIZE));
+        let pixels = (pixels.x as u32, pixels.y as u32);
+        let rasterize = || Raster::from_mesh(mesh, svg.size, pixels.0 as usize, pixels.1 as usize).to_image();
+
+        match pbr_svg {
+            Some(mut pbr_svg) => {
+                let outdated = pbr_svg.size != pixels || pbr_svg.mesh != mesh_handle.id || changed_meshes.contains(&mesh_handle.id);
+                if outdated || !images.contains(&pbr_svg.image) {
+                    match images.get_mut(&pbr_svg.image) {
+                        Some(old_image) => *old_image = rasterize(),
+                        None => pbr_svg.image = images.add(rasterize()),
+                    }
+                    pbr_svg.size = pixels;
+                    pbr_svg.mesh = mesh_handle.id;
+                }
+                // The bind group of the material only sees a new texture when the material changes too.
+                if outdated || tracker.is_changed() || !materials.contains(&pbr_svg.material) {
+                    let material = StandardMaterial { base_color_texture: Some(pbr_svg.image.clone()), ..svg_material.material.clone() };
+                    match materials.get_mut(&pbr_svg.material) {
+                        Some(old_material) => *old_material = material,
+                        None => {
+                            pbr_svg.material = materials.add(material);
+                            commands.entity(entity).insert(pbr_svg.material.clone());
+                        },
+                    }
+                }
+            },
+            None => {
+                let image = images.add(rasterize());
+                let material = materials.add(StandardMaterial { base_color_texture: Some(image.clone()), ..svg_material.material.clone() });
+                commands.entity(entity).insert_bundle((
+                    material.clone(),
+                    PbrSvg { material, image, size: pixels, mesh: mesh_handle.id },
+                ));
+            },
+        }
+    }
+
+    // The material was removed, go back to the SVG pipeline.
+    for (entity, pbr_svg) in removed.iter() {
+        materials.remove(&pbr_svg.material);
+        images.remove(&pbr_svg.image);
+        commands.entity(entity)
+            .remove::<PbrSvg>()
+            .remove::<Handle<StandardMaterial>>();
+    }
+}
@@ -0,0 +1,160 @@
+//! Picking of SVG entities with the mouse pointer, which tests the paths of the SVG instead of its
+//! triangles or bounding box, so a click on a transparent corner of an icon misses it.
+//!
+//! `bevy_mod_picking` has no backend API for this version of Bevy, so the picks are exposed as the
+//! [`SvgHovered`] resource and the [`SvgClicked`] event, which are updated for every camera that
+//! renders to a window.
+
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventWriter,
+        query::{With, Without},
+        reflect::ReflectComponent,
+        system::{Query, Res, ResMut},
+    },
+    input::{mouse::MouseButton, Input},
+    math::{Vec2, Vec3},
+    reflect::Reflect,
+    render::{
+        camera::{Camera, RenderTarget},
+        view::ComputedVisibility,
+    },
+    transform::components::GlobalTransform,
+    window::Windows,
+};
+
+use crate::{
+    path_entities::{path_mesh_transform, SvgGroupEntities, SvgPartOf, SvgPathEntities, SvgPathId},
+    render::{placement_correction, Placement},
+    svg::{element_id, Svg, SvgYAxis},
+};
+
+
+/// Makes an entity with an [`Svg`] pickable with the mouse pointer. For an entity with
+/// [`SvgPathEntities`], all of its path entities are pickable.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgPickable;
+
+/// A [`SvgPickable`] entity under the mouse pointer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgPick {
+    /// The entity that was hit, a path entity if the SVG is drawn with [`SvgPathEntities`].
+    pub entity: Entity,
+    /// The ID of the element that was hit, like [`Svg::hit_test`] returns it.
+    pub element: Option<String>,
+    /// The position of the pointer in the coordinate system of the SVG, where the y-axis points
+    /// down.
+    pub position: Vec2,
+}
+
+/// Resource with the topmost [`SvgPickable`] entity under the mouse pointer, updated every frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SvgHovered(pub Option<SvgPick>);
+
+/// Sent when the left mouse button is pressed over a [`SvgPickable`] entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgClicked(pub SvgPick);
+
+/// Bevy system which finds the [`SvgPickable`] entity under the mouse pointer, testing the ray of
+/// the pointer against the paths of the SVGs where they are drawn.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn pick_svgs(
+    windows: Res<Windows>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    svgs: Res<Assets<Svg>>,
+    default_y_axis: Res<SvgYAxis>,
+    mut hovered: ResMut<SvgHovered>,
+    mut clicked: EventWriter<SvgClicked>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    entities: Query<
+        (Entity, &Handle<Svg>, &ComputedVisibility, &GlobalTransform, Option<&SvgPartOf>, Option<&SvgPathId>),
+        // The root of path entities draws nothing itself.
+        Without<SvgPathEntities>,
+    >,
+    pickables: Query<(), With<SvgPickable>>,
+    grouped: Query<(), With<SvgGroupEntities>>,
+    placements: Query<Placement>,
+) {
+    // The distance along the ray of the nearest hit, in units of the ray of its camera.
+    let mut nearest: Option<(f32, SvgPick)> = None;
+    for (camera, camera_transform) in cameras.iter() {
+        let window = match camera.target {
+            RenderTarget::Window(id) => windows.get(id),
+            RenderTarget::Image(_) => None,
+        };
+        let (window, cursor) = match window.and_then(|window| Some((window, window.cursor_position()?))) {
+            Some(found) => found,
+            None => continue,
+        };
+        // Bevy uses a reversed depth, the near plane has a depth of one.
+        let ndc = cursor / Vec2::new(window.width(), window.height()) * 2.0 - Vec2::ONE;
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+        let ray_start = ndc_to_world.project_point3(ndc.extend(1.0));
+        let ray_end = ndc_to_world.project_point3(ndc.extend(0.5));
+
+        for (entity, handle, computed_visibility, global_transform, part_of, path_id) in entities.iter() {
+            let root = part_of.map_or(entity, |part_of| part_of.0);
+            if !computed_visibility.is_visible || !(pickables.contains(entity) || pickables.contains(root)) {
+                continue;
+            }
+            let (svg, placement) = match (svgs.get(handle), placements.get(root)) {
+                (Some(svg), Ok(placement)) => (svg, placement),
+                _ => continue,
+            };
+            let model = placement_correction(svg, placement, *default_y_axis) * global_transform.compute_matrix();
+            if model.determinant() == 0.0 {
+                continue;
+            }
+
+            // The mesh lies in the plane where its z is zero.
+            let to_mesh = model.inverse();
+            let (start, end) = (to_mesh.transform_point3(ray_start), to_mesh.transform_point3(ray_end));
+            if start.z == end.z {
+                continue;
+            }
+            let distance = start.z / (start.z - end.z);
+            if distance < 0.0 || nearest.as_ref().map_or(false, |(nearest, _)| *nearest <= distance) {
+                continue;
+            }
+            let mesh_point = start + (end - start) * distance;
+
+            let pick = match path_id.and_then(|path_id| svg.paths.get(path_id.index)) {
+                Some(path) => {
+                    // The mesh of a path entity is in the coordinate system of its group entities.
+                    let mesh_point = path_mesh_transform(path, grouped.contains(root)).mul_vec3(mesh_point);
+                    let position = to_svg(mesh_point);
+                    svg.is_hit(path, position).then(|| SvgPick {
+                        entity,
+                        element: element_id(path).map(str::to_owned),
+                        position,
+                    })
+                },
+                None => {
+                    let position = to_svg(mesh_point);
+                    svg.hit_paths(position).next().map(|path| SvgPick {
+                        entity,
+                        element: element_id(path).map(str::to_owned),
+                        position,
+                    })
+                },
+            };
+            if let Some(pick) = pick {
+                nearest = Some((distance, pick));
+            }
+        }
+    }
+
+    hovered.0 = nearest.map(|(_, pick)| pick);
+    if let Some(pick) = hovered.0.as_ref().filter(|_| mouse_buttons.just_pressed(MouseButton::Left)) {
+        clicked.send(SvgClicked(pick.clone()));
+    }
+}
+
+/// Converts a point of a mesh with the y-axis of Bevy into the coordinate system of the SVG.
+fn to_svg(mesh_point: Vec3) -> Vec2 {
+    Vec2::new(mesh_point.x, -mesh_point.y)
+}
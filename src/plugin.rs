@@ -11,26 +11,48 @@
 //! [`RenderWorld`](bevy::render::RenderWorld).
 //! Afterwards it is queued in the [`RenderStage::Queue`](bevy::render::RenderStage) for actual drawing/rendering.
 
-use std::ops::Deref;
+use std::{collections::{HashMap, HashSet}, ops::Deref, path::PathBuf};
 
 use bevy::{
     app::{App, Plugin},
-    asset::{AddAsset, AssetEvent, Assets, Handle},
+    asset::{AddAsset, AssetEvent, AssetServer, AssetServerSettings, Assets, Handle, HandleId},
     ecs::{
         entity::Entity,
-        event::EventReader,
-        schedule::{StageLabel, SystemStage},
-        system::{Commands, Query, Res, ResMut},
-        query::Changed,
+        event::{EventReader, EventWriter},
+        schedule::{ParallelSystemDescriptorCoercion, StageLabel, SystemLabel, SystemStage},
+        system::{Commands, Local, Query, Res, ResMut},
+        query::{Changed, Without},
     },
     hierarchy::DespawnRecursiveExt,
     log::debug,
-    render::mesh::Mesh,
+    render::{mesh::Mesh, view::VisibilitySystems},
     sprite::Mesh2dHandle,
+    tasks::AsyncComputeTaskPool,
+    transform::TransformSystem,
 };
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+use bevy::asset::FileAssetIo;
 use lyon_tessellation::{FillTessellator, StrokeTessellator};
 
-use crate::{loader::SvgAssetLoader, render, svg::Svg};
+use crate::{
+    animation::{advance_animation_players, SvgAnimationPlayer},
+    baked::SvgMeshLoader,
+    diagnostics::{report_failed_loads, report_skipped_features, LoadFailures, SvgLoadFailed, SvgLoadWarning},
+    font::{SvgFontDatabase, SvgFonts},
+    icons::SvgIconRegistry,
+    loader::{SharedMeshes, SvgAssetLoader, SvgDpi, SvgLanguages, SvgLoadSettings, SvgParserOptions, SvgStyleSheet, SvgStyleSheetLoader},
+    morph::{update_morphed_meshes, SvgMorph},
+    overrides::{
+        animate_dash_offset, update_overridden_meshes, OverriddenMesh, SvgColor, SvgColorMatrix, SvgCssVariables, SvgDashOffset, SvgDrawOn, SvgExtrusion,
+        SvgGroupTransforms, SvgNineSlice, SvgNodeVisibility, SvgPaintOverrides, SvgTextOverrides, SvgTiling, SvgTint,
+    },
+    path_entities::{spawn_path_entities, SvgGroupEntities, SvgGroupId, SvgPathEntities, SvgPathId},
+    progress::SvgLoadProgress,
+    render::{self, tessellation::{self, TessellatedRuns}},
+    svg::{ModifiedPaths, Origin, Svg, SvgBatching, SvgDoubleSided, SvgInstancing, SvgScaleMode, SvgSize, SvgYAxis},
+    time_slicing::{tessellate_time_sliced, SvgTimeSlicing},
+    Convert,
+};
 
 
 /// Stages for this plugin.
@@ -40,6 +62,31 @@ pub enum Stage {
     SVG,
 }
 
+/// Labels of the systems in [`Stage::SVG`].
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum SvgSystem {
+    /// Systems that link the meshes of loaded [`Svg`]s to their entities.
+    LinkMeshes,
+    /// System that tessellates the meshes of entities with runtime overrides, like
+    /// [`SvgDashOffset`](crate::prelude::SvgDashOffset), [`SvgCssVariables`](crate::prelude::SvgCssVariables)
+    /// or [`SvgAnimationPlayer`](crate::prelude::SvgAnimationPlayer).
+    UpdateOverrides,
+}
+
+/// Sent in the frame in which the mesh of a loaded [`Svg`] is attached to an entity, so that its
+/// visual exists, or when another mesh is attached after the [`Svg`] was modified. It is sent once
+/// per entity and frame.
+///
+/// The mesh of a time-sliced [`Svg`] is still empty when this is sent, see
+/// [`SvgTimeSlicing`](crate::prelude::SvgTimeSlicing).
+#[derive(Clone, Debug)]
+pub struct SvgMeshReady {
+    /// The entity that got the mesh.
+    pub entity: Entity,
+    /// The SVG whose mesh was attached.
+    pub svg: Handle<Svg>,
+}
+
 /// A plugin that provides resources and a system to draw [`Svg`]s.
 pub struct SvgPlugin;
 
@@ -47,9 +94,56 @@ impl Plugin for SvgPlugin {
     fn build(&self, app: &mut App) {
         let fill_tess = FillTessellator::new();
         let stroke_tess = StrokeTessellator::new();
+        let asset_root = asset_root(app);
+        let fonts = SvgFontDatabase::new(&app.world.get_resource::<SvgFonts>().cloned().unwrap_or_default());
+        let dpi = app.world.get_resource::<SvgDpi>().copied().unwrap_or_default();
+        let languages = app.world.get_resource::<SvgLanguages>().cloned().unwrap_or_default();
+        let parser = app.world.get_resource::<SvgParserOptions>().cloned().unwrap_or_default();
+        let settings = SvgLoadSettings::default();
+        let time_slicing = app.world.get_resource::<SvgTimeSlicing>().copied().unwrap_or_default();
+        let y_axis = app.world.get_resource::<SvgYAxis>().copied().unwrap_or_default();
+        let compute_pool = app.world.get_resource::<AsyncComputeTaskPool>().cloned();
+        let failures = LoadFailures::default();
+        let shared_meshes = SharedMeshes::default();
+        let progress = SvgLoadProgress::default();
+        #[cfg(feature = "lottie")]
+        app.add_asset_loader(crate::lottie::LottieLoader {
+            fonts: fonts.clone(),
+            parser: parser.clone(),
+            failures: failures.clone(),
+        });
         app
             .add_asset::<Svg>()
-            .init_asset_loader::<SvgAssetLoader>()
+            .add_asset_loader(SvgAssetLoader {
+                fonts: fonts.clone(),
+                dpi,
+                languages: languages.clone(),
+                parser: parser.clone(),
+                settings: settings.clone(),
+                time_slicing,
+                shared_meshes: shared_meshes.clone(),
+                progress: progress.clone(),
+                compute_pool,
+                failures: failures.clone(),
+                asset_root,
+            })
+            .add_asset_loader(SvgMeshLoader { failures: failures.clone() })
+            .add_asset::<SvgStyleSheet>()
+            .add_asset_loader(SvgStyleSheetLoader)
+            .add_event::<SvgLoadWarning>()
+            .add_event::<SvgLoadFailed>()
+            .add_event::<SvgMeshReady>()
+            .insert_resource(fonts)
+            .insert_resource(dpi)
+            .insert_resource(languages)
+            .insert_resource(parser)
+            .insert_resource(settings)
+            .insert_resource(time_slicing)
+            .insert_resource(y_axis)
+            .insert_resource(failures)
+            .insert_resource(shared_meshes)
+            .insert_resource(progress)
+            .init_resource::<SvgIconRegistry>()
             .insert_resource(fill_tess)
             .insert_resource(stroke_tess)
             .add_stage_after(
@@ -57,9 +151,101 @@ impl Plugin for SvgPlugin {
                 Stage::SVG,
                 SystemStage::parallel(),
             )
-            .add_system_to_stage(Stage::SVG, svg_mesh_linker)
-            .add_system_to_stage(Stage::SVG, set_svg_meshes)
+            .add_system_to_stage(Stage::SVG, svg_mesh_linker.label(SvgSystem::LinkMeshes))
+            .add_system_to_stage(Stage::SVG, set_svg_meshes.label(SvgSystem::LinkMeshes))
+            .add_system_to_stage(Stage::SVG, report_skipped_features)
+            .add_system_to_stage(Stage::SVG, report_failed_loads)
+            .add_system_to_stage(Stage::SVG, retessellate_modified_paths.before(SvgSystem::LinkMeshes))
+            .add_system_to_stage(Stage::SVG, tessellate_time_sliced)
+            .add_system_to_stage(Stage::SVG, reload_dependents)
+            .add_system_to_stage(Stage::SVG, spawn_path_entities)
+            .add_system_to_stage(Stage::SVG, animate_dash_offset.before(SvgSystem::UpdateOverrides))
+            .add_system_to_stage(Stage::SVG, advance_animation_players.before(SvgSystem::UpdateOverrides))
+            .add_system_to_stage(
+                Stage::SVG,
+                update_overridden_meshes.label(SvgSystem::UpdateOverrides).after(SvgSystem::LinkMeshes),
+            )
+            .add_system_to_stage(Stage::SVG, update_morphed_meshes.after(SvgSystem::UpdateOverrides))
             .add_plugin(render::SvgPlugin);
+        // Lets tools like `bevy-inspector-egui` and scene files see the asset and the components.
+        app
+            .register_type::<Svg>()
+            .register_type::<Handle<Svg>>()
+            .register_type::<Origin>()
+            .register_type::<SvgDoubleSided>()
+            .register_type::<SvgBatching>()
+            .register_type::<SvgInstancing>()
+            .register_type::<SvgSize>()
+            .register_type::<SvgScaleMode>()
+            .register_type::<SvgYAxis>()
+            .register_type::<SvgAnimationPlayer>()
+            .register_type::<SvgColor>()
+            .register_type::<SvgCssVariables>()
+            .register_type::<SvgDashOffset>()
+            .register_type::<SvgDrawOn>()
+            .register_type::<SvgExtrusion>()
+            .register_type::<SvgGroupTransforms>()
+            .register_type::<SvgMorph>()
+            .register_type::<SvgNineSlice>()
+            .register_type::<SvgNodeVisibility>()
+            .register_type::<SvgPaintOverrides>()
+            .register_type::<SvgTextOverrides>()
+            .register_type::<SvgTiling>()
+            .register_type::<SvgTint>()
+            .register_type::<SvgColorMatrix>()
+            .register_type::<SvgPathEntities>()
+            .register_type::<SvgGroupEntities>()
+            .register_type::<SvgPathId>()
+            .register_type::<SvgGroupId>();
+        #[cfg(feature = "picking")]
+        app
+            .init_resource::<crate::picking::SvgHovered>()
+            .add_event::<crate::picking::SvgClicked>()
+            .register_type::<crate::picking::SvgPickable>()
+            .add_system_to_stage(Stage::SVG, crate::picking::pick_svgs);
+        #[cfg(feature = "2d")]
+        app
+            .register_type::<crate::sdf::SvgSdf>()
+            .add_system_to_stage(Stage::SVG, crate::sdf::update_sdf_meshes.after(SvgSystem::UpdateOverrides));
+        #[cfg(feature = "3d")]
+        app
+            .register_type::<crate::billboard::SvgBillboard>()
+            .add_system_to_stage(Stage::SVG, crate::billboard::face_camera)
+            .add_system_to_stage(Stage::SVG, crate::pbr::update_pbr_svgs.after(SvgSystem::UpdateOverrides));
+        #[cfg(feature = "rapier")]
+        app
+            .register_type::<crate::colliders::SvgColliders>()
+            .register_type::<crate::colliders::SvgColliderShape>()
+            .add_system_to_stage(Stage::SVG, crate::colliders::generate_svg_colliders);
+        // The layout of the UI is only known after it is updated.
+        #[cfg(feature = "ui")]
+        app.add_system_to_stage(
+            bevy::app::CoreStage::PostUpdate,
+            crate::ui::rasterize_ui_svgs.after(bevy::ui::UiSystem::Flex),
+        );
+        #[cfg(any(feature = "2d", feature = "3d"))]
+        app.add_system_to_stage(
+            bevy::app::CoreStage::PostUpdate,
+            crate::bounds::update_svg_aabbs
+                .after(TransformSystem::TransformPropagate)
+                .after(VisibilitySystems::CalculateBounds)
+                .before(VisibilitySystems::CheckVisibility),
+        );
+    }
+}
+
+/// Returns the directory of the assets, if they are loaded from the file system.
+fn asset_root(app: &App) -> Option<PathBuf> {
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    {
+        let asset_folder = app.world.get_resource::<AssetServerSettings>()
+            .map_or_else(|| AssetServerSettings::default().asset_folder, |settings| settings.asset_folder.clone());
+        Some(FileAssetIo::get_root_path().join(asset_folder))
+    }
+    #[cfg(any(target_arch = "wasm32", target_os = "android"))]
+    {
+        let _ = app;
+        None
     }
 }
 
@@ -67,21 +253,29 @@ impl Plugin for SvgPlugin {
 /// This doesn't mess with the transform, at least for now.
 fn set_svg_meshes(
     svgs: Res<Assets<Svg>>,
+    mut ready_events: EventWriter<SvgMeshReady>,
     mut query: Query<
-        (&Handle<Svg>, Option<&mut Mesh2dHandle>, Option<&mut Handle<Mesh>>),
-        Changed<Handle<Svg>>,
+        (Entity, &Handle<Svg>, Option<&mut Mesh2dHandle>, Option<&mut Handle<Mesh>>),
+        (Changed<Handle<Svg>>, Without<SvgPathId>),
     >,
 ) {
-    for (handle, mesh_2d, mesh_3d) in query.iter_mut() {
+    for (entity, handle, mesh_2d, mesh_3d) in query.iter_mut() {
         if let Some(svg) = svgs.get(handle) {
-            mesh_2d.filter(|mesh| mesh.0 != svg.mesh)
+            let attached_2d = mesh_2d.filter(|mesh| mesh.0 != svg.mesh)
                 .map(|mut mesh| {
                     mesh.0 = svg.mesh.clone();
-                });
-            mesh_3d.filter(|mesh| mesh.deref() != &svg.mesh)
+                })
+                .is_some();
+            let attached_3d = mesh_3d.filter(|mesh| mesh.deref() != &svg.mesh)
                 .map(|mut mesh| {
                     *mesh = svg.mesh.clone();
-                });
+                })
+                .is_some();
+            // An entity that is spawned in the frame its asset is created may already have the
+            // mesh from `svg_mesh_linker`.
+            if attached_2d || attached_3d {
+                ready_events.send(SvgMeshReady { entity, svg: handle.clone_weak() });
+            }
         }
 
         // If the svg doesn't exist yet, wait for a `AssetEvent::Created` event
@@ -92,41 +286,69 @@ fn set_svg_meshes(
 fn svg_mesh_linker(
     mut commands: Commands,
     mut svg_events: EventReader<AssetEvent<Svg>>,
+    mut ready_events: EventWriter<SvgMeshReady>,
     mut meshes: ResMut<Assets<Mesh>>,
     svgs: Res<Assets<Svg>>,
+    shared_meshes: Res<SharedMeshes>,
     mut query: Query<
-        (Entity, &Handle<Svg>, Option<&mut Mesh2dHandle>, Option<&mut Handle<Mesh>>),
+        (Entity, &Handle<Svg>, Option<&OverriddenMesh>, Option<&mut Mesh2dHandle>, Option<&mut Handle<Mesh>>),
+        // Path entities have meshes of their own.
+        Without<SvgPathId>,
     >,
 ) {
+    // Meshes may be shared by several files with the same content.
+    let is_unused = |mesh: &Handle<Mesh>| svgs.iter().all(|(_, svg)| &svg.mesh != mesh);
+    // An entity is told once per frame, even if its asset was created and modified in it.
+    let mut notified = HashSet::new();
     for event in svg_events.iter() {
         match event {
             AssetEvent::Created { handle } => {
-                for (.., mesh_2d, mesh_3d) in query.iter_mut().filter(|(_, svg, ..)| svg == &handle) {
+                for (entity, _, _, mesh_2d, mesh_3d) in query.iter_mut().filter(|(_, svg, ..)| svg == &handle) {
                     let svg = svgs.get(handle).unwrap();
                     debug!("Svg `{}` created. Adding mesh component to entity.", svg.name);
-                    mesh_2d.map(|mut mesh| mesh.0 = svg.mesh.clone());
-                    mesh_3d.map(|mut mesh| *mesh = svg.mesh.clone());
+                    let attached_2d = mesh_2d.filter(|mesh| mesh.0 != svg.mesh)
+                        .map(|mut mesh| mesh.0 = svg.mesh.clone())
+                        .is_some();
+                    let attached_3d = mesh_3d.filter(|mesh| mesh.deref() != &svg.mesh)
+                        .map(|mut mesh| *mesh = svg.mesh.clone())
+                        .is_some();
+                    // An entity that is spawned in the frame its asset is created may already have
+                    // the mesh from `set_svg_meshes`.
+                    if (attached_2d || attached_3d) && notified.insert(entity) {
+                        ready_events.send(SvgMeshReady { entity, svg: handle.clone_weak() });
+                    }
                 }
             },
             AssetEvent::Modified { handle } => {
-                for (.., mesh_2d, mesh_3d) in query.iter_mut().filter(|(_, svg, ..)| svg == &handle) {
+                // Entities with an own mesh are re-tessellated by `update_overridden_meshes`.
+                for (entity, _, _, mesh_2d, mesh_3d) in query.iter_mut().filter(|(_, svg, overridden, ..)| svg == &handle && overridden.is_none()) {
                     let svg = svgs.get(handle).unwrap();
                     debug!("Svg `{}` modified. Changing mesh component of entity.", svg.name);
-                    mesh_2d.filter(|mesh| mesh.0 != svg.mesh)
+                    let attached_2d = mesh_2d.filter(|mesh| mesh.0 != svg.mesh)
                         .map(|mut mesh| {
                             let old_mesh = mesh.0.clone();
                             mesh.0 = svg.mesh.clone();
-                            meshes.remove(old_mesh);
-                        });
-                    mesh_3d.filter(|mesh| mesh.deref() != &svg.mesh)
+                            if is_unused(&old_mesh) {
+                                meshes.remove(old_mesh);
+                            }
+                        })
+                        .is_some();
+                    let attached_3d = mesh_3d.filter(|mesh| mesh.deref() != &svg.mesh)
                         .map(|mut mesh| {
                             let old_mesh = mesh.clone();
                             *mesh = svg.mesh.clone();
-                            meshes.remove(old_mesh);
-                        });
+                            if is_unused(&old_mesh) {
+                                meshes.remove(old_mesh);
+                            }
+                        })
+                        .is_some();
+                    if (attached_2d || attached_3d) && notified.insert(entity) {
+                        ready_events.send(SvgMeshReady { entity, svg: handle.clone_weak() });
+                    }
                 }
             },
             AssetEvent::Removed { handle } => {
+                shared_meshes.remove_owner(handle.id);
                 for (entity, ..) in query.iter_mut().filter(|(_, svg, ..)| svg == &handle) {
                     commands.entity(entity).despawn_recursive();
                 }
@@ -134,3 +356,86 @@ fn svg_mesh_linker(
         }
     }
 }
+
+/// Bevy system which tessellates the meshes of [`Svg`]s again whose paths were changed with
+/// [`Svg::path_mut`] and the like. Only the runs of paths with a changed one are tessellated
+/// again, the others are kept from the last time.
+fn retessellate_modified_paths(
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    mut runs: Local<HashMap<HandleId, TessellatedRuns>>,
+    mut svgs: ResMut<Assets<Svg>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    shared_meshes: Res<SharedMeshes>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+) {
+    for event in svg_events.iter() {
+        let handle = match event {
+            AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { handle } => {
+                runs.remove(&handle.id);
+                continue;
+            },
+            AssetEvent::Created { .. } => continue,
+        };
+        let svg = match svgs.get(handle) {
+            Some(svg) => svg,
+            None => continue,
+        };
+        let modified = std::mem::take(&mut *svg.modified_paths.lock().unwrap());
+        // Time-sliced svgs are tessellated from scratch by `tessellate_time_sliced`.
+        if matches!(modified, ModifiedPaths::Unchanged) || svg.time_sliced {
+            // The svg may have been reloaded, so the runs don't fit its paths anymore.
+            runs.remove(&handle.id);
+            continue;
+        }
+
+        debug!("Paths of Svg `{}` modified. Tessellating them again.", svg.name);
+        let mesh = tessellation::retessellate(svg, &modified, runs.entry(handle.id).or_default(), &mut fill_tess, &mut stroke_tess).convert();
+        // Files with the same content share their mesh, it is only changed for this one.
+        shared_meshes.remove_owner(handle.id);
+        let is_shared = svgs.iter().any(|(id, other)| id != handle.id && other.mesh == svg.mesh);
+        if is_shared {
+            svgs.get_mut(handle).unwrap().mesh = meshes.add(mesh);
+        } else if let Some(old_mesh) = meshes.get_mut(&svg.mesh) {
+            *old_mesh = mesh;
+        }
+    }
+}
+
+/// Bevy system which reloads [`Svg`]s when one of the style sheets or other SVG files changes
+/// that they reference, the asset server only watches the files themselves.
+fn reload_dependents(
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    mut style_sheet_events: EventReader<AssetEvent<SvgStyleSheet>>,
+    mut reloading: Local<HashSet<HandleId>>,
+    asset_server: Res<AssetServer>,
+    svgs: Res<Assets<Svg>>,
+) {
+    let modified = svg_events.iter()
+        .filter_map(|event| match event {
+            // A reload that was started here doesn't change the file, which prevents that files
+            // which reference each other are reloaded endlessly.
+            AssetEvent::Modified { handle } if !reloading.remove(&handle.id) => Some(handle.id),
+            _ => None,
+        })
+        .chain(style_sheet_events.iter().filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.id),
+            _ => None,
+        }))
+        .filter_map(|id| asset_server.get_handle_path(id))
+        .collect::<Vec<_>>();
+    if modified.is_empty() {
+        return;
+    }
+
+    for (id, svg) in svgs.iter() {
+        if svg.dependencies.iter().any(|dependency| modified.contains(dependency)) {
+            if let Some(path) = asset_server.get_handle_path(id) {
+                debug!("Reloading Svg `{}`, because a file that it references changed.", svg.name);
+                reloading.insert(id);
+                asset_server.reload_asset(path);
+            }
+        }
+    }
+}
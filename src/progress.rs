@@ -0,0 +1,71 @@
+//! Progress of SVG files that are loading, so that loading screens of large documents can show
+//! how far they are.
+
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::{Arc, RwLock}};
+
+
+/// What a loading SVG file is busy with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SvgLoadStage {
+    /// The document is parsed, which reports no progress of its own.
+    Parsing,
+    /// The paths are tessellated into the mesh.
+    Tessellating,
+}
+
+/// The progress of a single loading SVG file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SvgProgress {
+    /// What the file is busy with.
+    pub stage: SvgLoadStage,
+    /// Number of paths that are already tessellated.
+    pub tessellated_paths: usize,
+    /// Number of paths of the document, `0` while it is parsed.
+    pub total_paths: usize,
+}
+
+impl SvgProgress {
+    /// Returns the share of tessellated paths, from `0.0` to `1.0`.
+    pub fn fraction(&self) -> f32 {
+        match self.total_paths {
+            0 => 0.0,
+            total => (self.tessellated_paths as f32 / total as f32).min(1.0),
+        }
+    }
+}
+
+/// The [`SvgProgress`] of all SVG files that are loading, keyed by their path inside of the asset
+/// folder, inserted by the [`SvgPlugin`](crate::prelude::SvgPlugin).
+///
+/// Files are removed once they are completely tessellated or failed to load. Files that are
+/// tessellated over several frames, see [`SvgTimeSlicing`](crate::prelude::SvgTimeSlicing), stay
+/// until their mesh is complete.
+#[derive(Clone, Debug, Default)]
+pub struct SvgLoadProgress {
+    progress: Arc<RwLock<HashMap<PathBuf, SvgProgress>>>,
+}
+
+impl SvgLoadProgress {
+    /// Returns the progress of the file at `path`, `None` if it isn't loading.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<SvgProgress> {
+        self.progress.read().unwrap().get(path.as_ref()).copied()
+    }
+
+    /// Returns the paths and progress of all files that are loading.
+    pub fn all(&self) -> Vec<(PathBuf, SvgProgress)> {
+        self.progress.read().unwrap().iter().map(|(path, progress)| (path.clone(), *progress)).collect()
+    }
+
+    /// Returns whether any file is loading.
+    pub fn is_loading(&self) -> bool {
+        !self.progress.read().unwrap().is_empty()
+    }
+
+    pub(crate) fn set(&self, path: &Path, progress: SvgProgress) {
+        self.progress.write().unwrap().insert(path.to_owned(), progress);
+    }
+
+    pub(crate) fn remove(&self, path: &Path) {
+        self.progress.write().unwrap().remove(path);
+    }
+}
@@ -0,0 +1,164 @@
+//! Conversions between the paths of an [`Svg`] and the shapes of `bevy_prototype_lyon`, so that
+//! authored SVGs can be mixed with shapes that are generated at runtime.
+
+use bevy::{
+    asset::Assets,
+    math::Vec2,
+    render::mesh::Mesh,
+    transform::components::Transform,
+};
+use bevy_prototype_lyon::{
+    draw::{DrawMode, FillMode, StrokeMode},
+    entity::Path,
+    geometry::Geometry,
+};
+use lyon_geom::euclid::default::{Box2D, Transform2D};
+use lyon_svg::{parser::ViewBox, path::{iterator::PathIterator, path::Builder, PathEvent}};
+use lyon_tessellation::{FillTessellator, StrokeTessellator};
+
+use crate::{
+    overrides::Overrides,
+    paint::Paint,
+    render::{dash, tessellation},
+    svg::{document_transform, paint_color, DrawType, PathDescriptor, Svg},
+    Convert,
+};
+
+
+/// Tolerance that is used to flatten curves, when the bounds of shapes are calculated.
+const BOUNDS_TOLERANCE: f32 = 0.1;
+
+impl Svg {
+    /// Returns the visible paths of the SVG as shapes of `bevy_prototype_lyon`, with the draw mode
+    /// of their fill or stroke. They are placed like the mesh of the SVG, with its top left
+    /// corner at the origin and the y-axis of Bevy.
+    pub fn lyon_shapes(&self) -> Vec<(Path, DrawMode)> {
+        self.paths.iter()
+            .filter(|path| !self.is_hidden(path))
+            .map(|path| (Path::from(path), DrawMode::from(path)))
+            .collect()
+    }
+
+    /// Creates an [`Svg`] from shapes of `bevy_prototype_lyon`, its mesh is added to `meshes`.
+    /// Outlined shapes become a fill and a stroke.
+    ///
+    /// The SVG is sized to the bounds of the shapes, strokes included, and the top left corner of
+    /// the bounds becomes the top left corner of the SVG, where its [`Origin`](crate::prelude::Origin)
+    /// refers to.
+    pub fn from_lyon_shapes(shapes: &[(Path, DrawMode)], meshes: &mut Assets<Mesh>) -> Svg {
+        let mut bounds: Option<Box2D<f32>> = None;
+        for (path, mode) in shapes {
+            let stroke_width = match mode {
+                DrawMode::Fill(_) => 0.0,
+                DrawMode::Stroke(StrokeMode { options, .. }) | DrawMode::Outlined { outline_mode: StrokeMode { options, .. }, .. } => options.line_width,
+            };
+            let points = path.0.iter().flattened(BOUNDS_TOLERANCE).filter_map(|event| match event {
+                PathEvent::Begin { at } => Some(at),
+                PathEvent::Line { to, .. } => Some(to),
+                _ => None,
+            });
+            let shape_bounds = Box2D::from_points(points).inflate(stroke_width / 2.0, stroke_width / 2.0);
+            bounds = Some(bounds.map_or(shape_bounds, |bounds| bounds.union(&shape_bounds)));
+        }
+        let bounds = bounds.unwrap_or_else(Box2D::zero);
+
+        // Shapes have the y-axis of Bevy, SVGs one that points down from the top left corner.
+        let to_document = Transform2D::new(1.0, 0.0, 0.0, -1.0, -bounds.min.x, bounds.max.y);
+        let descriptor = |path: &Path, color, draw_type| {
+            let segments: Vec<PathEvent> = path.0.iter().map(|event| event.transformed(&to_document)).collect();
+            PathDescriptor {
+                id: String::new(),
+                length: match draw_type {
+                    DrawType::Stroke(_) => dash::length(&segments),
+                    DrawType::Fill(_) => 0.0,
+                },
+                segments,
+                abs_transform: Transform::identity(),
+                transform: Transform2D::identity(),
+                paint: Paint::Color(color),
+                paint_variable: None,
+                draw_type,
+                non_scaling_stroke: false,
+                dash: None,
+                groups: Vec::new(),
+            }
+        };
+        let mut paths = Vec::new();
+        for (path, mode) in shapes {
+            match mode {
+                DrawMode::Fill(fill) => paths.push(descriptor(path, fill.color, DrawType::Fill(fill.options))),
+                DrawMode::Stroke(stroke) => paths.push(descriptor(path, stroke.color, DrawType::Stroke(stroke.options))),
+                DrawMode::Outlined { fill_mode, outline_mode } => {
+                    paths.push(descriptor(path, fill_mode.color, DrawType::Fill(fill_mode.options)));
+                    paths.push(descriptor(path, outline_mode.color, DrawType::Stroke(outline_mode.options)));
+                },
+            }
+        }
+
+        let size = Vec2::new(bounds.width(), bounds.height());
+        let mut svg = Svg {
+            name: String::new(),
+            size,
+            view_box: ViewBox { x: 0.0, y: 0.0, w: f64::from(size.x), h: f64::from(size.y) },
+            content_rect: lyon_geom::rect(0.0, 0.0, size.x, size.y),
+            paths,
+            animations: Vec::new(),
+            transitions: Default::default(),
+            hidden: Default::default(),
+            skipped_features: Vec::new(),
+            dependencies: Vec::new(),
+            tolerance: None,
+            time_sliced: false,
+            texts: None,
+            modified_paths: Default::default(),
+            mesh: Default::default(),
+        };
+        let buffer = tessellation::generate_buffer(&svg, &Overrides::default(), &mut FillTessellator::new(), &mut StrokeTessellator::new());
+        svg.mesh = meshes.add(buffer.convert());
+        svg
+    }
+}
+
+/// Adds the segments of the path, placed like in the mesh of its [`Svg`].
+impl Geometry for PathDescriptor {
+    fn add_geometry(&self, b: &mut Builder) {
+        // Bevy has a different y-axis origin, so we need to flip that axis.
+        let to_mesh = document_transform(self).then(&Transform2D::scale(1.0, -1.0));
+        for event in self.segments.iter().map(|event| event.transformed(&to_mesh)) {
+            match event {
+                PathEvent::Begin { at } => {
+                    b.begin(at);
+                },
+                PathEvent::Line { to, .. } => {
+                    b.line_to(to);
+                },
+                PathEvent::Quadratic { ctrl, to, .. } => {
+                    b.quadratic_bezier_to(ctrl, to);
+                },
+                PathEvent::Cubic { ctrl1, ctrl2, to, .. } => {
+                    b.cubic_bezier_to(ctrl1, ctrl2, to);
+                },
+                PathEvent::End { close, .. } => b.end(close),
+            }
+        }
+    }
+}
+
+impl From<&PathDescriptor> for Path {
+    fn from(path: &PathDescriptor) -> Self {
+        let mut builder = lyon_tessellation::path::Path::builder();
+        path.add_geometry(&mut builder);
+        Path(builder.build())
+    }
+}
+
+/// Gradients, patterns and images become the solid color at the center of the path.
+impl From<&PathDescriptor> for DrawMode {
+    fn from(path: &PathDescriptor) -> Self {
+        let color = paint_color(path);
+        match path.draw_type {
+            DrawType::Fill(options) => DrawMode::Fill(FillMode { options, color }),
+            DrawType::Stroke(options) => DrawMode::Stroke(StrokeMode { options, color }),
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Geometric clipping of already tessellated [`VertexBuffers`].
+//!
+//! Both buffers are treated as a set of triangles. The triangles of the clip region are first cut
+//! into pieces that don't overlap, with the colors of overlapping triangles composited like they
+//! would be drawn, then every triangle of the subject is clipped against every piece that it
+//! overlaps. All positions are expected to be in the same coordinate system, only
+//! `x` and `y` are taken into account.
+
+use std::ops::Range;
+
+use bevy::math::{Vec2, Vec3};
+use copyless::VecHelper;
+
+use crate::render::vertex_buffer::{Vertex, VertexBuffers};
+
+
+/// Upper limit for the number of cells of a [`Grid`] along each axis.
+const MAX_GRID_CELLS: usize = 256;
+
+/// Intersects the triangles of `subject` with the triangles of `clip`.
+///
+/// The color of every resulting vertex is computed by `combine` from the interpolated colors
+/// of the subject and the clip region at that position, both in linear RGBA.
+pub(crate) fn intersect<F>(subject: &VertexBuffers, clip: &VertexBuffers, combine: F) -> VertexBuffers
+where
+    F: Fn([f32; 4], [f32; 4]) -> [f32; 4],
+{
+    // Content where the triangles of the clip region overlap would be drawn more than once.
+    let clip_triangles = flatten(triangles(clip));
+    let grid = Grid::new(&clip_triangles);
+    let mut candidates = Vec::new();
+    let mut buffer = VertexBuffers::new();
+
+    for subject_tri in triangles(subject) {
+        grid.find(subject_tri.min, subject_tri.max, &mut candidates);
+        for clip_tri in candidates.iter().map(|&i| &clip_triangles[i]).filter(|tri| tri.overlaps(&subject_tri)) {
+            let polygon = clip_tri.clip(&subject_tri.positions);
+            if polygon.len() < 3 {
+                continue;
+            }
+
+            let offset = buffer.vertices.len() as u32;
+            for position in &polygon {
+                let color = combine(subject_tri.color_at(*position), clip_tri.color_at(*position));
+                buffer.vertices.alloc().init(Vertex::new([position.x, position.y, 0.0], color));
+            }
+            for i in 1..polygon.len() as u32 - 1 {
+                buffer.indices.alloc().init(offset);
+                buffer.indices.alloc().init(offset + i);
+                buffer.indices.alloc().init(offset + i + 1);
+            }
+        }
+    }
+
+    buffer
+}
+
+/// Checks whether any two triangles of `buffer` overlap each other. Triangles that only share
+/// an edge or a corner don't count as overlapping.
+pub(crate) fn overlaps(buffer: &VertexBuffers) -> bool {
+    any_overlap(&triangles(buffer))
+}
+
+fn any_overlap(triangles: &[Triangle]) -> bool {
+    let mut triangles: Vec<&Triangle> = triangles.iter().collect();
+    triangles.sort_by(|a, b| a.min.x.partial_cmp(&b.min.x).unwrap_or(std::cmp::Ordering::Equal));
+
+    for (i, triangle) in triangles.iter().enumerate() {
+        // Sorted by their left edge, so only the following triangles up to the right edge can overlap.
+        for other in triangles[i + 1..].iter().take_while(|other| other.min.x <= triangle.max.x) {
+            if !triangle.overlaps(other) {
+                continue;
+            }
+            let polygon = triangle.clip(&other.positions);
+            let min_area = signed_area(&triangle.positions).abs().min(signed_area(&other.positions).abs());
+            if polygon_area(&polygon) > min_area * 1e-4 {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Keeps the color of the subject, the clip region only defines the visible area.
+pub(crate) fn keep_subject(subject: [f32; 4], _clip: [f32; 4]) -> [f32; 4] {
+    subject
+}
+
+/// Multiplies the alpha of the subject with the luminance and alpha of the mask.
+pub(crate) fn apply_mask([r, g, b, a]: [f32; 4], mask: [f32; 4]) -> [f32; 4] {
+    // Coefficients for the luminance as specified for SVG masks, applied to linear RGB.
+    let luminance = 0.2125 * mask[0] + 0.7154 * mask[1] + 0.0721 * mask[2];
+    [r, g, b, a * luminance * mask[3]]
+}
+
+/// Creates a buffer containing a rectangle from `min` to `max`, which can be used as clip region.
+pub(crate) fn rect(min: Vec2, max: Vec2) -> VertexBuffers {
+    let mut buffer = VertexBuffers::new();
+    for position in [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)] {
+        buffer.vertices.alloc().init(Vertex::new([position.x, position.y, 0.0], [1.0; 4]));
+    }
+    buffer.indices.extend_from_slice(&[0, 1, 2, 0, 2, 3]);
+    buffer
+}
+
+/// Cuts overlapping `triangles` into pieces that don't overlap, so every point is covered once.
+/// Where triangles overlap, the later one is composited over the earlier one, like it would be
+/// drawn, so a mask gets the luminance and alpha of its content where shapes overlap.
+fn flatten(triangles: Vec<Triangle>) -> Vec<Triangle> {
+    if !any_overlap(&triangles) {
+        return triangles;
+    }
+
+    let mut flat: Vec<Triangle> = Vec::with_capacity(triangles.len());
+    for triangle in triangles {
+        // The parts of the triangle that no earlier piece covers yet.
+        let mut uncovered = vec![triangle.positions.to_vec()];
+        let mut next = Vec::with_capacity(flat.len() + 1);
+        for piece in flat {
+            if !piece.overlaps(&triangle) {
+                next.push(piece);
+                continue;
+            }
+            let covered = triangle.clip(&piece.positions);
+            if polygon_area(&covered) <= piece.area() * 1e-4 {
+                next.push(piece);
+                continue;
+            }
+            for outside in subtract(&piece.positions, &triangle) {
+                fan(&outside, |position| piece.color_at(position), &mut next);
+            }
+            fan(&covered, |position| over(triangle.color_at(position), piece.color_at(position)), &mut next);
+            uncovered = uncovered.iter().flat_map(|polygon| subtract(polygon, &piece)).collect();
+        }
+        for polygon in &uncovered {
+            fan(polygon, |position| triangle.color_at(position), &mut next);
+        }
+        flat = next;
+    }
+
+    flat
+}
+
+/// Composites the linear RGBA color `top` over `bottom` with the source-over operator.
+fn over(top: [f32; 4], bottom: [f32; 4]) -> [f32; 4] {
+    let alpha = top[3] + bottom[3] * (1.0 - top[3]);
+    if alpha <= f32::EPSILON {
+        return [0.0; 4];
+    }
+    let mut color = [0.0, 0.0, 0.0, alpha];
+    for (channel, value) in color.iter_mut().take(3).enumerate() {
+        *value = (top[channel] * top[3] + bottom[channel] * bottom[3] * (1.0 - top[3])) / alpha;
+    }
+    color
+}
+
+/// Returns the parts of the convex `polygon` outside of `triangle`, as convex polygons that don't
+/// overlap.
+fn subtract(polygon: &[Vec2], triangle: &Triangle) -> Vec<Vec<Vec2>> {
+    let mut parts = Vec::new();
+    let mut inside = polygon.to_vec();
+    for i in 0..3 {
+        let edge = triangle.edge(i);
+        let outside = clip_half_plane(&inside, |p| -edge(p));
+        if polygon_area(&outside) > f32::EPSILON {
+            parts.push(outside);
+        }
+        inside = clip_half_plane(&inside, &edge);
+        if inside.len() < 3 {
+            break;
+        }
+    }
+    parts
+}
+
+/// Splits the convex `polygon` into a fan of triangles, colored by `color_at`.
+fn fan(polygon: &[Vec2], color_at: impl Fn(Vec2) -> [f32; 4], triangles: &mut Vec<Triangle>) {
+    for i in 1..polygon.len().saturating_sub(1) {
+        let positions = [polygon[0], polygon[i], polygon[i + 1]];
+        if signed_area(&positions).abs() > f32::EPSILON {
+            triangles.alloc().init(Triangle::new(positions, positions.map(&color_at)));
+        }
+    }
+}
+
+/// Keeps the part of the convex `polygon` where `distance` isn't negative, with the
+/// Sutherland–Hodgman algorithm.
+fn clip_half_plane(polygon: &[Vec2], distance: impl Fn(Vec2) -> f32) -> Vec<Vec2> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (j, &current) in polygon.iter().enumerate() {
+        let previous = polygon[(j + polygon.len() - 1) % polygon.len()];
+        let (d_current, d_previous) = (distance(current), distance(previous));
+
+        if d_current >= 0.0 {
+            if d_previous < 0.0 {
+                clipped.alloc().init(previous.lerp(current, d_previous / (d_previous - d_current)));
+            }
+            clipped.alloc().init(current);
+        } else if d_previous >= 0.0 {
+            clipped.alloc().init(previous.lerp(current, d_previous / (d_previous - d_current)));
+        }
+    }
+    clipped
+}
+
+/// Buckets triangles into the cells of a uniform grid over their bounds, to find the ones that
+/// may overlap a region without testing all of them.
+struct Grid {
+    min: Vec2,
+    max: Vec2,
+    cell_size: Vec2,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    fn new(triangles: &[Triangle]) -> Self {
+        let min = triangles.iter().fold(Vec2::splat(f32::INFINITY), |min, triangle| min.min(triangle.min));
+        let max = triangles.iter().fold(Vec2::splat(f32::NEG_INFINITY), |max, triangle| max.max(triangle.max));
+        // About one triangle per cell.
+        let columns = ((triangles.len() as f32).sqrt().ceil() as usize).clamp(1, MAX_GRID_CELLS);
+        let rows = columns;
+        let cell_size = ((max - min) / Vec2::new(columns as f32, rows as f32)).max(Vec2::splat(f32::EPSILON));
+        let mut grid = Self { min, max, cell_size, columns, rows, cells: vec![Vec::new(); columns * rows] };
+
+        for (i, triangle) in triangles.iter().enumerate() {
+            if let Some((columns, rows)) = grid.cells_of(triangle.min, triangle.max) {
+                for row in rows {
+                    for column in columns.clone() {
+                        grid.cells[row * grid.columns + column].push(i);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// Returns the columns and rows of the cells that the rectangle from `min` to `max` covers,
+    /// `None` if it is outside of the grid.
+    fn cells_of(&self, min: Vec2, max: Vec2) -> Option<(Range<usize>, Range<usize>)> {
+        if max.x < self.min.x || max.y < self.min.y || min.x > self.max.x || min.y > self.max.y {
+            return None;
+        }
+        let cell = |position: Vec2| ((position - self.min) / self.cell_size).max(Vec2::ZERO);
+        let (first, last) = (cell(min), cell(max));
+        let columns = (first.x as usize).min(self.columns - 1)..(last.x as usize).min(self.columns - 1) + 1;
+        let rows = (first.y as usize).min(self.rows - 1)..(last.y as usize).min(self.rows - 1) + 1;
+        Some((columns, rows))
+    }
+
+    /// Collects the indices of the triangles that may overlap the rectangle from `min` to `max`
+    /// into `found`, in ascending order.
+    fn find(&self, min: Vec2, max: Vec2, found: &mut Vec<usize>) {
+        found.clear();
+        if let Some((columns, rows)) = self.cells_of(min, max) {
+            for row in rows {
+                for column in columns.clone() {
+                    found.extend_from_slice(&self.cells[row * self.columns + column]);
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+    }
+}
+
+/// A single triangle of a [`VertexBuffers`].
+struct Triangle {
+    positions: [Vec2; 3],
+    colors: [[f32; 4]; 3],
+    min: Vec2,
+    max: Vec2,
+}
+
+/// Collects all non-degenerate triangles of `buffer`.
+fn triangles(buffer: &VertexBuffers) -> Vec<Triangle> {
+    let mut triangles = Vec::with_capacity(buffer.indices.len() / 3);
+    for idx in buffer.indices.chunks_exact(3) {
+        let vertices = [0, 1, 2].map(|i| buffer.vertices[idx[i] as usize]);
+        let positions = vertices.map(|v| Vec2::new(v.position[0], v.position[1]));
+        if signed_area(&positions).abs() <= f32::EPSILON {
+            continue;
+        }
+
+        triangles.alloc().init(Triangle::new(positions, vertices.map(|v| v.linear_color())));
+    }
+    triangles
+}
+
+fn signed_area(positions: &[Vec2; 3]) -> f32 {
+    (positions[1] - positions[0]).perp_dot(positions[2] - positions[0]) * 0.5
+}
+
+fn polygon_area(polygon: &[Vec2]) -> f32 {
+    let mut area = 0.0;
+    for (i, &current) in polygon.iter().enumerate() {
+        area += current.perp_dot(polygon[(i + 1) % polygon.len()]);
+    }
+    area.abs() * 0.5
+}
+
+impl Triangle {
+    fn new(positions: [Vec2; 3], colors: [[f32; 4]; 3]) -> Self {
+        Self {
+            positions,
+            colors,
+            min: positions[0].min(positions[1]).min(positions[2]),
+            max: positions[0].max(positions[1]).max(positions[2]),
+        }
+    }
+
+    fn area(&self) -> f32 {
+        signed_area(&self.positions).abs()
+    }
+
+    /// Returns the signed distance of a position to the line through the edge `i`, scaled by its
+    /// length, which is positive on the inside of the triangle.
+    fn edge(&self, i: usize) -> impl Fn(Vec2) -> f32 {
+        let orientation = signed_area(&self.positions).signum();
+        let edge_start = self.positions[i];
+        let edge = self.positions[(i + 1) % 3] - edge_start;
+        move |p: Vec2| edge.perp_dot(p - edge_start) * orientation
+    }
+
+    fn overlaps(&self, other: &Triangle) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+
+    /// Clips the convex polygon `positions` against this triangle with the Sutherland–Hodgman
+    /// algorithm.
+    fn clip(&self, positions: &[Vec2]) -> Vec<Vec2> {
+        let mut polygon = positions.to_vec();
+        for i in 0..3 {
+            polygon = clip_half_plane(&polygon, self.edge(i));
+            if polygon.len() < 3 {
+                return Vec::new();
+            }
+        }
+
+        polygon
+    }
+
+    /// Interpolates the vertex colors of the triangle at `position`.
+    fn color_at(&self, position: Vec2) -> [f32; 4] {
+        let weights = self.barycentric(position);
+        let mut color = [0.0; 4];
+        for (channel, value) in color.iter_mut().enumerate() {
+            *value = weights.x * self.colors[0][channel]
+                + weights.y * self.colors[1][channel]
+                + weights.z * self.colors[2][channel];
+        }
+        color
+    }
+
+    fn barycentric(&self, position: Vec2) -> Vec3 {
+        let [a, b, c] = self.positions;
+        let area = (b - a).perp_dot(c - a);
+        let v = (position - a).perp_dot(c - a) / area;
+        let w = (b - a).perp_dot(position - a) / area;
+        // Clamp to absorb floating point errors at the edges of the triangle.
+        Vec3::new(1.0 - v - w, v, w).clamp(Vec3::ZERO, Vec3::ONE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(buffer: &VertexBuffers) -> f32 {
+        triangles(buffer).iter().map(Triangle::area).sum()
+    }
+
+    #[test]
+    fn overlapping_clip_region_covers_subject_once() {
+        let subject = rect(Vec2::ZERO, Vec2::new(4.0, 4.0));
+        let mut clip = rect(Vec2::ZERO, Vec2::new(2.0, 2.0));
+        let other = rect(Vec2::ONE, Vec2::new(3.0, 3.0));
+        clip.indices.extend(other.indices.iter().map(|i| i + 4));
+        clip.vertices.extend(other.vertices);
+        assert!(overlaps(&clip));
+
+        let clipped = intersect(&subject, &clip, keep_subject);
+        assert!((area(&clipped) - 7.0).abs() < 1e-4);
+        assert!(!overlaps(&clipped));
+    }
+
+    #[test]
+    fn overlapping_mask_content_is_composited() {
+        let subject = rect(Vec2::ZERO, Vec2::new(2.0, 1.0));
+        // Two half transparent white squares that overlap on the right half of the subject.
+        let mut mask = rect(Vec2::ZERO, Vec2::new(2.0, 1.0));
+        let other = rect(Vec2::new(1.0, 0.0), Vec2::new(2.0, 1.0));
+        mask.indices.extend(other.indices.iter().map(|i| i + 4));
+        mask.vertices.extend(other.vertices);
+        for vertex in &mut mask.vertices {
+            *vertex = Vertex::new(vertex.position, [1.0, 1.0, 1.0, 0.5]);
+        }
+
+        let masked = intersect(&subject, &mask, apply_mask);
+        for vertex in &masked.vertices {
+            let expected = if vertex.position[0] < 1.0 { 0.5 } else { 0.75 };
+            let alpha = vertex.linear_color()[3];
+            if vertex.position[0] != 1.0 {
+                assert!((alpha - expected).abs() < 0.01, "{} at {:?}", alpha, vertex.position);
+            }
+        }
+        assert!((area(&masked) - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn subject_outside_of_clip_region_is_dropped() {
+        let subject = rect(Vec2::new(10.0, 10.0), Vec2::new(12.0, 12.0));
+        let clip = rect(Vec2::ZERO, Vec2::new(2.0, 2.0));
+        assert!(intersect(&subject, &clip, keep_subject).indices.is_empty());
+    }
+}
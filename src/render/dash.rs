@@ -0,0 +1,175 @@
+//! Splits paths into the dashes of a [`Dash`] pattern, so they can be stroked like solid paths.
+
+use lyon_geom::{CubicBezierSegment, LineSegment, QuadraticBezierSegment};
+use lyon_svg::path::{iterator::PathIterator, PathEvent};
+use lyon_tessellation::math::Point;
+
+use crate::svg::Dash;
+
+/// Tolerance that is used to flatten curves before they are split into dashes.
+const FLATTEN_TOLERANCE: f32 = 0.01;
+
+
+/// Returns the dashes of `events`, starting `offset` into the dash pattern.
+/// The dash pattern starts anew for every subpath.
+pub(crate) fn dash(events: &[PathEvent], dash: &Dash, offset: f32) -> Vec<PathEvent> {
+    let total: f32 = dash.array.iter().sum();
+    let mut dasher = Dasher { out: Vec::new(), array: &dash.array, index: 0, remaining: 0.0, current: None };
+    if !total.is_finite() || total <= 0.0 || dash.array.iter().any(|&length| length < 0.0) {
+        return events.to_vec();
+    }
+
+    for event in events {
+        match *event {
+            PathEvent::Begin { .. } => dasher.reset(offset.rem_euclid(total)),
+            PathEvent::Line { from, to } => dasher.line(from, to),
+            PathEvent::Quadratic { from, ctrl, to } => {
+                let mut prev = from;
+                QuadraticBezierSegment { from, ctrl, to }.for_each_flattened(FLATTEN_TOLERANCE, &mut |p| {
+                    dasher.line(prev, p);
+                    prev = p;
+                });
+            },
+            PathEvent::Cubic { from, ctrl1, ctrl2, to } => {
+                let mut prev = from;
+                CubicBezierSegment { from, ctrl1, ctrl2, to }.for_each_flattened(FLATTEN_TOLERANCE, &mut |p| {
+                    dasher.line(prev, p);
+                    prev = p;
+                });
+            },
+            PathEvent::End { last, first, close } => {
+                if close {
+                    dasher.line(last, first);
+                }
+                dasher.end();
+            },
+        }
+    }
+
+    dasher.out
+}
+
+/// Returns the length of `events` along all of their subpaths, closing segments included.
+pub(crate) fn length(events: &[PathEvent]) -> f32 {
+    events.iter().copied().flattened(FLATTEN_TOLERANCE)
+        .map(|event| match event {
+            PathEvent::Line { from, to } | PathEvent::End { last: from, first: to, close: true } => (to - from).length(),
+            _ => 0.0,
+        })
+        .sum()
+}
+
+/// Returns the first `length` of `events`, measured along all of their subpaths in order. Curves
+/// are flattened.
+pub(crate) fn trim(events: &[PathEvent], length: f32) -> Vec<PathEvent> {
+    let mut out = Vec::new();
+    let mut remaining = length;
+    let mut first = Point::origin();
+    for event in events.iter().copied().flattened(FLATTEN_TOLERANCE) {
+        let segment = match event {
+            PathEvent::Begin { at } if remaining > 0.0 => {
+                first = at;
+                out.push(event);
+                continue;
+            },
+            PathEvent::Begin { .. } => break,
+            PathEvent::Line { from, to } => LineSegment { from, to },
+            PathEvent::End { last, first, close: true } if (first - last).length() < remaining => {
+                remaining -= (first - last).length();
+                out.push(event);
+                continue;
+            },
+            PathEvent::End { last, first, close: true } => LineSegment { from: last, to: first },
+            _ => {
+                out.push(event);
+                continue;
+            },
+        };
+
+        let segment_length = segment.length();
+        if segment_length < remaining {
+            remaining -= segment_length;
+            out.push(PathEvent::Line { from: segment.from, to: segment.to });
+            continue;
+        }
+        // The stroke ends within this segment.
+        let end = match segment_length > 0.0 {
+            true => segment.sample(remaining / segment_length),
+            false => segment.from,
+        };
+        out.push(PathEvent::Line { from: segment.from, to: end });
+        out.push(PathEvent::End { last: end, first, close: false });
+        break;
+    }
+    out
+}
+
+/// Walks along the flattened path and keeps track of the position in the dash pattern.
+struct Dasher<'a> {
+    out: Vec<PathEvent>,
+    array: &'a [f32],
+    /// Index of the current dash or gap, even indices are dashes.
+    index: usize,
+    /// Length that is left of the current dash or gap.
+    remaining: f32,
+    /// Start and current end of the dash that is being emitted.
+    current: Option<(Point, Point)>,
+}
+
+impl Dasher<'_> {
+    /// Moves to the position `offset` into the pattern, at the start of a subpath.
+    fn reset(&mut self, mut offset: f32) {
+        self.end();
+        self.index = 0;
+        while offset > self.array[self.index] {
+            offset -= self.array[self.index];
+            self.index = (self.index + 1) % self.array.len();
+        }
+        self.remaining = self.array[self.index] - offset;
+    }
+
+    fn is_dash(&self) -> bool {
+        self.index % 2 == 0
+    }
+
+    fn line(&mut self, from: Point, to: Point) {
+        let segment = LineSegment { from, to };
+        let length = segment.length();
+        let mut t = 0.0;
+        while length - t > 0.0 {
+            let step = self.remaining.min(length - t);
+            let start = segment.sample(t / length);
+            t += step;
+            let end = segment.sample(t / length);
+            self.remaining -= step;
+
+            if self.is_dash() {
+                match self.current {
+                    Some((_, ref mut last)) => {
+                        self.out.push(PathEvent::Line { from: *last, to: end });
+                        *last = end;
+                    },
+                    None => {
+                        self.out.push(PathEvent::Begin { at: start });
+                        self.out.push(PathEvent::Line { from: start, to: end });
+                        self.current = Some((start, end));
+                    },
+                }
+            }
+            if self.remaining <= 0.0 {
+                if self.is_dash() {
+                    self.end();
+                }
+                self.index = (self.index + 1) % self.array.len();
+                self.remaining = self.array[self.index];
+            }
+        }
+    }
+
+    /// Ends the dash that is being emitted, if any.
+    fn end(&mut self) {
+        if let Some((first, last)) = self.current.take() {
+            self.out.push(PathEvent::End { last, first, close: false });
+        }
+    }
+}
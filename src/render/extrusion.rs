@@ -0,0 +1,94 @@
+//! Extrudes already tessellated [`VertexBuffers`] into a solid, see
+//! [`SvgExtrusion`](crate::prelude::SvgExtrusion).
+
+use std::collections::HashMap;
+
+use copyless::VecHelper;
+
+use crate::render::vertex_buffer::{IndexType, Vertex, VertexBuffers};
+
+
+/// Factor that the colors of the walls are darkened with, so the depth shows without lighting.
+const WALL_SHADE: f32 = 0.7;
+
+/// Extrudes `buffer` by `depth` along the negative z-axis, away from the viewer. The triangles of
+/// `buffer` stay the front, a copy with the opposite winding is the back, and the edges of the
+/// outlines are connected by walls. All triangles are wound counterclockwise seen from the
+/// outside, which the normals of the mesh are computed from.
+pub(crate) fn apply(buffer: &VertexBuffers, depth: f32) -> VertexBuffers {
+    if depth == 0.0 || buffer.indices.is_empty() {
+        return buffer.clone();
+    }
+
+    // The walls are wound like the triangles they belong to, which tessellating doesn't keep
+    // the same.
+    let mut front = buffer.clone();
+    for triangle in front.indices.chunks_exact_mut(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| front.vertices[index as usize].position);
+        if (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]) < 0.0 {
+            triangle.swap(1, 2);
+        }
+    }
+    let mut solid = front.clone();
+    let back_offset = solid.vertices.len() as IndexType;
+    for vertex in &front.vertices {
+        let mut vertex = *vertex;
+        vertex.position[2] -= depth;
+        solid.vertices.alloc().init(vertex);
+    }
+    for triangle in front.indices.chunks_exact(3) {
+        solid.indices.extend([triangle[0], triangle[2], triangle[1]].map(|index| index + back_offset));
+    }
+
+    for (start, end) in outline(&front) {
+        let offset = solid.vertices.len() as IndexType;
+        let (start, end) = (front.vertices[start as usize], front.vertices[end as usize]);
+        for vertex in [end, start] {
+            let color = shade(vertex.color);
+            solid.vertices.alloc().init(Vertex { color, ..vertex });
+        }
+        for vertex in [start, end] {
+            let mut position = vertex.position;
+            position[2] -= depth;
+            solid.vertices.alloc().init(Vertex { position, color: shade(vertex.color), ..vertex });
+        }
+        solid.indices.extend([0, 1, 2, 0, 2, 3].map(|index| index + offset));
+    }
+    solid
+}
+
+/// Returns the edges of the triangles of `buffer` that no other triangle shares, in the direction
+/// of the winding of their triangle. Edges are compared by the positions of their vertices, since
+/// touching shapes don't share vertices.
+fn outline(buffer: &VertexBuffers) -> Vec<(IndexType, IndexType)> {
+    let key = |index: IndexType| {
+        let [x, y, _] = buffer.vertices[index as usize].position;
+        (x.to_bits(), y.to_bits())
+    };
+    // The edges by their end points in either order, with how often they are used.
+    let mut edges: HashMap<_, ((IndexType, IndexType), usize)> = HashMap::new();
+    for triangle in buffer.indices.chunks_exact(3) {
+        for (start, end) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+            let (a, b) = (key(start), key(end));
+            if a == b {
+                continue;
+            }
+            let edge = edges.entry(if a < b { (a, b) } else { (b, a) }).or_insert(((start, end), 0));
+            edge.1 += 1;
+        }
+    }
+    let mut outline: Vec<_> = edges.into_values()
+        .filter(|(_, count)| *count == 1)
+        .map(|(edge, _)| edge)
+        .collect();
+    // The order of a hash map changes from run to run, the mesh shouldn't.
+    outline.sort_unstable();
+    outline
+}
+
+/// Darkens the packed RGBA `color` by [`WALL_SHADE`], keeping its alpha.
+fn shade(color: u32) -> u32 {
+    let [r, g, b, a] = color.to_le_bytes();
+    let [r, g, b] = [r, g, b].map(|channel| (f32::from(channel) * WALL_SHADE) as u8);
+    u32::from_le_bytes([r, g, b, a])
+}
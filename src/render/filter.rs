@@ -0,0 +1,231 @@
+//! Evaluation of [`Filter`]s on tessellated geometry, by the way of a [`Raster`].
+//!
+//! Filters that only consist of color matrices are applied to the vertex colors directly,
+//! since the geometry stays the same. Filters in the file apply to their group, so they are baked
+//! when the file is loaded, a [`SvgColorMatrix`](crate::prelude::SvgColorMatrix) changes the colors
+//! of the whole SVG at runtime in the shader instead.
+
+use std::collections::HashMap;
+
+use bevy::math::Vec2;
+
+use crate::{
+    filter::{BlendMode, CompositeOperator, Filter, Input, PrimitiveKind},
+    render::{clip, raster::Raster, tessellation::transform_buffer_2d, vertex_buffer::{Vertex, VertexBuffers}},
+};
+
+
+/// Applies `filter` to the tessellated content of a group.
+pub(crate) fn apply(filter: &Filter, mut source: VertexBuffers) -> VertexBuffers {
+    let inverse = match filter.transform.inverse() {
+        Some(inverse) => inverse,
+        None => return VertexBuffers::new(),
+    };
+
+    // Filters are evaluated in the user space of the group.
+    transform_buffer_2d(&mut source, &inverse);
+    if let Some(matrices) = color_matrices(filter) {
+        let region = &filter.region;
+        let region = clip::rect(Vec2::new(region.min_x(), region.min_y()), Vec2::new(region.max_x(), region.max_y()));
+        let mut buffer = clip::intersect(&source, &region, clip::keep_subject);
+        for vertex in &mut buffer.vertices {
+            let color = matrices.iter().fold(vertex.linear_color(), |color, matrix| apply_color_matrix(matrix, color));
+            *vertex = Vertex::new(vertex.position, color);
+        }
+        transform_buffer_2d(&mut buffer, &filter.transform);
+        return buffer;
+    }
+
+    let mut source_graphic = Raster::new(&filter.region);
+    source_graphic.draw(&source);
+
+    let mut results: HashMap<&str, Raster> = HashMap::new();
+    let mut last = source_graphic.clone();
+    for primitive in &filter.primitives {
+        let mut result = match &primitive.kind {
+            PrimitiveKind::GaussianBlur { input, std_dev } => {
+                let mut raster = resolve_input(input, &source_graphic, &last, &results);
+                let std_dev = raster.to_pixels(*std_dev);
+                raster.blur(std_dev);
+                raster
+            },
+            PrimitiveKind::DropShadow { input, offset, std_dev, color } => {
+                let raster = resolve_input(input, &source_graphic, &last, &results);
+                let [r, g, b, a] = color.as_linear_rgba_f32();
+                let mut shadow = raster.clone();
+                shadow.pixels.iter_mut().for_each(|pixel| {
+                    let alpha = pixel[3] * a;
+                    *pixel = [r * alpha, g * alpha, b * alpha, alpha];
+                });
+                shadow.blur(shadow.to_pixels(*std_dev));
+                shadow = shadow.offset(shadow.to_pixels(*offset));
+                shadow.draw_over(&raster);
+                shadow
+            },
+            PrimitiveKind::ColorMatrix { input, matrix } => {
+                let mut raster = resolve_input(input, &source_graphic, &last, &results);
+                raster.map_colors(|color| apply_color_matrix(matrix, color));
+                raster
+            },
+            PrimitiveKind::Offset { input, offset } => {
+                let raster = resolve_input(input, &source_graphic, &last, &results);
+                raster.offset(raster.to_pixels(*offset))
+            },
+            PrimitiveKind::Flood { color } => {
+                let [r, g, b, a] = color.as_linear_rgba_f32();
+                let mut raster = source_graphic.clone();
+                raster.pixels.iter_mut().for_each(|pixel| *pixel = [r * a, g * a, b * a, a]);
+                raster
+            },
+            PrimitiveKind::Merge { inputs } => {
+                let mut raster = source_graphic.clone();
+                raster.pixels.iter_mut().for_each(|pixel| *pixel = [0.0; 4]);
+                for input in inputs {
+                    raster.draw_over(&resolve_input(input, &source_graphic, &last, &results));
+                }
+                raster
+            },
+            PrimitiveKind::Blend { input1, input2, mode } => {
+                let mut raster = resolve_input(input2, &source_graphic, &last, &results);
+                let top = resolve_input(input1, &source_graphic, &last, &results);
+                raster.combine(&top, |src, dst| blend(*mode, src, dst));
+                raster
+            },
+            PrimitiveKind::Composite { input1, input2, operator } => {
+                let mut raster = resolve_input(input2, &source_graphic, &last, &results);
+                let src = resolve_input(input1, &source_graphic, &last, &results);
+                raster.combine(&src, |src, dst| composite(*operator, src, dst));
+                raster
+            },
+        };
+        result.clip(&primitive.region);
+        results.insert(primitive.result.as_str(), result.clone());
+        last = result;
+    }
+
+    let mut buffer = last.to_buffer();
+    transform_buffer_2d(&mut buffer, &filter.transform);
+    buffer
+}
+
+/// Returns the raster that is referenced by `input`.
+fn resolve_input(input: &Input, source_graphic: &Raster, last: &Raster, results: &HashMap<&str, Raster>) -> Raster {
+    match input {
+        Input::SourceGraphic => source_graphic.clone(),
+        Input::SourceAlpha => {
+            let mut raster = source_graphic.clone();
+            raster.pixels.iter_mut().for_each(|pixel| *pixel = [0.0, 0.0, 0.0, pixel[3]]);
+            raster
+        },
+        // A reference to an unknown result falls back to the result of the previous primitive.
+        Input::Reference(name) => results.get(name.as_str()).unwrap_or(last).clone(),
+    }
+}
+
+/// Returns the matrices of `filter`, if it consists of a chain of color matrices that can be
+/// applied to the vertex colors directly.
+fn color_matrices(filter: &Filter) -> Option<Vec<&[f32; 20]>> {
+    let mut previous: Option<&str> = None;
+    filter.primitives.iter()
+        .map(|primitive| {
+            let (input, matrix) = match &primitive.kind {
+                PrimitiveKind::ColorMatrix { input, matrix } => (input, matrix),
+                _ => return None,
+            };
+            let chained = match (input, previous) {
+                (Input::SourceGraphic, None) => true,
+                (Input::Reference(name), Some(previous)) => name == previous,
+                _ => false,
+            };
+            // A constant alpha would also make the transparent parts of the filter region visible.
+            if !chained || matrix[19] > 0.0 || primitive.region != filter.region {
+                return None;
+            }
+            previous = Some(&primitive.result);
+            Some(matrix)
+        })
+        .collect()
+}
+
+/// Applies a color `matrix` to an unpremultiplied RGBA color.
+fn apply_color_matrix(matrix: &[f32; 20], color: [f32; 4]) -> [f32; 4] {
+    let mut result = [0.0; 4];
+    for (row, value) in result.iter_mut().enumerate() {
+        let m = &matrix[row * 5..row * 5 + 5];
+        *value = (m[0] * color[0] + m[1] * color[1] + m[2] * color[2] + m[3] * color[3] + m[4]).clamp(0.0, 1.0);
+    }
+    result
+}
+
+/// Blends the premultiplied colors `src` on top of `dst` with the given `mode`.
+pub(crate) fn blend(mode: BlendMode, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let (src_alpha, dst_alpha) = (src[3], dst[3]);
+    let mut result = [0.0; 4];
+    for channel in 0..3 {
+        // The blend functions work on unpremultiplied colors.
+        let cs = if src_alpha > 0.0 { src[channel] / src_alpha } else { 0.0 };
+        let cb = if dst_alpha > 0.0 { dst[channel] / dst_alpha } else { 0.0 };
+        let blended = match mode {
+            BlendMode::Normal => cs,
+            BlendMode::Multiply => cs * cb,
+            BlendMode::Screen => cb + cs - cb * cs,
+            BlendMode::Overlay => hard_light(cs, cb),
+            BlendMode::Darken => cs.min(cb),
+            BlendMode::Lighten => cs.max(cb),
+            BlendMode::ColorDodge => if cb <= 0.0 { 0.0 } else if cs >= 1.0 { 1.0 } else { (cb / (1.0 - cs)).min(1.0) },
+            BlendMode::ColorBurn => if cb >= 1.0 { 1.0 } else if cs <= 0.0 { 0.0 } else { 1.0 - ((1.0 - cb) / cs).min(1.0) },
+            BlendMode::HardLight => hard_light(cb, cs),
+            BlendMode::SoftLight => {
+                if cs <= 0.5 {
+                    cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+                } else {
+                    let d = if cb <= 0.25 { ((16.0 * cb - 12.0) * cb + 4.0) * cb } else { cb.sqrt() };
+                    cb + (2.0 * cs - 1.0) * (d - cb)
+                }
+            },
+            BlendMode::Difference => (cs - cb).abs(),
+            BlendMode::Exclusion => cs + cb - 2.0 * cs * cb,
+        };
+        result[channel] = (1.0 - dst_alpha) * src[channel] + (1.0 - src_alpha) * dst[channel] + src_alpha * dst_alpha * blended;
+    }
+    result[3] = src_alpha + dst_alpha - src_alpha * dst_alpha;
+    result
+}
+
+/// The `hard-light` blend function, `backdrop` is multiplied or screened depending on `source`.
+fn hard_light(backdrop: f32, source: f32) -> f32 {
+    if source <= 0.5 {
+        backdrop * 2.0 * source
+    } else {
+        let source = 2.0 * source - 1.0;
+        backdrop + source - backdrop * source
+    }
+}
+
+/// Combines the premultiplied colors `src` and `dst` with the given Porter-Duff `operator`.
+fn composite(operator: CompositeOperator, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let (fa, fb) = match operator {
+        CompositeOperator::Over => (1.0, 1.0 - src[3]),
+        CompositeOperator::In => (dst[3], 0.0),
+        CompositeOperator::Out => (1.0 - dst[3], 0.0),
+        CompositeOperator::Atop => (dst[3], 1.0 - src[3]),
+        CompositeOperator::Xor => (1.0 - dst[3], 1.0 - src[3]),
+        CompositeOperator::Arithmetic { k1, k2, k3, k4 } => {
+            let mut result = [0.0; 4];
+            for channel in 0..4 {
+                result[channel] = (k1 * src[channel] * dst[channel] + k2 * src[channel] + k3 * dst[channel] + k4).clamp(0.0, 1.0);
+            }
+            // Premultiplied colors can't exceed their alpha.
+            for channel in 0..3 {
+                result[channel] = result[channel].min(result[3]);
+            }
+            return result;
+        },
+    };
+
+    let mut result = [0.0; 4];
+    for channel in 0..4 {
+        result[channel] = src[channel] * fa + dst[channel] * fb;
+    }
+    result
+}
@@ -0,0 +1,94 @@
+//! Hides elements of already tessellated [`VertexBuffers`] by leaving out their range of indices,
+//! so toggling the visibility of an element doesn't need to tessellate the SVG again.
+
+use std::{collections::{HashMap, HashSet}, ops::Range};
+
+use crate::render::vertex_buffer::IndexType;
+
+
+/// Where the triangles of elements are in the indices of a buffer, keyed by the ID of the
+/// elements. Recorded by [`generate_maskable_buffer`](super::tessellation::generate_maskable_buffer).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ElementRanges {
+    /// Ranges of indices of every element, shapes with a fill and a stroke have two.
+    pub(crate) ranges: HashMap<String, Vec<Range<usize>>>,
+    /// Elements whose triangles were merged with others by the effects of a group, like clip
+    /// paths, filters or blend modes, or that are part of a clip path, mask or pattern.
+    pub(crate) unmaskable: HashSet<String>,
+    /// Elements that were drawn even though they are hidden in the file.
+    pub(crate) shown: HashSet<String>,
+}
+
+impl ElementRanges {
+    /// Returns `indices` without the triangles of the elements that `visibility` hides, or `None`
+    /// if one of the elements can't be hidden, or shown, without tessellating again. `hidden` are
+    /// the IDs of the elements that are hidden in the file.
+    pub(crate) fn mask(&self, indices: &[IndexType], visibility: &HashMap<String, bool>, hidden: &HashSet<String>) -> Option<Vec<IndexType>> {
+        let mut masked = vec![false; indices.len()];
+        for id in visibility.keys().chain(self.shown.iter()) {
+            let drawn = self.shown.contains(id) || !hidden.contains(id);
+            let visible = visibility.get(id).copied().unwrap_or_else(|| !hidden.contains(id));
+            match (visible, drawn) {
+                // The element isn't part of the buffer.
+                (true, false) => return None,
+                (false, true) if self.unmaskable.contains(id) => return None,
+                (false, true) => {
+                    for range in self.ranges.get(id).into_iter().flatten() {
+                        masked[range.clone()].iter_mut().for_each(|masked| *masked = true);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        Some(indices.iter().zip(masked).filter(|(_, masked)| !masked).map(|(index, _)| *index).collect())
+    }
+
+    /// Updates the ranges after the indices in `replaced` were replaced by `len` other indices.
+    /// Ranges around them grow or shrink, the ones after them move, and the ones inside of them
+    /// are removed.
+    pub(crate) fn replace(&mut self, replaced: Range<usize>, len: usize) {
+        for ranges in self.ranges.values_mut() {
+            *ranges = ranges.drain(..)
+                .filter_map(|range| {
+                    if range.end <= replaced.start {
+                        Some(range)
+                    } else if range.start >= replaced.end {
+                        Some(range.start + len - replaced.len()..range.end + len - replaced.len())
+                    } else if range.start <= replaced.start && range.end >= replaced.end {
+                        Some(range.start..range.end + len - replaced.len())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+        }
+        self.ranges.retain(|_, ranges| !ranges.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_moves_ranges() {
+        let mut ranges = ElementRanges::default();
+        for (id, range) in [("before", 0..3), ("group", 3..15), ("text", 6..12), ("tspan", 6..9), ("after", 12..15), ("next", 15..18)] {
+            ranges.ranges.insert(id.to_owned(), vec![range]);
+        }
+
+        ranges.replace(6..12, 3);
+        assert_eq!(ranges.ranges["before"], vec![0..3]);
+        assert_eq!(ranges.ranges["group"], vec![3..12]);
+        assert_eq!(ranges.ranges["text"], vec![6..9]);
+        assert!(!ranges.ranges.contains_key("tspan"));
+        assert_eq!(ranges.ranges["after"], vec![9..12]);
+        assert_eq!(ranges.ranges["next"], vec![12..15]);
+
+        ranges.replace(6..9, 9);
+        assert_eq!(ranges.ranges["group"], vec![3..18]);
+        assert_eq!(ranges.ranges["text"], vec![6..15]);
+        assert_eq!(ranges.ranges["next"], vec![18..21]);
+    }
+}
@@ -12,14 +12,34 @@ use bevy::{
 use bevy::core_pipeline::Transparent2d;
 #[cfg(feature = "3d")]
 use bevy::core_pipeline::Transparent3d;
+#[cfg(any(feature = "2d", feature = "3d"))]
+use bevy::{
+    math::{Mat4, Vec2, Vec3Swizzles},
+    transform::components::GlobalTransform,
+};
 use lyon_tessellation::{FillTessellator, StrokeTessellator};
 
+#[cfg(any(feature = "2d", feature = "3d"))]
+use crate::svg::{Origin, Svg, SvgScaleMode, SvgSize, SvgYAxis};
+
 #[cfg(feature = "2d")]
 mod pipeline_2d;
 #[cfg(feature = "3d")]
 mod pipeline_3d;
+mod clip;
+pub(crate) mod dash;
+mod extrusion;
+mod filter;
+pub(crate) mod masking;
+pub(crate) mod nine_slice;
+pub(crate) mod raster;
+#[cfg(feature = "2d")]
+pub(crate) mod sdf;
 pub(crate) mod tessellation;
-mod vertex_buffer;
+mod tiling;
+#[cfg(any(feature = "2d", feature = "3d"))]
+mod tint;
+pub(crate) mod vertex_buffer;
 
 
 /// Plugin that renders [`Svg`](crate::svg::Svg)s in 2D
@@ -52,9 +72,18 @@ impl Plugin for SvgPlugin {
             .insert_resource(stroke_tess);
         // Register our custom draw function and pipeline, and add our render systems
         let render_app = app.get_sub_app_mut(RenderApp).unwrap();
+        #[cfg(any(feature = "2d", feature = "3d"))]
+        render_app
+            .init_resource::<tint::SvgTintLayout>()
+            .init_resource::<tint::SvgTintUniforms>()
+            .add_system_to_stage(RenderStage::Extract, tint::extract_svg_tints)
+            .add_system_to_stage(RenderStage::Prepare, tint::prepare_svg_tints)
+            .add_system_to_stage(RenderStage::Queue, tint::queue_svg_tints);
         #[cfg(feature = "2d")]
         render_app
             .add_render_command::<Transparent2d, pipeline_2d::DrawSvg2d>()
+            .init_resource::<sdf::SvgSdfLayout>()
+            .init_resource::<sdf::SvgSdfBindGroups>()
             .init_resource::<pipeline_2d::Svg2dPipeline>()
             .init_resource::<SpecializedRenderPipelines<pipeline_2d::Svg2dPipeline>>()
             .init_resource::<pipeline_2d::ExtractedSvgs2d>()
@@ -72,3 +101,45 @@ impl Plugin for SvgPlugin {
             .add_system_to_stage(RenderStage::Queue, pipeline_3d::queue_svg_3d);
     }
 }
+
+/// The components of an entity that decide where its SVG is drawn.
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub(crate) type Placement = (
+    &'static Origin,
+    Option<&'static SvgSize>,
+    Option<&'static SvgScaleMode>,
+    Option<&'static SvgYAxis>,
+    &'static GlobalTransform,
+);
+
+/// Returns the matrix that is applied to the model matrix of an entity to place the mesh of `svg`
+/// with the [`Origin`], [`SvgSize`], [`SvgScaleMode`] and [`SvgYAxis`] of `placement`, the
+/// components of the entity itself or of the root of its path entities.
+#[cfg(any(feature = "2d", feature = "3d"))]
+pub(crate) fn placement_correction(
+    svg: &Svg,
+    (origin, size, scale_mode, y_axis, global_transform): (&Origin, Option<&SvgSize>, Option<&SvgScaleMode>, Option<&SvgYAxis>, &GlobalTransform),
+    default_y_axis: SvgYAxis,
+) -> Mat4 {
+    let scale = global_transform.scale.xy();
+    let (mut mesh_scale, mesh_offset) = size.map_or((Vec2::ONE, Vec2::ZERO), |size| {
+        size.layout(svg.size, scale_mode.copied().unwrap_or_default())
+    });
+    // The origin refers to the size of the entity, which the mesh is centered in.
+    let scaled_size = size.map_or(svg.size, |size| Vec2::new(size.width, size.height)) * scale;
+    let mut origin_offset = origin.compute_translation(scaled_size) + (mesh_offset * scale).extend(0.0);
+    // The mesh has the y-axis of Bevy, it is flipped to stay upright for one that points down.
+    if y_axis.copied().unwrap_or(default_y_axis) == SvgYAxis::Down {
+        mesh_scale.y = -mesh_scale.y;
+        origin_offset.y = -origin_offset.y;
+    }
+
+    let translation = Mat4::from_translation(origin_offset);
+    let root = global_transform.compute_matrix();
+    if mesh_scale == Vec2::ONE || root.determinant() == 0.0 {
+        return translation;
+    }
+    // The mesh is scaled in the coordinate system of the root entity, which also applies to the
+    // model matrices of its path entities.
+    translation * root * Mat4::from_scale(mesh_scale.extend(1.0)) * root.inverse()
+}
@@ -0,0 +1,134 @@
+//! Nine-slice scaling of already tessellated [`VertexBuffers`].
+//!
+//! The triangles are split at the margins into the nine regions, and the regions are moved and
+//! scaled so that the corners keep their size once the mesh is stretched to the size of the entity.
+//! Positions are expected with the y-axis of Bevy, where the top of the SVG is at zero.
+
+use bevy::math::{Rect, Vec2, Vec3};
+use copyless::VecHelper;
+
+use crate::render::vertex_buffer::{Vertex, VertexBuffers};
+
+
+/// The margins of a nine-slice in the coordinate system of the SVG, before and after the mesh is
+/// scaled to the size of the entity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct NineSlice {
+    /// Where the SVG is split, distances from its edges.
+    pub(crate) margins: Rect<f32>,
+    /// Where the split lines end up in the mesh, so that the margins keep their size once the
+    /// mesh is scaled.
+    pub(crate) target: Rect<f32>,
+}
+
+impl NineSlice {
+    /// Computes where the split lines end up for an SVG of `size` that is scaled by `scale`.
+    pub(crate) fn new(margins: Rect<f32>, size: Vec2, scale: Vec2) -> Self {
+        let fit = |start: f32, end: f32, length: f32, scale: f32| {
+            let (start, end) = (start / scale, end / scale);
+            // Margins that don't fit into the scaled size shrink, like they do in CSS.
+            let factor = match start + end > length {
+                true => length / (start + end),
+                false => 1.0,
+            };
+            (start * factor, end * factor)
+        };
+        let (left, right) = fit(margins.left, margins.right, size.x, scale.x.abs());
+        let (top, bottom) = fit(margins.top, margins.bottom, size.y, scale.y.abs());
+        Self { margins, target: Rect { left, right, top, bottom } }
+    }
+}
+
+/// A vertex whose color can be interpolated.
+#[derive(Clone, Copy)]
+struct Corner {
+    position: Vec3,
+    color: [f32; 4],
+}
+
+impl Corner {
+    fn lerp(&self, other: &Corner, t: f32) -> Corner {
+        let mut color = self.color;
+        for (channel, other) in color.iter_mut().zip(other.color) {
+            *channel += (other - *channel) * t;
+        }
+        Corner { position: self.position.lerp(other.position, t), color }
+    }
+}
+
+/// Splits the triangles of `buffer`, an SVG of `size`, into the nine regions of `nine_slice` and
+/// moves them to where they end up.
+pub(crate) fn apply(buffer: &VertexBuffers, nine_slice: &NineSlice, size: Vec2) -> VertexBuffers {
+    let NineSlice { margins, target } = *nine_slice;
+    let columns = [margins.left, size.x - margins.right];
+    // The y-axis points up, the SVG extends downwards from zero.
+    let rows = [-margins.top, -(size.y - margins.bottom)];
+
+    let mut result = VertexBuffers::new();
+    for triangle in buffer.indices.chunks_exact(3) {
+        let polygon: Vec<Corner> = triangle.iter()
+            .map(|index| {
+                let vertex = buffer.vertices[*index as usize];
+                Corner { position: Vec3::from(vertex.position), color: vertex.linear_color() }
+            })
+            .collect();
+        for column in split(polygon, columns, |corner| corner.position.x) {
+            for piece in split(column, rows, |corner| corner.position.y) {
+                let start = result.vertices.len() as u32;
+                for corner in &piece {
+                    let x = remap(corner.position.x, margins.left, margins.right, target.left, target.right, size.x);
+                    let y = -remap(-corner.position.y, margins.top, margins.bottom, target.top, target.bottom, size.y);
+                    result.vertices.alloc().init(Vertex::new([x, y, corner.position.z], corner.color));
+                }
+                for i in 1..piece.len().saturating_sub(1) as u32 {
+                    result.indices.extend_from_slice(&[start, start + i, start + i + 1]);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Splits the convex `polygon` at the two `lines` along the axis that `coordinate` returns, into
+/// the parts before, between and after them. Empty parts are left out.
+fn split(polygon: Vec<Corner>, lines: [f32; 2], coordinate: impl Fn(&Corner) -> f32 + Copy) -> Vec<Vec<Corner>> {
+    let (low, high) = (lines[0].min(lines[1]), lines[0].max(lines[1]));
+    let (before, rest) = split_at(&polygon, low, coordinate);
+    let (between, after) = split_at(&rest, high, coordinate);
+    [before, between, after].into_iter().filter(|part| part.len() >= 3).collect()
+}
+
+/// Splits the convex `polygon` at `line` into the parts below and above it.
+fn split_at(polygon: &[Corner], line: f32, coordinate: impl Fn(&Corner) -> f32) -> (Vec<Corner>, Vec<Corner>) {
+    let mut below = Vec::new();
+    let mut above = Vec::new();
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (a, b) = (coordinate(current) - line, coordinate(next) - line);
+        if a <= 0.0 {
+            below.push(*current);
+        }
+        if a >= 0.0 {
+            above.push(*current);
+        }
+        if (a < 0.0 && b > 0.0) || (a > 0.0 && b < 0.0) {
+            let crossing = current.lerp(next, a / (a - b));
+            below.push(crossing);
+            above.push(crossing);
+        }
+    }
+    (below, above)
+}
+
+/// Moves `value`, a distance from the start of an axis of `length`, from the regions split by the
+/// margins `start` and `end` into the ones split by `target_start` and `target_end`.
+fn remap(value: f32, start: f32, end: f32, target_start: f32, target_end: f32, length: f32) -> f32 {
+    let (center, target_center) = (length - start - end, length - target_start - target_end);
+    if value <= start {
+        value * target_start / start.max(f32::EPSILON)
+    } else if value >= length - end {
+        length - (length - value) * target_end / end.max(f32::EPSILON)
+    } else {
+        target_start + (value - start) * target_center / center.max(f32::EPSILON)
+    }
+}
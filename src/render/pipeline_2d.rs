@@ -1,92 +1,276 @@
+use std::collections::{HashMap, HashSet};
+
 use bevy::{
-    asset::{Assets, Handle},
-    core::FloatOrd,
+    asset::{Assets, Handle, HandleId},
+    core::{cast_slice, FloatOrd},
     core_pipeline::Transparent2d,
-    ecs::{entity::Entity, world::{FromWorld, World}, system::{Query, Res, ResMut}},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Without,
+        system::{lifetimeless::{Read, SQuery, SRes}, Commands, Query, Res, ResMut, SystemParamItem},
+        world::{FromWorld, World},
+    },
     log::debug,
-    math::{Vec3, Vec3Swizzles},
+    math::{Mat4, Vec3, Vec4},
     render::{
-        mesh::Mesh,
+        mesh::{GpuBufferInfo, Mesh},
         render_asset::RenderAssets,
-        render_phase::{DrawFunctions, RenderPhase, SetItemPipeline},
+        render_phase::{DrawFunctions, EntityRenderCommand, RenderCommandResult, RenderPhase, SetItemPipeline, TrackedRenderPass},
         render_resource::{
-            BlendState, ColorTargetState, ColorWrites, FragmentState, FrontFace,
-            MultisampleState, PolygonMode, PrimitiveState, PipelineCache,
-            RenderPipelineDescriptor, Shader, SpecializedRenderPipeline, SpecializedRenderPipelines, TextureFormat,
-            VertexBufferLayout, VertexFormat, VertexState, VertexStepMode,
+            BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindingResource, BlendState, Buffer, BufferInitDescriptor,
+            BufferUsages, ColorTargetState, ColorWrites, FragmentState, FrontFace, IndexFormat, MultisampleState, PolygonMode,
+            PrimitiveState, PipelineCache, RenderPipelineDescriptor, Shader, SpecializedRenderPipeline,
+            SpecializedRenderPipelines, TextureFormat, VertexAttribute, VertexBufferLayout, VertexFormat, VertexState,
+            VertexStepMode,
         },
-        texture::BevyDefault,
+        renderer::RenderDevice,
+        texture::{BevyDefault, Image},
         view::{ComputedVisibility, Msaa}, RenderWorld,
     },
     sprite::{
-        DrawMesh2d, Mesh2dHandle, Mesh2dPipeline, Mesh2dPipelineKey,
+        Mesh2dHandle, Mesh2dPipeline, Mesh2dPipelineKey,
         SetMesh2dBindGroup, SetMesh2dViewBindGroup, Mesh2dUniform,
     },
     transform::components::GlobalTransform,
 };
 use copyless::VecHelper;
 
-use crate::{render::SVG_2D_SHADER_HANDLE, svg::{Origin, Svg}};
+use crate::{
+    baked::mesh_data,
+    material::SvgMaterialMesh,
+    overrides::{SvgColorMatrix, SvgTint},
+    path_entities::{SvgPartOf, SvgPathEntities},
+    render::{
+        placement_correction, sdf::{SetSvgSdfBindGroup, SvgSdfBindGroups, SvgSdfLayout}, tint::{SetSvgTintBindGroup, SvgTintLayout},
+        vertex_buffer::vertex_buffer_layout, Placement, SVG_2D_SHADER_HANDLE,
+    },
+    sdf::SdfMesh,
+    svg::{Svg, SvgBatching, SvgInstancing, SvgYAxis},
+};
+
 
+/// Number of floats per instance in the instance buffer, a model matrix and a tint.
+const INSTANCE_FLOATS: usize = 20;
 
 #[derive(Default)]
 pub struct ExtractedSvgs2d {
     svgs: Vec<ExtractedSvg2d>,
+    /// The merged meshes of the entities with [`SvgBatching`].
+    batch: ExtractedBatch,
+}
+
+/// The meshes of all entities with [`SvgBatching`], merged in the order they are drawn.
+#[derive(Default)]
+pub struct ExtractedBatch {
+    /// The position in world space and the tinted color of every vertex.
+    vertices: Vec<[u32; 4]>,
+    indices: Vec<u32>,
 }
 
 #[derive(Clone)]
 pub struct ExtractedSvg2d {
     pub entity: Entity,
     pub mesh2d_handle: Mesh2dHandle,
-    pub origin_offset: Vec3,
+    /// The distance field atlas of an entity with a [`SvgSdf`](crate::prelude::SvgSdf).
+    pub sdf_atlas: Option<Handle<Image>>,
+    /// Applied to the model matrix to place the mesh, see [`placement_correction`].
+    pub correction: Mat4,
     pub z: f32,
+    /// The tint of an entity with [`SvgInstancing`], which is drawn together with the other ones
+    /// that share its mesh.
+    pub instance_tint: Option<Vec4>,
+    /// Whether the entity draws all instances of its mesh, set once they are prepared.
+    pub instanced: bool,
+    /// Whether the entity draws the merged meshes of all entities with [`SvgBatching`].
+    pub batched: bool,
+    /// Whether the entity is drawn by a [`SvgMaterial2dPlugin`](crate::prelude::SvgMaterial2dPlugin),
+    /// the mesh is still placed here.
+    pub material: bool,
+}
+
+/// The instances of a mesh that are drawn by the backmost one of them, see [`SvgInstancing`].
+#[derive(Component)]
+pub struct SvgInstanceBuffer {
+    buffer: Buffer,
+    count: u32,
+}
+
+/// The merged meshes of all entities with [`SvgBatching`], drawn by the backmost one of them.
+#[derive(Component)]
+pub struct SvgBatchBuffers {
+    vertices: Buffer,
+    indices: Buffer,
+    count: u32,
 }
 
 /// Extract [`Svg`]s with a [`Mesh2dHandle`] component into [`RenderWorld`].
+#[allow(clippy::type_complexity)]
 pub fn extract_svg_2d(
     mut render_world: ResMut<RenderWorld>,
     svgs: Res<Assets<Svg>>,
-    query: Query<(Entity, &ComputedVisibility, &Handle<Svg>, &Mesh2dHandle, &Origin, &GlobalTransform)>,
+    meshes: Res<Assets<Mesh>>,
+    default_y_axis: Res<SvgYAxis>,
+    query: Query<
+        (
+            Entity, &ComputedVisibility, &Handle<Svg>, &Mesh2dHandle, &GlobalTransform, Option<&SvgPartOf>,
+            Option<&SdfMesh>, Option<&SvgInstancing>, Option<&SvgBatching>, Option<&SvgTint>, Option<&SvgMaterialMesh>,
+            Option<&SvgColorMatrix>,
+        ),
+        Without<SvgPathEntities>,
+    >,
+    placements: Query<Placement>,
 ) {
     debug!("Extracting `Svg`s from `World`.");
     let mut extracted_svgs = render_world.get_resource_mut::<ExtractedSvgs2d>().unwrap();
     extracted_svgs.svgs.clear();
-    for (entity, computed_visibility, svg_handle, mesh2d_handle, origin, global_transform) in query.iter() {
+    let mut batched = Vec::new();
+    for (entity, computed_visibility, svg_handle, mesh2d_handle, global_transform, part_of, sdf_mesh, instancing, batching, tint, material, color_matrix) in query.iter() {
         if !computed_visibility.is_visible {
             continue;
         }
 
-        if let Some(svg) = svgs.get(svg_handle) {
-            let mut transform = global_transform.clone();
-            let scaled_size = svg.size * transform.scale.xy();
-            transform.translation += origin.compute_translation(scaled_size);
-
+        // Path entities are placed like the whole SVG of their root entity.
+        let placement = placements.get(part_of.map_or(entity, |part_of| part_of.0));
+        if let (Some(svg), Ok(placement)) = (svgs.get(svg_handle), placement) {
+            let correction = placement_correction(svg, placement, *default_y_axis);
+            // Entities with distance fields, instances or materials are drawn with their own pipeline,
+            // and the color matrix is only applied by the uniform of each entity.
+            if batching.is_some() && sdf_mesh.is_none() && instancing.is_none() && material.is_none() && color_matrix.is_none() {
+                let model = correction * global_transform.compute_matrix();
+                let tint = tint.map_or(Vec4::ONE, |tint| Vec4::from(tint.0.as_linear_rgba_f32()));
+                batched.push((FloatOrd(global_transform.translation.z), entity, model, mesh2d_handle, tint));
+                continue;
+            }
             extracted_svgs.svgs.alloc().init(ExtractedSvg2d {
                 entity,
                 mesh2d_handle: mesh2d_handle.clone(),
-                origin_offset: origin.compute_translation(scaled_size),
+                sdf_atlas: sdf_mesh.map(|sdf_mesh| sdf_mesh.atlas.clone_weak()),
+                correction,
                 z: global_transform.translation.z,
+                // Entities with distance fields have a mesh of their own.
+                instance_tint: instancing.filter(|_| sdf_mesh.is_none() && material.is_none() && color_matrix.is_none())
+                    .map(|_| tint.map_or(Vec4::ONE, |tint| Vec4::from(tint.0.as_linear_rgba_f32()))),
+                instanced: false,
+                batched: false,
+                material: material.is_some(),
             });
         }
     }
 
+    // The meshes are merged from back to front, like the phase would sort them.
+    batched.sort_by_key(|(z, ..)| *z);
+    let ExtractedBatch { vertices, indices } = &mut extracted_svgs.batch;
+    vertices.clear();
+    indices.clear();
+    for (_, _, model, mesh2d_handle, tint) in &batched {
+        let (positions, colors, mesh_indices) = match meshes.get(&mesh2d_handle.0) {
+            Some(mesh) => mesh_data(mesh),
+            None => continue,
+        };
+        let offset = vertices.len() as u32;
+        for (position, color) in positions.iter().zip(colors) {
+            let position = model.transform_point3(Vec3::from(*position));
+            vertices.alloc().init([position.x.to_bits(), position.y.to_bits(), position.z.to_bits(), tinted(*color, *tint)]);
+        }
+        indices.extend(mesh_indices.iter().map(|index| index + offset));
+    }
+    if let Some((FloatOrd(z), entity, _, mesh2d_handle, _)) = batched.first() {
+        extracted_svgs.svgs.alloc().init(ExtractedSvg2d {
+            entity: *entity,
+            mesh2d_handle: (*mesh2d_handle).clone(),
+            sdf_atlas: None,
+            correction: Mat4::IDENTITY,
+            z: *z,
+            instance_tint: None,
+            instanced: false,
+            batched: true,
+            material: false,
+        });
+    }
+
     debug!("Extracted {} `Svg2d`s from `World` and inserted them into `RenderWorld`.", extracted_svgs.svgs.len());
 }
 
 pub fn prepare_svg_2d(
-    svgs_2d: ResMut<ExtractedSvgs2d>,
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut svgs_2d: ResMut<ExtractedSvgs2d>,
     mut query: Query<(Entity, &mut Mesh2dUniform)>,
 ) {
     for svg2d in &svgs_2d.svgs {
-        // Get the Uniform (aka data that will be send to the shader) and apply the origin offset
-        // to the translation.
+        // Get the Uniform (aka data that will be send to the shader) and place the mesh with the
+        // origin, size and y-axis of the SVG.
         if let Ok((_, mut uniform)) = query.get_mut(svg2d.entity) {
-            let column = uniform.transform.col_mut(3);
-            column.x += svg2d.origin_offset.x;
-            column.y += svg2d.origin_offset.y;
-            column.z += svg2d.origin_offset.z;
+            if svg2d.correction != Mat4::IDENTITY {
+                uniform.transform = svg2d.correction * uniform.transform;
+                uniform.inverse_transpose_model = uniform.transform.inverse().transpose();
+            }
         }
     }
+
+    // The instances of each mesh are written into one buffer, sorted by their z value like the
+    // phase would sort them, and drawn by the backmost one.
+    let mut meshes: HashMap<HandleId, Vec<usize>> = HashMap::new();
+    for (i, svg2d) in svgs_2d.svgs.iter().enumerate() {
+        if svg2d.instance_tint.is_some() {
+            meshes.entry(svg2d.mesh2d_handle.0.id).or_default().push(i);
+        }
+    }
+    let mut drawn_by_others = HashSet::new();
+    for mut instances in meshes.into_values() {
+        instances.sort_by_key(|&i| FloatOrd(svgs_2d.svgs[i].z));
+        let mut data = Vec::with_capacity(instances.len() * INSTANCE_FLOATS);
+        for &i in &instances {
+            let svg2d = &svgs_2d.svgs[i];
+            if let (Ok((_, uniform)), Some(tint)) = (query.get(svg2d.entity), svg2d.instance_tint) {
+                data.extend_from_slice(&uniform.transform.to_cols_array());
+                data.extend_from_slice(&tint.to_array());
+            }
+        }
+        if data.is_empty() {
+            continue;
+        }
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("svg_instance_buffer"),
+            contents: cast_slice(&data),
+            usage: BufferUsages::VERTEX,
+        });
+        let first = &mut svgs_2d.svgs[instances[0]];
+        first.instanced = true;
+        commands.get_or_spawn(first.entity).insert(SvgInstanceBuffer { buffer, count: (data.len() / INSTANCE_FLOATS) as u32 });
+        drawn_by_others.extend(instances[1..].iter().map(|&i| svgs_2d.svgs[i].entity));
+    }
+    svgs_2d.svgs.retain(|svg2d| !drawn_by_others.contains(&svg2d.entity));
+
+    // The merged meshes are drawn by the backmost entity with `SvgBatching`, not at all if none of
+    // their meshes were loaded.
+    if svgs_2d.batch.indices.is_empty() {
+        svgs_2d.svgs.retain(|svg2d| !svg2d.batched);
+    }
+    let batch = &svgs_2d.batch;
+    if let Some(first) = svgs_2d.svgs.iter().find(|svg2d| svg2d.batched) {
+        let vertices = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("svg_batch_vertex_buffer"),
+            contents: cast_slice(&batch.vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let indices = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("svg_batch_index_buffer"),
+            contents: cast_slice(&batch.indices),
+            usage: BufferUsages::INDEX,
+        });
+        commands.get_or_spawn(first.entity).insert(SvgBatchBuffers { vertices, indices, count: batch.indices.len() as u32 });
+    }
+}
+
+/// Returns the packed linear RGBA `color` multiplied with `tint`.
+fn tinted(color: u32, tint: Vec4) -> u32 {
+    if tint == Vec4::ONE {
+        return color;
+    }
+    let channels = Vec4::from(color.to_le_bytes().map(f32::from)) * tint;
+    u32::from_le_bytes(channels.to_array().map(|channel| channel.round().clamp(0.0, 255.0) as u8))
 }
 
 /// Queue all extraced 2D [`Svg`]s for rendering with the [`Svg2dPipeline`] custom pipeline and [`DrawSvg2d`] draw function
@@ -98,9 +282,14 @@ pub fn queue_svg_2d(
     mut pipeline_cache: ResMut<PipelineCache>,
     msaa: Res<Msaa>,
     render_meshes: Res<RenderAssets<Mesh>>,
+    render_images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    sdf_layout: Res<SvgSdfLayout>,
+    mut sdf_bind_groups: ResMut<SvgSdfBindGroups>,
     svgs_2d: ResMut<ExtractedSvgs2d>,
     mut views: Query<&mut RenderPhase<Transparent2d>>,
 ) {
+    sdf_bind_groups.bind_groups.clear();
     if svgs_2d.svgs.is_empty() {
         debug!("No `Svg2d`s found to queue.");
         return;
@@ -115,14 +304,38 @@ pub fn queue_svg_2d(
     // Iterate each view (a camera is a view)
     for mut transparent_phase in views.iter_mut() {
         // Queue all entities visible to that view
-        for svg2d in &svgs_2d.svgs {
+        for svg2d in svgs_2d.svgs.iter().filter(|svg2d| !svg2d.material) {
             // Get our specialized pipeline
             let mut mesh2d_key = mesh_key;
             if let Some(mesh) = render_meshes.get(&svg2d.mesh2d_handle.0) {
                 mesh2d_key |= Mesh2dPipelineKey::from_primitive_topology(mesh.primitive_topology);
             }
 
-            let pipeline_id = pipelines.specialize(&mut pipeline_cache, &svg_2d_pipeline, mesh2d_key);
+            if let Some(atlas) = &svg2d.sdf_atlas {
+                // Drawn as soon as the atlas is on the GPU.
+                let gpu_atlas = match render_images.get(atlas) {
+                    Some(gpu_atlas) => gpu_atlas,
+                    None => continue,
+                };
+                sdf_bind_groups.bind_groups.entry(svg2d.entity).or_insert_with(|| {
+                    render_device.create_bind_group(&BindGroupDescriptor {
+                        entries: &[
+                            BindGroupEntry { binding: 0, resource: BindingResource::TextureView(&gpu_atlas.texture_view) },
+                            BindGroupEntry { binding: 1, resource: BindingResource::Sampler(&gpu_atlas.sampler) },
+                        ],
+                        label: Some("svg_sdf_bind_group"),
+                        layout: &sdf_layout.0,
+                    })
+                });
+            }
+
+            let key = Svg2dPipelineKey {
+                mesh: mesh2d_key,
+                sdf: svg2d.sdf_atlas.is_some(),
+                instanced: svg2d.instanced,
+                batched: svg2d.batched,
+            };
+            let pipeline_id = pipelines.specialize(&mut pipeline_cache, &svg_2d_pipeline, key);
             transparent_phase.add(Transparent2d {
                 entity: svg2d.entity,
                 draw_function: draw_svg_2d,
@@ -145,50 +358,156 @@ pub type DrawSvg2d = (
     SetMesh2dViewBindGroup<0>,
     // Set the mesh uniform as bind group 1
     SetMesh2dBindGroup<1>,
-    // Draw the mesh
-    DrawMesh2d,
+    // Set the tint uniform as bind group 2
+    SetSvgTintBindGroup<2>,
+    // Set the distance field atlas as bind group 3, if the SVG is drawn from one
+    SetSvgSdfBindGroup<3>,
+    // Draw the mesh, once for every instance
+    DrawSvgMesh2d,
 );
 
+/// Draws the mesh of an SVG entity, with all instances of its [`SvgInstanceBuffer`] if it has one,
+/// or the merged meshes of its [`SvgBatchBuffers`].
+pub struct DrawSvgMesh2d;
+
+impl EntityRenderCommand for DrawSvgMesh2d {
+    type Param = (
+        SRes<RenderAssets<Mesh>>,
+        SQuery<(Read<Mesh2dHandle>, Option<Read<SvgInstanceBuffer>>, Option<Read<SvgBatchBuffers>>)>,
+    );
+
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        (meshes, query): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let (mesh_handle, instances, batch) = match query.get_inner(item) {
+            Ok(item) => item,
+            Err(_) => return RenderCommandResult::Failure,
+        };
+        if let Some(batch) = batch {
+            pass.set_vertex_buffer(0, batch.vertices.slice(..));
+            pass.set_index_buffer(batch.indices.slice(..), 0, IndexFormat::Uint32);
+            pass.draw_indexed(0..batch.count, 0, 0..1);
+            return RenderCommandResult::Success;
+        }
+        let gpu_mesh = match meshes.into_inner().get(&mesh_handle.0) {
+            Some(gpu_mesh) => gpu_mesh,
+            None => return RenderCommandResult::Failure,
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        let instances = match instances {
+            Some(instances) => {
+                pass.set_vertex_buffer(1, instances.buffer.slice(..));
+                0..instances.count
+            },
+            None => 0..1,
+        };
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed { buffer, index_format, count } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            },
+            GpuBufferInfo::NonIndexed { vertex_count } => pass.draw(0..*vertex_count, instances),
+        }
+        RenderCommandResult::Success
+    }
+}
+
+/// Specializes the [`Svg2dPipeline`] for a mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Svg2dPipelineKey {
+    pub mesh: Mesh2dPipelineKey,
+    /// Whether the mesh consists of quads that are drawn from a distance field atlas.
+    pub sdf: bool,
+    /// Whether the mesh is drawn for all instances in a [`SvgInstanceBuffer`].
+    pub instanced: bool,
+    /// Whether the merged meshes in [`SvgBatchBuffers`] are drawn instead of the mesh.
+    pub batched: bool,
+}
+
 /// Pipeline for 2d [`Svg`]s.
 pub struct Svg2dPipeline {
     mesh2d_pipeline: Mesh2dPipeline,
+    tint_layout: BindGroupLayout,
+    sdf_layout: BindGroupLayout,
 }
 
 impl FromWorld for Svg2dPipeline {
     fn from_world(world: &mut World) -> Self {
         Self {
             mesh2d_pipeline: Mesh2dPipeline::from_world(world),
+            tint_layout: world.get_resource::<SvgTintLayout>().unwrap().0.clone(),
+            sdf_layout: world.get_resource::<SvgSdfLayout>().unwrap().0.clone(),
         }
     }
 }
 
 // Specializie the `Mesh2dPipeline` to draw [`Svg`]s in 2D.
 impl SpecializedRenderPipeline for Svg2dPipeline {
-    type Key = Mesh2dPipelineKey;
+    type Key = Svg2dPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        // Customize how to store the meshes' vertex attributes in the vertex buffer
-        // Meshes for our Svgs only have position and color
-        let formats = vec![
-            // Position
-            VertexFormat::Float32x3,
-            // Color
-            VertexFormat::Uint32,
+        let mut shader_defs = Vec::new();
+        let mut layout = vec![
+            // Bind group 0 is the view uniform
+            self.mesh2d_pipeline.view_layout.clone(),
+            // Bind group 1 is the mesh uniform
+            self.mesh2d_pipeline.mesh_layout.clone(),
+            // Bind group 2 is the tint uniform
+            self.tint_layout.clone(),
         ];
+        if key.sdf {
+            shader_defs.push("SDF".to_owned());
+            // Bind group 3 is the distance field atlas
+            layout.push(self.sdf_layout.clone());
+        }
+        let mut buffers = Vec::new();
+        if key.batched {
+            shader_defs.push("BATCHED".to_owned());
+            shader_defs.push("VERTEX_TINT".to_owned());
+            // The merged meshes only have positions in world space and colors with the tint
+            buffers.push(VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, vec![VertexFormat::Float32x3, VertexFormat::Uint32]));
+        } else {
+            // Meshes for our Svgs are drawn with their position and color, the rest is for other
+            // materials, bevy interleaves them in the order of their ids
+            buffers.push(vertex_buffer_layout(&[
+                Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+                Mesh::ATTRIBUTE_COLOR.at_shader_location(1),
+                // For distance fields
+                Mesh::ATTRIBUTE_UV_0.at_shader_location(3),
+            ]));
+        }
+        if key.instanced {
+            shader_defs.push("INSTANCED".to_owned());
+            shader_defs.push("VERTEX_TINT".to_owned());
+            // The columns of the model matrix and the tint of every instance, after the attributes
+            // of the mesh
+            let size = VertexFormat::Float32x4.size();
+            buffers.push(VertexBufferLayout {
+                array_stride: size * 5,
+                step_mode: VertexStepMode::Instance,
+                attributes: (0..5)
+                    .map(|i| VertexAttribute { format: VertexFormat::Float32x4, offset: size * i, shader_location: 5 + i as u32 })
+                    .collect(),
+            });
+        }
 
         RenderPipelineDescriptor {
             vertex: VertexState {
                 // Use our custom shader
                 shader: SVG_2D_SHADER_HANDLE.typed::<Shader>(),
                 entry_point: "vertex".into(),
-                shader_defs: Vec::new(),
+                shader_defs: shader_defs.clone(),
                 // Use our custom vertex buffer
-                buffers: vec![VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, formats)],
+                buffers,
             },
             fragment: Some(FragmentState {
                 // Use our custom shader
                 shader: SVG_2D_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: Vec::new(),
+                shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![ColorTargetState {
                     format: TextureFormat::bevy_default(),
@@ -197,24 +516,19 @@ impl SpecializedRenderPipeline for Svg2dPipeline {
                 }],
             }),
             // Use the two standard uniforms for 2d meshes
-            layout: Some(vec![
-                // Bind group 0 is the view uniform
-                self.mesh2d_pipeline.view_layout.clone(),
-                // Bind group 1 is the mesh uniform
-                self.mesh2d_pipeline.mesh_layout.clone(),
-            ]),
+            layout: Some(layout),
             primitive: PrimitiveState {
                 front_face: FrontFace::Cw,
                 cull_mode: None,
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
                 conservative: false,
-                topology: key.primitive_topology(),
+                topology: key.mesh.primitive_topology(),
                 strip_index_format: None,
             },
             depth_stencil: None,
             multisample: MultisampleState {
-                count: key.msaa_samples(),
+                count: key.mesh.msaa_samples(),
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -1,19 +1,19 @@
 use bevy::{
     asset::{Assets, Handle},
     core_pipeline::Transparent3d,
-    ecs::{entity::Entity, query::With, world::{FromWorld, World}, system::{Query, Res, ResMut},},
+    ecs::{entity::Entity, query::{With, Without}, world::{FromWorld, World}, system::{Query, Res, ResMut},},
     log::debug,
-    math::{Vec3, Vec3Swizzles},
+    math::Mat4,
     pbr::MeshUniform,
     render::{
         mesh::Mesh,
         render_asset::RenderAssets,
         render_phase::{DrawFunctions, RenderPhase, SetItemPipeline},
         render_resource::{
-            BlendState, ColorTargetState, ColorWrites, FragmentState, FrontFace,
+            BindGroupLayout, BlendState, ColorTargetState, ColorWrites, FragmentState, FrontFace,
             MultisampleState, PolygonMode, PrimitiveState, PipelineCache,
             RenderPipelineDescriptor, Shader, SpecializedRenderPipeline, SpecializedRenderPipelines, TextureFormat,
-            VertexBufferLayout, VertexFormat, VertexState, VertexStepMode,
+            VertexState,
         },
         texture::BevyDefault,
         view::{ComputedVisibility, Msaa}, RenderWorld,
@@ -22,7 +22,13 @@ use bevy::{
 };
 use copyless::VecHelper;
 
-use crate::{render::SVG_3D_SHADER_HANDLE, svg::{Origin, Svg}};
+use crate::{
+    overrides::SvgExtrusion,
+    path_entities::{SvgPartOf, SvgPathEntities},
+    pbr::PbrSvg,
+    render::{placement_correction, tint::{SetSvgTintBindGroup, SvgTintLayout}, vertex_buffer::vertex_buffer_layout, Placement, SVG_3D_SHADER_HANDLE},
+    svg::{Svg, SvgDoubleSided, SvgYAxis},
+};
 
 
 #[derive(Default)]
@@ -34,34 +40,48 @@ pub struct ExtractedSvgs3d {
 pub struct ExtractedSvg3d {
     pub entity: Entity,
     pub mesh3d_handle: Handle<Mesh>,
-    pub origin_offset: Vec3,
+    /// Applied to the model matrix to place the mesh, see [`placement_correction`].
+    pub correction: Mat4,
     pub z: f32,
+    /// Whether the mesh is an extruded solid, which is drawn with depth writes.
+    pub solid: bool,
+    /// Whether the back of the mesh is drawn, see [`SvgDoubleSided`].
+    pub double_sided: bool,
+    /// Whether the entity is drawn with a [`SvgStandardMaterial`](crate::prelude::SvgStandardMaterial),
+    /// the mesh is still placed here.
+    pub pbr: bool,
 }
 
 /// Extract [`Svg`]s with a [`Handle`] to a [`Mesh`] component into [`RenderWorld`].
+#[allow(clippy::type_complexity)]
 pub fn extract_svg_3d(
     mut render_world: ResMut<RenderWorld>,
     svgs: Res<Assets<Svg>>,
-    query: Query<(Entity, &ComputedVisibility, &Handle<Svg>, &Handle<Mesh>, &Origin, &GlobalTransform), With<Handle<Svg>>>,
+    default_y_axis: Res<SvgYAxis>,
+    query: Query<(Entity, &ComputedVisibility, &Handle<Svg>, &Handle<Mesh>, &GlobalTransform, Option<&SvgPartOf>, Option<&SvgExtrusion>, Option<&PbrSvg>), (With<Handle<Svg>>, Without<SvgPathEntities>)>,
+    placements: Query<Placement>,
+    double_sided: Query<&SvgDoubleSided>,
 ) {
     debug!("Extracting `Svg`s from `World`.");
     let mut extracted_svgs = render_world.get_resource_mut::<ExtractedSvgs3d>().unwrap();
     extracted_svgs.svgs.clear();
-    for (entity, computed_visibility, svg_handle, mesh3d_handle, origin, global_transform) in query.iter() {
+    for (entity, computed_visibility, svg_handle, mesh3d_handle, global_transform, part_of, extrusion, pbr_svg) in query.iter() {
         if !computed_visibility.is_visible {
             continue;
         }
 
-        if let Some(svg) = svgs.get(svg_handle) {
-            let mut transform = global_transform.clone();
-            let scaled_size = svg.size * transform.scale.xy();
-            transform.translation += origin.compute_translation(scaled_size);
-
+        // Path entities are placed like the whole SVG of their root entity.
+        let root = part_of.map_or(entity, |part_of| part_of.0);
+        let placement = placements.get(root);
+        if let (Some(svg), Ok(placement)) = (svgs.get(svg_handle), placement) {
             extracted_svgs.svgs.alloc().init(ExtractedSvg3d {
                 entity,
                 mesh3d_handle: mesh3d_handle.clone(),
-                origin_offset: origin.compute_translation(scaled_size),
+                correction: placement_correction(svg, placement, *default_y_axis),
                 z: global_transform.translation.z,
+                solid: extrusion.map_or(false, |extrusion| extrusion.depth != 0.0),
+                double_sided: double_sided.get(root).map_or(true, |double_sided| double_sided.0),
+                pbr: pbr_svg.is_some(),
             });
         }
     }
@@ -74,13 +94,13 @@ pub fn prepare_svg_3d(
     mut query: Query<(Entity, &mut MeshUniform)>,
 ) {
     for svg2d in &svgs_2d.svgs {
-        // Get the Uniform (aka data that will be send to the shader) and apply the origin offset
-        // to the translation.
+        // Get the Uniform (aka data that will be send to the shader) and place the mesh with the
+        // origin, size and y-axis of the SVG.
         if let Ok((_, mut uniform)) = query.get_mut(svg2d.entity) {
-            let column = uniform.transform.col_mut(3);
-            column.x += svg2d.origin_offset.x;
-            column.y += svg2d.origin_offset.y;
-            column.z += svg2d.origin_offset.z;
+            if svg2d.correction != Mat4::IDENTITY {
+                uniform.transform = svg2d.correction * uniform.transform;
+                uniform.inverse_transpose_model = uniform.transform.inverse().transpose();
+            }
         }
     }
 }
@@ -111,14 +131,15 @@ pub fn queue_svg_3d(
     // Iterate each view (a camera is a view)
     for mut transparent_phase in views.iter_mut() {
         // Queue all entities visible to that view
-        for svg3d in &svgs_3d.svgs {
+        for svg3d in svgs_3d.svgs.iter().filter(|svg3d| !svg3d.pbr) {
             // Get our specialized pipeline
             let mut mesh3d_key = mesh_key;
             if let Some(mesh) = render_meshes.get(&svg3d.mesh3d_handle) {
                 mesh3d_key |= MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
             }
 
-            let pipeline_id = pipelines.specialize(&mut pipeline_cache, &svg_3d_pipeline, mesh3d_key);
+            let key = Svg3dPipelineKey { mesh: mesh3d_key, solid: svg3d.solid, double_sided: svg3d.double_sided };
+            let pipeline_id = pipelines.specialize(&mut pipeline_cache, &svg_3d_pipeline, key);
             transparent_phase.add(Transparent3d {
                 entity: svg3d.entity,
                 draw_function: draw_svg_3d,
@@ -137,50 +158,66 @@ pub type DrawSvg3d = (
     SetMeshViewBindGroup<0>,
     // Set the mesh uniform as bind group 1
     SetMeshBindGroup<1>,
+    // Set the tint uniform as bind group 2
+    SetSvgTintBindGroup<2>,
     // Draw the mesh
     DrawMesh,
 );
 
+/// Specializes the [`Svg3dPipeline`] for a mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Svg3dPipelineKey {
+    pub mesh: MeshPipelineKey,
+    /// Whether the mesh is an extruded solid, whose front has to hide its back and walls.
+    pub solid: bool,
+    /// Whether the triangles whose normals face away from the camera are drawn.
+    pub double_sided: bool,
+}
+
 // Pipeline for 2d [`Svg`]s.
 pub struct Svg3dPipeline {
     mesh3d_pipeline: MeshPipeline,
+    tint_layout: BindGroupLayout,
 }
 
 impl FromWorld for Svg3dPipeline {
     fn from_world(world: &mut World) -> Self {
         Self {
             mesh3d_pipeline: MeshPipeline::from_world(world),
+            tint_layout: world.get_resource::<SvgTintLayout>().unwrap().0.clone(),
         }
     }
 }
 
 // Specializie the `Mesh2dPipeline` to draw [`Svg`]s in 2D.
 impl SpecializedRenderPipeline for Svg3dPipeline {
-    type Key = MeshPipelineKey;
+    type Key = Svg3dPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
         // Customize how to store the meshes' vertex attributes in the vertex buffer
-        // Meshes for our Svgs only have position and color
-        let formats = vec![
-            // Position
-            VertexFormat::Float32x3,
-            // Color
-            VertexFormat::Uint32,
-        ];
+        // Meshes for our Svgs are drawn with their position, color and normal, the rest is for
+        // other materials, bevy interleaves them in the order of their ids
+        let vertex_layout = vertex_buffer_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(1),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(2),
+        ]);
+        // The winding of the triangles doesn't tell their side, the normals do.
+        let shader_defs = if key.double_sided { Vec::new() } else { vec!["ONE_SIDED".to_owned()] };
 
         RenderPipelineDescriptor {
             vertex: VertexState {
                 // Use our custom shader
                 shader: SVG_3D_SHADER_HANDLE.typed::<Shader>(),
                 entry_point: "vertex".into(),
-                shader_defs: Vec::new(),
+                shader_defs: shader_defs.clone(),
                 // Use our custom vertex buffer
-                buffers: vec![VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, formats)],
+                buffers: vec![vertex_layout],
             },
             fragment: Some(FragmentState {
                 // Use our custom shader
                 shader: SVG_3D_SHADER_HANDLE.typed::<Shader>(),
-                shader_defs: Vec::new(),
+                shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![ColorTargetState {
                     format: TextureFormat::bevy_default(),
@@ -194,6 +231,8 @@ impl SpecializedRenderPipeline for Svg3dPipeline {
                 self.mesh3d_pipeline.view_layout.clone(),
                 // Bind group 1 is the mesh uniform
                 self.mesh3d_pipeline.mesh_layout.clone(),
+                // Bind group 2 is the tint uniform
+                self.tint_layout.clone(),
             ]),
             primitive: PrimitiveState {
                 front_face: FrontFace::Cw,
@@ -201,12 +240,12 @@ impl SpecializedRenderPipeline for Svg3dPipeline {
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
                 conservative: false,
-                topology: key.primitive_topology(),
+                topology: key.mesh.primitive_topology(),
                 strip_index_format: None,
             },
             depth_stencil: Some(bevy::render::render_resource::DepthStencilState {
                 format: TextureFormat::Depth32Float,
-                depth_write_enabled: false,
+                depth_write_enabled: key.solid,
                 depth_compare: bevy::render::render_resource::CompareFunction::Greater,
                 stencil: bevy::render::render_resource::StencilState {
                     front: bevy::render::render_resource::StencilFaceState::IGNORE,
@@ -221,7 +260,7 @@ impl SpecializedRenderPipeline for Svg3dPipeline {
                 },
             }),
             multisample: MultisampleState {
-                count: key.msaa_samples(),
+                count: key.mesh.msaa_samples(),
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
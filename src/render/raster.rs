@@ -0,0 +1,368 @@
+//! A minimal CPU rasterizer for [`VertexBuffers`], used for effects that can't be expressed
+//! with geometry alone, like filters and raster images, and for SVGs drawn as images.
+//!
+//! A [`Raster`] is turned back into geometry by placing a vertex at the center of every pixel,
+//! the GPU then interpolates the colors between them.
+
+use bevy::{
+    math::Vec2,
+    render::{
+        color::Color,
+        mesh::Mesh,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::Image,
+    },
+};
+use copyless::VecHelper;
+use lyon_tessellation::math::Rect;
+
+use crate::{baked::mesh_data, paint::{Paint, RasterImage}, render::vertex_buffer::{Vertex, VertexBuffers}};
+
+/// Upper limit for the width and height of a [`Raster`] in pixels.
+const MAX_RASTER_SIZE: f32 = 512.0;
+/// Lower limit for the width and height of a [`Raster`] in pixels.
+const MIN_RASTER_SIZE: f32 = 16.0;
+/// Number of samples per pixel along each axis when a whole mesh is rasterized.
+const SUPERSAMPLING: usize = 4;
+/// Upper limit for the width and height of the raster that a whole mesh is drawn into, large
+/// rasters get fewer samples per pixel.
+const MAX_SAMPLED_SIZE: usize = 2048;
+
+
+/// An image with premultiplied, linear RGBA pixels that covers a rectangular region.
+#[derive(Clone, Debug)]
+pub(crate) struct Raster {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// Position of the top left corner of the raster.
+    origin: Vec2,
+    /// Size of a single pixel.
+    pixel_size: Vec2,
+    pub(crate) pixels: Vec<[f32; 4]>,
+}
+
+impl Raster {
+    /// Creates a new, transparent raster covering `region`.
+    /// One pixel covers one unit, as long as the raster doesn't get too small or too large.
+    pub(crate) fn new(region: &Rect) -> Self {
+        let width = region.width().clamp(MIN_RASTER_SIZE, MAX_RASTER_SIZE).ceil();
+        let height = region.height().clamp(MIN_RASTER_SIZE, MAX_RASTER_SIZE).ceil();
+        Self::with_resolution(region, width as usize, height as usize)
+    }
+
+    /// Creates a new, transparent raster of `width` x `height` pixels covering `region`.
+    pub(crate) fn with_resolution(region: &Rect, width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            origin: Vec2::new(region.min_x(), region.min_y()),
+            pixel_size: Vec2::new(region.width() / width as f32, region.height() / height as f32),
+            pixels: vec![[0.0; 4]; width * height],
+        }
+    }
+
+    /// Creates a raster from the pixels of `image`, covering the image in image space.
+    /// Images that are larger than the maximum raster size get scaled down.
+    pub(crate) fn from_image(image: &RasterImage) -> Self {
+        let (width, height) = (image.width as usize, image.height as usize);
+        let mut raster = Self::new(&lyon_tessellation::math::rect(0.0, 0.0, width as f32, height as f32));
+
+        // Every pixel of the raster is the average of the image pixels that it covers.
+        let scale = Vec2::new(width as f32 / raster.width as f32, height as f32 / raster.height as f32);
+        for y in 0..raster.height {
+            for x in 0..raster.width {
+                let min = (Vec2::new(x as f32, y as f32) * scale).floor();
+                let max = (Vec2::new(x as f32 + 1.0, y as f32 + 1.0) * scale).ceil().min(Vec2::new(width as f32, height as f32));
+                let mut sum = [0.0; 4];
+                let mut count = 0.0;
+                for src_y in min.y as usize..max.y as usize {
+                    for src_x in min.x as usize..max.x as usize {
+                        let offset = (src_y * width + src_x) * 4;
+                        let color = Color::rgba_u8(
+                            image.data[offset],
+                            image.data[offset + 1],
+                            image.data[offset + 2],
+                            image.data[offset + 3],
+                        );
+                        let color = premultiply(color.as_linear_rgba_f32());
+                        for channel in 0..4 {
+                            sum[channel] += color[channel];
+                        }
+                        count += 1.0;
+                    }
+                }
+                if count > 0.0 {
+                    raster.pixels[y * raster.width + x] = sum.map(|channel| channel / count);
+                }
+            }
+        }
+
+        raster
+    }
+
+    /// Creates a raster of `width` x `height` pixels from the tessellated `mesh` of an SVG of
+    /// `size`, which is stretched over the whole raster. The edges of the triangles are smoothed
+    /// by drawing them at a higher resolution.
+    pub(crate) fn from_mesh(mesh: &Mesh, size: Vec2, width: usize, height: usize) -> Self {
+        let (positions, colors, indices) = mesh_data(mesh);
+        let mut buffer = VertexBuffers::new();
+        buffer.vertices.reserve(positions.len());
+        // The mesh has the y-axis of Bevy, the rows of a raster go down.
+        for (&[x, y, z], &color) in positions.iter().zip(colors) {
+            buffer.vertices.alloc().init(Vertex { position: [x, -y, z], color, uv: [0.0; 2] });
+        }
+        buffer.indices.extend_from_slice(indices);
+
+        let samples = (MAX_SAMPLED_SIZE / width.max(height).max(1)).clamp(1, SUPERSAMPLING);
+        let region = lyon_tessellation::math::rect(0.0, 0.0, size.x, size.y);
+        let mut raster = Self::with_resolution(&region, width * samples, height * samples);
+        raster.draw(&buffer);
+        raster.downsample(samples)
+    }
+
+    /// Creates a raster covering `region` that is filled with `paint`, sampled at the center of
+    /// every pixel. The region is given in the coordinate system of the painted path.
+    pub(crate) fn from_paint(region: &Rect, paint: &Paint) -> Self {
+        let mut raster = Self::new(region);
+        for (i, pixel) in raster.pixels.iter_mut().enumerate() {
+            let center = raster.origin + (Vec2::new((i % raster.width) as f32, (i / raster.width) as f32) + 0.5) * raster.pixel_size;
+            let color = paint.color_at(lyon_tessellation::math::point(center.x, center.y));
+            *pixel = premultiply(color.as_linear_rgba_f32());
+        }
+
+        raster
+    }
+
+    /// Converts a length in the coordinate system of the raster into pixels.
+    pub(crate) fn to_pixels(&self, length: Vec2) -> Vec2 {
+        length / self.pixel_size
+    }
+
+    /// Draws all triangles of `buffer` on top of the raster.
+    pub(crate) fn draw(&mut self, buffer: &VertexBuffers) {
+        for idx in buffer.indices.chunks_exact(3) {
+            let vertices = [0, 1, 2].map(|i| buffer.vertices[idx[i] as usize]);
+            let positions = vertices.map(|v| (Vec2::new(v.position[0], v.position[1]) - self.origin) / self.pixel_size);
+            let colors = vertices.map(|v| premultiply(v.linear_color()));
+            self.draw_triangle(positions, colors);
+        }
+    }
+
+    fn draw_triangle(&mut self, [a, b, c]: [Vec2; 3], colors: [[f32; 4]; 3]) {
+        let area = (b - a).perp_dot(c - a);
+        if area.abs() <= f32::EPSILON {
+            return;
+        }
+
+        let min = a.min(b).min(c).floor().max(Vec2::ZERO);
+        let max = a.max(b).max(c).ceil().min(Vec2::new(self.width as f32, self.height as f32));
+        for y in min.y as usize..max.y as usize {
+            for x in min.x as usize..max.x as usize {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let v = (p - a).perp_dot(c - a) / area;
+                let w = (b - a).perp_dot(p - a) / area;
+                let u = 1.0 - v - w;
+                if u < 0.0 || v < 0.0 || w < 0.0 {
+                    continue;
+                }
+
+                let pixel = &mut self.pixels[y * self.width + x];
+                let mut src = [0.0; 4];
+                for (channel, value) in src.iter_mut().enumerate() {
+                    *value = u * colors[0][channel] + v * colors[1][channel] + w * colors[2][channel];
+                }
+                *pixel = over(src, *pixel);
+            }
+        }
+    }
+
+    /// Applies a gaussian blur with a standard deviation of `std_dev` pixels.
+    pub(crate) fn blur(&mut self, std_dev: Vec2) {
+        if std_dev.x > 0.0 {
+            self.pixels = blur_1d(&self.pixels, self.width, self.height, std_dev.x, true);
+        }
+        if std_dev.y > 0.0 {
+            self.pixels = blur_1d(&self.pixels, self.width, self.height, std_dev.y, false);
+        }
+    }
+
+    /// Replaces the color of every pixel with the result of `f`, which maps unpremultiplied colors.
+    pub(crate) fn map_colors<F: Fn([f32; 4]) -> [f32; 4]>(&mut self, f: F) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel = premultiply(f(unpremultiply(*pixel))));
+    }
+
+    /// Returns a copy of the raster, with the content moved by `offset` pixels.
+    /// The offset is rounded to whole pixels.
+    pub(crate) fn offset(&self, offset: Vec2) -> Self {
+        let (dx, dy) = (offset.x.round() as isize, offset.y.round() as isize);
+        let mut moved = Self { pixels: vec![[0.0; 4]; self.pixels.len()], ..self.clone() };
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let (src_x, src_y) = (x - dx, y - dy);
+                if src_x < 0 || src_y < 0 || src_x >= self.width as isize || src_y >= self.height as isize {
+                    continue;
+                }
+                moved.pixels[y as usize * self.width + x as usize] = self.pixels[src_y as usize * self.width + src_x as usize];
+            }
+        }
+        moved
+    }
+
+    /// Composites `other`, which must cover the same region with the same size, on top of the raster.
+    pub(crate) fn draw_over(&mut self, other: &Raster) {
+        self.combine(other, over);
+    }
+
+    /// Replaces every pixel with the result of `f`, called with the pixel of `other` as first and
+    /// the pixel of this raster as second argument. Both rasters must cover the same region.
+    pub(crate) fn combine<F: Fn([f32; 4], [f32; 4]) -> [f32; 4]>(&mut self, other: &Raster, f: F) {
+        for (pixel, src) in self.pixels.iter_mut().zip(&other.pixels) {
+            *pixel = f(*src, *pixel);
+        }
+    }
+
+    /// Makes every pixel whose center lies outside of `region` transparent.
+    pub(crate) fn clip(&mut self, region: &Rect) {
+        for (i, pixel) in self.pixels.iter_mut().enumerate() {
+            let center = self.origin + (Vec2::new((i % self.width) as f32, (i / self.width) as f32) + 0.5) * self.pixel_size;
+            if center.x < region.min_x() || center.y < region.min_y() || center.x > region.max_x() || center.y > region.max_y() {
+                *pixel = [0.0; 4];
+            }
+        }
+    }
+
+    /// Returns a raster that is `factor` times smaller along both axes, covering the same region.
+    /// Every pixel is the average of the pixels it covers, which smooths the edges of triangles
+    /// drawn at the higher resolution.
+    pub(crate) fn downsample(&self, factor: usize) -> Self {
+        let (width, height) = (self.width / factor, self.height / factor);
+        let mut raster = Self {
+            width,
+            height,
+            origin: self.origin,
+            pixel_size: self.pixel_size * factor as f32,
+            pixels: vec![[0.0; 4]; width * height],
+        };
+        let weight = 1.0 / (factor * factor) as f32;
+        for (i, pixel) in raster.pixels.iter_mut().enumerate() {
+            let (x, y) = ((i % width) * factor, (i / width) * factor);
+            for src_y in y..y + factor {
+                for src in &self.pixels[src_y * self.width + x..src_y * self.width + x + factor] {
+                    for channel in 0..4 {
+                        pixel[channel] += src[channel] * weight;
+                    }
+                }
+            }
+        }
+        raster
+    }
+
+    /// Converts the raster into an sRGB [`Image`], with the first row at the top.
+    pub(crate) fn to_image(&self) -> Image {
+        let data = self.pixels.iter()
+            .flat_map(|pixel| {
+                let [r, g, b, a] = unpremultiply(*pixel);
+                Color::rgba_linear(r, g, b, a).as_rgba_f32().map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+            })
+            .collect();
+        Image::new(
+            Extent3d { width: self.width as u32, height: self.height as u32, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    /// Turns the raster into geometry, with one vertex at the center of each pixel.
+    /// Cells that are completely transparent are left out.
+    pub(crate) fn to_buffer(&self) -> VertexBuffers {
+        let mut buffer = VertexBuffers::new();
+        if self.width < 2 || self.height < 2 {
+            return buffer;
+        }
+
+        buffer.vertices.reserve(self.pixels.len());
+        for (i, pixel) in self.pixels.iter().enumerate() {
+            let (x, y) = (i % self.width, i / self.width);
+            // Vertices of the outermost pixels are moved onto the border, so the mesh covers the whole raster.
+            let border = |i: usize, len: usize| match i {
+                0 => 0.0,
+                i if i == len - 1 => len as f32,
+                i => i as f32 + 0.5,
+            };
+            let position = self.origin + Vec2::new(border(x, self.width), border(y, self.height)) * self.pixel_size;
+            buffer.vertices.alloc().init(Vertex::new([position.x, position.y, 0.0], unpremultiply(*pixel)));
+        }
+
+        for y in 0..self.height - 1 {
+            for x in 0..self.width - 1 {
+                let top_left = (y * self.width + x) as u32;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + self.width as u32;
+                let bottom_right = bottom_left + 1;
+                let corners = [top_left, top_right, bottom_left, bottom_right];
+                if corners.iter().all(|&i| self.pixels[i as usize][3] <= 0.0) {
+                    continue;
+                }
+                buffer.indices.extend_from_slice(&[top_left, top_right, bottom_right, top_left, bottom_right, bottom_left]);
+            }
+        }
+
+        buffer
+    }
+}
+
+/// Blurs the rows (`horizontal`) or columns of the pixels with a gaussian kernel.
+fn blur_1d(pixels: &[[f32; 4]], width: usize, height: usize, std_dev: f32, horizontal: bool) -> Vec<[f32; 4]> {
+    let radius = (std_dev * 3.0).ceil() as isize;
+    let mut kernel: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * std_dev * std_dev)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|weight| *weight /= sum);
+
+    let (len, lines) = if horizontal { (width, height) } else { (height, width) };
+    let index = |line: usize, i: usize| if horizontal { line * width + i } else { i * width + line };
+
+    let mut blurred = vec![[0.0; 4]; pixels.len()];
+    for line in 0..lines {
+        for i in 0..len {
+            let mut color = [0.0; 4];
+            for (k, weight) in kernel.iter().enumerate() {
+                let j = i as isize + k as isize - radius;
+                // Everything outside of the raster is transparent.
+                if j < 0 || j >= len as isize {
+                    continue;
+                }
+                let pixel = pixels[index(line, j as usize)];
+                for channel in 0..4 {
+                    color[channel] += pixel[channel] * weight;
+                }
+            }
+            blurred[index(line, i)] = color;
+        }
+    }
+
+    blurred
+}
+
+/// Composites the premultiplied color `src` over `dst`.
+fn over(src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let mut result = [0.0; 4];
+    for channel in 0..4 {
+        result[channel] = src[channel] + dst[channel] * (1.0 - src[3]);
+    }
+    result
+}
+
+fn premultiply([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    [r * a, g * a, b * a, a]
+}
+
+fn unpremultiply([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    if a <= 0.0 {
+        [0.0; 4]
+    } else {
+        [r / a, g / a, b / a, a]
+    }
+}
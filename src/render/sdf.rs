@@ -0,0 +1,225 @@
+//! Signed distance fields of the shapes of an SVG, which the 2D pipeline draws instead of the
+//! tessellated mesh for [`SvgSdf`](crate::prelude::SvgSdf).
+
+use std::collections::HashMap;
+
+use bevy::{
+    ecs::{
+        entity::Entity,
+        system::{lifetimeless::SRes, SystemParamItem},
+        world::{FromWorld, World},
+    },
+    render::{
+        render_phase::{EntityRenderCommand, RenderCommandResult, TrackedRenderPass},
+        render_resource::{
+            BindGroup, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Extent3d,
+            FilterMode, SamplerBindingType, SamplerDescriptor, ShaderStages, TextureDimension, TextureFormat,
+            TextureSampleType, TextureViewDimension,
+        },
+        renderer::RenderDevice,
+        texture::Image,
+    },
+};
+use copyless::VecHelper;
+use lyon_svg::path::iterator::PathIterator;
+use lyon_tessellation::{math::Point, path::PathEvent, FillRule};
+
+use crate::{
+    atlas::pack,
+    render::vertex_buffer::{Vertex, VertexBuffers},
+    svg::{distance_to_line, document_transform, paint_color, winding_number, DrawType, PathDescriptor, Svg},
+};
+
+
+/// Transparent space in texels around the distance field of every shape in the atlas.
+const PADDING: u32 = 1;
+/// Upper limit for the width and height of the distance field of a single shape in texels.
+const MAX_FIELD_SIZE: f32 = 2048.0;
+
+/// A shape whose distance field is baked into the atlas.
+struct Field {
+    /// Top left corner of the region that the field covers, in the coordinate system of the SVG.
+    min: Point,
+    /// Size of the region that the field covers.
+    size: Point,
+    width: u32,
+    height: u32,
+    color: [f32; 4],
+    texels: Vec<u8>,
+}
+
+/// Bakes the distance fields of all visible shapes of `svg` into one atlas, with `resolution`
+/// texels per unit of the SVG, reaching `spread` units beyond the edges of the shapes.
+///
+/// Returns a quad for every shape, in the order the shapes are drawn, with texture coordinates
+/// into the atlas. The distance is stored in the red channel of the atlas, `0.5` at the edge of a
+/// shape and more inside of it.
+pub(crate) fn bake(svg: &Svg, resolution: f32, spread: f32) -> (VertexBuffers, Image) {
+    let resolution = resolution.max(f32::EPSILON);
+    let spread = spread.max(1.0 / resolution);
+    let fields: Vec<Field> = svg.paths.iter()
+        .filter(|path| !svg.is_hidden(path))
+        .filter_map(|path| bake_field(path, resolution, spread))
+        .collect();
+
+    let sizes: Vec<_> = fields.iter().map(|field| (field.width, field.height)).collect();
+    let (positions, atlas_width, atlas_height) = pack(&sizes, PADDING);
+    let mut data = vec![0; atlas_width as usize * atlas_height as usize];
+    let mut buffer = VertexBuffers::new();
+    for (field, &(x, y)) in fields.iter().zip(&positions) {
+        for (row, texels) in field.texels.chunks_exact(field.width as usize).enumerate() {
+            let start = (y as usize + row) * atlas_width as usize + x as usize;
+            data[start..start + texels.len()].copy_from_slice(texels);
+        }
+
+        let offset = buffer.vertices.len() as u32;
+        for (corner_x, corner_y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            // The mesh has the y-axis of Bevy.
+            let position = [field.min.x + field.size.x * corner_x, -(field.min.y + field.size.y * corner_y), 0.0];
+            let uv = [
+                (x as f32 + field.width as f32 * corner_x) / atlas_width as f32,
+                (y as f32 + field.height as f32 * corner_y) / atlas_height as f32,
+            ];
+            buffer.vertices.alloc().init(Vertex { uv, ..Vertex::new(position, field.color) });
+        }
+        buffer.indices.extend([0, 1, 2, 0, 2, 3].map(|index| index + offset));
+    }
+
+    let mut atlas = Image::new(
+        Extent3d { width: atlas_width, height: atlas_height, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::R8Unorm,
+    );
+    // The distance between the texels is interpolated, which is what keeps the edges smooth.
+    atlas.sampler_descriptor = SamplerDescriptor {
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..Default::default()
+    };
+    (buffer, atlas)
+}
+
+/// Bakes the distance field of `path`, `None` if it has no outline.
+fn bake_field(path: &PathDescriptor, resolution: f32, spread: f32) -> Option<Field> {
+    let to_document = document_transform(path);
+    // The lines of the flattened path in the coordinate system of the SVG, and whether they are
+    // drawn by a stroke. Subpaths that aren't closed are still closed for a fill.
+    let lines: Vec<(Point, Point, bool)> = path.segments.iter()
+        .map(|event| event.transformed(&to_document))
+        .flattened(0.25 / resolution)
+        .filter_map(|event| match event {
+            PathEvent::Line { from, to } => Some((from, to, true)),
+            PathEvent::End { last, first, close } => Some((last, first, close)),
+            _ => None,
+        })
+        .collect();
+    let half_width = match path.draw_type {
+        DrawType::Fill(_) => 0.0,
+        DrawType::Stroke(options) => options.line_width / 2.0 * to_document.determinant().abs().sqrt(),
+    };
+
+    let (first, _, _) = lines.first()?;
+    let (mut min, mut max) = (*first, *first);
+    for (from, to, _) in &lines {
+        min = min.min(from.min(*to));
+        max = max.max(from.max(*to));
+    }
+    let reach = spread + half_width;
+    let min = min - lyon_tessellation::math::vector(reach, reach);
+    let max = max + lyon_tessellation::math::vector(reach, reach);
+    let width = ((max.x - min.x) * resolution).ceil().clamp(1.0, MAX_FIELD_SIZE);
+    let height = ((max.y - min.y) * resolution).ceil().clamp(1.0, MAX_FIELD_SIZE);
+    let size = Point::new(width, height) / resolution;
+
+    let mut texels = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let point = min + lyon_tessellation::math::vector(x as f32 + 0.5, y as f32 + 0.5) / resolution;
+            let distance = signed_distance(path, &lines, half_width, point);
+            texels.alloc().init(((0.5 - distance / (2.0 * spread)).clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+
+    let mut color = paint_color(path).as_linear_rgba_f32();
+    color[3] *= path.groups.iter().map(|group| group.opacity).product::<f32>();
+    Some(Field { min, size, width: width as u32, height: height as u32, color, texels })
+}
+
+/// Returns the distance of `point` to the edge of the shape of `path`, negative inside of it.
+fn signed_distance(path: &PathDescriptor, lines: &[(Point, Point, bool)], half_width: f32, point: Point) -> f32 {
+    match path.draw_type {
+        DrawType::Fill(options) => {
+            let distance = lines.iter()
+                .map(|&(from, to, _)| distance_to_line(from, to, point))
+                .fold(f32::INFINITY, f32::min);
+            let winding: i32 = lines.iter().map(|&(from, to, _)| winding_number(from, to, point)).sum();
+            let inside = match options.fill_rule {
+                FillRule::EvenOdd => winding % 2 != 0,
+                FillRule::NonZero => winding != 0,
+            };
+            if inside { -distance } else { distance }
+        },
+        DrawType::Stroke(_) => lines.iter()
+            .filter(|(_, _, stroked)| *stroked)
+            .map(|&(from, to, _)| distance_to_line(from, to, point))
+            .fold(f32::INFINITY, f32::min) - half_width,
+    }
+}
+
+/// Layout of the bind group of the distance field atlas of an SVG.
+pub struct SvgSdfLayout(pub BindGroupLayout);
+
+impl FromWorld for SvgSdfLayout {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+        Self(render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("svg_sdf_layout"),
+        }))
+    }
+}
+
+/// The bind groups of the distance field atlases of the entities that are drawn with
+/// [`SvgSdf`](crate::prelude::SvgSdf), created every frame.
+#[derive(Default)]
+pub struct SvgSdfBindGroups {
+    pub(crate) bind_groups: HashMap<Entity, BindGroup>,
+}
+
+/// Set the bind group of the distance field atlas of an SVG entity at index `I`. Entities without
+/// one are drawn with a pipeline that doesn't have this bind group.
+pub struct SetSvgSdfBindGroup<const I: usize>;
+
+impl<const I: usize> EntityRenderCommand for SetSvgSdfBindGroup<I> {
+    type Param = SRes<SvgSdfBindGroups>;
+
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        bind_groups: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        if let Some(bind_group) = bind_groups.into_inner().bind_groups.get(&item) {
+            pass.set_bind_group(I, bind_group, &[]);
+        }
+        RenderCommandResult::Success
+    }
+}
@@ -6,10 +6,38 @@ var<uniform> view: View;
 [[group(1), binding(0)]]
 var<uniform> mesh: Mesh2d;
 
+struct SvgTint {
+    color: vec4<f32>;
+    // The color matrix, applied to the color before the tint
+    matrix: mat4x4<f32>;
+    offset: vec4<f32>;
+};
+[[group(2), binding(0)]]
+var<uniform> tint: SvgTint;
+
+#ifdef SDF
+[[group(3), binding(0)]]
+var sdf_atlas: texture_2d<f32>;
+[[group(3), binding(1)]]
+var sdf_sampler: sampler;
+#endif
+
 // The structure of the SVG vertex buffer as specified in our `SpecializedPipeline`.
 struct Vertex {
     [[location(0)]] position: vec3<f32>;
     [[location(1)]] color: u32;
+#ifdef SDF
+    // Location 2 is the normal, which isn't used
+    [[location(3)]] uv: vec2<f32>;
+#endif
+#ifdef INSTANCED
+    // The model matrix and tint of the instance, which replace the mesh and tint uniforms
+    [[location(5)]] model_0: vec4<f32>;
+    [[location(6)]] model_1: vec4<f32>;
+    [[location(7)]] model_2: vec4<f32>;
+    [[location(8)]] model_3: vec4<f32>;
+    [[location(9)]] tint: vec4<f32>;
+#endif
 };
 
 struct VertexOutput {
@@ -17,16 +45,33 @@ struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     // Vertex color passed to the framgent shader in location(0).
     [[location(0)]] color: vec4<f32>;
+#ifdef SDF
+    [[location(1)]] uv: vec2<f32>;
+#endif
 };
 
 /// Entry point for the vertex shader.
 [[stage(vertex)]]
 fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
-    // Project the world position into screen position
-    out.clip_position = view.view_proj * mesh.model * vec4<f32>(vertex.position, 1.0);
     // Unpack the `u32` from the vertex buffer into the `vec4<f32>` used by the fragment shader
     out.color = vec4<f32>((vec4<u32>(vertex.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
+#ifdef INSTANCED
+    let model = mat4x4<f32>(vertex.model_0, vertex.model_1, vertex.model_2, vertex.model_3);
+    out.clip_position = view.view_proj * model * vec4<f32>(vertex.position, 1.0);
+    out.color = out.color * vertex.tint;
+#else
+#ifdef BATCHED
+    // The merged meshes are already in world space, with the tint in the color
+    out.clip_position = view.view_proj * vec4<f32>(vertex.position, 1.0);
+#else
+    // Project the world position into screen position
+    out.clip_position = view.view_proj * mesh.model * vec4<f32>(vertex.position, 1.0);
+#endif
+#endif
+#ifdef SDF
+    out.uv = vertex.uv;
+#endif
     return out;
 }
 
@@ -34,10 +79,27 @@ fn vertex(vertex: Vertex) -> VertexOutput {
 struct FragmentInput {
     // The color is interpolated between vertices by default
     [[location(0)]] color: vec4<f32>;
+#ifdef SDF
+    [[location(1)]] uv: vec2<f32>;
+#endif
 };
 
 /// Entry point for the fragment shader.
 [[stage(fragment)]]
 fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
-    return in.color;
+    var color = in.color;
+#ifdef SDF
+    // The edge of the shape is at 0.5, it is smoothed over about one pixel on the screen
+    let distance = textureSample(sdf_atlas, sdf_sampler, in.uv).r;
+    let width = max(fwidth(distance) * 0.5, 0.0001);
+    color.a = color.a * smoothstep(0.5 - width, 0.5 + width, distance);
+#endif
+#ifdef VERTEX_TINT
+    // The tint of the instance or batched mesh is already in the color
+    return color;
+#else
+    color = clamp(tint.matrix * color + tint.offset, vec4<f32>(0.0), vec4<f32>(1.0));
+    // The tint is multiplied into the color, so white keeps the colors of the SVG
+    return color * tint.color;
+#endif
 }
@@ -6,10 +6,20 @@ var<uniform> view: View;
 [[group(1), binding(0)]]
 var<uniform> mesh: Mesh2d;
 
+struct SvgTint {
+    color: vec4<f32>;
+    // The color matrix, applied to the color before the tint
+    matrix: mat4x4<f32>;
+    offset: vec4<f32>;
+};
+[[group(2), binding(0)]]
+var<uniform> tint: SvgTint;
+
 // The structure of the SVG vertex buffer as specified in our `SpecializedPipeline`.
 struct Vertex {
     [[location(0)]] position: vec3<f32>;
     [[location(1)]] color: u32;
+    [[location(2)]] normal: vec3<f32>;
 };
 
 struct VertexOutput {
@@ -17,6 +27,10 @@ struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     // Vertex color passed to the framgent shader in location(0).
     [[location(0)]] color: vec4<f32>;
+#ifdef ONE_SIDED
+    [[location(1)]] world_position: vec3<f32>;
+    [[location(2)]] world_normal: vec3<f32>;
+#endif
 };
 
 /// Entry point for the vertex shader.
@@ -24,7 +38,16 @@ struct VertexOutput {
 fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
     // Project the world position into screen position
-    out.clip_position = view.view_proj * mesh.model * vec4<f32>(vertex.position, 1.0);
+    let world_position = mesh.model * vec4<f32>(vertex.position, 1.0);
+    out.clip_position = view.view_proj * world_position;
+#ifdef ONE_SIDED
+    out.world_position = world_position.xyz;
+    out.world_normal = mat3x3<f32>(
+        mesh.inverse_transpose_model[0].xyz,
+        mesh.inverse_transpose_model[1].xyz,
+        mesh.inverse_transpose_model[2].xyz
+    ) * vertex.normal;
+#endif
     // Unpack the `u32` from the vertex buffer into the `vec4<f32>` used by the fragment shader
     out.color = vec4<f32>((vec4<u32>(vertex.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
     return out;
@@ -34,10 +57,22 @@ fn vertex(vertex: Vertex) -> VertexOutput {
 struct FragmentInput {
     // The color is interpolated between vertices by default
     [[location(0)]] color: vec4<f32>;
+#ifdef ONE_SIDED
+    [[location(1)]] world_position: vec3<f32>;
+    [[location(2)]] world_normal: vec3<f32>;
+#endif
 };
 
 /// Entry point for the fragment shader.
 [[stage(fragment)]]
 fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
-    return in.color;
+#ifdef ONE_SIDED
+    // The back of the mesh faces away from the camera
+    if (dot(in.world_normal, view.world_position - in.world_position) < 0.0) {
+        discard;
+    }
+#endif
+    let color = clamp(tint.matrix * in.color + tint.offset, vec4<f32>(0.0), vec4<f32>(1.0));
+    // The tint is multiplied into the color, so white keeps the colors of the SVG
+    return color * tint.color;
 }
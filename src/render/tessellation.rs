@@ -1,59 +1,965 @@
+use std::{borrow::Cow, collections::{hash_map::DefaultHasher, HashMap, HashSet}, hash::{Hash, Hasher}, ops::Range, sync::Arc};
+
 use bevy::{
-    log::{error, debug},
-    math::Vec3,
+    log::{error, debug, warn},
+    math::{Mat4, Vec2, Vec3},
+    render::color::Color,
     transform::components::Transform,
+    utils::Instant,
 };
-use lyon_tessellation::{FillTessellator, StrokeTessellator, FillOptions, BuffersBuilder};
+use lyon_geom::euclid::default::Transform2D;
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{geometry_builder::Positions, math::{rect, Point, Rect}, FillTessellator, StrokeTessellator, BuffersBuilder};
 
 use crate::{
-    render::vertex_buffer::{VertexBuffers, VertexConstructor, BufferExt},
-    svg::{DrawType, Svg},
+    filter::BlendMode,
+    overrides::Overrides,
+    paint::{LinearGradient, Paint, Pattern, RadialGradient, SpreadMethod},
+    render::{clip, dash, extrusion, filter, masking::ElementRanges, nine_slice, raster::Raster, tiling, vertex_buffer::{self, IndexType, Vertex, VertexBuffers, VertexConstructor, BufferExt}},
+    loader::SvgLoadError,
+    svg::{ClipPath, DrawType, Group, Mask, ModifiedPaths, PathDescriptor, Svg},
 };
 
+/// Upper limit of pattern tiles that get generated for a single path.
+const MAX_PATTERN_TILES: usize = 4096;
+/// Number of strips between two stops of a linear gradient. The stops are interpolated in sRGB,
+/// the vertex colors in linear RGB, which only match at the ends of a strip.
+const GRADIENT_STRIPS_PER_STOP: usize = 8;
+/// Number of cells of the grid that samples a radial gradient along its radius.
+const RADIAL_GRADIENT_CELLS: f32 = 16.0;
+/// Upper limit for the number of cells of the grid that samples a radial gradient along each axis.
+const MAX_RADIAL_GRADIENT_CELLS: usize = 128;
+/// Number of paths between two reports of the progress while loading.
+const PROGRESS_INTERVAL: usize = 256;
+
 
 pub(crate) fn generate_buffer(
     svg: &Svg,
+    overrides: &Overrides,
     fill_tess: &mut FillTessellator,
     stroke_tess: &mut StrokeTessellator,
 ) -> VertexBuffers {
+    tessellate_svg(svg, overrides, fill_tess, stroke_tess, None).0
+}
+
+/// Like [`generate_buffer`], but fails if one of the paths can't be tessellated. `progress` is
+/// called with the number of tessellated paths every now and then.
+pub(crate) fn try_generate_buffer(
+    svg: &Svg,
+    overrides: &Overrides,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    progress: Option<&dyn Fn(usize)>,
+) -> Result<VertexBuffers, SvgLoadError> {
+    match tessellate_svg(svg, overrides, fill_tess, stroke_tess, progress) {
+        (_, Some(failure)) => Err(failure),
+        (buffers, None) => Ok(buffers),
+    }
+}
+
+/// Tessellates `svg` and returns the first failure, paths that fail are left out.
+fn tessellate_svg(
+    svg: &Svg,
+    overrides: &Overrides,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    progress: Option<&dyn Fn(usize)>,
+) -> (VertexBuffers, Option<SvgLoadError>) {
     debug!("Tessellating SVG: {}", svg.name);
 
+    // Bevy has a different y-axis origin, so we need to flip that axis
     let flip_y = Transform::from_scale(Vec3::new(1.0, -1.0, 1.0));
+    let mut tessellator = Tessellator {
+        fill_tess,
+        stroke_tess,
+        overrides,
+        hidden: &svg.hidden,
+        tolerance: svg.tolerance,
+        regions: HashMap::new(),
+        geometries: HashMap::new(),
+        failure: None,
+        progress,
+        tessellated: 0,
+        ranges: None,
+        unmaskable: HashSet::new(),
+    };
+    let mut buffers = tessellator.tessellate_paths(&svg.paths, flip_y);
+    if let Some(ref slice) = overrides.nine_slice {
+        buffers = nine_slice::apply(&buffers, slice, svg.size);
+    }
+    if let Some(ref tiles) = overrides.tiling {
+        buffers = tiling::apply(&buffers, tiles, svg.size);
+    }
+    if let Some(depth) = overrides.extrusion {
+        buffers = extrusion::apply(&buffers, depth);
+    }
+    vertex_buffer::map_uvs(&mut buffers, svg.size);
+    debug!("Tessellating SVG: {} ... Done", svg.name);
+
+    (buffers, tessellator.failure)
+}
+
+/// Like [`generate_buffer`], but all elements of `overrides.visibility` are drawn, and where the
+/// triangles of each element end up is recorded, so they can be hidden with
+/// [`ElementRanges::mask`]. Nine-slice scaling, tiling and extrusion are left out, they move the
+/// triangles.
+pub(crate) fn generate_maskable_buffer(
+    svg: &Svg,
+    overrides: &Overrides,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+) -> (VertexBuffers, ElementRanges) {
+    generate_maskable_paths(svg, 0..svg.paths.len(), overrides, fill_tess, stroke_tess)
+}
+
+/// Like [`generate_maskable_buffer`], but only the `paths` of `svg` are tessellated, with the
+/// effects of their groups, to replace the triangles of an element in a buffer of the whole SVG.
+/// The ranges are relative to the returned buffer, groups around the paths get all of it.
+pub(crate) fn generate_maskable_paths(
+    svg: &Svg,
+    paths: Range<usize>,
+    overrides: &Overrides,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+) -> (VertexBuffers, ElementRanges) {
+    let shown: HashSet<String> = overrides.visibility.keys().cloned().collect();
+    let overrides = Overrides {
+        visibility: shown.iter().map(|id| (id.clone(), true)).collect(),
+        nine_slice: None,
+        tiling: None,
+        extrusion: None,
+        ..overrides.clone()
+    };
+    // Bevy has a different y-axis origin, so we need to flip that axis
+    let flip_y = Transform::from_scale(Vec3::new(1.0, -1.0, 1.0));
+    let mut tessellator = Tessellator {
+        fill_tess,
+        stroke_tess,
+        overrides: &overrides,
+        hidden: &svg.hidden,
+        tolerance: svg.tolerance,
+        regions: HashMap::new(),
+        geometries: HashMap::new(),
+        failure: None,
+        progress: None,
+        tessellated: 0,
+        ranges: Some(Vec::new()),
+        unmaskable: HashSet::new(),
+    };
+    let mut buffers = tessellator.tessellate_paths(&svg.paths[paths], flip_y);
+    vertex_buffer::map_uvs(&mut buffers, svg.size);
+
+    let mut ranges: HashMap<String, Vec<Range<usize>>> = HashMap::new();
+    for (id, range) in tessellator.ranges.take().unwrap_or_default() {
+        ranges.entry(id).or_default().push(range);
+    }
+    (buffers, ElementRanges { ranges, unmaskable: tessellator.unmaskable, shown })
+}
+
+/// Tessellates only the path at `index` of `svg`, with the effects of its groups, like
+/// [`generate_buffer`] does. `to_local` is applied to the vertices afterwards.
+pub(crate) fn generate_path_buffer(
+    svg: &Svg,
+    index: usize,
+    to_local: Mat4,
+    overrides: &Overrides,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+) -> VertexBuffers {
+    // Bevy has a different y-axis origin, so we need to flip that axis
+    let flip_y = Transform::from_scale(Vec3::new(1.0, -1.0, 1.0));
+    let mut tessellator = Tessellator {
+        fill_tess,
+        stroke_tess,
+        overrides,
+        hidden: &svg.hidden,
+        tolerance: svg.tolerance,
+        regions: HashMap::new(),
+        geometries: HashMap::new(),
+        failure: None,
+        progress: None,
+        tessellated: 0,
+        ranges: None,
+        unmaskable: HashSet::new(),
+    };
+    let mut buffers = tessellator.tessellate_paths(&svg.paths[index..=index], flip_y);
+    // Like the whole SVG, so a texture lines up across all of its paths.
+    vertex_buffer::map_uvs(&mut buffers, svg.size);
+    if to_local != Mat4::IDENTITY {
+        for vertex in &mut buffers.vertices {
+            vertex.position = to_local.transform_point3(Vec3::from(vertex.position)).into();
+        }
+    }
+    buffers
+}
+
+/// The tessellated runs of paths at the top level of an [`Svg`], single paths or all paths of a
+/// group, which [`retessellate`] keeps if they didn't change. Keyed by the index of their first
+/// path, with the index after their last one.
+#[derive(Default)]
+pub(crate) struct TessellatedRuns {
+    runs: HashMap<usize, (usize, VertexBuffers)>,
+}
+
+/// Like [`generate_buffer`] without overrides, but only the runs of paths that contain one of the
+/// `modified` paths, or whose extent changed, are tessellated again. The others are taken from
+/// `runs`, which is updated with the new ones.
+pub(crate) fn retessellate(
+    svg: &Svg,
+    modified: &ModifiedPaths,
+    runs: &mut TessellatedRuns,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+) -> VertexBuffers {
+    // Groups that are blended with what is drawn below them can't be tessellated on their own.
+    if svg.paths.iter().any(|path| path.groups.first().map_or(false, |group| group.blend_mode != BlendMode::Normal)) {
+        runs.runs.clear();
+        return generate_buffer(svg, &Overrides::default(), fill_tess, stroke_tess);
+    }
+
+    let overrides = Overrides::default();
+    let mut tessellator = Tessellator {
+        fill_tess,
+        stroke_tess,
+        overrides: &overrides,
+        hidden: &svg.hidden,
+        tolerance: svg.tolerance,
+        regions: HashMap::new(),
+        geometries: HashMap::new(),
+        failure: None,
+        progress: None,
+        tessellated: 0,
+        ranges: None,
+        unmaskable: HashSet::new(),
+    };
+    let mut previous = std::mem::take(&mut runs.runs);
     let mut buffers = VertexBuffers::new();
+    let mut start = 0;
+    while start < svg.paths.len() {
+        let (end, run) = match previous.remove(&start) {
+            Some((end, run)) if end == run_end(&svg.paths, start) && !modified.intersects(start..end) => (end, run),
+            _ => {
+                let mut run = VertexBuffers::new();
+                let end = tessellator.tessellate_run(&svg.paths, start, 0, &mut run);
+                (end, run)
+            },
+        };
+        buffers.extend_one(run.clone());
+        runs.runs.insert(start, (end, run));
+        start = end;
+    }
+    finish_buffer(buffers, svg.size)
+}
 
-    let mut color = None;
-    for path in &svg.paths {
-        let mut buffer = VertexBuffers::new();
+/// Returns the index after the last path of the run at the top level that starts at `start`.
+fn run_end(paths: &[PathDescriptor], start: usize) -> usize {
+    match paths[start].groups.first() {
+        Some(group) => start + paths[start..].iter()
+            .take_while(|path| path.groups.first().map_or(false, |other| Arc::ptr_eq(group, other)))
+            .count(),
+        None => start + 1,
+    }
+}
+
+/// Tessellates the paths of `svg` from the index `start` on and adds them to `buffers`, until all
+/// paths are done or the `deadline` passed. Returns the index of the first path that is left.
+///
+/// Unlike [`generate_buffer`], the y-axis of `buffers` isn't flipped, that happens in
+/// [`finish_buffer`].
+pub(crate) fn tessellate_until(
+    svg: &Svg,
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    start: usize,
+    buffers: &mut VertexBuffers,
+    deadline: Instant,
+) -> usize {
+    let overrides = Overrides::default();
+    let mut tessellator = Tessellator {
+        fill_tess,
+        stroke_tess,
+        overrides: &overrides,
+        hidden: &svg.hidden,
+        tolerance: svg.tolerance,
+        regions: HashMap::new(),
+        geometries: HashMap::new(),
+        failure: None,
+        progress: None,
+        tessellated: 0,
+        ranges: None,
+        unmaskable: HashSet::new(),
+    };
+    let mut next = start;
+    while next < svg.paths.len() && Instant::now() < deadline {
+        next = tessellator.tessellate_run(&svg.paths, next, 0, buffers);
+    }
+    next
+}
 
-        if color.is_none() {
-            color = Some(path.color);
+/// Flips the y-axis of `buffers` that were tessellated with [`tessellate_until`], and sets their
+/// texture coordinates for an SVG of `size`.
+pub(crate) fn finish_buffer(mut buffers: VertexBuffers, size: Vec2) -> VertexBuffers {
+    // Bevy has a different y-axis origin, so we need to flip that axis
+    transform_buffer(&mut buffers, &Transform::from_scale(Vec3::new(1.0, -1.0, 1.0)));
+    vertex_buffer::map_uvs(&mut buffers, size);
+    buffers
+}
+
+/// Bundles the lyon tessellators with everything else that is needed while tessellating.
+struct Tessellator<'a> {
+    fill_tess: &'a mut FillTessellator,
+    stroke_tess: &'a mut StrokeTessellator,
+    /// Runtime changes of the entity that the buffer is generated for.
+    overrides: &'a Overrides,
+    /// IDs of the elements that are hidden in the file.
+    hidden: &'a HashSet<String>,
+    /// Tolerance that replaces the one of the fill and stroke options of all paths.
+    tolerance: Option<f32>,
+    /// Already tessellated clip and mask regions, keyed by the address of their [`ClipPath`]
+    /// or [`Mask`].
+    regions: HashMap<usize, VertexBuffers>,
+    /// Already tessellated geometry of paths, keyed by [`geometry_hash`]. Instances of the same
+    /// shape, like those created with `<use>`, only get tessellated once.
+    geometries: HashMap<u64, Vec<Geometry>>,
+    /// The first path that couldn't be tessellated.
+    failure: Option<SvgLoadError>,
+    /// Reports the number of tessellated paths while a file is loading.
+    progress: Option<&'a dyn Fn(usize)>,
+    /// Number of paths that are tessellated or skipped so far.
+    tessellated: usize,
+    /// Index ranges of the elements in the buffer of the current group, if they are recorded.
+    ranges: Option<Vec<(String, Range<usize>)>>,
+    /// Elements whose index ranges got lost, see [`ElementRanges::unmaskable`].
+    unmaskable: HashSet<String>,
+}
+
+/// The tessellated geometry of a path, without its paint and transform.
+struct Geometry {
+    segments: Vec<PathEvent>,
+    draw_type: DrawType,
+    buffer: lyon_tessellation::VertexBuffers<Point, IndexType>,
+}
+
+impl Tessellator<'_> {
+    /// Tessellates all `paths` into a single buffer, `base_transform` is applied after the
+    /// transform of each path.
+    fn tessellate_paths(&mut self, paths: &[PathDescriptor], base_transform: Transform) -> VertexBuffers {
+        let mut buffers = self.tessellate_level(paths, 0);
+        transform_buffer(&mut buffers, &base_transform);
+        buffers
+    }
+
+    /// Tessellates the paths of a clip path, mask or pattern like [`Self::tessellate_paths`],
+    /// without counting them towards the progress.
+    fn tessellate_region(&mut self, paths: &[PathDescriptor], base_transform: Transform) -> VertexBuffers {
+        let (progress, tessellated) = (self.progress.take(), self.tessellated);
+        // Elements of regions aren't drawn themselves, so they can't be hidden by their ranges.
+        let ranges = self.ranges.as_mut().map(std::mem::take);
+        let buffers = self.tessellate_paths(paths, base_transform);
+        if let Some(region_ranges) = std::mem::replace(&mut self.ranges, ranges) {
+            self.unmaskable.extend(region_ranges.into_iter().map(|(id, _)| id));
         }
+        self.progress = progress;
+        self.tessellated = tessellated;
+        buffers
+    }
+
+    /// Tessellates `paths`, which all share the same ancestor groups up to `depth`.
+    /// Paths that are in the same group at `depth` are tessellated together, so the effects
+    /// of the group can be applied to them as a whole.
+    fn tessellate_level(&mut self, paths: &[PathDescriptor], depth: usize) -> VertexBuffers {
+        let mut buffers = VertexBuffers::new();
 
-        // Bevy has a different y-axis origin, so we need to flip that axis
-        let transform = flip_y * path.abs_transform;
-        match path.draw_type {
-            DrawType::Fill => {
-                if let Err(e) = fill_tess.tessellate(
-                    path.segments.clone(),
-                    &FillOptions::tolerance(0.001),
-                    &mut BuffersBuilder::new(&mut buffer, VertexConstructor { color: path.color, transform })
-                ) {
-                    error!("FillTessellator error: {:?}", e)
+        let mut start = 0;
+        while start < paths.len() {
+            start = self.tessellate_run(paths, start, depth, &mut buffers);
+        }
+
+        buffers
+    }
+
+    /// Tessellates the path at `start` into `buffers`, together with the following paths that
+    /// are in the same group at `depth`. Returns the index of the next path.
+    fn tessellate_run(&mut self, paths: &[PathDescriptor], start: usize, depth: usize, buffers: &mut VertexBuffers) -> usize {
+        match paths[start].groups.get(depth) {
+            Some(group) => {
+                let end = start + paths[start..].iter()
+                    .take_while(|path| path.groups.get(depth).map_or(false, |other| Arc::ptr_eq(group, other)))
+                    .count();
+                if self.is_hidden(&group.id) {
+                    self.advance(end - start);
+                    return end;
                 }
+                let outer_ranges = self.ranges.as_mut().map(std::mem::take);
+                let buffer = self.tessellate_level(&paths[start..end], depth + 1);
+                let inner_ranges = std::mem::replace(&mut self.ranges, outer_ranges);
+                let keeps_triangles = inner_ranges.is_some() && self.keeps_triangles(group, &buffer);
+                let buffer = self.apply_group(group, buffer);
+                if group.blend_mode != BlendMode::Normal {
+                    // Everything that was drawn before in the same parent is the backdrop.
+                    *buffers = apply_blend_mode(buffers, &buffer, group.blend_mode);
+                    if let Some(ranges) = self.ranges.as_mut() {
+                        let lost = ranges.drain(..).chain(inner_ranges.into_iter().flatten());
+                        self.unmaskable.extend(lost.map(|(id, _)| id).chain(std::iter::once(group.id.clone())));
+                    }
+                } else {
+                    let offset = buffers.indices.len();
+                    if let Some(inner_ranges) = inner_ranges {
+                        if keeps_triangles {
+                            self.record_ranges(inner_ranges.into_iter().map(|(id, range)| (id, range.start + offset..range.end + offset)));
+                        } else {
+                            self.unmaskable.extend(inner_ranges.into_iter().map(|(id, _)| id));
+                        }
+                    }
+                    self.record_ranges(std::iter::once((group.id.clone(), offset..offset + buffer.indices.len())));
+                    buffers.extend_one(buffer);
+                }
+                end
             },
-            DrawType::Stroke(opts) => {
-                if let Err(e) = stroke_tess.tessellate(
-                    path.segments.clone(),
-                    &opts,
-                    &mut BuffersBuilder::new(&mut buffer, VertexConstructor { color: path.color, transform })
-                ) {
-                    error!("StrokeTessellator error: {:?}", e)
+            None => {
+                if !self.is_hidden(&paths[start].id) {
+                    let buffer = self.tessellate_single(&paths[start]);
+                    let offset = buffers.indices.len();
+                    self.record_ranges(std::iter::once((paths[start].id.clone(), offset..offset + buffer.indices.len())));
+                    buffers.extend_one(buffer);
                 }
+                self.advance(1);
+                start + 1
+            },
+        }
+    }
+
+    /// Records the index ranges of elements in the buffer of the current group, if they are
+    /// recorded. Elements without an ID are left out.
+    fn record_ranges(&mut self, elements: impl Iterator<Item = (String, Range<usize>)>) {
+        if let Some(ranges) = self.ranges.as_mut() {
+            ranges.extend(elements.filter(|(id, _)| !id.is_empty()));
+        }
+    }
+
+    /// Returns true if [`Self::apply_group`] keeps the triangles of `buffer` as they are, so the
+    /// index ranges of the elements in the group stay valid.
+    fn keeps_triangles(&self, group: &Group, buffer: &VertexBuffers) -> bool {
+        let opacity = self.overrides.opacities.get(&group.id).copied().unwrap_or(group.opacity);
+        // Overlapping triangles with an opacity are rasterized.
+        group.filters.is_empty() && group.clip_path.is_none() && group.mask.is_none()
+            && (opacity >= 1.0 || !clip::overlaps(buffer))
+    }
+
+    /// Counts `paths` more paths as done and reports the progress after every
+    /// [`PROGRESS_INTERVAL`] paths.
+    fn advance(&mut self, paths: usize) {
+        let before = self.tessellated / PROGRESS_INTERVAL;
+        self.tessellated += paths;
+        if let Some(progress) = self.progress.filter(|_| self.tessellated / PROGRESS_INTERVAL > before) {
+            progress(self.tessellated);
+        }
+    }
+
+    /// Returns true if the element with the given `id` is not drawn.
+    fn is_hidden(&self, id: &str) -> bool {
+        !id.is_empty() && self.overrides.visibility.get(id).map_or_else(|| self.hidden.contains(id), |visible| !visible)
+    }
+
+    /// Returns the color that replaces the paint of `path`, from the ID of the path or of its
+    /// innermost group with one.
+    fn replaced_paint(&self, path: &PathDescriptor) -> Option<Color> {
+        let paints = match path.draw_type {
+            DrawType::Fill(_) => &self.overrides.fills,
+            DrawType::Stroke(_) => &self.overrides.strokes,
+        };
+        if paints.is_empty() {
+            return None;
+        }
+        std::iter::once(path.id.as_str())
+            .chain(path.groups.iter().rev().map(|group| group.id.as_str()))
+            .filter(|id| !id.is_empty())
+            .find_map(|id| paints.get(id).copied())
+    }
+
+    /// Tessellates a single path, including its paint.
+    fn tessellate_single(&mut self, path: &PathDescriptor) -> VertexBuffers {
+        match &path.paint {
+            // Replaced paints are solid colors.
+            _ if self.replaced_paint(path).is_some() => self.tessellate_path(path, path.abs_transform),
+            Paint::Pattern(pattern) => {
+                // Tessellate in path space, so the tiles can be clipped to the shape of the path.
+                let region = self.tessellate_path(path, Transform::identity());
+                let mut buffer = self.tessellate_pattern(pattern, &region);
+                transform_buffer(&mut buffer, &path.abs_transform);
+                buffer
+            },
+            Paint::Image(image) => {
+                let region = self.tessellate_path(path, Transform::identity());
+                let mut buffer = Raster::from_image(image).to_buffer();
+                transform_buffer_2d(&mut buffer, &image.transform);
+                let mut buffer = clip::intersect(&buffer, &region, clip::keep_subject);
+                transform_buffer(&mut buffer, &path.abs_transform);
+                buffer
+            },
+            Paint::LinearGradient(LinearGradient { spread, .. }) | Paint::RadialGradient(RadialGradient { spread, .. }) if *spread != SpreadMethod::Pad => {
+                // The stripes are much finer than the geometry of the path, so the gradient is
+                // sampled on a raster instead of at the vertices of the path.
+                let region = self.tessellate_path(path, Transform::identity());
+                let buffer = bounds(&region).map_or_else(VertexBuffers::new, |bounds| Raster::from_paint(&bounds, &path.paint).to_buffer());
+                let mut buffer = clip::intersect(&buffer, &region, clip::keep_subject);
+                transform_buffer(&mut buffer, &path.abs_transform);
+                buffer
+            },
+            Paint::LinearGradient(gradient) => {
+                // The vertices of the path are too few to catch the stops in between, so the path
+                // is cut into strips that end at the stops.
+                let region = self.tessellate_path(path, Transform::identity());
+                let mut buffer = match bounds(&region).and_then(|bounds| gradient_strips(gradient, &bounds)) {
+                    Some(strips) => clip::intersect(&strips, &region, clip::keep_subject),
+                    None => region,
+                };
+                transform_buffer(&mut buffer, &path.abs_transform);
+                buffer
+            },
+            Paint::RadialGradient(gradient) => {
+                // The rings are curved, so the gradient is sampled on a grid that is clipped to
+                // the path.
+                let region = self.tessellate_path(path, Transform::identity());
+                let buffer = bounds(&region).map_or_else(VertexBuffers::new, |bounds| gradient_grid(gradient, &bounds));
+                let mut buffer = clip::intersect(&buffer, &region, clip::keep_subject);
+                transform_buffer(&mut buffer, &path.abs_transform);
+                buffer
+            },
+            _ => self.tessellate_path(path, path.abs_transform),
+        }
+    }
+
+    /// Applies the effects of `group` to the tessellated content of the group.
+    fn apply_group(&mut self, group: &Group, mut buffer: VertexBuffers) -> VertexBuffers {
+        for group_filter in &group.filters {
+            buffer = filter::apply(group_filter, buffer);
+        }
+        if let Some(ref clip_path) = group.clip_path {
+            buffer = clip::intersect(&buffer, self.clip_region(clip_path), clip::keep_subject);
+        }
+        if let Some(ref mask) = group.mask {
+            buffer = clip::intersect(&buffer, self.mask_region(mask), clip::apply_mask);
+        }
+        let opacity = self.overrides.opacities.get(&group.id).copied().unwrap_or(group.opacity);
+        if opacity < 1.0 {
+            buffer = apply_opacity(buffer, opacity);
+        }
+        if let Some(animated) = self.overrides.transforms.get(&group.id) {
+            // The content is already in the coordinate system of the SVG, so it is moved back
+            // into the coordinate system of the group first.
+            let to_svg = group.transform.then(&group.parent_transform);
+            if let (Some(inverse), Some(correction)) = (to_svg.inverse(), animated.correction()) {
+                transform_buffer_2d(&mut buffer, &inverse.then(&correction).then(&to_svg));
             }
         }
-        buffers.extend_one(buffer);
+
+        buffer
     }
-    debug!("Tessellating SVG: {} ... Done", svg.name);
 
+    fn tessellate_path(&mut self, path: &PathDescriptor, transform: Transform) -> VertexBuffers {
+        let replaced = self.replaced_paint(path);
+        let overridden = replaced.as_ref()
+            .or_else(|| path.paint_variable.as_ref().and_then(|name| self.overrides.css_variables.get(name)));
+        let opacities = match path.draw_type {
+            DrawType::Fill(_) => &self.overrides.fill_opacities,
+            DrawType::Stroke(_) => &self.overrides.stroke_opacities,
+        };
+        let opacity = opacities.get(&path.id).copied();
+        let paint = match (&path.paint, replaced) {
+            // The alpha of the paint is the fill or stroke opacity.
+            (Paint::Color(color), _) if overridden.is_some() || opacity.is_some() => {
+                let alpha = overridden.map_or(1.0, Color::a) * opacity.unwrap_or_else(|| color.a());
+                let mut value = overridden.copied().unwrap_or(*color);
+                value.set_a(alpha);
+                Cow::Owned(Paint::Color(value))
+            },
+            // Gradients, patterns and images are replaced by the solid color.
+            (_, Some(replaced)) => Cow::Owned(Paint::Color(replaced)),
+            _ => Cow::Borrowed(&path.paint),
+        };
+        let revealed = match (&path.draw_type, self.overrides.draw_on) {
+            (DrawType::Stroke(_), Some(progress)) => Cow::Owned(dash::trim(&path.segments, path.length * progress.max(0.0))),
+            _ => Cow::Borrowed(&path.segments),
+        };
+        let segments = match (&path.draw_type, &path.dash) {
+            (DrawType::Stroke(_), Some(pattern)) => dash::dash(&revealed, pattern, pattern.offset + self.overrides.dash_offset),
+            _ => revealed.into_owned(),
+        };
+        let draw_type = match (path.non_scaling_stroke, self.overrides.scale) {
+            (true, Some(scale)) => Cow::Owned(path.draw_type.clone().scale_stroke(1.0 / scale)),
+            _ => Cow::Borrowed(&path.draw_type),
+        };
+
+        let geometry = self.geometry(&path.id, segments, &draw_type);
+        let constructor = VertexConstructor { paint: &paint, transform };
+        VertexBuffers {
+            vertices: geometry.vertices.iter().map(|&position| constructor.vertex(position)).collect(),
+            indices: geometry.indices.clone(),
+        }
+    }
+
+    /// Returns the tessellated geometry of `segments`, tessellating it if that didn't happen yet.
+    /// `id` is the ID of the element that the geometry belongs to.
+    fn geometry(&mut self, id: &str, segments: Vec<PathEvent>, draw_type: &DrawType) -> &lyon_tessellation::VertexBuffers<Point, IndexType> {
+        let candidates = self.geometries.entry(geometry_hash(&segments, draw_type)).or_default();
+        let index = match candidates.iter().position(|geometry| geometry.draw_type == *draw_type && geometry.segments == segments) {
+            Some(index) => index,
+            None => {
+                let mut buffer = lyon_tessellation::VertexBuffers::new();
+                let result = match draw_type {
+                    DrawType::Fill(opts) => {
+                        let opts = self.overrides.fill_rule.map_or(*opts, |fill_rule| opts.with_fill_rule(fill_rule));
+                        let opts = self.tolerance.map_or(opts, |tolerance| opts.with_tolerance(tolerance));
+                        self.fill_tess.tessellate(
+                            segments.iter().copied(),
+                            &opts,
+                            &mut BuffersBuilder::new(&mut buffer, Positions)
+                        ).map_err(|e| format!("FillTessellator error: {:?}", e))
+                    },
+                    DrawType::Stroke(opts) => {
+                        let opts = self.tolerance.map_or(*opts, |tolerance| opts.with_tolerance(tolerance));
+                        self.stroke_tess.tessellate(
+                            segments.iter().copied(),
+                            &opts,
+                            &mut BuffersBuilder::new(&mut buffer, Positions)
+                        ).map_err(|e| format!("StrokeTessellator error: {:?}", e))
+                    }
+                };
+                if let Err(message) = result {
+                    error!("{}", message);
+                    self.failure.get_or_insert(SvgLoadError::Tessellation {
+                        element_id: Some(id.to_owned()).filter(|id| !id.is_empty()),
+                        message,
+                    });
+                }
+                candidates.push(Geometry { segments, draw_type: draw_type.clone(), buffer });
+                candidates.len() - 1
+            },
+        };
+        &candidates[index].buffer
+    }
+
+    /// Returns the tessellated region of `clip_path`, tessellating it if that didn't happen yet.
+    fn clip_region(&mut self, clip_path: &Arc<ClipPath>) -> &VertexBuffers {
+        let key = Arc::as_ptr(clip_path) as usize;
+        if !self.regions.contains_key(&key) {
+            let mut region = self.tessellate_region(&clip_path.paths, Transform::identity());
+            if let Some(ref nested) = clip_path.clip_path {
+                region = clip::intersect(&region, self.clip_region(nested), clip::keep_subject);
+            }
+            self.regions.insert(key, region);
+        }
+
+        &self.regions[&key]
+    }
+
+    /// Returns the tessellated content of `mask`, tessellating it if that didn't happen yet.
+    fn mask_region(&mut self, mask: &Arc<Mask>) -> &VertexBuffers {
+        let key = Arc::as_ptr(mask) as usize;
+        if !self.regions.contains_key(&key) {
+            let content = self.tessellate_region(&mask.paths, Transform::identity());
+            let rect = &mask.rect;
+            let mut mask_rect = clip::rect(Vec2::new(rect.min_x(), rect.min_y()), Vec2::new(rect.max_x(), rect.max_y()));
+            transform_buffer_2d(&mut mask_rect, &mask.transform);
+            let mut region = clip::intersect(&content, &mask_rect, clip::keep_subject);
+            if let Some(ref nested) = mask.mask {
+                region = clip::intersect(&region, self.mask_region(nested), clip::apply_mask);
+            }
+            self.regions.insert(key, region);
+        }
+
+        &self.regions[&key]
+    }
+
+    /// Repeats the tile of `pattern` across `region` and clips it to the shape of `region`.
+    /// The `region` and the resulting buffer are both in path space.
+    fn tessellate_pattern(&mut self, pattern: &Pattern, region: &VertexBuffers) -> VertexBuffers {
+        let (rect, inverse) = match pattern.transform.inverse() {
+            Some(inverse) if !pattern.rect.is_empty() => (pattern.rect, inverse),
+            _ => return VertexBuffers::new(),
+        };
+
+        let mut tile = self.tessellate_region(&pattern.tile, Transform::identity());
+        transform_buffer_2d(&mut tile, &pattern.content_transform);
+        // The content of a pattern is clipped to its tile.
+        let tile_clip = clip::rect(Vec2::new(rect.min_x(), rect.min_y()), Vec2::new(rect.max_x(), rect.max_y()));
+        let tile = clip::intersect(&tile, &tile_clip, clip::keep_subject);
+
+        // Bounding box of the region in pattern space, to know how many tiles are needed.
+        let (mut min, mut max) = (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY));
+        for vertex in &region.vertices {
+            let p = inverse.transform_point(Point::new(vertex.position[0], vertex.position[1]));
+            min = min.min(Vec2::new(p.x, p.y));
+            max = max.max(Vec2::new(p.x, p.y));
+        }
+        if !min.is_finite() || !max.is_finite() {
+            return VertexBuffers::new();
+        }
+
+        let columns = ((min.x - rect.min_x()) / rect.width()).floor() as i64..((max.x - rect.min_x()) / rect.width()).ceil() as i64;
+        let rows = ((min.y - rect.min_y()) / rect.height()).floor() as i64..((max.y - rect.min_y()) / rect.height()).ceil() as i64;
+        let tile_count = (columns.end - columns.start).max(0) as usize * (rows.end - rows.start).max(0) as usize;
+        if tile_count > MAX_PATTERN_TILES {
+            warn!("Pattern needs {} tiles, which exceeds the limit of {}. Skipping it.", tile_count, MAX_PATTERN_TILES);
+            return VertexBuffers::new();
+        }
+
+        let mut tiles = VertexBuffers::new();
+        for column in columns {
+            for row in rows.clone() {
+                let mut buffer = tile.clone();
+                let offset = Transform2D::translation(column as f32 * rect.width(), row as f32 * rect.height());
+                transform_buffer_2d(&mut buffer, &offset.then(&pattern.transform));
+                tiles.extend_one(buffer);
+            }
+        }
+
+        let opacity = pattern.opacity;
+        clip::intersect(&tiles, region, |[r, g, b, a], _| [r, g, b, a * opacity])
+    }
+}
+
+/// Hashes the points of `segments` and the kind of `draw_type`, paths with the same hash are
+/// compared in full to find instances of the same shape.
+fn geometry_hash(segments: &[PathEvent], draw_type: &DrawType) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    matches!(draw_type, DrawType::Fill(_)).hash(&mut hasher);
+    for event in segments {
+        let points: &[Point] = match event {
+            PathEvent::Begin { at } => &[*at],
+            PathEvent::Line { from, to } => &[*from, *to],
+            PathEvent::Quadratic { from, ctrl, to } => &[*from, *ctrl, *to],
+            PathEvent::Cubic { from, ctrl1, ctrl2, to } => &[*from, *ctrl1, *ctrl2, *to],
+            PathEvent::End { last, first, close } => {
+                close.hash(&mut hasher);
+                &[*last, *first]
+            },
+        };
+        for point in points {
+            point.x.to_bits().hash(&mut hasher);
+            point.y.to_bits().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Blends `buffer` on top of the `backdrop` with the given `mode`.
+///
+/// The part of the backdrop below `buffer` is rasterized together with it, the rest of the
+/// backdrop keeps its geometry.
+fn apply_blend_mode(backdrop: &VertexBuffers, buffer: &VertexBuffers, mode: BlendMode) -> VertexBuffers {
+    let (region, outer) = match (bounds(buffer), bounds(backdrop)) {
+        (Some(region), Some(outer)) => (region, outer.union(&region)),
+        // Blending with a transparent backdrop doesn't change anything.
+        _ => {
+            let mut buffers = backdrop.clone();
+            buffers.extend_one(buffer.clone());
+            return buffers;
+        },
+    };
+
+    let (min, max) = (Vec2::new(region.min_x(), region.min_y()), Vec2::new(region.max_x(), region.max_y()));
+    let (outer_min, outer_max) = (Vec2::new(outer.min_x(), outer.min_y()), Vec2::new(outer.max_x(), outer.max_y()));
+    let outside = [
+        clip::rect(outer_min, Vec2::new(min.x, outer_max.y)),
+        clip::rect(Vec2::new(max.x, outer_min.y), outer_max),
+        clip::rect(Vec2::new(min.x, outer_min.y), Vec2::new(max.x, min.y)),
+        clip::rect(Vec2::new(min.x, max.y), Vec2::new(max.x, outer_max.y)),
+    ];
+    let mut buffers = VertexBuffers::new();
+    buffers.extend(outside.iter().map(|outside| clip::intersect(backdrop, outside, clip::keep_subject)));
+
+    let mut raster = Raster::new(&region);
+    raster.draw(backdrop);
+    let mut source = Raster::new(&region);
+    source.draw(buffer);
+    raster.combine(&source, |src, dst| filter::blend(mode, src, dst));
+    buffers.extend_one(raster.to_buffer());
     buffers
 }
+
+/// Makes the content of a group transparent as a whole.
+///
+/// Overlapping triangles have to be composited with each other first, otherwise shapes below
+/// would shine through. In that case the content is rasterized and turned back into geometry,
+/// otherwise the opacity goes straight into the vertex colors.
+fn apply_opacity(mut buffer: VertexBuffers, opacity: f32) -> VertexBuffers {
+    if let Some(region) = bounds(&buffer).filter(|_| clip::overlaps(&buffer)) {
+        let mut raster = Raster::new(&region);
+        raster.draw(&buffer);
+        raster.pixels.iter_mut().for_each(|pixel| *pixel = pixel.map(|channel| channel * opacity));
+        return raster.to_buffer();
+    }
+
+    for vertex in &mut buffer.vertices {
+        let [r, g, b, a] = vertex.linear_color();
+        *vertex = Vertex::new(vertex.position, [r, g, b, a * opacity]);
+    }
+    buffer
+}
+
+/// Returns the bounding box of all vertices in `buffer`.
+pub(crate) fn bounds(buffer: &VertexBuffers) -> Option<Rect> {
+    let (mut min, mut max) = (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY));
+    for vertex in &buffer.vertices {
+        min = min.min(Vec2::new(vertex.position[0], vertex.position[1]));
+        max = max.max(Vec2::new(vertex.position[0], vertex.position[1]));
+    }
+    (min.is_finite() && max.is_finite()).then(|| rect(min.x, min.y, max.x - min.x, max.y - min.y))
+}
+
+/// Covers `bounds` with strips across the vector of the padded `gradient`, which end at its stops,
+/// so the vertex colors interpolated within a strip follow the gradient. `None` if the gradient
+/// has a single color everywhere.
+fn gradient_strips(gradient: &LinearGradient, bounds: &Rect) -> Option<VertexBuffers> {
+    let origin = Vec2::new(bounds.min_x(), bounds.min_y());
+    let offset_at = |position: Vec2| gradient.offset_at(Point::new(position.x, position.y));
+    let origin_offset = offset_at(origin);
+    // The offset changes linearly along the gradient vector, in path space.
+    let along = Vec2::new(offset_at(origin + Vec2::X), offset_at(origin + Vec2::Y)) - origin_offset;
+    if !along.is_finite() || along.length_squared() <= 0.0 {
+        return None;
+    }
+    let across = along.perp().normalize();
+
+    let corners = [
+        origin,
+        Vec2::new(bounds.max_x(), bounds.min_y()),
+        Vec2::new(bounds.max_x(), bounds.max_y()),
+        Vec2::new(bounds.min_x(), bounds.max_y()),
+    ];
+    let range = |values: [f32; 4]| values.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &value| (min.min(value), max.max(value)));
+    let (min_offset, max_offset) = range(corners.map(|corner| origin_offset + along.dot(corner - origin)));
+    let (min_width, max_width) = range(corners.map(|corner| across.dot(corner - origin)));
+
+    let mut ends = vec![min_offset];
+    ends.extend(gradient.stops.iter().map(|stop| stop.offset).filter(|offset| *offset > min_offset && *offset < max_offset));
+    ends.push(max_offset);
+    ends.dedup();
+
+    // The position in path space at `offset` along and `width` across the gradient vector.
+    let position = |offset: f32, width: f32| origin + along * ((offset - origin_offset) / along.length_squared()) + across * width;
+    let mut buffer = VertexBuffers::new();
+    for pair in ends.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let color_at = |offset: f32| gradient.color_between(from, to, offset).as_linear_rgba_f32();
+        let strips = if color_at(from) == color_at(to) { 1 } else { GRADIENT_STRIPS_PER_STOP };
+        for strip in 0..strips {
+            let start = from + (to - from) * strip as f32 / strips as f32;
+            let end = from + (to - from) * (strip + 1) as f32 / strips as f32;
+            let offset = buffer.vertices.len() as u32;
+            for (t, width) in [(start, min_width), (start, max_width), (end, max_width), (end, min_width)] {
+                let corner = position(t, width);
+                buffer.vertices.push(Vertex::new([corner.x, corner.y, 0.0], color_at(t)));
+            }
+            buffer.indices.extend([0, 1, 2, 0, 2, 3].map(|index| index + offset));
+        }
+    }
+
+    Some(buffer)
+}
+
+/// Covers `bounds` with a grid of vertices that sample the padded `gradient`, fine enough that
+/// the vertex colors interpolated between them follow its rings.
+fn gradient_grid(gradient: &RadialGradient, bounds: &Rect) -> VertexBuffers {
+    // The gradient transform maps path space into gradient space, the radius is in the latter.
+    let scale = gradient.transform.determinant().abs().sqrt();
+    let cell_size = gradient.radius / scale / RADIAL_GRADIENT_CELLS;
+    let cells = |length: f32| ((length / cell_size).ceil() as usize).clamp(1, MAX_RADIAL_GRADIENT_CELLS);
+    let (columns, rows) = (cells(bounds.width()), cells(bounds.height()));
+
+    let mut buffer = VertexBuffers::new();
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let x = bounds.min_x() + bounds.width() * column as f32 / columns as f32;
+            let y = bounds.min_y() + bounds.height() * row as f32 / rows as f32;
+            buffer.vertices.push(Vertex::new([x, y, 0.0], gradient.color_at(Point::new(x, y)).as_linear_rgba_f32()));
+        }
+    }
+    for row in 0..rows {
+        for column in 0..columns {
+            let top_left = (row * (columns + 1) + column) as u32;
+            let bottom_left = top_left + columns as u32 + 1;
+            buffer.indices.extend([top_left, top_left + 1, bottom_left + 1, top_left, bottom_left + 1, bottom_left]);
+        }
+    }
+
+    buffer
+}
+
+/// Applies a 2D transform to the position of every vertex in `buffer`.
+pub(crate) fn transform_buffer_2d(buffer: &mut VertexBuffers, transform: &Transform2D<f32>) {
+    for vertex in &mut buffer.vertices {
+        let p = transform.transform_point(Point::new(vertex.position[0], vertex.position[1]));
+        vertex.position[0] = p.x;
+        vertex.position[1] = p.y;
+    }
+}
+
+/// Applies a [`Transform`] to the position of every vertex in `buffer`.
+fn transform_buffer(buffer: &mut VertexBuffers, transform: &Transform) {
+    for vertex in &mut buffer.vertices {
+        vertex.position = (*transform * Vec3::from(vertex.position)).into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon_tessellation::math::point;
+
+    use crate::paint::GradientStop;
+
+    use super::*;
+
+    fn gradient(end: Point) -> LinearGradient {
+        LinearGradient {
+            start: point(0.0, 0.0),
+            end,
+            stops: vec![
+                GradientStop { offset: 0.0, color: Color::BLACK },
+                GradientStop { offset: 0.5, color: Color::WHITE },
+                GradientStop { offset: 1.0, color: Color::BLACK },
+            ],
+            spread: SpreadMethod::Pad,
+            transform: Transform2D::identity(),
+        }
+    }
+
+    #[test]
+    fn gradient_strips_end_at_stops() {
+        let strips = gradient_strips(&gradient(point(10.0, 0.0)), &rect(0.0, 0.0, 10.0, 10.0)).unwrap();
+        let middle: Vec<_> = strips.vertices.iter().filter(|vertex| (vertex.position[0] - 5.0).abs() < 1e-4).collect();
+        assert!(!middle.is_empty());
+        assert!(middle.iter().all(|vertex| vertex.linear_color()[0] == 1.0));
+        for vertex in &strips.vertices {
+            assert!((-1e-4..=10.0001).contains(&vertex.position[0]));
+            assert_eq!(vertex.linear_color()[0] == 0.0, vertex.position[0].abs() < 1e-4 || (vertex.position[0] - 10.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn radial_gradient_grid_samples_inside_of_path() {
+        let gradient = RadialGradient {
+            center: point(5.0, 5.0),
+            radius: 5.0,
+            focal: point(5.0, 5.0),
+            stops: vec![
+                GradientStop { offset: 0.0, color: Color::WHITE },
+                GradientStop { offset: 1.0, color: Color::BLACK },
+            ],
+            spread: SpreadMethod::Pad,
+            transform: Transform2D::identity(),
+        };
+        let grid = gradient_grid(&gradient, &rect(0.0, 0.0, 10.0, 10.0));
+        // The center of the disc is white, not the color of the last stop like its outline.
+        let center = grid.vertices.iter().find(|vertex| vertex.position[0] == 5.0 && vertex.position[1] == 5.0).unwrap();
+        assert_eq!(center.linear_color()[0], 1.0);
+        assert_eq!(grid.indices.len(), 32 * 32 * 6);
+    }
+
+    #[test]
+    fn gradient_strips_of_single_color() {
+        assert!(gradient_strips(&gradient(point(0.0, 0.0)), &rect(0.0, 0.0, 10.0, 10.0)).is_none());
+    }
+}
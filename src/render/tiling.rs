@@ -0,0 +1,52 @@
+//! Repeats already tessellated [`VertexBuffers`] to fill a region, see [`SvgTiling`].
+
+use bevy::{log::warn, math::Vec2};
+use copyless::VecHelper;
+
+use crate::{
+    overrides::SvgTiling,
+    render::{clip, vertex_buffer::VertexBuffers},
+};
+
+
+/// Upper limit of tiles that get generated to fill a region.
+const MAX_TILES: usize = 4096;
+
+/// Repeats `buffer`, an SVG of `size` with the y-axis of Bevy, in both directions to fill the
+/// region of `tiling`, and cuts off everything outside of it.
+pub(crate) fn apply(buffer: &VertexBuffers, tiling: &SvgTiling, size: Vec2) -> VertexBuffers {
+    let rect = tiling.rect;
+    let first = Vec2::new((rect.min_x() / size.x).floor(), (rect.min_y() / size.y).floor());
+    let last = Vec2::new((rect.max_x() / size.x).ceil(), (rect.max_y() / size.y).ceil());
+    let count = last - first;
+    if !count.is_finite() || count.min_element() <= 0.0 {
+        return VertexBuffers::new();
+    }
+    if count.x * count.y > MAX_TILES as f32 {
+        warn!("Tiling the SVG would need {} tiles, only {} are drawn.", count.x * count.y, MAX_TILES);
+    }
+
+    let mut tiles = VertexBuffers::new();
+    let tiles_to_draw = (first.y as i64..last.y as i64)
+        .flat_map(|row| (first.x as i64..last.x as i64).map(move |column| (column, row)))
+        .take(MAX_TILES);
+    for (column, row) in tiles_to_draw {
+        let offset = tiles.vertices.len() as u32;
+        let mirror_x = tiling.mirror && column.rem_euclid(2) == 1;
+        let mirror_y = tiling.mirror && row.rem_euclid(2) == 1;
+        for vertex in &buffer.vertices {
+            let mut vertex = *vertex;
+            // Positions within the tile, in the coordinate system of the SVG.
+            let (x, y) = (vertex.position[0], -vertex.position[1]);
+            let x = if mirror_x { size.x - x } else { x };
+            let y = if mirror_y { size.y - y } else { y };
+            vertex.position[0] = column as f32 * size.x + x;
+            vertex.position[1] = -(row as f32 * size.y + y);
+            tiles.vertices.alloc().init(vertex);
+        }
+        tiles.indices.extend(buffer.indices.iter().map(|index| index + offset));
+    }
+
+    let region = clip::rect(Vec2::new(rect.min_x(), -rect.max_y()), Vec2::new(rect.max_x(), -rect.min_y()));
+    clip::intersect(&tiles, &region, clip::keep_subject)
+}
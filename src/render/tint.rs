@@ -0,0 +1,159 @@
+//! The [`SvgTint`] and [`SvgColorMatrix`] of SVG entities, which are bound as uniform to the
+//! shaders of both pipelines.
+
+use bevy::{
+    asset::Handle,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::{With, Without},
+        system::{
+            lifetimeless::{Read, SQuery, SRes},
+            Commands, Query, Res, ResMut, SystemParamItem,
+        },
+        world::{FromWorld, World},
+    },
+    math::{Mat4, Vec4},
+    render::{
+        render_phase::{EntityRenderCommand, RenderCommandResult, TrackedRenderPass},
+        render_resource::{
+            std140::AsStd140,
+            BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry, BindingType, BufferBindingType, BufferSize, DynamicUniformVec, ShaderStages,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        view::ComputedVisibility,
+        RenderWorld,
+    },
+};
+
+use crate::{overrides::{SvgColorMatrix, SvgTint}, path_entities::SvgPathEntities, svg::Svg};
+
+
+/// The tint and color matrix of an SVG as they are sent to the shaders.
+#[derive(Clone, AsStd140)]
+pub struct SvgTintUniform {
+    color: Vec4,
+    /// The first 4 columns of the color matrix.
+    matrix: Mat4,
+    /// The last column of the color matrix, which is added to the color.
+    offset: Vec4,
+}
+
+impl SvgTintUniform {
+    fn new(tint: Option<&SvgTint>, color_matrix: Option<&SvgColorMatrix>) -> Self {
+        let color = tint.map_or(Vec4::ONE, |tint| Vec4::from(tint.0.as_linear_rgba_f32()));
+        let (matrix, offset) = match color_matrix {
+            Some(color_matrix) => {
+                let m = color_matrix.matrix();
+                let row = |i: usize| Vec4::new(m[i * 5], m[i * 5 + 1], m[i * 5 + 2], m[i * 5 + 3]);
+                (Mat4::from_cols(row(0), row(1), row(2), row(3)).transpose(), Vec4::new(m[4], m[9], m[14], m[19]))
+            },
+            None => (Mat4::IDENTITY, Vec4::ZERO),
+        };
+        Self { color, matrix, offset }
+    }
+}
+
+/// The tints of all extracted SVG entities, which are written into one uniform buffer.
+#[derive(Default)]
+pub struct SvgTintUniforms {
+    tints: Vec<(Entity, SvgTintUniform)>,
+    uniforms: DynamicUniformVec<SvgTintUniform>,
+    bind_group: Option<BindGroup>,
+}
+
+/// Offset of the tint of an entity in the uniform buffer.
+#[derive(Component)]
+pub struct SvgTintIndex(u32);
+
+/// Layout of the bind group of the tints, shared by the 2D and 3D pipelines.
+pub struct SvgTintLayout(pub BindGroupLayout);
+
+impl FromWorld for SvgTintLayout {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+        Self(render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: BufferSize::new(SvgTintUniform::std140_size_static() as u64),
+                },
+                count: None,
+            }],
+            label: Some("svg_tint_layout"),
+        }))
+    }
+}
+
+/// Extract the [`SvgTint`] and [`SvgColorMatrix`] of every visible [`Svg`] entity into
+/// [`RenderWorld`], white and the identity for entities without them.
+#[allow(clippy::type_complexity)]
+pub fn extract_svg_tints(
+    mut render_world: ResMut<RenderWorld>,
+    query: Query<(Entity, &ComputedVisibility, Option<&SvgTint>, Option<&SvgColorMatrix>), (With<Handle<Svg>>, Without<SvgPathEntities>)>,
+) {
+    let mut tint_uniforms = render_world.get_resource_mut::<SvgTintUniforms>().unwrap();
+    tint_uniforms.tints.clear();
+    for (entity, computed_visibility, tint, color_matrix) in query.iter() {
+        if computed_visibility.is_visible {
+            tint_uniforms.tints.push((entity, SvgTintUniform::new(tint, color_matrix)));
+        }
+    }
+}
+
+/// Write the extracted tints into the uniform buffer and remember the offset of each entity.
+pub fn prepare_svg_tints(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut tint_uniforms: ResMut<SvgTintUniforms>,
+) {
+    let SvgTintUniforms { tints, uniforms, .. } = &mut *tint_uniforms;
+    uniforms.clear();
+    for (entity, tint) in tints.iter() {
+        let offset = uniforms.push(tint.clone());
+        commands.get_or_spawn(*entity).insert(SvgTintIndex(offset));
+    }
+    uniforms.write_buffer(&render_device, &render_queue);
+}
+
+/// Create the bind group of the uniform buffer with the tints.
+pub fn queue_svg_tints(
+    render_device: Res<RenderDevice>,
+    tint_layout: Res<SvgTintLayout>,
+    mut tint_uniforms: ResMut<SvgTintUniforms>,
+) {
+    tint_uniforms.bind_group = tint_uniforms.uniforms.binding().map(|resource| {
+        render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[BindGroupEntry { binding: 0, resource }],
+            label: Some("svg_tint_bind_group"),
+            layout: &tint_layout.0,
+        })
+    });
+}
+
+/// Set the bind group of the tint of an SVG entity at index `I`.
+pub struct SetSvgTintBindGroup<const I: usize>;
+
+impl<const I: usize> EntityRenderCommand for SetSvgTintBindGroup<I> {
+    type Param = (SRes<SvgTintUniforms>, SQuery<Read<SvgTintIndex>>);
+
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        (tint_uniforms, query): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        match (tint_uniforms.into_inner().bind_group.as_ref(), query.get(item)) {
+            (Some(bind_group), Ok(index)) => {
+                pass.set_bind_group(I, bind_group, &[index.0]);
+                RenderCommandResult::Success
+            },
+            _ => RenderCommandResult::Failure,
+        }
+    }
+}
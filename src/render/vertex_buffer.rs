@@ -1,23 +1,42 @@
 use bevy::{
-    math::Vec3,
+    math::{Vec2, Vec3},
     render::{
-        color::Color, mesh::{Indices, Mesh},
-        render_resource::PrimitiveTopology,
+        mesh::{Indices, Mesh, MeshVertexAttribute, VertexAttributeDescriptor},
+        render_resource::{PrimitiveTopology, VertexAttribute, VertexBufferLayout, VertexStepMode},
     },
     transform::components::Transform,
 };
 use copyless::VecHelper;
-use lyon_tessellation::{self, FillVertex, FillVertexConstructor, StrokeVertex, StrokeVertexConstructor};
+use lyon_tessellation::{self, math::Point};
 
-use crate::Convert;
+use crate::{paint::Paint, Convert};
 
 
 /// A vertex with all the necessary attributes to be inserted into a Bevy
 /// [`Mesh`](bevy::render::mesh::Mesh).
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) struct Vertex {
-    position: [f32; 3],
-    color: u32,
+    pub(crate) position: [f32; 3],
+    pub(crate) color: u32,
+    /// Texture coordinates, set for the whole buffer with [`map_uvs`] once it is tessellated.
+    pub(crate) uv: [f32; 2],
+}
+
+impl Vertex {
+    /// Creates a new [`Vertex`] from a position and a color in linear RGBA.
+    pub(crate) fn new(position: [f32; 3], color: [f32; 4]) -> Self {
+        let [r, g, b, a] = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0) as u8);
+        Self {
+            position,
+            color: u32::from_le_bytes([r, g, b, a]),
+            uv: [0.0; 2],
+        }
+    }
+
+    /// Returns the color of the vertex in linear RGBA.
+    pub(crate) fn linear_color(&self) -> [f32; 4] {
+        self.color.to_le_bytes().map(|channel| channel as f32 / 255.0)
+    }
 }
 
 /// The index type of a Bevy [`Mesh`](bevy::render::mesh::Mesh).
@@ -30,63 +49,173 @@ impl Convert<Mesh> for VertexBuffers {
     fn convert(self) -> Mesh {
         let mut positions = Vec::with_capacity(self.vertices.len());
         let mut colors = Vec::with_capacity(self.vertices.len());
+        let mut uvs = Vec::with_capacity(self.vertices.len());
 
         for vert in self.vertices.into_iter() {
             positions.alloc().init(vert.position);
             colors.alloc().init(vert.color);
+            uvs.alloc().init(vert.uv);
         }
 
-        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-        mesh.set_indices(Some(Indices::U32(self.indices)));
-        mesh.insert_attribute(
-            Mesh::ATTRIBUTE_POSITION,
-            positions
-        );
-        mesh.insert_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            colors
-        );
-
-        mesh
+        mesh(positions, colors, uvs, self.indices)
     }
 }
 
-/// Zero-sized type used to implement various vertex construction traits from Lyon.
-pub(crate) struct VertexConstructor {
-    pub(crate) color: Color,
-    pub(crate) transform: Transform,
+/// Creates a mesh from the positions, colors and texture coordinates of its vertices and the
+/// indices of its triangles, with the normals of [`normals`] and the tangents of [`tangents`].
+pub(crate) fn mesh(positions: Vec<[f32; 3]>, colors: Vec<u32>, uvs: Vec<[f32; 2]>, indices: Vec<IndexType>) -> Mesh {
+    let normals = normals(&positions, &indices);
+    let tangents = tangents(&positions, &normals, &uvs, &indices);
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        positions
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_COLOR,
+        colors
+    );
+    // The SVG pipelines don't light the mesh, but other materials like `StandardMaterial` do.
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        normals
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        uvs
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_TANGENT,
+        tangents
+    );
+
+    mesh
 }
 
-/// Enables the construction of a [`Vertex`] when using a `FillTessellator`.
-impl FillVertexConstructor<Vertex> for VertexConstructor {
-    fn new_vertex(&mut self, vertex: FillVertex) -> Vertex {
-        let vertex = vertex.position();
-        let pos = self.transform * Vec3::new(
-            vertex.x,
-            vertex.y,
-            0.0,
-        );
+/// The attributes of the meshes of [`mesh`], in the order bevy interleaves them in the vertex
+/// buffer. That is the order of their ids, not the one they were inserted in, so the color comes
+/// last.
+const ATTRIBUTES: [MeshVertexAttribute; 5] = [
+    Mesh::ATTRIBUTE_POSITION,
+    Mesh::ATTRIBUTE_NORMAL,
+    Mesh::ATTRIBUTE_UV_0,
+    Mesh::ATTRIBUTE_TANGENT,
+    Mesh::ATTRIBUTE_COLOR,
+];
 
-        Vertex {
-            position: [pos.x, pos.y, pos.z],
-            color: self.color.as_linear_rgba_u32(),
+/// Returns the layout of the vertex buffer of the meshes of [`mesh`] for a pipeline that reads the
+/// attributes of `descriptors` at their shader locations, like
+/// [`MeshVertexBufferLayout::get_layout`](bevy::render::mesh::MeshVertexBufferLayout::get_layout).
+pub(crate) fn vertex_buffer_layout(descriptors: &[VertexAttributeDescriptor]) -> VertexBufferLayout {
+    let offset = |index: usize| ATTRIBUTES[..index].iter().map(|attribute| attribute.format.size()).sum::<u64>();
+    let attributes = descriptors.iter()
+        .filter_map(|descriptor| {
+            let index = ATTRIBUTES.iter().position(|attribute| attribute.id == descriptor.id)?;
+            Some(VertexAttribute { format: ATTRIBUTES[index].format, offset: offset(index), shader_location: descriptor.shader_location })
+        })
+        .collect();
+    VertexBufferLayout { array_stride: offset(ATTRIBUTES.len()), step_mode: VertexStepMode::Vertex, attributes }
+}
+
+/// Returns the texture coordinates at `position`, with the y-axis of Bevy, which span the SVG of
+/// `size` from `(0, 0)` at its top left corner to `(1, 1)` at its bottom right corner.
+pub(crate) fn view_box_uv(position: [f32; 3], size: Vec2) -> [f32; 2] {
+    let uv = Vec2::new(position[0], -position[1]) / size;
+    if uv.is_finite() { uv.into() } else { [0.0; 2] }
+}
+
+/// Sets the texture coordinates of all vertices of `buffer`, an SVG of `size` with the y-axis of
+/// Bevy, see [`view_box_uv`]. Tiles outside of the SVG continue the coordinates beyond one.
+pub(crate) fn map_uvs(buffer: &mut VertexBuffers, size: Vec2) {
+    for vertex in &mut buffer.vertices {
+        vertex.uv = view_box_uv(vertex.position, size);
+    }
+}
+
+/// Returns the normals of the vertices at `positions`. Flat meshes face the viewer along the
+/// z-axis, whatever the winding of their triangles. Extruded solids get the average of the
+/// triangles around each vertex, which are wound counterclockwise seen from the outside.
+fn normals(positions: &[[f32; 3]], indices: &[IndexType]) -> Vec<[f32; 3]> {
+    let is_flat = positions.windows(2).all(|pair| pair[0][2] == pair[1][2]);
+    if is_flat {
+        return vec![[0.0, 0.0, 1.0]; positions.len()];
+    }
+
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| Vec3::from(positions[index as usize]));
+        // Weighted by the area of the triangle.
+        let normal = (b - a).cross(c - a);
+        for index in triangle {
+            normals[*index as usize] += normal;
+        }
+    }
+    normals.into_iter()
+        .map(|normal| match normal.normalize_or_zero() {
+            Vec3::ZERO => [0.0, 0.0, 1.0],
+            normal => normal.into(),
+        })
+        .collect()
+}
+
+/// Returns the tangents of the vertices at `positions`, along which the first texture coordinate
+/// grows, with the handedness of the second one in `w`. Vertices whose texture coordinates don't
+/// change across their triangles get any tangent that is perpendicular to their normal.
+fn tangents(positions: &[[f32; 3]], normals: &[[f32; 3]], uvs: &[[f32; 2]], indices: &[IndexType]) -> Vec<[f32; 4]> {
+    let mut tangents = vec![Vec3::ZERO; positions.len()];
+    let mut bitangents = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| Vec3::from(positions[index as usize]));
+        let [uv_a, uv_b, uv_c] = [triangle[0], triangle[1], triangle[2]].map(|index| Vec2::from(uvs[index as usize]));
+        let (edge_1, edge_2) = (b - a, c - a);
+        let (delta_1, delta_2) = (uv_b - uv_a, uv_c - uv_a);
+        let determinant = delta_1.x * delta_2.y - delta_2.x * delta_1.y;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge_1 * delta_2.y - edge_2 * delta_1.y) / determinant;
+        let bitangent = (edge_2 * delta_1.x - edge_1 * delta_2.x) / determinant;
+        for index in triangle {
+            tangents[*index as usize] += tangent;
+            bitangents[*index as usize] += bitangent;
         }
     }
+    tangents.into_iter().zip(bitangents).zip(normals)
+        .map(|((tangent, bitangent), normal)| {
+            let normal = Vec3::from(*normal);
+            // Made perpendicular to the normal.
+            let perpendicular = |tangent: Vec3| (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+            let tangent = match perpendicular(tangent) {
+                Vec3::ZERO => perpendicular(if normal.x.abs() < 0.9 { Vec3::X } else { Vec3::Y }),
+                tangent => tangent,
+            };
+            let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+            [tangent.x, tangent.y, tangent.z, handedness]
+        })
+        .collect()
+}
+
+/// Turns the tessellated positions of a path into [`Vertex`]es, with the color of its paint.
+pub(crate) struct VertexConstructor<'a> {
+    pub(crate) paint: &'a Paint,
+    pub(crate) transform: Transform,
 }
 
-/// Enables the construction of a [`Vertex`] when using a `StrokeTessellator`.
-impl StrokeVertexConstructor<Vertex> for VertexConstructor {
-    fn new_vertex(&mut self, vertex: StrokeVertex) -> Vertex {
-        let vertex = vertex.position();
+impl VertexConstructor<'_> {
+    /// Creates the vertex at `position`, in the space of the path.
+    pub(crate) fn vertex(&self, position: Point) -> Vertex {
+        let color = self.paint.color_at(position);
         let pos = self.transform * Vec3::new(
-            vertex.x,
-            vertex.y,
+            position.x,
+            position.y,
             0.0,
         );
 
         Vertex {
             position: [pos.x, pos.y, pos.z],
-            color: self.color.as_linear_rgba_u32(),
+            color: color.as_linear_rgba_u32(),
+            uv: [0.0; 2],
         }
     }
 }
@@ -124,3 +253,38 @@ impl BufferExt<VertexBuffers> for VertexBuffers {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_offset_matches_mesh() {
+        // The attributes at the shader locations of the 3D pipeline
+        let descriptors = [
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(1),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(2),
+        ];
+        let mesh = mesh(vec![[0.0; 3]; 3], vec![u32::MAX; 3], vec![[0.0; 2]; 3], vec![0, 1, 2]);
+        let expected = mesh.get_mesh_vertex_buffer_layout().get_layout(&descriptors).unwrap();
+        let layout = vertex_buffer_layout(&descriptors);
+
+        let color = |layout: &VertexBufferLayout| layout.attributes.iter().find(|attribute| attribute.shader_location == 1).unwrap().offset;
+        assert_eq!(color(&layout), color(&expected));
+        assert_eq!(layout, expected);
+    }
+
+    #[test]
+    fn uv_and_tangent_offsets_match_mesh() {
+        let descriptors = [
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(3),
+            Mesh::ATTRIBUTE_TANGENT.at_shader_location(4),
+        ];
+        let mesh = mesh(vec![[0.0; 3]; 3], vec![u32::MAX; 3], vec![[0.0; 2]; 3], vec![0, 1, 2]);
+        let expected = mesh.get_mesh_vertex_buffer_layout().get_layout(&descriptors).unwrap();
+
+        assert_eq!(vertex_buffer_layout(&descriptors), expected);
+    }
+}
@@ -0,0 +1,122 @@
+//! Drawing 2D SVGs from signed distance fields, whose edges stay crisp at any zoom without
+//! tessellating them again.
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle, HandleId},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::Without,
+        reflect::ReflectComponent,
+        system::{Commands, Query, Res, ResMut},
+    },
+    reflect::Reflect,
+    render::{mesh::Mesh, texture::Image},
+    sprite::Mesh2dHandle,
+};
+
+use crate::{render::sdf, svg::Svg, Convert};
+
+
+/// Draws the [`Svg`] of a 2D entity from signed distance fields instead of its tessellated mesh.
+///
+/// The distance field of every shape is baked once into a texture, and the shapes are drawn as
+/// quads whose edges are found in the shader, so they stay smooth when the camera zooms in.
+/// Sharp corners get rounded when they are magnified far beyond the `resolution`.
+///
+/// Every shape is drawn in a single color, without the effects of its groups, like clip paths,
+/// masks, filters or dashes. Runtime overrides that tessellate the SVG again, like
+/// [`SvgColor`](crate::prelude::SvgColor), don't apply, [`SvgTint`](crate::prelude::SvgTint)
+/// does. Baked SVGs don't keep their paths and are drawn with their mesh.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgSdf {
+    /// Texels of the distance fields per unit of the SVG.
+    pub resolution: f32,
+    /// How far the distance fields reach beyond the edges of the shapes, in units of the SVG.
+    pub spread: f32,
+}
+
+impl Default for SvgSdf {
+    fn default() -> Self {
+        Self { resolution: 1.0, spread: 4.0 }
+    }
+}
+
+/// The quads and distance field atlas of an entity with a [`SvgSdf`].
+#[derive(Component)]
+pub(crate) struct SdfMesh {
+    sdf: SvgSdf,
+    svg: HandleId,
+    mesh: Handle<Mesh>,
+    pub(crate) atlas: Handle<Image>,
+}
+
+/// Bevy system which bakes the distance fields of every entity with a [`SvgSdf`] again when it or
+/// its [`Svg`] changed, and switches entities whose [`SvgSdf`] was removed back to the shared mesh
+/// of their [`Svg`].
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_sdf_meshes(
+    mut commands: Commands,
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    svgs: Res<Assets<Svg>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut query: Query<(Entity, &Handle<Svg>, &SvgSdf, Option<&mut SdfMesh>, &mut Mesh2dHandle)>,
+    mut removed: Query<(Entity, &SdfMesh, &Handle<Svg>, &mut Mesh2dHandle), Without<SvgSdf>>,
+) {
+    let modified: Vec<_> = svg_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, handle, sdf, sdf_mesh, mut mesh_2d) in query.iter_mut() {
+        let svg = match svgs.get(handle) {
+            Some(svg) if !svg.paths.is_empty() => svg,
+            _ => continue,
+        };
+        let mesh = match sdf_mesh {
+            Some(mut sdf_mesh) => {
+                let outdated = sdf_mesh.sdf != *sdf || sdf_mesh.svg != handle.id || modified.contains(handle);
+                if outdated || !meshes.contains(&sdf_mesh.mesh) {
+                    let (buffer, atlas) = sdf::bake(svg, sdf.resolution, sdf.spread);
+                    match meshes.get_mut(&sdf_mesh.mesh) {
+                        Some(old_mesh) => *old_mesh = buffer.convert(),
+                        None => sdf_mesh.mesh = meshes.add(buffer.convert()),
+                    }
+                    match images.get_mut(&sdf_mesh.atlas) {
+                        Some(old_atlas) => *old_atlas = atlas,
+                        None => sdf_mesh.atlas = images.add(atlas),
+                    }
+                    sdf_mesh.sdf = *sdf;
+                    sdf_mesh.svg = handle.id;
+                }
+                sdf_mesh.mesh.clone()
+            },
+            None => {
+                let (buffer, atlas) = sdf::bake(svg, sdf.resolution, sdf.spread);
+                let mesh = meshes.add(buffer.convert());
+                commands.entity(entity).insert(SdfMesh { sdf: *sdf, svg: handle.id, mesh: mesh.clone(), atlas: images.add(atlas) });
+                mesh
+            },
+        };
+
+        // Other systems link the shared mesh again when the `Handle<Svg>` changes.
+        if mesh_2d.0 != mesh {
+            mesh_2d.0 = mesh;
+        }
+    }
+
+    // The distance fields were removed, go back to the shared mesh.
+    for (entity, sdf_mesh, handle, mut mesh_2d) in removed.iter_mut() {
+        meshes.remove(&sdf_mesh.mesh);
+        images.remove(&sdf_mesh.atlas);
+        commands.entity(entity).remove::<SdfMesh>();
+        if let Some(svg) = svgs.get(handle) {
+            mesh_2d.0 = svg.mesh.clone();
+        }
+    }
+}
@@ -0,0 +1,435 @@
+//! (De)serialization of parsed and tessellated [`Svg`]s with `serde`, see [`SerializedSvg`].
+//!
+//! Paths that share a [`Group`], and groups that share a [`ClipPath`] or [`Mask`], are drawn
+//! together during tessellation. Groups, clip paths and masks are therefore stored once in a table
+//! and referenced by their index, so the deserialized [`Svg`] shares them in the same way.
+
+use std::{collections::{HashMap, HashSet}, path::PathBuf, sync::Arc};
+
+use bevy::{
+    asset::AssetPath,
+    math::{Quat, Vec2, Vec3},
+    render::{color::Color, mesh::Mesh},
+    transform::components::Transform,
+};
+use lyon_geom::euclid::default::Transform2D;
+use lyon_svg::{parser::ViewBox, path::PathEvent};
+use lyon_tessellation::{math::Rect, FillTessellator, StrokeTessellator};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::Animation,
+    baked,
+    css_animation::Transition,
+    diagnostics::SkippedFeature,
+    filter::{BlendMode, Filter},
+    loader::SvgLoadError,
+    overrides::Overrides,
+    paint::{LinearGradient, Paint, Pattern, RadialGradient, RasterImage},
+    render::{tessellation, vertex_buffer},
+    svg::{ClipPath, Dash, DrawType, Group, Mask, PathDescriptor, Svg},
+    Convert,
+};
+
+
+/// Version of the serialized layout, increased whenever it changes.
+const VERSION: u32 = 1;
+
+/// An [`Svg`] with its paths, styles, animations and tessellated mesh, in a form that can be
+/// serialized with any `serde` format. This caches parsed SVGs, sends them over the network or
+/// stores them in save files. Created with [`Svg::to_serialized`] and turned back into an [`Svg`]
+/// with [`Svg::from_serialized`].
+///
+/// Unlike baked `.svgmesh` files, the deserialized [`Svg`] keeps its paths, so runtime changes
+/// like [`SvgColor`](crate::prelude::SvgColor) and animations work as they do on a loaded file.
+/// The layout is only meant to be read by the same version of this crate.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SerializedSvg {
+    version: u32,
+    name: String,
+    size: Vec2,
+    /// x, y, width and height.
+    view_box: [f64; 4],
+    content_rect: Rect,
+    paths: Vec<SerializedPath>,
+    groups: Vec<SerializedGroup>,
+    clip_paths: Vec<SerializedClipPath>,
+    masks: Vec<SerializedMask>,
+    animations: Vec<Animation>,
+    transitions: HashMap<String, Transition>,
+    hidden: HashSet<String>,
+    skipped_features: Vec<SkippedFeature>,
+    /// Paths and labels of the assets.
+    dependencies: Vec<(PathBuf, Option<String>)>,
+    tolerance: Option<f32>,
+    time_sliced: bool,
+    /// `None` if the SVG wasn't tessellated yet, it is tessellated when it is deserialized.
+    mesh: Option<SerializedMesh>,
+}
+
+/// A [`PathDescriptor`] whose groups are indices into [`SerializedSvg::groups`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SerializedPath {
+    id: String,
+    segments: Vec<PathEvent>,
+    /// Translation, rotation and scale.
+    abs_transform: (Vec3, Quat, Vec3),
+    transform: Transform2D<f32>,
+    paint: SerializedPaint,
+    paint_variable: Option<String>,
+    draw_type: DrawType,
+    non_scaling_stroke: bool,
+    dash: Option<Dash>,
+    length: f32,
+    groups: Vec<usize>,
+}
+
+/// A [`Paint`] whose pattern tile is made of [`SerializedPath`]s.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum SerializedPaint {
+    Color(Color),
+    LinearGradient(LinearGradient),
+    RadialGradient(RadialGradient),
+    Pattern {
+        tile: Vec<SerializedPath>,
+        content_transform: Transform2D<f32>,
+        rect: Rect,
+        opacity: f32,
+        transform: Transform2D<f32>,
+    },
+    Image(RasterImage),
+}
+
+/// A [`Group`] whose clip path and mask are indices into [`SerializedSvg::clip_paths`] and
+/// [`SerializedSvg::masks`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SerializedGroup {
+    id: String,
+    transform: Transform2D<f32>,
+    parent_transform: Transform2D<f32>,
+    clip_path: Option<usize>,
+    mask: Option<usize>,
+    filters: Vec<Filter>,
+    opacity: f32,
+    blend_mode: BlendMode,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SerializedClipPath {
+    paths: Vec<SerializedPath>,
+    clip_path: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SerializedMask {
+    paths: Vec<SerializedPath>,
+    rect: Rect,
+    transform: Transform2D<f32>,
+    mask: Option<usize>,
+}
+
+/// The vertices and triangles of a tessellated mesh, like in the `.svgmesh` format.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SerializedMesh {
+    /// Positions of the vertices, with the y-axis of Bevy.
+    positions: Vec<[f32; 3]>,
+    /// Colors of the vertices in linear RGBA, one byte per channel.
+    colors: Vec<u32>,
+    indices: Vec<u32>,
+}
+
+/// Turns `svg` into a [`SerializedSvg`], `mesh` is its tessellated mesh if there is one.
+pub(crate) fn encode(svg: &Svg, mesh: Option<&Mesh>) -> SerializedSvg {
+    let mut encoder = Encoder::default();
+    let paths = encoder.paths(&svg.paths);
+    let mesh = mesh.map(|mesh| {
+        let (positions, colors, indices) = baked::mesh_data(mesh);
+        SerializedMesh { positions: positions.to_vec(), colors: colors.to_vec(), indices: indices.to_vec() }
+    });
+    let ViewBox { x, y, w, h } = svg.view_box;
+
+    SerializedSvg {
+        version: VERSION,
+        name: svg.name.clone(),
+        size: svg.size,
+        view_box: [x, y, w, h],
+        content_rect: svg.content_rect,
+        paths,
+        groups: encoder.groups,
+        clip_paths: encoder.clip_paths,
+        masks: encoder.masks,
+        animations: svg.animations.clone(),
+        transitions: svg.transitions.clone(),
+        hidden: svg.hidden.clone(),
+        skipped_features: svg.skipped_features.clone(),
+        dependencies: svg.dependencies.iter()
+            .map(|path| (path.path().to_owned(), path.label().map(ToOwned::to_owned)))
+            .collect(),
+        tolerance: svg.tolerance,
+        time_sliced: svg.time_sliced,
+        mesh,
+    }
+}
+
+/// Turns `serialized` back into an [`Svg`] and its mesh, which is tessellated if it wasn't
+/// serialized. Fails if it references groups, clip paths or masks that it doesn't contain.
+pub(crate) fn decode(serialized: SerializedSvg) -> Result<(Svg, Mesh), SvgLoadError> {
+    if serialized.version != VERSION {
+        return Err(SvgLoadError::InvalidSerializedSvg("unsupported version"));
+    }
+    let mut decoder = Decoder {
+        groups: serialized.groups.into_iter().map(Slot::Serialized).collect(),
+        clip_paths: serialized.clip_paths.into_iter().map(Slot::Serialized).collect(),
+        masks: serialized.masks.into_iter().map(Slot::Serialized).collect(),
+    };
+    let [x, y, w, h] = serialized.view_box;
+
+    let svg = Svg {
+        name: serialized.name,
+        size: serialized.size,
+        view_box: ViewBox { x, y, w, h },
+        content_rect: serialized.content_rect,
+        paths: decoder.paths(serialized.paths)?,
+        animations: serialized.animations,
+        transitions: serialized.transitions,
+        hidden: serialized.hidden,
+        skipped_features: serialized.skipped_features,
+        dependencies: serialized.dependencies.into_iter()
+            .map(|(path, label)| AssetPath::new(path, label))
+            .collect(),
+        tolerance: serialized.tolerance,
+        time_sliced: serialized.time_sliced,
+        texts: None,
+        modified_paths: Default::default(),
+        mesh: Default::default(),
+    };
+
+    let mesh = match serialized.mesh {
+        Some(SerializedMesh { positions, colors, indices }) => {
+            if positions.len() != colors.len() {
+                return Err(SvgLoadError::InvalidSerializedSvg("every vertex needs a position and a color"));
+            }
+            if indices.iter().any(|index| *index as usize >= positions.len()) {
+                return Err(SvgLoadError::InvalidSerializedSvg("index out of bounds"));
+            }
+            let uvs = positions.iter().map(|position| vertex_buffer::view_box_uv(*position, svg.size)).collect();
+            vertex_buffer::mesh(positions, colors, uvs, indices)
+        },
+        None => tessellation::generate_buffer(&svg, &Overrides::default(), &mut FillTessellator::new(), &mut StrokeTessellator::new())
+            .convert(),
+    };
+
+    Ok((svg, mesh))
+}
+
+/// Collects the groups, clip paths and masks of the paths into tables, each shared one only once.
+#[derive(Default)]
+struct Encoder {
+    groups: Vec<SerializedGroup>,
+    clip_paths: Vec<SerializedClipPath>,
+    masks: Vec<SerializedMask>,
+    group_indices: HashMap<*const Group, usize>,
+    clip_path_indices: HashMap<*const ClipPath, usize>,
+    mask_indices: HashMap<*const Mask, usize>,
+}
+
+impl Encoder {
+    fn paths(&mut self, paths: &[PathDescriptor]) -> Vec<SerializedPath> {
+        paths.iter().map(|path| self.path(path)).collect()
+    }
+
+    fn path(&mut self, path: &PathDescriptor) -> SerializedPath {
+        let paint = match path.paint {
+            Paint::Color(color) => SerializedPaint::Color(color),
+            Paint::LinearGradient(ref gradient) => SerializedPaint::LinearGradient(gradient.clone()),
+            Paint::RadialGradient(ref gradient) => SerializedPaint::RadialGradient(gradient.clone()),
+            Paint::Pattern(ref pattern) => SerializedPaint::Pattern {
+                tile: self.paths(&pattern.tile),
+                content_transform: pattern.content_transform,
+                rect: pattern.rect,
+                opacity: pattern.opacity,
+                transform: pattern.transform,
+            },
+            Paint::Image(ref image) => SerializedPaint::Image(image.clone()),
+        };
+        let Transform { translation, rotation, scale } = path.abs_transform;
+
+        SerializedPath {
+            id: path.id.clone(),
+            segments: path.segments.clone(),
+            abs_transform: (translation, rotation, scale),
+            transform: path.transform,
+            paint,
+            paint_variable: path.paint_variable.clone(),
+            draw_type: path.draw_type.clone(),
+            non_scaling_stroke: path.non_scaling_stroke,
+            dash: path.dash.clone(),
+            length: path.length,
+            groups: path.groups.iter().map(|group| self.group(group)).collect(),
+        }
+    }
+
+    fn group(&mut self, group: &Arc<Group>) -> usize {
+        if let Some(index) = self.group_indices.get(&Arc::as_ptr(group)) {
+            return *index;
+        }
+        let serialized = SerializedGroup {
+            id: group.id.clone(),
+            transform: group.transform,
+            parent_transform: group.parent_transform,
+            clip_path: group.clip_path.as_ref().map(|clip_path| self.clip_path(clip_path)),
+            mask: group.mask.as_ref().map(|mask| self.mask(mask)),
+            filters: group.filters.clone(),
+            opacity: group.opacity,
+            blend_mode: group.blend_mode,
+        };
+        self.groups.push(serialized);
+        self.group_indices.insert(Arc::as_ptr(group), self.groups.len() - 1);
+        self.groups.len() - 1
+    }
+
+    fn clip_path(&mut self, clip_path: &Arc<ClipPath>) -> usize {
+        if let Some(index) = self.clip_path_indices.get(&Arc::as_ptr(clip_path)) {
+            return *index;
+        }
+        let serialized = SerializedClipPath {
+            paths: self.paths(&clip_path.paths),
+            clip_path: clip_path.clip_path.as_ref().map(|clip_path| self.clip_path(clip_path)),
+        };
+        self.clip_paths.push(serialized);
+        self.clip_path_indices.insert(Arc::as_ptr(clip_path), self.clip_paths.len() - 1);
+        self.clip_paths.len() - 1
+    }
+
+    fn mask(&mut self, mask: &Arc<Mask>) -> usize {
+        if let Some(index) = self.mask_indices.get(&Arc::as_ptr(mask)) {
+            return *index;
+        }
+        let serialized = SerializedMask {
+            paths: self.paths(&mask.paths),
+            rect: mask.rect,
+            transform: mask.transform,
+            mask: mask.mask.as_ref().map(|mask| self.mask(mask)),
+        };
+        self.masks.push(serialized);
+        self.mask_indices.insert(Arc::as_ptr(mask), self.masks.len() - 1);
+        self.masks.len() - 1
+    }
+}
+
+/// An entry of a table, which is decoded when it is referenced for the first time.
+enum Slot<S, T> {
+    Serialized(S),
+    /// The entry is being decoded, another reference to it is a cycle.
+    Decoding,
+    Decoded(Arc<T>),
+}
+
+impl<S, T> Slot<S, T> {
+    /// Returns the entry at `index`, which is marked as being decoded if it wasn't decoded yet.
+    fn take(slots: &mut [Self], index: usize) -> Result<Self, SvgLoadError> {
+        let slot = slots.get_mut(index).ok_or(SvgLoadError::InvalidSerializedSvg("reference out of bounds"))?;
+        match std::mem::replace(slot, Slot::Decoding) {
+            Slot::Decoding => Err(SvgLoadError::InvalidSerializedSvg("cyclic reference")),
+            Slot::Decoded(value) => {
+                *slot = Slot::Decoded(Arc::clone(&value));
+                Ok(Slot::Decoded(value))
+            },
+            serialized => Ok(serialized),
+        }
+    }
+}
+
+/// Resolves the references of paths into the tables, each entry is decoded only once.
+struct Decoder {
+    groups: Vec<Slot<SerializedGroup, Group>>,
+    clip_paths: Vec<Slot<SerializedClipPath, ClipPath>>,
+    masks: Vec<Slot<SerializedMask, Mask>>,
+}
+
+impl Decoder {
+    fn paths(&mut self, paths: Vec<SerializedPath>) -> Result<Vec<PathDescriptor>, SvgLoadError> {
+        paths.into_iter().map(|path| self.path(path)).collect()
+    }
+
+    fn path(&mut self, path: SerializedPath) -> Result<PathDescriptor, SvgLoadError> {
+        let paint = match path.paint {
+            SerializedPaint::Color(color) => Paint::Color(color),
+            SerializedPaint::LinearGradient(gradient) => Paint::LinearGradient(gradient),
+            SerializedPaint::RadialGradient(gradient) => Paint::RadialGradient(gradient),
+            SerializedPaint::Pattern { tile, content_transform, rect, opacity, transform } => Paint::Pattern(Pattern {
+                tile: self.paths(tile)?,
+                content_transform,
+                rect,
+                opacity,
+                transform,
+            }),
+            SerializedPaint::Image(image) => Paint::Image(image),
+        };
+        let (translation, rotation, scale) = path.abs_transform;
+
+        Ok(PathDescriptor {
+            id: path.id,
+            length: path.length,
+            segments: path.segments,
+            abs_transform: Transform { translation, rotation, scale },
+            transform: path.transform,
+            paint,
+            paint_variable: path.paint_variable,
+            draw_type: path.draw_type,
+            non_scaling_stroke: path.non_scaling_stroke,
+            dash: path.dash,
+            groups: path.groups.into_iter().map(|index| self.group(index)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn group(&mut self, index: usize) -> Result<Arc<Group>, SvgLoadError> {
+        let serialized = match Slot::take(&mut self.groups, index)? {
+            Slot::Serialized(serialized) => serialized,
+            Slot::Decoded(group) => return Ok(group),
+            Slot::Decoding => unreachable!(),
+        };
+        let group = Arc::new(Group {
+            id: serialized.id,
+            transform: serialized.transform,
+            parent_transform: serialized.parent_transform,
+            clip_path: serialized.clip_path.map(|index| self.clip_path(index)).transpose()?,
+            mask: serialized.mask.map(|index| self.mask(index)).transpose()?,
+            filters: serialized.filters,
+            opacity: serialized.opacity,
+            blend_mode: serialized.blend_mode,
+        });
+        self.groups[index] = Slot::Decoded(Arc::clone(&group));
+        Ok(group)
+    }
+
+    fn clip_path(&mut self, index: usize) -> Result<Arc<ClipPath>, SvgLoadError> {
+        let serialized = match Slot::take(&mut self.clip_paths, index)? {
+            Slot::Serialized(serialized) => serialized,
+            Slot::Decoded(clip_path) => return Ok(clip_path),
+            Slot::Decoding => unreachable!(),
+        };
+        let clip_path = Arc::new(ClipPath {
+            paths: self.paths(serialized.paths)?,
+            clip_path: serialized.clip_path.map(|index| self.clip_path(index)).transpose()?,
+        });
+        self.clip_paths[index] = Slot::Decoded(Arc::clone(&clip_path));
+        Ok(clip_path)
+    }
+
+    fn mask(&mut self, index: usize) -> Result<Arc<Mask>, SvgLoadError> {
+        let serialized = match Slot::take(&mut self.masks, index)? {
+            Slot::Serialized(serialized) => serialized,
+            Slot::Decoded(mask) => return Ok(mask),
+            Slot::Decoding => unreachable!(),
+        };
+        let mask = Arc::new(Mask {
+            paths: self.paths(serialized.paths)?,
+            rect: serialized.rect,
+            transform: serialized.transform,
+            mask: serialized.mask.map(|index| self.mask(index)).transpose()?,
+        });
+        self.masks[index] = Slot::Decoded(Arc::clone(&mask));
+        Ok(mask)
+    }
+}
@@ -0,0 +1,1305 @@
+//! Reads the raw SVG document, for everything that `usvg` doesn't keep while parsing.
+//!
+//! `usvg` only preserves the IDs of the elements, so every element with an interesting property
+//! gets an ID, which is then used to look up the properties while collecting the paths.
+//! The rules of style sheets and custom properties are resolved here as well, so these properties
+//! can also come from CSS. Animations are dropped by `usvg` too, so SMIL animations and CSS
+//! animations of `@keyframes` rules are read from here.
+
+use std::{borrow::Cow, collections::{HashMap, HashSet}, io::Read, rc::Rc};
+
+use bevy::log::warn;
+
+use crate::{animation::{self, Animation}, css_animation::{self, CssAnimation, Transition}, diagnostics::SkippedFeature, filter::BlendMode};
+
+
+/// Prefix of the IDs that get generated for elements without one.
+pub(crate) const GENERATED_ID_PREFIX: &str = "bevy_svg_";
+
+/// Namespace of the `xlink:href` attribute.
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Name under which paints with `currentColor` are drawn, like the colors of custom properties,
+/// so they can be recolored with [`SvgColor`](crate::overrides::SvgColor).
+pub(crate) const CURRENT_COLOR_VARIABLE: &str = "--bevy_svg_current_color";
+
+/// Prefix of custom properties in style sheets, see [`escape_custom_properties`].
+const CUSTOM_PROPERTY_ESCAPE: char = '_';
+
+/// Elements that are turned into paths by `usvg`, and keep their ID while doing so.
+const SHAPES: &[&str] = &["path", "rect", "circle", "ellipse", "line", "polyline", "polygon"];
+
+/// Elements that draw something where they are, as opposed to definitions like gradients or clip
+/// paths, which are only drawn where they are referenced.
+const DRAWN_ELEMENTS: &[&str] = &[
+    "path", "rect", "circle", "ellipse", "line", "polyline", "polygon", "g", "a", "switch", "svg", "use", "image",
+    "text", "foreignObject",
+];
+
+/// Shapes that can have markers.
+const MARKABLE_SHAPES: &[&str] = &["path", "line", "polyline", "polygon"];
+
+/// Properties that place markers on a shape, in the order of [`Inherited::markers`].
+const MARKER_PROPERTIES: [&str; 3] = ["marker-start", "marker-mid", "marker-end"];
+
+/// Attributes of the children of a `switch` that decide whether they are drawn.
+const CONDITIONAL_ATTRIBUTES: [&str; 3] = ["systemLanguage", "requiredFeatures", "requiredExtensions"];
+
+/// Features of SVG 1.1 that can be drawn, for `requiredFeatures`.
+const SUPPORTED_FEATURES: &[&str] = &[
+    "SVG", "SVG-static", "CoreAttribute", "Structure", "BasicStructure", "ContainerAttribute",
+    "ConditionalProcessing", "Image", "Style", "ViewportAttribute", "Shape", "Text", "BasicText",
+    "PaintAttribute", "BasicPaintAttribute", "OpacityAttribute", "GraphicsAttribute",
+    "BasicGraphicsAttribute", "Marker", "Gradient", "Pattern", "Clip", "BasicClip", "Mask",
+    "BasicFilter", "XlinkAttribute", "Hyperlinking",
+];
+
+/// Properties of the raw document that `usvg` doesn't know about, keyed by element ID.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Source {
+    /// Value of `mix-blend-mode`, only for elements that don't use `normal`.
+    pub(crate) blend_modes: HashMap<String, BlendMode>,
+    /// The custom properties that the fill and stroke of shapes come from, only for shapes that
+    /// use at least one of them.
+    pub(crate) paint_variables: HashMap<String, PaintVariables>,
+    /// All CSS and SMIL animations of the document. SMIL animations come last, so they take
+    /// precedence over CSS animations of the same property.
+    pub(crate) animations: Vec<Animation>,
+    /// Value of `paint-order`, only for shapes and texts that don't use `normal`.
+    pub(crate) paint_orders: HashMap<String, PaintOrder>,
+    /// The `transition`s of the colors of custom properties, keyed by the name of the custom
+    /// property that the `fill` or `stroke` of an element with a transition comes from.
+    pub(crate) transitions: HashMap<String, Transition>,
+    /// IDs of the elements with `display: none`, which are not drawn unless they are shown at runtime.
+    pub(crate) hidden: HashSet<String>,
+    /// IDs of the shapes with `vector-effect="non-scaling-stroke"`.
+    pub(crate) non_scaling_strokes: HashSet<String>,
+    /// Content that can't be drawn and is removed from the document, in document order.
+    pub(crate) skipped_features: Vec<SkippedFeature>,
+    /// The `symbol`s with an ID, in document order.
+    pub(crate) symbols: Vec<Symbol>,
+}
+
+/// A `symbol` of the document, which gets a hidden `use` element so it can be loaded on its own,
+/// like a sprite of a sprite sheet.
+#[derive(Clone, Debug)]
+pub(crate) struct Symbol {
+    /// The ID of the `symbol`.
+    pub(crate) id: String,
+    /// The ID of the `use` element that places the symbol at the origin of the document.
+    pub(crate) use_id: String,
+    /// The size of the `viewBox` of the symbol, which is the frame of the sprite.
+    pub(crate) size: Option<(f64, f64)>,
+}
+
+/// Names of the custom properties, like `--accent`, that the fill and stroke of a shape come from.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PaintVariables {
+    pub(crate) fill: Option<String>,
+    pub(crate) stroke: Option<String>,
+}
+
+/// A part of a shape that is painted on its own, see [`PaintOrder`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PaintLayer {
+    Fill,
+    Stroke,
+    Markers,
+}
+
+/// The order in which the fill, the stroke and the markers of a shape are painted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct PaintOrder {
+    pub(crate) layers: [PaintLayer; 3],
+    /// Whether the shape has markers, which `usvg` puts into a group right after the shape.
+    pub(crate) markers: bool,
+}
+
+impl Default for PaintOrder {
+    fn default() -> Self {
+        Self { layers: [PaintLayer::Fill, PaintLayer::Stroke, PaintLayer::Markers], markers: false }
+    }
+}
+
+/// Values that an element passes on to its children.
+#[derive(Clone, Default)]
+struct Inherited {
+    /// All custom properties that are defined for the element, resolved to their values.
+    variables: Rc<HashMap<String, String>>,
+    paint_variables: PaintVariables,
+    /// Whether the element or one of its ancestors, other than the root, sets its own `color`.
+    /// `currentColor` then no longer comes from the `color` of the root.
+    own_color: bool,
+    /// The layers of `paint-order`, `None` for `normal`.
+    paint_order: Option<[PaintLayer; 3]>,
+    /// Whether `marker-start`, `marker-mid` and `marker-end` refer to a marker.
+    markers: [bool; 3],
+}
+
+impl Source {
+    /// Returns the blend mode of the element with the given `id`.
+    pub(crate) fn blend_mode(&self, id: &str) -> BlendMode {
+        self.blend_modes.get(id).copied().unwrap_or(BlendMode::Normal)
+    }
+
+    /// Returns true if the element with the given `id` is the target of an animation.
+    pub(crate) fn is_animated(&self, id: &str) -> bool {
+        self.animations.iter().any(|animation| animation.target == id)
+    }
+
+    /// Returns the paint order of the shape or text with the given `id`.
+    pub(crate) fn paint_order(&self, id: &str) -> PaintOrder {
+        self.paint_orders.get(id).copied().unwrap_or_default()
+    }
+
+    /// Returns true if the stroke of the shape with the given `id` keeps its width when it is scaled.
+    pub(crate) fn is_non_scaling_stroke(&self, id: &str) -> bool {
+        self.non_scaling_strokes.contains(id)
+    }
+
+    /// Returns the custom properties that the paint of the shape with the given `id` comes from.
+    pub(crate) fn paint_variables(&self, id: &str) -> PaintVariables {
+        self.paint_variables.get(id).cloned().unwrap_or_default()
+    }
+}
+
+/// Decompresses `data` if it is gzip compressed and returns it as text.
+pub(crate) fn decode(data: &[u8]) -> Result<String, usvg::Error> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::with_capacity(data.len() * 2);
+        flate2::read::GzDecoder::new(data).read_to_end(&mut decoded).map_err(|_| usvg::Error::MalformedGZip)?;
+        String::from_utf8(decoded).map_err(|_| usvg::Error::NotAnUtf8Str)
+    } else {
+        std::str::from_utf8(data).map(str::to_owned).map_err(|_| usvg::Error::NotAnUtf8Str)
+    }
+}
+
+/// Returns the references to all external style sheets of the document `text`, from
+/// `<?xml-stylesheet?>` processing instructions and `<link rel="stylesheet">` elements.
+pub(crate) fn style_sheet_links(text: &str) -> Vec<String> {
+    let doc = match parse(text) {
+        Ok(doc) => doc,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut links = Vec::new();
+    for node in doc.descendants() {
+        let (href, kind) = if let Some(pi) = node.pi() {
+            if pi.target != "xml-stylesheet" {
+                continue;
+            }
+            let value = pi.value.unwrap_or_default();
+            (pseudo_attribute(value, "href"), pseudo_attribute(value, "type"))
+        } else if node.has_tag_name("link") && node.attribute("rel").map_or(false, |rel| rel.split_whitespace().any(|rel| rel == "stylesheet")) {
+            (node.attribute("href"), node.attribute("type"))
+        } else {
+            continue;
+        };
+
+        match href {
+            Some(href) if matches!(kind, None | Some("text/css")) => links.push(href.to_owned()),
+            _ => {},
+        }
+    }
+    links
+}
+
+/// Returns the files that elements of the document `text` are referenced from, like `icons.svg`
+/// for `<use href="icons.svg#gear"/>`.
+pub(crate) fn external_references(text: &str) -> Vec<String> {
+    let doc = match parse(text) {
+        Ok(doc) => doc,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files: Vec<String> = Vec::new();
+    for node in doc.descendants().filter(|node| node.is_element() && !node.has_tag_name("image")) {
+        if let Some((file, _)) = href_attribute(&node).and_then(|href| external_reference(href.value())) {
+            if !files.iter().any(|known| known == file) {
+                files.push(file.to_owned());
+            }
+        }
+    }
+    files
+}
+
+/// Where the content of a `text` element is in a document.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TextContent {
+    /// Range of the content, or of the `/>` at the end of an element without content.
+    range: std::ops::Range<usize>,
+    /// Tag name of an element without content, which needs a closing tag once it has content.
+    empty_element: Option<String>,
+}
+
+/// Returns where the content of every `text` element with an ID is in the document `text`, keyed
+/// by the ID.
+pub(crate) fn text_contents(text: &str) -> HashMap<String, TextContent> {
+    let doc = match parse(text) {
+        Ok(doc) => doc,
+        Err(_) => return HashMap::new(),
+    };
+
+    doc.descendants()
+        .filter(|node| node.has_tag_name("text"))
+        .filter_map(|node| {
+            let id = node.attribute("id")?;
+            let range = node.range();
+            let content = match (node.first_child(), node.last_child()) {
+                (Some(first), Some(last)) => TextContent { range: first.range().start..last.range().end, empty_element: None },
+                _ if text[range.clone()].ends_with("/>") => {
+                    // The name is taken from the text, so a namespace prefix like `svg:` is kept.
+                    let name = text[range.start + 1..range.end - 2]
+                        .split(|c: char| c.is_whitespace() || c == '/')
+                        .next()
+                        .unwrap_or_default();
+                    TextContent { range: range.end - 2..range.end, empty_element: Some(name.to_owned()) }
+                },
+                _ => {
+                    let end = range.start + text[range].rfind("</")?;
+                    TextContent { range: end..end, empty_element: None }
+                },
+            };
+            Some((id.to_owned(), content))
+        })
+        .collect()
+}
+
+/// Replaces the content of the `text` element at `content` of the document `text` with the plain
+/// text `replacement`.
+pub(crate) fn replace_text_content(text: &str, content: &TextContent, replacement: &str) -> String {
+    let replacement = match content.empty_element {
+        Some(ref name) => format!(">{}</{}>", escape(replacement), name),
+        None => escape(replacement),
+    };
+    apply_edits(text.to_owned(), vec![(content.range.clone(), replacement)])
+}
+
+/// Returns the document `text` with only the `text` element with the given `id` left to draw,
+/// along with its ancestors and everything that is referenced. Laying it out is the same as
+/// laying out the element in the whole document, without the cost of parsing the rest of it.
+/// `None` if there is no such element.
+pub(crate) fn text_document(text: &str, id: &str) -> Option<String> {
+    let doc = parse(text).ok()?;
+    let target = doc.descendants().find(|node| node.has_tag_name("text") && node.attribute("id") == Some(id))?;
+    let referenced = referenced_ids(&doc);
+    let mut edits = Vec::new();
+    remove_unrelated(doc.root_element(), target.id(), &referenced, &mut edits);
+    drop(doc);
+    Some(apply_edits(text.to_owned(), edits))
+}
+
+/// Collects the ranges of the drawn elements inside of `node` that neither contain the element
+/// `target` nor an element that is referenced into `edits`.
+fn remove_unrelated(node: roxmltree::Node<'_, '_>, target: roxmltree::NodeId, referenced: &HashSet<&str>, edits: &mut Vec<(std::ops::Range<usize>, String)>) {
+    for child in node.children().filter(roxmltree::Node::is_element) {
+        // Definitions are kept as a whole, and so is the content of the target.
+        if child.id() == target || !DRAWN_ELEMENTS.contains(&child.tag_name().name()) {
+            continue;
+        }
+        let needed = child.descendants().any(|descendant| {
+            descendant.id() == target || descendant.attribute("id").map_or(false, |id| referenced.contains(id))
+        });
+        match needed {
+            true => remove_unrelated(child, target, referenced, edits),
+            false => edits.push((child.range(), String::new())),
+        }
+    }
+}
+
+/// Returns the IDs of all elements that are referenced in `doc`, by an `href` or by `url(#id)` in
+/// an attribute or a `<style>` element.
+fn referenced_ids<'a>(doc: &'a roxmltree::Document<'_>) -> HashSet<&'a str> {
+    let mut referenced = HashSet::new();
+    for node in doc.descendants() {
+        if let Some(css) = node.text().filter(|_| node.has_tag_name("style")) {
+            url_references(css, &mut referenced);
+        }
+        for attribute in node.attributes() {
+            match attribute.value().trim().strip_prefix('#').filter(|_| is_href(attribute)) {
+                Some(reference) => { referenced.insert(reference); },
+                None => url_references(attribute.value(), &mut referenced),
+            }
+        }
+    }
+    referenced
+}
+
+/// Adds the IDs of all `url(#id)` references in `value` to `referenced`.
+fn url_references<'a>(mut value: &'a str, referenced: &mut HashSet<&'a str>) {
+    while let Some(position) = value.find("url(#") {
+        value = &value[position + "url(#".len()..];
+        let end = value.find(')').unwrap_or(value.len());
+        referenced.insert(value[..end].trim());
+        value = &value[end..];
+    }
+}
+
+/// Copies the elements that the document `text` references from other files into its own
+/// `defs`, and points the references at the copies. `documents` holds the text of the other
+/// files, keyed by the file as it is written in the reference.
+///
+/// The copies take along everything they reference themselves, with their IDs prefixed so they
+/// can't collide with the IDs of the document. References of the other files to yet other files
+/// are not followed.
+pub(crate) fn inline_external_references(text: String, documents: &HashMap<String, String>) -> String {
+    let doc = match parse(&text) {
+        Ok(doc) => doc,
+        Err(_) => return text,
+    };
+    // The parsed documents with the prefix of their IDs, keyed by their file.
+    let mut parsed = HashMap::new();
+    for (i, (file, document)) in documents.iter().enumerate() {
+        match parse(document) {
+            Ok(external) => { parsed.insert(file.as_str(), (external, document.as_str(), format!("{}external_{}_", GENERATED_ID_PREFIX, i))); },
+            Err(err) => warn!("Could not parse `{}`: {}, skipping its references.", file, err),
+        }
+    }
+
+    let mut edits = Vec::new();
+    let mut copies = String::new();
+    let mut copied: HashMap<&str, HashSet<String>> = HashMap::new();
+    for node in doc.descendants().filter(|node| node.is_element() && !node.has_tag_name("image")) {
+        let href = match href_attribute(&node) {
+            Some(href) => href,
+            None => continue,
+        };
+        let (file, id) = match external_reference(href.value()) {
+            Some(reference) => reference,
+            None => continue,
+        };
+        let (external, external_text, prefix) = match parsed.get(file) {
+            Some(external) => external,
+            None => continue,
+        };
+        if !external.descendants().any(|node| node.attribute("id") == Some(id)) {
+            warn!("Element `{}` does not exist in `{}`, skipping the reference.", id, file);
+            continue;
+        }
+        copy_external(external, external_text, id, prefix, copied.entry(file).or_default(), &mut copies);
+        let attribute = &text[href.range()];
+        edits.push((href.range(), attribute.replacen(href.value(), &format!("#{}{}", prefix, id), 1)));
+    }
+
+    if !copies.is_empty() {
+        let root = doc.root_element();
+        if let Some(end) = text[..root.range().end].rfind("</") {
+            edits.push((end..end, format!("<defs>{}</defs>", copies)));
+        }
+    }
+    drop(doc);
+
+    apply_edits(text, edits)
+}
+
+/// Copies the element with the given `id` of the `external` document, and everything that it
+/// references, into `copies`. All IDs of the copies get the `prefix`, the IDs that are already
+/// in `copied` are skipped.
+fn copy_external(external: &roxmltree::Document<'_>, text: &str, id: &str, prefix: &str, copied: &mut HashSet<String>, copies: &mut String) {
+    let mut pending = vec![id.to_owned()];
+    while let Some(id) = pending.pop() {
+        if copied.contains(&id) {
+            continue;
+        }
+        let element = match external.descendants().find(|node| node.attribute("id") == Some(id.as_str())) {
+            Some(element) => element,
+            None => continue,
+        };
+        // Everything inside of the element is copied along with it.
+        copied.extend(element.descendants().filter_map(|node| node.attribute("id")).map(str::to_owned));
+
+        let start = element.range().start;
+        let mut edits = Vec::new();
+        for attribute in element.descendants().flat_map(|node| node.attributes()) {
+            let value = attribute.value();
+            let renamed = if attribute.name() == "id" {
+                format!("{}{}", prefix, value)
+            } else if let Some(reference) = value.trim().strip_prefix('#').filter(|_| is_href(attribute)) {
+                pending.push(reference.to_owned());
+                format!("#{}{}", prefix, reference)
+            } else if value.contains("url(#") {
+                let mut renamed = String::with_capacity(value.len());
+                let mut rest = value;
+                while let Some(position) = rest.find("url(#") {
+                    let reference_start = position + "url(#".len();
+                    let reference_end = rest[reference_start..].find(')').map_or(rest.len(), |end| reference_start + end);
+                    pending.push(rest[reference_start..reference_end].trim().to_owned());
+                    renamed.push_str(&rest[..reference_start]);
+                    renamed.push_str(prefix);
+                    rest = &rest[reference_start..];
+                }
+                renamed.push_str(rest);
+                renamed
+            } else {
+                continue;
+            };
+            // The name is taken from the text, so a namespace prefix like `xlink:` is kept.
+            let range = attribute.range();
+            let name = text[range.clone()].split('=').next().unwrap_or_default().trim_end();
+            edits.push((range.start - start..range.end - start, format!("{}=\"{}\"", name, escape(&renamed))));
+        }
+        copies.push_str(&apply_edits(text[element.range()].to_owned(), edits));
+    }
+}
+
+/// Returns the `href` attribute of `node`, with or without the `xlink` namespace.
+fn href_attribute<'a, 'input>(node: &roxmltree::Node<'a, 'input>) -> Option<&'a roxmltree::Attribute<'input>> {
+    node.attributes().iter().find(|attribute| is_href(attribute))
+}
+
+/// Returns true if `attribute` is `href`, with or without the `xlink` namespace.
+fn is_href(attribute: &roxmltree::Attribute<'_>) -> bool {
+    attribute.name() == "href" && matches!(attribute.namespace(), None | Some(XLINK_NAMESPACE))
+}
+
+/// Splits a reference to an element of another file, like `icons.svg#gear`, into the file and
+/// the ID of the element.
+fn external_reference(href: &str) -> Option<(&str, &str)> {
+    let (file, id) = href.trim().split_once('#')?;
+    (!file.is_empty() && !id.is_empty()).then(|| (file, id))
+}
+
+/// Collects the [`Source`] of the document `text`, and returns the text with an ID added to
+/// every element that needs one.
+///
+/// The rules of the `external` style sheets and all `<style>` elements are resolved here as
+/// well, following the CSS cascade, and written into the `style` attribute of the elements they
+/// apply to. Documents that can't be parsed are returned as they are, `usvg` will report the error.
+/// Physical lengths, like `mm`, are converted into pixels with `dpi`. The branches of `switch`
+/// elements are chosen for the preferred `languages`.
+pub(crate) fn preprocess(text: String, external: &[String], dpi: f32, languages: &[String]) -> (String, Source) {
+    let doc = match parse(&text) {
+        Ok(doc) => doc,
+        Err(_) => return (text, Source::default()),
+    };
+
+    let style_sheet_texts = style_sheet_texts(&doc, external);
+    let style_sheet = style_sheet(&style_sheet_texts);
+    let keyframes = css_animation::keyframes(&style_sheet_texts);
+    let mut source = Source::default();
+    let mut edits = Vec::new();
+    let mut generated_ids = 0;
+    // Whether a child of a `switch` is the branch that gets drawn, keyed by its node ID.
+    let branches = switch_branches(&doc, languages);
+    // Inherited values of every element, keyed by its node ID.
+    let mut inherited: HashMap<usize, Inherited> = HashMap::new();
+    // IDs of the elements that needed one, keyed by their node ID.
+    let mut ids: HashMap<usize, String> = HashMap::new();
+    // The attributes that are animated on each element, keyed by its node ID.
+    let mut animated: HashMap<usize, Vec<&str>> = HashMap::new();
+    for node in doc.descendants().filter(animation::is_animation) {
+        if let Some(target) = animation_target(&node) {
+            animated.entry(target.id().get_usize()).or_default().push(node.attribute("attributeName").unwrap_or_default());
+        }
+    }
+    for node in doc.descendants().filter(roxmltree::Node::is_element) {
+        if node.ancestors().skip(1).any(|ancestor| ancestor.has_tag_name("foreignObject")) {
+            continue;
+        }
+        if node.has_tag_name("foreignObject") {
+            // The content may not even be SVG, so it is removed entirely. A branch of a `switch`
+            // that isn't drawn anyway is not worth a warning.
+            edits.push((node.range(), String::new()));
+            if branches.get(&node.id().get_usize()) != Some(&false) {
+                warn!("`foreignObject` is not supported, skipping it.");
+                source.skipped_features.push(SkippedFeature {
+                    feature: node.tag_name().name().to_owned(),
+                    element_id: node.attribute("id").map(str::to_owned),
+                });
+            }
+            continue;
+        }
+        let (style, from_style_sheet) = cascade(&node, &style_sheet);
+        let mut style: Vec<(&str, Cow<'_, str>)> = style.into_iter().map(|(name, value)| (name, Cow::Borrowed(value))).collect();
+        let mut rewrite = from_style_sheet;
+        let parent = node.parent_element()
+            .and_then(|parent| inherited.get(&parent.id().get_usize()))
+            .cloned()
+            .unwrap_or_default();
+
+        // Custom properties are inherited, the declarations of the element override those of its parent.
+        let mut variables = parent.variables;
+        for (name, value) in style.iter().filter(|(name, _)| name.starts_with("--")) {
+            if let Some(value) = substitute(value, &variables) {
+                Rc::make_mut(&mut variables).insert((*name).to_owned(), value);
+            }
+        }
+        style.retain(|(name, _)| !name.starts_with("--"));
+        // `var()` in presentation attributes is resolved the same way as in the `style` attribute.
+        for attribute in node.attributes() {
+            if attribute.name() != "style" && attribute.value().contains("var(") && !style.iter().any(|(name, _)| *name == attribute.name()) {
+                style.push((attribute.name(), Cow::Borrowed(attribute.value())));
+            }
+        }
+
+        let is_shape = SHAPES.contains(&node.tag_name().name());
+        let css_animations = css_animation::animations_of(&style, &keyframes);
+        let mut animated_attributes = animated.get(&node.id().get_usize()).cloned().unwrap_or_default();
+        animated_attributes.extend(css_animations.iter().flat_map(CssAnimation::properties));
+        // Animated colors of shapes are drawn like colors of custom properties, which can be changed at runtime.
+        let declared = |name: &str| match is_shape && animated_attributes.contains(&name) {
+            true => Some(format!("var({})", animated_color_variable(&node, name))),
+            false => property(&node, &style, name).map(str::to_owned),
+        };
+        let own_color = parent.own_color || (node.parent_element().is_some() && property(&node, &style, "color").is_some());
+        // Like `usvg`, `currentColor` refers to the `color` of the element that declares the paint.
+        let paint_variable = |value: String| match value.trim().eq_ignore_ascii_case("currentColor") {
+            true => (!own_color).then(|| CURRENT_COLOR_VARIABLE.to_owned()),
+            false => variable_name(&value),
+        };
+        let paint_variables = PaintVariables {
+            fill: declared("fill").map_or(parent.paint_variables.fill, paint_variable),
+            stroke: declared("stroke").map_or(parent.paint_variables.stroke, paint_variable),
+        };
+        for (_, value) in style.iter_mut().filter(|(_, value)| value.contains("var(")) {
+            // A reference to an undefined variable makes the declaration invalid, which falls
+            // back to the inherited value.
+            *value = Cow::Owned(substitute(value, &variables).unwrap_or_else(|| "inherit".to_owned()));
+            rewrite = true;
+        }
+
+        let transitions = css_animation::transitions_of(&style);
+        for (name, variable) in [("fill", &paint_variables.fill), ("stroke", &paint_variables.stroke)] {
+            if let (Some(variable), Some(transition)) = (variable, transitions.get(name).or_else(|| transitions.get("all"))) {
+                source.transitions.entry(variable.clone()).or_insert(*transition);
+            }
+        }
+
+        // Named elements with `display: none` are kept, so they can be shown at runtime with
+        // `SvgNodeVisibility`. They are left out while tessellating instead.
+        let hidden = node.attribute("id").filter(|_| node.parent_element().is_some() && property(&node, &style, "display") == Some("none"));
+        if let Some(id) = hidden {
+            if style.iter().any(|(name, _)| *name == "display") {
+                style.retain(|(name, _)| *name != "display");
+                rewrite = true;
+            }
+            if let Some(display) = node.attribute_node("display") {
+                edits.push((display.range(), String::new()));
+            }
+            source.hidden.insert(id.to_owned());
+        }
+
+        // Right behind the tag name, which directly follows the `<`.
+        let start = node.range().start + 1;
+        let tag_end = start + text[start..].find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(0);
+        let mut inserted = String::new();
+
+        // `usvg` draws the first child of a `switch` without conditions, every other child gets
+        // an extension that isn't supported.
+        match branches.get(&node.id().get_usize()) {
+            Some(true) => {
+                let conditions = node.attributes().filter(|attribute| CONDITIONAL_ATTRIBUTES.contains(&attribute.name()));
+                edits.extend(conditions.map(|attribute| (attribute.range(), String::new())));
+            },
+            Some(false) if !node.has_attribute("requiredExtensions") => inserted.push_str(" requiredExtensions=\"bevy_svg\""),
+            _ => {},
+        }
+
+        if rewrite {
+            let declarations: Vec<String> = style.iter().map(|(name, value)| format!("{}:{}", name, value)).collect();
+            let attribute = format!("style=\"{}\"", escape(&declarations.join(";")));
+            match node.attribute_node("style") {
+                Some(existing) => edits.push((existing.range(), attribute)),
+                None => inserted.push_str(&format!(" {}", attribute)),
+            }
+        }
+
+        let blend_mode = property(&node, &style, "mix-blend-mode").map_or(BlendMode::Normal, parse_blend_mode);
+        let has_paint_variables = is_shape && (paint_variables.fill.is_some() || paint_variables.stroke.is_some());
+        let paint_order = match property(&node, &style, "paint-order") {
+            Some(value) => parse_paint_order(value),
+            None => parent.paint_order,
+        };
+        let mut markers = parent.markers;
+        for (marker, name) in markers.iter_mut().zip(MARKER_PROPERTIES) {
+            if let Some(value) = property(&node, &style, name) {
+                *marker = value != "none";
+            }
+        }
+        let painted = is_shape || node.has_tag_name("text");
+        let non_scaling_stroke = is_shape && property(&node, &style, "vector-effect") == Some("non-scaling-stroke");
+        if blend_mode != BlendMode::Normal || has_paint_variables || !animated_attributes.is_empty() || (painted && paint_order.is_some()) || non_scaling_stroke {
+            let id = match node.attribute("id") {
+                Some(id) => id.to_owned(),
+                None => {
+                    generated_ids += 1;
+                    let id = format!("{}{}", GENERATED_ID_PREFIX, generated_ids);
+                    inserted.push_str(&format!(" id=\"{}\"", id));
+                    id
+                },
+            };
+            if blend_mode != BlendMode::Normal {
+                source.blend_modes.insert(id.clone(), blend_mode);
+            }
+            if has_paint_variables {
+                source.paint_variables.insert(id.clone(), paint_variables.clone());
+            }
+            if non_scaling_stroke {
+                source.non_scaling_strokes.insert(id.clone());
+            }
+            if let (true, Some(layers)) = (painted, paint_order) {
+                let markers = MARKABLE_SHAPES.contains(&node.tag_name().name()) && markers.contains(&true);
+                source.paint_orders.insert(id.clone(), PaintOrder { layers, markers });
+            }
+            let color_variable = |name: &str| is_shape.then(|| animated_color_variable(&node, name));
+            for css_animation in &css_animations {
+                let animations = css_animation::animations(&node, css_animation, &id, |name| property(&node, &style, name), color_variable, dpi);
+                source.animations.extend(animations);
+            }
+            ids.insert(node.id().get_usize(), id);
+        }
+
+        if !inserted.is_empty() {
+            edits.push((tag_end..tag_end, inserted));
+        }
+        inherited.insert(node.id().get_usize(), Inherited { variables, paint_variables, own_color, paint_order, markers });
+    }
+
+    // Every symbol is placed once, hidden, so that `usvg` keeps its content.
+    let mut uses = String::new();
+    for symbol in doc.descendants().filter(|node| node.has_tag_name("symbol")) {
+        let id = match symbol.attribute("id") {
+            Some(id) if !symbol.ancestors().any(|ancestor| ancestor.has_tag_name("foreignObject")) => id,
+            _ => continue,
+        };
+        let size = symbol.attribute("viewBox")
+            .map(|view_box| view_box.split(|c: char| c.is_whitespace() || c == ',').filter(|s| !s.is_empty()).filter_map(|s| s.parse::<f64>().ok()).collect::<Vec<_>>())
+            .and_then(|view_box| match view_box[..] {
+                [_, _, width, height] if width > 0.0 && height > 0.0 => Some((width, height)),
+                _ => None,
+            });
+        let use_id = format!("{}symbol_{}", GENERATED_ID_PREFIX, id);
+        let dimensions = size.map_or_else(String::new, |(width, height)| format!(" width=\"{}\" height=\"{}\"", width, height));
+        uses.push_str(&format!("<use id=\"{}\" xmlns:xlink=\"{}\" xlink:href=\"#{}\"{}/>", use_id, XLINK_NAMESPACE, escape(id), dimensions));
+        source.hidden.insert(use_id.clone());
+        source.symbols.push(Symbol { id: id.to_owned(), use_id, size });
+    }
+    if !uses.is_empty() {
+        if let Some(end) = text[..doc.root_element().range().end].rfind("</") {
+            edits.push((end..end, uses));
+        }
+    }
+
+    // All targets of SMIL animations have an ID by now, even those that come after their animations.
+    for node in doc.descendants().filter(animation::is_animation) {
+        let target = match animation_target(&node) {
+            Some(target) => target,
+            None => continue,
+        };
+        let id = match ids.get(&target.id().get_usize()) {
+            Some(id) => id.clone(),
+            None => continue,
+        };
+        let is_shape = SHAPES.contains(&target.tag_name().name());
+        let color_variable = |name: &str| is_shape.then(|| animated_color_variable(&target, name));
+        match animation::parse(&node, &target, id.clone(), color_variable) {
+            Some(animation) => source.animations.push(animation),
+            None => source.skipped_features.push(SkippedFeature {
+                feature: node.tag_name().name().to_owned(),
+                element_id: Some(id).filter(|id| !id.starts_with(GENERATED_ID_PREFIX)),
+            }),
+        }
+    }
+    drop(doc);
+
+    let text = apply_edits(text, edits);
+    (reverse_start_markers(text), source)
+}
+
+/// `usvg` doesn't know `orient="auto-start-reverse"`, which diagramming tools use for arrows that
+/// point both ways. Each of these markers gets a copy for `marker-start`, with its content
+/// turned around the reference point, and the marker itself uses `orient="auto"`.
+fn reverse_start_markers(text: String) -> String {
+    let doc = match parse(&text) {
+        Ok(doc) => doc,
+        Err(_) => return text,
+    };
+
+    let mut edits = Vec::new();
+    let mut reversed = HashMap::new();
+    for marker in doc.descendants().filter(|node| node.has_tag_name("marker") && node.attribute("orient") == Some("auto-start-reverse")) {
+        if let Some(orient) = marker.attribute_node("orient") {
+            edits.push((orient.range(), "orient=\"auto\"".to_owned()));
+        }
+        let (id, first, last) = match (marker.attribute("id"), marker.first_child(), marker.last_child()) {
+            (Some(id), Some(first), Some(last)) => (id, first, last),
+            _ => continue,
+        };
+
+        let reference = (number(&marker, "refX", 0.0), number(&marker, "refY", 0.0));
+        // The content is clipped to the view box, which has to be turned around as well.
+        let view_box = marker.attribute("viewBox")
+            .and_then(|value| {
+                let numbers: Vec<f64> = value.split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|number| !number.is_empty())
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .ok()?;
+                <[f64; 4]>::try_from(numbers).ok()
+            })
+            .unwrap_or([0.0, 0.0, number(&marker, "markerWidth", 3.0), number(&marker, "markerHeight", 3.0)]);
+        let [x, y, width, height] = view_box;
+
+        let start = marker.range().start + 1;
+        let tag_end = start + text[start..].find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(0);
+        let reversed_id = format!("{}reversed_{}", GENERATED_ID_PREFIX, id);
+        let mut copy = text[marker.range().start..tag_end].to_owned();
+        for attribute in marker.attributes() {
+            if !matches!(attribute.name(), "id" | "orient" | "viewBox") {
+                copy.push(' ');
+                copy.push_str(&text[attribute.range()]);
+            }
+        }
+        copy.push_str(&format!(
+            " id=\"{}\" orient=\"auto\" viewBox=\"{} {} {} {}\"><g transform=\"rotate(180 {} {})\">{}</g>{}",
+            reversed_id, 2.0 * reference.0 - x - width, 2.0 * reference.1 - y - height, width, height,
+            reference.0, reference.1, &text[first.range().start..last.range().end], &text[last.range().end..marker.range().end],
+        ));
+        edits.push((marker.range().end..marker.range().end, copy));
+        reversed.insert(format!("url(#{})", id), format!("url(#{})", reversed_id));
+    }
+    if reversed.is_empty() {
+        return text;
+    }
+
+    for node in doc.descendants().filter(roxmltree::Node::is_element) {
+        if let Some(reference) = node.attribute("marker-start").and_then(|value| reversed.get(value.trim())) {
+            let attribute = node.attribute_node("marker-start").map(roxmltree::Attribute::range);
+            edits.extend(attribute.map(|range| (range, format!("marker-start=\"{}\"", reference))));
+        }
+        let style = match node.attribute_node("style") {
+            Some(style) => style,
+            None => continue,
+        };
+        let mut changed = false;
+        let mut declarations = Vec::new();
+        for declaration in inline_declarations(style.value()) {
+            let important = if declaration.important { "!important" } else { "" };
+            match (declaration.name, reversed.get(declaration.value.trim())) {
+                ("marker-start", Some(reference)) => {
+                    declarations.push(format!("marker-start:{}{}", reference, important));
+                    changed = true;
+                },
+                ("marker", Some(reference)) => {
+                    declarations.push(format!("marker-start:{}{}", reference, important));
+                    declarations.push(format!("marker-mid:{}{}", declaration.value, important));
+                    declarations.push(format!("marker-end:{}{}", declaration.value, important));
+                    changed = true;
+                },
+                (name, _) => declarations.push(format!("{}:{}{}", name, declaration.value, important)),
+            }
+        }
+        if changed {
+            edits.push((style.range(), format!("style=\"{}\"", escape(&declarations.join(";")))));
+        }
+    }
+    drop(doc);
+
+    apply_edits(text, edits)
+}
+
+/// Evaluates the conditions of the children of all `switch` elements in `doc`. Returns whether
+/// each child is the one that is drawn, keyed by its node ID.
+fn switch_branches(doc: &roxmltree::Document<'_>, languages: &[String]) -> HashMap<usize, bool> {
+    let mut branches = HashMap::new();
+    for switch in doc.descendants().filter(|node| node.has_tag_name("switch")) {
+        let mut chosen = false;
+        for child in switch.children().filter(roxmltree::Node::is_element) {
+            let passes = !chosen && passes_conditions(&child, languages);
+            chosen |= passes;
+            branches.insert(child.id().get_usize(), passes);
+        }
+    }
+    branches
+}
+
+/// Returns true if `systemLanguage`, `requiredFeatures` and `requiredExtensions` of `node` all
+/// evaluate to true.
+fn passes_conditions(node: &roxmltree::Node<'_, '_>, languages: &[String]) -> bool {
+    // The content of a `foreignObject` can't be drawn, so the `switch` falls back to the next child.
+    if node.has_tag_name("foreignObject") {
+        return false;
+    }
+    // No extensions are supported, an empty list evaluates to false as well.
+    if node.has_attribute("requiredExtensions") {
+        return false;
+    }
+    if let Some(features) = node.attribute("requiredFeatures") {
+        let mut features = features.split_whitespace().peekable();
+        let supported = features.peek().is_some() && features.all(|feature| {
+            let name = feature.strip_prefix("http://www.w3.org/TR/SVG11/feature#").unwrap_or(feature);
+            SUPPORTED_FEATURES.contains(&name)
+        });
+        if !supported {
+            return false;
+        }
+    }
+    match node.attribute("systemLanguage") {
+        // A preferred language matches the same language, or one of its regions, like `en` matches `en-US`.
+        Some(system_languages) => system_languages.split(',').map(str::trim).any(|system_language| {
+            languages.iter().any(|language| {
+                let language = language.trim();
+                system_language.get(..language.len()).map_or(false, |prefix| prefix.eq_ignore_ascii_case(language))
+                    && matches!(system_language.as_bytes().get(language.len()), None | Some(b'-'))
+            })
+        }),
+        None => true,
+    }
+}
+
+/// Replaces the ranges of `text` with the given contents, the ranges must not overlap.
+fn apply_edits(text: String, mut edits: Vec<(std::ops::Range<usize>, String)>) -> String {
+    if edits.is_empty() {
+        return text;
+    }
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut patched = String::with_capacity(text.len() + edits.iter().map(|(_, edit)| edit.len()).sum::<usize>());
+    let mut last = 0;
+    for (range, edit) in edits {
+        patched.push_str(&text[last..range.start]);
+        patched.push_str(&edit);
+        last = range.end;
+    }
+    patched.push_str(&text[last..]);
+    patched
+}
+
+/// Returns the value of an attribute of `node` that is a plain number, or `default`.
+fn number(node: &roxmltree::Node<'_, '_>, name: &str, default: f64) -> f64 {
+    node.attribute(name).and_then(|value| value.trim().parse().ok()).unwrap_or(default)
+}
+
+/// Returns the element that the animation `node` animates, its parent unless it references
+/// another element.
+fn animation_target<'a, 'input>(node: &roxmltree::Node<'a, 'input>) -> Option<roxmltree::Node<'a, 'input>> {
+    match animation::href(node) {
+        Some(id) => node.ancestors().last()?.descendants().find(|other| other.attribute("id") == Some(id)),
+        None => node.parent_element(),
+    }
+}
+
+/// Name of the custom property that the animated `fill` or `stroke` color of a shape is drawn with.
+fn animated_color_variable(node: &roxmltree::Node<'_, '_>, name: &str) -> String {
+    format!("--{}animated_{}_{}", GENERATED_ID_PREFIX, name, node.id().get_usize())
+}
+
+fn parse(text: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    let mut options = roxmltree::ParsingOptions::default();
+    options.allow_dtd = true;
+    roxmltree::Document::parse_with_options(text, options)
+}
+
+/// Returns the value of a pseudo attribute in the content of a processing instruction,
+/// like `href="style.css"`.
+fn pseudo_attribute<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = value;
+    while let Some(start) = rest.find(name) {
+        let after = rest[start + name.len()..].trim_start();
+        // Make sure that a complete name was found, not the end of a longer one.
+        let complete = rest[..start].chars().next_back().map_or(true, char::is_whitespace);
+        if let (true, Some(after)) = (complete, after.strip_prefix('=')) {
+            let after = after.trim_start();
+            let quote = after.chars().next().filter(|&c| c == '"' || c == '\'')?;
+            let after = &after[1..];
+            return after.find(quote).map(|end| &after[..end]);
+        }
+        rest = &rest[start + name.len()..];
+    }
+    None
+}
+
+/// Collects the `external` style sheets and the content of all `<style>` elements of `doc`,
+/// external style sheets come first.
+fn style_sheet_texts(doc: &roxmltree::Document<'_>, external: &[String]) -> Vec<String> {
+    let internal = doc.descendants()
+        .filter(|node| node.has_tag_name("style") && matches!(node.attribute("type"), None | Some("text/css")))
+        .filter_map(|node| node.text());
+    external.iter()
+        .map(String::as_str)
+        .chain(internal)
+        .map(|css| escape_custom_properties(&strip_comments(css)))
+        .collect()
+}
+
+/// Removes all comments from `css`, so that they don't get in the way of the rules that are
+/// read by hand.
+fn strip_comments(css: &str) -> Cow<'_, str> {
+    if !css.contains("/*") {
+        return Cow::Borrowed(css);
+    }
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = rest[start + 2..].find("*/").map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+    stripped.push_str(rest);
+    Cow::Owned(stripped)
+}
+
+fn style_sheet(texts: &[String]) -> simplecss::StyleSheet<'_> {
+    let mut style_sheet = simplecss::StyleSheet::new();
+    for text in texts {
+        style_sheet.parse_more(text);
+    }
+    style_sheet
+}
+
+/// `simplecss` doesn't accept the names of custom properties, so they are prefixed with
+/// [`CUSTOM_PROPERTY_ESCAPE`] before the style sheet is parsed.
+fn escape_custom_properties(css: &str) -> String {
+    let mut escaped = String::with_capacity(css.len());
+    let mut previous = '{';
+    for (i, c) in css.char_indices() {
+        if matches!(previous, '{' | ';') && css[i..].starts_with("--") {
+            escaped.push(CUSTOM_PROPERTY_ESCAPE);
+        }
+        if !c.is_whitespace() {
+            previous = c;
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Resolves the declarations of the `style` attribute and all matching rules of `style_sheet`
+/// for `node`, in the order of the CSS cascade. Only the winning declaration of each property is
+/// returned, together with whether any of them came from the style sheet.
+fn cascade<'a>(node: &roxmltree::Node<'a, '_>, style_sheet: &simplecss::StyleSheet<'a>) -> (Vec<(&'a str, &'a str)>, bool) {
+    // Important declarations win, then the `style` attribute, then the most specific rule.
+    // Rules with the same specificity keep their order, so later rules win.
+    let mut declared: Vec<(bool, bool, [u8; 3], simplecss::Declaration<'a>)> = style_sheet.rules.iter()
+        .filter(|rule| rule.selector.matches(&XmlNode(*node)))
+        .flat_map(|rule| {
+            let specificity = rule.selector.specificity();
+            rule.declarations.iter().map(move |declaration| {
+                let name = declaration.name.strip_prefix(CUSTOM_PROPERTY_ESCAPE).filter(|name| name.starts_with("--"));
+                let declaration = simplecss::Declaration { name: name.unwrap_or(declaration.name), ..*declaration };
+                (declaration.important, false, specificity, declaration)
+            })
+        })
+        .collect();
+    let from_style_sheet = !declared.is_empty();
+    if let Some(style) = node.attribute("style") {
+        declared.extend(inline_declarations(style).map(|declaration| (declaration.important, true, [0; 3], declaration)));
+    }
+    declared.sort_by_key(|(important, inline, specificity, _)| (*important, *inline, *specificity));
+
+    let mut style: Vec<(&str, &str)> = Vec::new();
+    for (_, _, _, declaration) in declared {
+        // `usvg` only expands the `marker` shorthand in style sheets, not in the `style` attribute.
+        let names: &[&str] = match declaration.name {
+            "marker" => &["marker-start", "marker-mid", "marker-end"],
+            _ => std::slice::from_ref(&declaration.name),
+        };
+        for &name in names {
+            style.retain(|(existing, _)| *existing != name);
+            style.push((name, declaration.value));
+        }
+    }
+
+    (style, from_style_sheet)
+}
+
+/// Parses the declarations of a `style` attribute. Custom properties are split off by hand, as
+/// `simplecss` would stop at them.
+fn inline_declarations(style: &str) -> impl Iterator<Item = simplecss::Declaration<'_>> {
+    style.split(';').filter_map(|declaration| {
+        match declaration.trim().strip_prefix("--").and(declaration.split_once(':')) {
+            Some((name, value)) => {
+                let value = value.trim();
+                let (value, important) = match value.strip_suffix("!important") {
+                    Some(value) => (value.trim_end(), true),
+                    None => (value, false),
+                };
+                Some(simplecss::Declaration { name: name.trim(), value, important })
+            },
+            None => simplecss::DeclarationTokenizer::from(declaration).next(),
+        }
+    })
+}
+
+/// Returns the value of a presentation property of `node`, a declaration in the resolved `style`
+/// takes precedence over the attribute with the same name.
+fn property<'a>(node: &roxmltree::Node<'a, '_>, style: &'a [(&str, Cow<'_, str>)], name: &str) -> Option<&'a str> {
+    style.iter()
+        .find(|(declared, _)| *declared == name)
+        .map(|(_, value)| value.as_ref())
+        .or_else(|| node.attribute(name))
+        .map(str::trim)
+}
+
+/// Replaces all `var()` functions in `value` with the value of the referenced custom property,
+/// or its fallback. Returns `None` if a custom property is undefined and there is no fallback.
+fn substitute(value: &str, variables: &HashMap<String, String>) -> Option<String> {
+    let mut substituted = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("var(") {
+        substituted.push_str(&rest[..start]);
+        let arguments = &rest[start + "var(".len()..];
+        let end = closing_parenthesis(arguments)?;
+        let (name, fallback) = match arguments[..end].split_once(',') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (arguments[..end].trim(), None),
+        };
+        match variables.get(name) {
+            Some(value) => substituted.push_str(value),
+            None => substituted.push_str(&substitute(fallback?, variables)?),
+        }
+        rest = &arguments[end + 1..];
+    }
+    substituted.push_str(rest);
+    Some(substituted)
+}
+
+/// Returns the position of the parenthesis that closes the already opened one.
+fn closing_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Returns the name of the custom property, if `value` is a single `var()` function.
+fn variable_name(value: &str) -> Option<String> {
+    let arguments = value.trim().strip_prefix("var(")?;
+    let end = closing_parenthesis(arguments)?;
+    if !arguments[end + 1..].trim().is_empty() {
+        return None;
+    }
+    let name = arguments[..end].split(',').next()?.trim();
+    name.starts_with("--").then(|| name.to_owned())
+}
+
+/// Escapes `value`, so it can be used as the value of an attribute in double quotes.
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;")
+}
+
+/// Makes a [`roxmltree::Node`] usable with the selectors of [`simplecss`].
+#[derive(Clone, Copy)]
+struct XmlNode<'a, 'input>(roxmltree::Node<'a, 'input>);
+
+impl simplecss::Element for XmlNode<'_, '_> {
+    fn parent_element(&self) -> Option<Self> {
+        self.0.parent_element().map(XmlNode)
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.0.prev_sibling_element().map(XmlNode)
+    }
+
+    fn has_local_name(&self, local_name: &str) -> bool {
+        self.0.tag_name().name() == local_name
+    }
+
+    fn attribute_matches(&self, local_name: &str, operator: simplecss::AttributeOperator<'_>) -> bool {
+        self.0.attribute(local_name).map_or(false, |value| operator.matches(value))
+    }
+
+    fn pseudo_class_matches(&self, class: simplecss::PseudoClass<'_>) -> bool {
+        match class {
+            simplecss::PseudoClass::FirstChild => self.0.prev_sibling_element().is_none(),
+            // Everything else depends on user interaction or on a browser.
+            _ => false,
+        }
+    }
+}
+
+/// Parses the value of `paint-order`, `None` for `normal`. Layers that aren't listed are painted
+/// afterwards, in their usual order.
+fn parse_paint_order(value: &str) -> Option<[PaintLayer; 3]> {
+    let mut layers = Vec::with_capacity(3);
+    for keyword in value.split_whitespace() {
+        let layer = match keyword {
+            "normal" => return None,
+            "fill" => PaintLayer::Fill,
+            "stroke" => PaintLayer::Stroke,
+            "markers" => PaintLayer::Markers,
+            keyword => {
+                warn!("Unknown `paint-order` `{}`, using `normal` instead.", keyword);
+                return None;
+            },
+        };
+        if !layers.contains(&layer) {
+            layers.push(layer);
+        }
+    }
+    for layer in PaintOrder::default().layers {
+        if !layers.contains(&layer) {
+            layers.push(layer);
+        }
+    }
+    let layers = <[PaintLayer; 3]>::try_from(layers).ok()?;
+    (layers != PaintOrder::default().layers).then(|| layers)
+}
+
+fn parse_blend_mode(value: &str) -> BlendMode {
+    match value {
+        "normal" => BlendMode::Normal,
+        "multiply" => BlendMode::Multiply,
+        "screen" => BlendMode::Screen,
+        "overlay" => BlendMode::Overlay,
+        "darken" => BlendMode::Darken,
+        "lighten" => BlendMode::Lighten,
+        "color-dodge" => BlendMode::ColorDodge,
+        "color-burn" => BlendMode::ColorBurn,
+        "hard-light" => BlendMode::HardLight,
+        "soft-light" => BlendMode::SoftLight,
+        "difference" => BlendMode::Difference,
+        "exclusion" => BlendMode::Exclusion,
+        mode => {
+            warn!("Blend mode `{}` is not supported, using `normal` instead.", mode);
+            BlendMode::Normal
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the declarations of the element with `id` in `svg` after the cascade.
+    fn cascaded(svg: &str, id: &str) -> Vec<(String, String)> {
+        let doc = parse(svg).unwrap();
+        let texts = style_sheet_texts(&doc, &[]);
+        let style_sheet = style_sheet(&texts);
+        let node = doc.descendants().find(|node| node.attribute("id") == Some(id)).unwrap();
+        cascade(&node, &style_sheet).0.into_iter().map(|(name, value)| (name.to_owned(), value.to_owned())).collect()
+    }
+
+    fn value<'a>(style: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        style.iter().find(|(declared, _)| declared == name).map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn decode_gzipped_file() {
+        let compressed = include_bytes!("../assets/spinner.svgz");
+        let plain = include_str!("../assets/spinner.svg");
+        assert_eq!(decode(compressed).unwrap(), plain);
+        assert_eq!(decode(plain.as_bytes()).unwrap(), plain);
+        assert!(matches!(decode(&compressed[..compressed.len() / 2]), Err(usvg::Error::MalformedGZip)));
+    }
+
+    #[test]
+    fn text_document_keeps_text_and_references() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+            <defs><linearGradient id="gradient"/></defs>
+            <clipPath id="clip"><use xlink:href="#shape"/></clipPath>
+            <rect id="background"/>
+            <g id="shapes"><circle id="shape"/><circle id="other"/></g>
+            <g id="hud" clip-path="url(#clip)"><text id="score" fill="url(#gradient)">0</text><text id="name">Player</text></g>
+        </svg>"##;
+        let document = text_document(svg, "score").unwrap();
+        let doc = parse(&document).unwrap();
+        let has = |id: &str| doc.descendants().any(|node| node.attribute("id") == Some(id));
+        for kept in ["gradient", "clip", "shapes", "shape", "hud", "score"] {
+            assert!(has(kept), "{} was removed", kept);
+        }
+        for removed in ["background", "other", "name"] {
+            assert!(!has(removed), "{} was kept", removed);
+        }
+        assert!(text_document(svg, "shape").is_none());
+    }
+
+    #[test]
+    fn cascade_prefers_more_specific_rules() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>#a { fill: blue } .c { fill: green } rect { fill: red; stroke: red }</style>
+            <rect id="a" class="c"/>
+            <rect id="b" class="c"/>
+        </svg>"#;
+        let style = cascaded(svg, "a");
+        assert_eq!(value(&style, "fill"), Some("blue"));
+        assert_eq!(value(&style, "stroke"), Some("red"));
+        assert_eq!(value(&cascaded(svg, "b"), "fill"), Some("green"));
+    }
+
+    #[test]
+    fn cascade_prefers_later_rules_of_same_specificity() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>.c { fill: red } .d { fill: green }</style>
+            <rect id="a" class="c d"/>
+        </svg>"#;
+        assert_eq!(value(&cascaded(svg, "a"), "fill"), Some("green"));
+    }
+
+    #[test]
+    fn cascade_prefers_important_over_style_attribute() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>.c { fill: green !important; stroke: red } #a { opacity: 0.5 !important }</style>
+            <rect id="a" class="c" style="fill: yellow; stroke: blue; opacity: 1 !important"/>
+        </svg>"#;
+        let style = cascaded(svg, "a");
+        assert_eq!(value(&style, "fill"), Some("green"));
+        assert_eq!(value(&style, "stroke"), Some("blue"));
+        // Both are important, the `style` attribute still wins.
+        assert_eq!(value(&style, "opacity"), Some("1"));
+    }
+
+    #[test]
+    fn substitute_nested_fallbacks() {
+        let mut variables = HashMap::new();
+        assert_eq!(substitute("var(--a, var(--b, red))", &variables).as_deref(), Some("red"));
+        assert_eq!(substitute("var(--a, var(--b))", &variables), None);
+        variables.insert("--b".to_owned(), "blue".to_owned());
+        assert_eq!(substitute("var(--a, var(--b, red))", &variables).as_deref(), Some("blue"));
+        assert_eq!(substitute("1px var(--a, var(--b)) 2px", &variables).as_deref(), Some("1px blue 2px"));
+        variables.insert("--a".to_owned(), "green".to_owned());
+        assert_eq!(substitute("var(--a, var(--b, red))", &variables).as_deref(), Some("green"));
+    }
+
+    #[test]
+    fn undefined_variable_falls_back_to_inherit() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>g { --accent: blue }</style>
+            <g fill="red"><rect id="a" style="fill: var(--missing); stroke: var(--missing, var(--accent))"/></g>
+        </svg>"#;
+        let (text, _) = preprocess(svg.to_owned(), &[], 96.0, &[]);
+        let doc = parse(&text).unwrap();
+        let rect = doc.descendants().find(|node| node.attribute("id") == Some("a")).unwrap();
+        let style = rect.attribute("style").unwrap();
+        assert!(style.contains("fill:inherit"), "{}", style);
+        assert!(style.contains("stroke:blue"), "{}", style);
+    }
+
+    #[test]
+    fn paint_order_fills_in_missing_layers() {
+        assert_eq!(parse_paint_order("stroke"), Some([PaintLayer::Stroke, PaintLayer::Fill, PaintLayer::Markers]));
+        assert_eq!(parse_paint_order("markers stroke"), Some([PaintLayer::Markers, PaintLayer::Stroke, PaintLayer::Fill]));
+        assert_eq!(parse_paint_order("stroke stroke"), Some([PaintLayer::Stroke, PaintLayer::Fill, PaintLayer::Markers]));
+        // The usual order and unknown keywords need no special handling.
+        assert_eq!(parse_paint_order("normal"), None);
+        assert_eq!(parse_paint_order("fill"), None);
+        assert_eq!(parse_paint_order("stroke glow"), None);
+    }
+
+    #[test]
+    fn switch_draws_first_matching_child() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <switch>
+                <text id="de" systemLanguage="de">Hallo</text>
+                <text id="en" systemLanguage="fr, en-US">Hello</text>
+                <text id="fallback">Hi</text>
+            </switch>
+            <switch>
+                <g id="extension" requiredExtensions="http://example.org/extension"/>
+                <g id="feature" requiredFeatures="http://www.w3.org/TR/SVG11/feature#Shape"/>
+            </switch>
+        </svg>"#;
+        let doc = parse(svg).unwrap();
+        let branches = switch_branches(&doc, &["en".to_owned()]);
+        let drawn = |id: &str| branches[&doc.descendants().find(|node| node.attribute("id") == Some(id)).unwrap().id().get_usize()];
+        assert!(!drawn("de"));
+        assert!(drawn("en"));
+        assert!(!drawn("fallback"));
+        assert!(!drawn("extension"));
+        assert!(drawn("feature"));
+    }
+}
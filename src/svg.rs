@@ -1,14 +1,31 @@
-use bevy::{asset::Handle, ecs::component::Component, math::{Mat4, Vec2, Vec3}, reflect::TypeUuid, render::{color::Color, mesh::Mesh}, transform::components::Transform};
+use std::{collections::{HashMap, HashSet}, ops::Range, rc::Rc, sync::{Arc, Mutex}};
+
+use bevy::{asset::{AssetPath, Assets, Handle}, ecs::{component::Component, reflect::ReflectComponent}, log::warn, math::{Mat4, Vec2, Vec3}, reflect::{Reflect, TypeUuid}, render::{color::Color, mesh::Mesh, render_resource::TextureFormat, texture::{CompressedImageFormats, Image, ImageType}}, transform::components::Transform};
 use copyless::VecHelper;
-use lyon_geom::euclid::default::Transform2D;
-use lyon_svg::{parser::ViewBox, path::PathEvent};
+use lyon_geom::euclid::default::{Box2D, Transform2D};
+use lyon_svg::{parser::ViewBox, path::{iterator::PathIterator, PathEvent}};
 use lyon_tessellation::math::Point;
+use usvg::NodeExt;
+
+use crate::{animation::Animation, baked::{self, StaticSvg}, css_animation::Transition, diagnostics::SkippedFeature, filter::{resolve_filter, unsupported_primitive, BlendMode, Filter}, font::SvgFontDatabase, loader::{self, ParseOptions, SvgDpi, SvgLanguages, SvgLoadError, SvgParserOptions}, paint::{LinearGradient, Paint, Pattern, RadialGradient, RasterImage}, render::{dash, raster::Raster}, source::{self, PaintLayer, Source, GENERATED_ID_PREFIX}, text::TextSource, Convert};
+#[cfg(feature = "serde")]
+use crate::serialization::{self, SerializedSvg};
 
-use crate::Convert;
 
+/// Tolerance that is used to flatten curves, when fills are tessellated.
+pub(crate) const FILL_TOLERANCE: f32 = 0.001;
+/// Tolerance that is used to flatten curves, when strokes are tessellated.
+pub(crate) const STROKE_TOLERANCE: f32 = 0.01;
+/// Tolerance that is used to flatten curves, when the bounding box of a node is calculated.
+const BOUNDS_TOLERANCE: f32 = 0.1;
+/// Tolerance that is used to flatten curves, when paths are hit tested.
+const HIT_TEST_TOLERANCE: f32 = 0.1;
 
 /// A loaded and deserialized SVG file.
-#[derive(Debug, TypeUuid)]
+///
+/// Only the name, the size and the mesh are reflected, the paths and everything else that was
+/// parsed from the file is left out.
+#[derive(Debug, Reflect, TypeUuid)]
 #[uuid = "d2c5985d-e221-4257-9e3b-ff0fb87e28ba"]
 pub struct Svg {
     /// The name of the file.
@@ -16,61 +33,218 @@ pub struct Svg {
     /// Size of the SVG.
     pub size: Vec2,
     /// ViewBox of the SVG.
+    #[reflect(ignore)]
     pub view_box: ViewBox,
-    /// All paths that make up the SVG.
+    /// The area that the view box covers, after it was scaled and aligned into the size of the SVG
+    /// according to `preserveAspectRatio`. It is larger than the size with `slice`.
+    #[reflect(ignore)]
+    pub content_rect: lyon_tessellation::math::Rect,
+    /// All paths that make up the SVG. Changing them here doesn't change the mesh, use
+    /// [`Svg::path_mut`], [`Svg::element_paths_mut`] or [`Svg::paths_mut`] for that.
+    #[reflect(ignore)]
     pub paths: Vec<PathDescriptor>,
+    /// The SMIL and CSS animations of the SVG, played by a [`SvgAnimationPlayer`](crate::prelude::SvgAnimationPlayer).
+    #[reflect(ignore)]
+    pub(crate) animations: Vec<Animation>,
+    /// The transitions of the colors of custom properties, keyed by their name.
+    #[reflect(ignore)]
+    pub(crate) transitions: HashMap<String, Transition>,
+    /// IDs of the elements with `display: none`, they can be shown with
+    /// [`SvgNodeVisibility`](crate::prelude::SvgNodeVisibility).
+    #[reflect(ignore)]
+    pub(crate) hidden: HashSet<String>,
+    /// Content of the file that can't be drawn and was skipped while loading.
+    #[reflect(ignore)]
+    pub skipped_features: Vec<SkippedFeature>,
+    /// The style sheets and other SVG files that the file references, it is reloaded when one of
+    /// them changes.
+    #[reflect(ignore)]
+    pub(crate) dependencies: Vec<AssetPath<'static>>,
+    /// The tolerance of the [`SvgSettings`](crate::prelude::SvgSettings) of the file, used
+    /// whenever it is tessellated.
+    #[reflect(ignore)]
+    pub(crate) tolerance: Option<f32>,
+    /// Whether the mesh is tessellated over several frames, see
+    /// [`SvgTimeSlicing`](crate::prelude::SvgTimeSlicing).
+    #[reflect(ignore)]
+    pub(crate) time_sliced: bool,
+    /// The document that `text` elements with an ID are laid out again from, when their content
+    /// is replaced with [`SvgTextOverrides`](crate::prelude::SvgTextOverrides). `None` if it has
+    /// no such elements.
+    #[reflect(ignore)]
+    pub(crate) texts: Option<Arc<TextSource>>,
+    /// The paths that were changed through [`Svg::path_mut`] and the like, since the mesh was
+    /// last tessellated. Taken by the system that tessellates them again, which only reads the
+    /// asset, because changing it would send another event.
+    #[reflect(ignore)]
+    pub(crate) modified_paths: Mutex<ModifiedPaths>,
     /// The fully tessellated paths as [`Mesh`].
     pub mesh: Handle<Mesh>,
 }
 
 impl Svg {
-    pub(crate) fn from_tree(tree: usvg::Tree) -> Svg {
-        let view_box = tree.svg_node().view_box;
-        let size = tree.svg_node().size;
-        let mut descriptors = Vec::new();
+    /// Parses and tessellates the SVG document `text`, without going through the asset server.
+    /// This turns generated or downloaded markup into an [`Svg`] that can be added to the
+    /// [`Assets`], its mesh is added to `meshes`.
+    ///
+    /// Texts are laid out with the `fonts`, physical units and `switch` elements use the defaults
+    /// of [`SvgDpi`] and [`SvgLanguages`], the parser those of
+    /// [`SvgParserOptions`]. References to other files, like style sheets or images with a relative
+    /// path, can't be resolved.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(text: &str, fonts: &SvgFontDatabase, meshes: &mut Assets<Mesh>) -> Result<Svg, SvgLoadError> {
+        let options = ParseOptions {
+            fonts,
+            dpi: SvgDpi::default(),
+            languages: &SvgLanguages::default(),
+            parser: &SvgParserOptions::default(),
+            tolerance: None,
+            default_size: None,
+            time_sliced_above: None,
+            fragments: false,
+            resources_dir: None,
+            progress: None,
+        };
+        let (mut svg, mesh, _) = loader::parse(text.to_owned(), String::new(), &[], &options)?;
+        svg.mesh = meshes.add(mesh);
+        Ok(svg)
+    }
 
-        for node in tree.root().descendants() {
-            match *node.borrow() {
-                usvg::NodeKind::Path(ref path) => {
-                    let t = path.transform;
-                    let abs_t = Transform::from_matrix(
-                        Mat4::from_cols(
-                            [t.a.abs() as f32, t.b as f32,       0.0, 0.0].into(),
-                            [t.c as f32,       t.d.abs() as f32, 0.0, 0.0].into(),
-                            [0.0,              0.0,              1.0, 0.0].into(),
-                            [t.e as f32,       t.f as f32,       0.0, 1.0].into()
-                        )
-                    );
-
-                    if let Some(ref fill) = path.fill {
-                        let color = match fill.paint {
-                            usvg::Paint::Color(c) =>
-                                Color::rgba_u8(c.red, c.green, c.blue, fill.opacity.to_u8()),
-                            _ => Color::default(),
-                        };
-
-                        descriptors.alloc().init(PathDescriptor {
-                            segments: path.convert().collect(),
-                            abs_transform: abs_t,
-                            color,
-                            draw_type: DrawType::Fill,
-                        });
-                    }
+    /// Parses and tessellates an SVG or compressed SVGZ file from its content, like
+    /// [`Svg::from_str`]. This registers SVGs that are embedded with [`include_bytes!`] into the
+    /// binary, for platforms without a file system like the web:
+    ///
+    /// ```ignore
+    /// let svg = Svg::from_bytes(include_bytes!("../assets/logo.svg"), &fonts, &mut meshes)?;
+    /// let handle = svgs.add(svg);
+    /// ```
+    pub fn from_bytes(bytes: &[u8], fonts: &SvgFontDatabase, meshes: &mut Assets<Mesh>) -> Result<Svg, SvgLoadError> {
+        let text = source::decode(bytes)?;
+        Svg::from_str(&text, fonts, meshes)
+    }
 
-                    if let Some(ref stroke) = path.stroke {
-                        let (color, draw_type) = stroke.convert();
+    /// Creates an [`Svg`] from one that was tessellated at compile time by the `svg!` macro of the
+    /// `macros` feature, its mesh is added to `meshes`.
+    ///
+    /// ```ignore
+    /// static ICON: StaticSvg = svg!("assets/icon.svg");
+    ///
+    /// let svg = Svg::from_static(&ICON, &mut meshes);
+    /// ```
+    pub fn from_static(svg: &StaticSvg, meshes: &mut Assets<Mesh>) -> Svg {
+        let mut baked = svg.svg();
+        baked.mesh = meshes.add(svg.mesh());
+        baked
+    }
 
-                        descriptors.alloc().init(PathDescriptor {
-                            segments: path.convert().collect(),
-                            abs_transform: abs_t,
-                            color,
-                            draw_type,
-                        });
-                    }
-                },
-                _ => {}
-            }
+    /// Parses and tessellates an SVG or SVGZ file like [`Svg::from_bytes`] and returns it in the
+    /// `.svgmesh` format. Baked files are loaded without parsing or tessellating them, which makes
+    /// loading faster for shipped builds, but they are drawn as they are: runtime changes like
+    /// [`SvgColor`](crate::prelude::SvgColor) or [`SvgAnimationPlayer`](crate::prelude::SvgAnimationPlayer)
+    /// have no effect on them.
+    ///
+    /// A baked file that is stored next to its SVG file, like `icon.svgmesh` next to `icon.svg`,
+    /// is loaded instead of it, as long as the SVG file doesn't change.
+    pub fn bake(bytes: &[u8], fonts: &SvgFontDatabase) -> Result<Vec<u8>, SvgLoadError> {
+        let text = source::decode(bytes)?;
+        let options = ParseOptions {
+            fonts,
+            dpi: SvgDpi::default(),
+            languages: &SvgLanguages::default(),
+            parser: &SvgParserOptions::default(),
+            tolerance: None,
+            default_size: None,
+            time_sliced_above: None,
+            fragments: false,
+            resources_dir: None,
+            progress: None,
+        };
+        let (svg, mesh, _) = loader::parse(text, String::new(), &[], &options)?;
+        Ok(baked::encode(&svg, &mesh, bytes))
+    }
+
+    /// Returns the SVG with its paths, styles, animations and tessellated mesh in a form that can
+    /// be serialized with `serde`, to cache it, send it over the network or store it in a save
+    /// file. The mesh is left out if it isn't in `meshes`, like while it is still tessellated.
+    /// Only with the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn to_serialized(&self, meshes: &Assets<Mesh>) -> SerializedSvg {
+        serialization::encode(self, meshes.get(&self.mesh))
+    }
+
+    /// Creates an [`Svg`] from one that was serialized with [`Svg::to_serialized`], its mesh is
+    /// added to `meshes`. The paths are tessellated again if the mesh wasn't serialized. Only with
+    /// the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn from_serialized(serialized: SerializedSvg, meshes: &mut Assets<Mesh>) -> Result<Svg, SvgLoadError> {
+        let (mut svg, mesh) = serialization::decode(serialized)?;
+        svg.mesh = meshes.add(mesh);
+        Ok(svg)
+    }
+
+    /// Draws the SVG into an sRGB [`Image`] of `width` x `height` pixels, which the whole SVG is
+    /// stretched over, for sprites, UI images or the textures of materials. The edges are smoothed
+    /// with several samples per pixel.
+    ///
+    /// This is a CPU fallback, not a render pass: the tessellated mesh in `meshes` is drawn by a
+    /// scanline rasterizer, which is slow for large images, and images above 2048 pixels get fewer
+    /// samples per pixel. Runtime overrides of an entity like [`SvgColor`](crate::prelude::SvgColor)
+    /// and the effects of the shaders, [`SvgTint`](crate::prelude::SvgTint) and
+    /// [`SvgColorMatrix`](crate::prelude::SvgColorMatrix), are not applied. Returns `None` while
+    /// the mesh isn't in `meshes`, or if the SVG or the image has no area.
+    pub fn rasterize(&self, width: u32, height: u32, meshes: &Assets<Mesh>) -> Option<Image> {
+        if width == 0 || height == 0 || self.size.min_element() <= 0.0 {
+            return None;
+        }
+        let mesh = meshes.get(&self.mesh)?;
+        Some(Raster::from_mesh(mesh, self.size, width as usize, height as usize).to_image())
+    }
+
+    pub(crate) fn from_tree(tree: &usvg::Tree, source: &Source) -> Svg {
+        let view_box = tree.svg_node().view_box;
+        let size = tree.svg_node().size;
+        // The view box is fitted into the size of the SVG, like a browser does.
+        let transform = usvg::utils::view_box_to_transform(view_box.rect, view_box.aspect, size);
+        let content_rect = view_box.rect.transform(&transform).unwrap_or(view_box.rect);
+        let viewport = size.to_rect(0.0, 0.0);
+        let mut state = State { transform, ..Default::default() };
+        let overflows = content_rect.x() < -1e-6 || content_rect.y() < -1e-6
+            || content_rect.right() > viewport.right() + 1e-6 || content_rect.bottom() > viewport.bottom() + 1e-6;
+        if overflows {
+            // With `slice`, everything that ends up outside of the size is cut off.
+            let path = usvg::Path {
+                data: Rc::new(usvg::PathData::from_rect(viewport)),
+                ..Default::default()
+            };
+            let clip_path = ClipPath {
+                paths: vec![PathDescriptor {
+                    id: String::new(),
+                    segments: (&path, usvg::Transform::default()).convert().collect(),
+                    abs_transform: Transform::identity(),
+                    transform: Transform2D::identity(),
+                    paint: Paint::Color(Color::BLACK),
+                    paint_variable: None,
+                    draw_type: DrawType::Fill(lyon_tessellation::FillOptions::tolerance(FILL_TOLERANCE)),
+                    non_scaling_stroke: false,
+                    dash: None,
+                    length: 0.0,
+                    groups: Vec::new(),
+                }],
+                clip_path: None,
+            };
+            state.groups.push(Arc::new(Group {
+                id: String::new(),
+                transform: Transform2D::identity(),
+                parent_transform: Transform2D::identity(),
+                clip_path: Some(Arc::new(clip_path)),
+                mask: None,
+                filters: Vec::new(),
+                opacity: 1.0,
+                blend_mode: BlendMode::Normal,
+            }));
         }
+        let mut descriptors = Vec::new();
+        collect_paths(tree, source, &tree.root(), &state, &mut descriptors);
 
         Svg {
             name: Default::default(),
@@ -81,13 +255,1007 @@ impl Svg {
                 w: view_box.rect.width(),
                 h: view_box.rect.height(),
             },
+            content_rect: lyon_geom::rect(content_rect.x() as f32, content_rect.y() as f32, content_rect.width() as f32, content_rect.height() as f32),
+            paths: descriptors,
+            animations: source.animations.clone(),
+            transitions: source.transitions.clone(),
+            hidden: source.hidden.clone(),
+            skipped_features: source.skipped_features.iter().cloned().chain(unsupported_features(tree)).collect(),
+            dependencies: Vec::new(),
+            tolerance: None,
+            time_sliced: false,
+            texts: None,
+            modified_paths: Default::default(),
+            mesh: Default::default(),
+        }
+    }
+
+    /// Creates an [`Svg`] of only the element with the ID `id` and its content, without the
+    /// effects of its ancestors. The content is moved by `offset` and the SVG gets the given
+    /// `size`, which is how it is fitted to the bounds of the element.
+    ///
+    /// Returns `None` if there is no element with the ID.
+    pub(crate) fn fragment_from_tree(tree: &usvg::Tree, source: &Source, id: &str, offset: Vec2, size: Vec2) -> Option<Svg> {
+        let node = tree.node_by_id(id)?;
+        let view_box = tree.svg_node().view_box;
+        let mut transform = usvg::Transform::new_translate(f64::from(offset.x), f64::from(offset.y));
+        transform.append(&usvg::utils::view_box_to_transform(view_box.rect, view_box.aspect, tree.svg_node().size));
+        if let Some(parent) = node.parent() {
+            transform.append(&parent.abs_transform());
+        }
+        let state = State { transform, ..Default::default() };
+
+        let mut descriptors = Vec::new();
+        match *node.borrow() {
+            usvg::NodeKind::Group(ref group) => collect_group(tree, source, &node, group, &state, &mut descriptors),
+            _ => if let Some(parent) = node.parent() {
+                collect_paths(tree, source, &parent, &state, &mut descriptors);
+                descriptors.retain(|path| path.id == id);
+            },
+        }
+        // The element is shown, even if it is hidden in the file, like the icons of a sheet
+        // where only one is shown at a time.
+        let mut hidden = source.hidden.clone();
+        hidden.remove(id);
+
+        Some(Svg {
+            name: String::new(),
+            size,
+            view_box: ViewBox { x: 0.0, y: 0.0, w: f64::from(size.x), h: f64::from(size.y) },
+            content_rect: lyon_geom::rect(0.0, 0.0, size.x, size.y),
             paths: descriptors,
+            animations: source.animations.clone(),
+            transitions: source.transitions.clone(),
+            hidden,
+            skipped_features: source.skipped_features.clone(),
+            dependencies: Vec::new(),
+            tolerance: None,
+            time_sliced: false,
+            texts: None,
+            modified_paths: Default::default(),
             mesh: Default::default(),
+        })
+    }
+
+    /// Returns the path at `index` to change it, like the segments of a bar in a chart. When the
+    /// [`Svg`] was borrowed with [`Assets::get_mut`], its mesh is tessellated again, but only the
+    /// changed paths and the paths that are drawn together with them in a group.
+    pub fn path_mut(&mut self, index: usize) -> Option<&mut PathDescriptor> {
+        let path = self.paths.get_mut(index)?;
+        self.modified_paths.get_mut().unwrap().insert(index..index + 1);
+        Some(path)
+    }
+
+    /// Returns the paths of the element with the ID `id` to change them, like the needle of a
+    /// gauge, see [`Svg::path_mut`]. The paths of a group are all paths inside of it.
+    pub fn element_paths_mut<'a>(&'a mut self, id: &'a str) -> impl Iterator<Item = &'a mut PathDescriptor> + 'a {
+        let modified = self.modified_paths.get_mut().unwrap();
+        for (index, path) in self.paths.iter().enumerate() {
+            if is_part_of(path, id) {
+                modified.insert(index..index + 1);
+            }
+        }
+        self.paths.iter_mut().filter(move |path| is_part_of(path, id))
+    }
+
+    /// Returns all paths to change them, to add, remove or reorder paths as well. When the
+    /// [`Svg`] was borrowed with [`Assets::get_mut`], its whole mesh is tessellated again.
+    pub fn paths_mut(&mut self) -> &mut Vec<PathDescriptor> {
+        *self.modified_paths.get_mut().unwrap() = ModifiedPaths::All;
+        &mut self.paths
+    }
+
+    /// Returns the area that the content of the SVG covers in its coordinate system, where the
+    /// y-axis points down, including the width of strokes. Unlike the view box, this is where
+    /// something is drawn. Returns `None` if nothing is drawn.
+    ///
+    /// Baked SVGs and the ones created with [`Svg::from_static`] don't keep their paths and have no
+    /// bounding box, the `Aabb` of their entities is still computed from their meshes.
+    pub fn bounding_box(&self) -> Option<lyon_tessellation::math::Rect> {
+        let mut bounds: Option<Box2D<f32>> = None;
+        for path in self.paths.iter().filter(|path| !self.is_hidden(path)) {
+            let to_document = document_transform(path);
+            let points: Vec<Point> = outline_points(&path.segments).map(|point| to_document.transform_point(point)).collect();
+            if points.is_empty() {
+                continue;
+            }
+            let mut path_bounds = Box2D::from_points(points);
+            if let DrawType::Stroke(options) = path.draw_type {
+                let scale = Vec2::new(to_document.m11, to_document.m12).length()
+                    .max(Vec2::new(to_document.m21, to_document.m22).length());
+                let extent = stroke_extent(&options) * scale;
+                path_bounds = path_bounds.inflate(extent, extent);
+            }
+            bounds = Some(bounds.map_or(path_bounds, |bounds| bounds.union(&path_bounds)));
         }
+        bounds.map(|bounds| bounds.to_rect())
+    }
+
+    /// Returns whether something is drawn at `point`, which is given in the coordinate system of the
+    /// SVG, where the y-axis points down.
+    ///
+    /// The paths themselves are tested, not their triangles: fills with their fill rule and strokes
+    /// with their width. Clip paths, masks and the transparency of the paint are not taken into
+    /// account.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        self.hit_paths(point).next().is_some()
+    }
+
+    /// Returns the ID of the topmost element that is drawn at `point`, like [`Svg::contains_point`]
+    /// tests it. Shapes without an ID give the ID of the innermost group with one, and `None` is
+    /// returned if there is none, or nothing is drawn at the point.
+    pub fn hit_test(&self, point: Vec2) -> Option<&str> {
+        self.hit_paths(point).next().and_then(element_id)
+    }
+
+    /// Returns the paths that are drawn at `point`, the topmost first.
+    pub(crate) fn hit_paths(&self, point: Vec2) -> impl Iterator<Item = &PathDescriptor> + '_ {
+        self.paths.iter().rev().filter(move |path| self.is_hit(path, point))
     }
+
+    /// Returns whether `path` is drawn at `point`, which is given in the coordinate system of the
+    /// SVG.
+    pub(crate) fn is_hit(&self, path: &PathDescriptor, point: Vec2) -> bool {
+        !self.is_hidden(path) && document_transform(path).inverse()
+            .map_or(false, |to_path| path_contains(path, to_path.transform_point(Point::new(point.x, point.y))))
+    }
+
+    /// Returns whether `path` or one of its groups has `display: none` in the file.
+    pub(crate) fn is_hidden(&self, path: &PathDescriptor) -> bool {
+        std::iter::once(&path.id)
+            .chain(path.groups.iter().map(|group| &group.id))
+            .any(|id| self.hidden.contains(id))
+    }
+
+    /// Looks up the drawn element with the ID `id`, a shape, an image or a group, like the
+    /// `spawn-point` circle of a level. Returns `None` if there is no such element.
+    pub fn node(&self, id: &str) -> Option<SvgNode> {
+        if id.is_empty() {
+            return None;
+        }
+        let shape: Vec<&PathDescriptor> = self.paths.iter().filter(|path| path.id == id).collect();
+        let (kind, paths, transform) = match shape.first() {
+            Some(first) => {
+                // Groups that only hold the effects of the shape have its ID, they are skipped.
+                let parent = first.groups.iter().rev()
+                    .find(|group| group.id != id)
+                    .map_or_else(Transform2D::identity, |group| group.transform.then(&group.parent_transform));
+                let transform = first.transform.then(&parent.inverse().unwrap_or_else(Transform2D::identity));
+                (SvgNodeKind::Shape, shape, transform)
+            },
+            None => {
+                let group = self.paths.iter().flat_map(|path| path.groups.iter()).find(|group| group.id == id)?;
+                let paths = self.paths.iter()
+                    .filter(|path| path.groups.iter().any(|ancestor| Arc::ptr_eq(ancestor, group)))
+                    .collect();
+                (SvgNodeKind::Group, paths, group.transform)
+            },
+        };
+
+        let mut geometry = Vec::new();
+        let mut points = Vec::new();
+        for path in &paths {
+            let to_document = document_transform(path);
+            geometry.extend(path.segments.iter().map(|event| event.transformed(&to_document)));
+            points.extend(outline_points(&path.segments).map(|point| to_document.transform_point(point)));
+        }
+        let fill = paths.iter().find(|path| matches!(path.draw_type, DrawType::Fill(_)));
+        let stroke = paths.iter().find_map(|path| match path.draw_type {
+            DrawType::Stroke(options) => Some((path, options.line_width)),
+            DrawType::Fill(_) => None,
+        });
+
+        Some(SvgNode {
+            id: id.to_owned(),
+            kind,
+            geometry,
+            fill: fill.map(|path| paint_color(path)),
+            stroke: stroke.map(|(path, _)| paint_color(path)),
+            stroke_width: stroke.map_or(0.0, |(_, width)| width),
+            transform,
+            bounding_box: Box2D::from_points(points).to_rect(),
+        })
+    }
+}
+
+/// An element of an [`Svg`], looked up by its ID with [`Svg::node`].
+///
+/// All coordinates are in the coordinate system of the SVG, where the y-axis points down.
+#[derive(Clone, Debug)]
+pub struct SvgNode {
+    /// The ID of the element.
+    pub id: String,
+    /// Whether the element is a shape or a group.
+    pub kind: SvgNodeKind,
+    /// The outlines of the element, those of all of its content for a group.
+    pub geometry: Vec<PathEvent>,
+    /// The color of the fill, `None` if the element isn't filled. Gradients give their color at
+    /// the center of the shape, patterns and images [`Color::NONE`]. For groups, this is the fill
+    /// of the first filled shape inside of it.
+    pub fill: Option<Color>,
+    /// The color of the stroke, `None` if the element has no stroke. Like the fill, a group has the
+    /// stroke of the first stroked shape inside of it.
+    pub stroke: Option<Color>,
+    /// The width of the stroke in the coordinate system of the shape, zero without a stroke.
+    pub stroke_width: f32,
+    /// The transform of the element, relative to the group that it is in.
+    pub transform: Transform2D<f32>,
+    /// The bounding box of the outlines, without the width of strokes.
+    pub bounding_box: lyon_tessellation::math::Rect,
+}
+
+/// The kind of element that a [`SvgNode`] stands for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SvgNodeKind {
+    /// A shape, text or image, which is drawn with a fill, a stroke or both.
+    Shape,
+    /// A group, `g` in SVG, or another element with content, like a nested `svg`.
+    Group,
+}
+
+/// Returns the transform that maps the segments of `path` into the coordinate system of the SVG,
+/// the same one that is used to tessellate it.
+pub(crate) fn document_transform(path: &PathDescriptor) -> Transform2D<f32> {
+    let matrix = path.abs_transform.compute_matrix();
+    Transform2D::new(
+        matrix.x_axis.x, matrix.x_axis.y,
+        matrix.y_axis.x, matrix.y_axis.y,
+        matrix.w_axis.x, matrix.w_axis.y,
+    )
+}
+
+/// Returns how far a stroke with the `options` reaches beyond its outline at most, in the
+/// coordinate system of its path, including miter joins and square caps.
+fn stroke_extent(options: &lyon_tessellation::StrokeOptions) -> f32 {
+    let mut factor: f32 = 1.0;
+    if matches!(options.line_join, lyon_tessellation::LineJoin::Miter | lyon_tessellation::LineJoin::MiterClip) {
+        factor = factor.max(options.miter_limit);
+    }
+    if options.start_cap == lyon_tessellation::LineCap::Square || options.end_cap == lyon_tessellation::LineCap::Square {
+        factor = factor.max(std::f32::consts::SQRT_2);
+    }
+    options.line_width / 2.0 * factor
 }
 
-#[derive(Clone, Component, Copy, Debug, PartialEq)]
+/// Returns whether `path` is part of the element with the ID `id`, it is the element itself or
+/// inside of it.
+pub(crate) fn is_part_of(path: &PathDescriptor, id: &str) -> bool {
+    !id.is_empty() && std::iter::once(&path.id)
+        .chain(path.groups.iter().map(|group| &group.id))
+        .any(|other| other == id)
+}
+
+/// Returns the ID of the element that `path` belongs to, the one of the innermost group with an ID
+/// for shapes without one.
+pub(crate) fn element_id(path: &PathDescriptor) -> Option<&str> {
+    std::iter::once(&path.id)
+        .chain(path.groups.iter().rev().map(|group| &group.id))
+        .find(|id| !id.is_empty() && !id.starts_with(GENERATED_ID_PREFIX))
+        .map(String::as_str)
+}
+
+/// Returns whether `point`, in the coordinate system of the segments of `path`, lies inside of its
+/// fill or on its stroke.
+fn path_contains(path: &PathDescriptor, point: Point) -> bool {
+    // The lines of the flattened path, and whether they are drawn by a stroke. Subpaths that aren't
+    // closed are still closed for a fill.
+    let lines = path.segments.iter().copied().flattened(HIT_TEST_TOLERANCE).filter_map(|event| match event {
+        PathEvent::Line { from, to } => Some((from, to, true)),
+        PathEvent::End { last, first, close } => Some((last, first, close)),
+        _ => None,
+    });
+    match path.draw_type {
+        DrawType::Fill(options) => {
+            let winding: i32 = lines.map(|(from, to, _)| winding_number(from, to, point)).sum();
+            match options.fill_rule {
+                lyon_tessellation::FillRule::EvenOdd => winding % 2 != 0,
+                lyon_tessellation::FillRule::NonZero => winding != 0,
+            }
+        },
+        DrawType::Stroke(options) => lines
+            .filter(|(_, _, stroked)| *stroked)
+            .any(|(from, to, _)| distance_to_line(from, to, point) <= options.line_width / 2.0),
+    }
+}
+
+/// Returns what the line from `from` to `to` adds to the winding number of `point`.
+pub(crate) fn winding_number(from: Point, to: Point, point: Point) -> i32 {
+    let side = (to - from).cross(point - from);
+    match (from.y <= point.y, to.y <= point.y) {
+        (true, false) if side > 0.0 => 1,
+        (false, true) if side < 0.0 => -1,
+        _ => 0,
+    }
+}
+
+/// Returns the distance of `point` to the line from `from` to `to`.
+pub(crate) fn distance_to_line(from: Point, to: Point, point: Point) -> f32 {
+    let line = to - from;
+    let length = line.square_length();
+    let t = match length > 0.0 {
+        true => ((point - from).dot(line) / length).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    (from + line * t - point).length()
+}
+
+/// Returns the points of the flattened `segments`.
+fn outline_points(segments: &[PathEvent]) -> impl Iterator<Item = Point> + '_ {
+    segments.iter().copied().flattened(BOUNDS_TOLERANCE).filter_map(|event| match event {
+        PathEvent::Begin { at } => Some(at),
+        PathEvent::Line { to, .. } => Some(to),
+        _ => None,
+    })
+}
+
+/// Returns the color of the paint of `path`, sampled at the center of its outline.
+pub(crate) fn paint_color(path: &PathDescriptor) -> Color {
+    match path.paint {
+        Paint::Color(color) => color,
+        ref paint => paint.color_at(Box2D::from_points(outline_points(&path.segments)).center()),
+    }
+}
+
+/// State that is passed down while collecting the paths of a node tree.
+#[derive(Clone, Default)]
+struct State {
+    /// Accumulated transform of all ancestor groups.
+    transform: usvg::Transform,
+    /// All ancestor groups, the outermost first.
+    groups: Vec<Arc<Group>>,
+}
+
+/// Returns the IDs of all elements that are drawn, which can be loaded on their own as fragments.
+/// Content of `defs` and IDs that were generated while loading are left out.
+pub(crate) fn fragment_ids(tree: &usvg::Tree) -> Vec<String> {
+    tree.root().children()
+        .filter(|node| !matches!(*node.borrow(), usvg::NodeKind::Defs))
+        .flat_map(|node| node.descendants())
+        .filter_map(|node| match *node.borrow() {
+            usvg::NodeKind::Group(ref group) => Some(group.id.clone()),
+            usvg::NodeKind::Path(ref path) => Some(path.id.clone()),
+            usvg::NodeKind::Image(ref image) => Some(image.id.clone()),
+            _ => None,
+        })
+        .filter(|id| !id.is_empty() && !id.starts_with(GENERATED_ID_PREFIX))
+        .collect()
+}
+
+/// Converts all paths below `parent` into [`PathDescriptor`]s.
+fn collect_paths(tree: &usvg::Tree, source: &Source, parent: &usvg::Node, state: &State, descriptors: &mut Vec<PathDescriptor>) {
+    let mut children = parent.children().peekable();
+    while let Some(node) = children.next() {
+        match *node.borrow() {
+            usvg::NodeKind::Path(ref path) if path.visibility == usvg::Visibility::Visible => {
+                let mut t = state.transform;
+                t.append(&path.transform);
+                let abs_t = abs_transform(&t);
+                let blend_mode = source.blend_mode(&path.id);
+                let needs_group = blend_mode != BlendMode::Normal || source.is_animated(&path.id);
+                let groups = match needs_group {
+                    // Fill and stroke are blended and animated together, so they need a group of
+                    // their own, unless `usvg` already created one for the effects of the shape.
+                    true if wrapped_shape_id(parent).is_none() => {
+                        let mut groups = state.groups.clone();
+                        groups.push(Arc::new(Group {
+                            id: path.id.clone(),
+                            transform: path.transform.convert(),
+                            parent_transform: state.transform.convert(),
+                            clip_path: None,
+                            mask: None,
+                            filters: Vec::new(),
+                            opacity: 1.0,
+                            blend_mode,
+                        }));
+                        groups
+                    },
+                    _ => state.groups.clone(),
+                };
+
+                let paint_variables = source.paint_variables(&path.id);
+                // The paths of a text with several spans have no ID, only their group has one.
+                let paint_order = match (path.id.as_str(), &*parent.borrow()) {
+                    ("", usvg::NodeKind::Group(ref group)) => source.paint_order(&group.id),
+                    (id, _) => source.paint_order(id),
+                };
+                // `usvg` puts the markers of a shape into a group right after it, which is taken
+                // along when the markers are painted before the fill or the stroke.
+                let mut markers = match paint_order.markers && paint_order.layers[2] != PaintLayer::Markers {
+                    true => children.next_if(|next| matches!(*next.borrow(), usvg::NodeKind::Group(ref group) if group.id.is_empty())),
+                    false => None,
+                };
+
+                let mut fill = path.fill.as_ref().map(|fill| PathDescriptor {
+                    id: path.id.clone(),
+                    segments: (path, t).convert().collect(),
+                    abs_transform: abs_t,
+                    transform: t.convert(),
+                    paint: resolve_paint(tree, source, path, &t, &fill.paint, fill.opacity),
+                    paint_variable: paint_variables.fill,
+                    draw_type: fill.convert(),
+                    non_scaling_stroke: false,
+                    dash: None,
+                    length: 0.0,
+                    groups: groups.clone(),
+                });
+                let non_scaling_stroke = source.is_non_scaling_stroke(&path.id);
+                let mut stroke = path.stroke.as_ref().map(|stroke| PathDescriptor {
+                    id: path.id.clone(),
+                    segments: (path, t).convert().collect(),
+                    abs_transform: abs_t,
+                    transform: t.convert(),
+                    paint: resolve_paint(tree, source, path, &t, &stroke.paint, stroke.opacity),
+                    paint_variable: paint_variables.stroke,
+                    draw_type: match non_scaling_stroke {
+                        // The width is given in the coordinate system of the SVG, not in the one of the path.
+                        true => stroke.convert().scale_stroke(1.0 / transform_scale(&t) as f32),
+                        false => stroke.convert(),
+                    },
+                    non_scaling_stroke,
+                    dash: stroke.dasharray.as_ref().map(|array| Dash {
+                        array: array.iter().map(|&length| length as f32).collect(),
+                        offset: stroke.dashoffset,
+                    }),
+                    length: 0.0,
+                    groups,
+                });
+                if let Some(stroke) = stroke.as_mut() {
+                    stroke.length = dash::length(&stroke.segments);
+                }
+
+                for layer in paint_order.layers {
+                    match layer {
+                        PaintLayer::Fill => descriptors.extend(fill.take()),
+                        PaintLayer::Stroke => descriptors.extend(stroke.take()),
+                        PaintLayer::Markers => if let Some(markers) = markers.take() {
+                            if let usvg::NodeKind::Group(ref group) = *markers.borrow() {
+                                collect_group(tree, source, &markers, group, state, descriptors);
+                            }
+                        },
+                    }
+                }
+            },
+            usvg::NodeKind::Group(ref group) => collect_group(tree, source, &node, group, state, descriptors),
+            usvg::NodeKind::Image(ref image) if image.visibility == usvg::Visibility::Visible => {
+                let mut t = state.transform;
+                t.append(&image.transform);
+                let (view_box, rect) = (image.view_box, image.view_box.rect);
+
+                if let usvg::ImageKind::SVG(ref sub_tree) = image.kind {
+                    // Nested SVGs are drawn like any other content, fitted into the image rectangle.
+                    let mut state = state.clone();
+                    state.transform = t;
+                    state.transform.append(&usvg::Transform::new_translate(rect.x(), rect.y()));
+                    state.transform.append(&usvg::utils::view_box_to_transform(sub_tree.svg_node().view_box.rect, view_box.aspect, rect.size()));
+                    collect_paths(sub_tree, &Source::default(), &sub_tree.root(), &state, descriptors);
+                    continue;
+                }
+
+                let mut raster = match decode_image(&image.kind) {
+                    Some(raster) => raster,
+                    None => {
+                        warn!("Could not decode image `{}`, skipping it.", image.id);
+                        continue;
+                    },
+                };
+                // Image space into the user space of the image, then apply the same sign correction as `PathConvIter`.
+                let mut to_user_space = usvg::Transform::new_translate(rect.x(), rect.y());
+                let image_rect = usvg::Rect::new(0.0, 0.0, raster.width as f64, raster.height as f64);
+                to_user_space.append(&usvg::utils::view_box_to_transform(image_rect.unwrap_or(rect), view_box.aspect, rect.size()));
+                raster.transform = to_user_space.convert().then(&path_sign_correction(&t));
+
+                // The image is drawn inside of its rectangle, which also clips it with `slice`.
+                let path = usvg::Path {
+                    data: Rc::new(usvg::PathData::from_rect(rect)),
+                    ..Default::default()
+                };
+                descriptors.alloc().init(PathDescriptor {
+                    id: image.id.clone(),
+                    segments: (&path, t).convert().collect(),
+                    abs_transform: abs_transform(&t),
+                    transform: t.convert(),
+                    paint: Paint::Image(raster),
+                    paint_variable: None,
+                    draw_type: DrawType::Fill(lyon_tessellation::FillOptions::tolerance(FILL_TOLERANCE)),
+                    non_scaling_stroke: false,
+                    dash: None,
+                    length: 0.0,
+                    groups: state.groups.clone(),
+                });
+            },
+            // Everything else, like the content of `defs`, is only drawn when it is referenced.
+            _ => {}
+        }
+    }
+}
+
+/// Finds the content of `tree` that can't be drawn: filter primitives that aren't supported and
+/// images whose format feature is disabled.
+fn unsupported_features(tree: &usvg::Tree) -> Vec<SkippedFeature> {
+    let mut skipped = Vec::new();
+    for node in tree.root().descendants() {
+        match *node.borrow() {
+            usvg::NodeKind::Group(ref group) => {
+                let element_id = match group.id.as_str() {
+                    "" => wrapped_shape_id(&node),
+                    id => Some(id.to_owned()),
+                };
+                for filter_node in group.filter.iter().filter_map(|id| tree.defs_by_id(id)) {
+                    if let usvg::NodeKind::Filter(ref filter) = *filter_node.borrow() {
+                        skipped.extend(filter.primitives.iter()
+                            .filter_map(|primitive| unsupported_primitive(&primitive.kind))
+                            .map(|feature| SkippedFeature { feature: feature.to_owned(), element_id: element_id.clone() }));
+                    }
+                }
+            },
+            usvg::NodeKind::Image(ref image) => {
+                let feature = match image.kind {
+                    usvg::ImageKind::PNG(_) if !cfg!(feature = "png") => "PNG image",
+                    usvg::ImageKind::JPEG(_) if !cfg!(feature = "jpeg") => "JPEG image",
+                    _ => continue,
+                };
+                let element_id = Some(image.id.clone()).filter(|id| !id.is_empty());
+                skipped.push(SkippedFeature { feature: feature.to_owned(), element_id });
+            },
+            _ => {},
+        }
+    }
+    skipped
+}
+
+/// Converts all paths of the group `node` into [`PathDescriptor`]s.
+fn collect_group(tree: &usvg::Tree, source: &Source, node: &usvg::Node, group: &usvg::Group, state: &State, descriptors: &mut Vec<PathDescriptor>) {
+    let mut state = state.clone();
+    let parent_transform = state.transform;
+    state.transform.append(&group.transform);
+    let clip_path = group.clip_path.as_ref().and_then(|id| {
+        let clip_path = resolve_clip_path(tree, source, node, id, &state.transform);
+        if clip_path.is_none() {
+            warn!("Could not resolve clip path `{}`, drawing the group unclipped.", id);
+        }
+        clip_path.map(Arc::new)
+    });
+    let mask = group.mask.as_ref().and_then(|id| {
+        let mask = resolve_mask(tree, source, node, id, &state.transform);
+        if mask.is_none() {
+            warn!("Could not resolve mask `{}`, drawing the group unmasked.", id);
+        }
+        mask.map(Arc::new)
+    });
+    let filters = group.filter.iter()
+        .filter_map(|id| {
+            let filter = resolve_filter(tree, node, id, &state.transform);
+            if filter.is_none() {
+                warn!("Could not resolve filter `{}`, drawing the group unfiltered.", id);
+            }
+            filter
+        })
+        .collect();
+    // The group that `usvg` created for the effects of a shape stands in for the shape.
+    let id = match group.id.as_str() {
+        "" => wrapped_shape_id(node).unwrap_or_default(),
+        id => id.to_owned(),
+    };
+    state.groups.push(Arc::new(Group {
+        blend_mode: source.blend_mode(&id),
+        id,
+        transform: group.transform.convert(),
+        parent_transform: parent_transform.convert(),
+        clip_path,
+        mask,
+        filters,
+        opacity: group.opacity.value() as f32,
+    }));
+    collect_paths(tree, source, node, &state, descriptors);
+}
+
+/// Returns the ID of the shape inside of `node`, if `node` is the anonymous group that `usvg`
+/// creates around a single shape to hold its effects, like its opacity or clip path.
+fn wrapped_shape_id(node: &usvg::Node) -> Option<String> {
+    match *node.borrow() {
+        usvg::NodeKind::Group(ref group) if group.id.is_empty() => {},
+        _ => return None,
+    }
+    let child = node.first_child()?;
+    if child.next_sibling().is_some() {
+        return None;
+    }
+    let id = match *child.borrow() {
+        usvg::NodeKind::Path(ref path) => path.id.clone(),
+        _ => return None,
+    };
+    Some(id)
+}
+
+/// Resolves the clip path with the given `id`, which is referenced by the group `node`.
+/// `transform` is the accumulated transform of the group.
+fn resolve_clip_path(tree: &usvg::Tree, source: &Source, node: &usvg::Node, id: &str, transform: &usvg::Transform) -> Option<ClipPath> {
+    let clip_node = tree.defs_by_id(id)?;
+    let clip = match *clip_node.borrow() {
+        usvg::NodeKind::ClipPath(ref clip) => clip.clone(),
+        _ => return None,
+    };
+
+    let mut clip_transform = *transform;
+    clip_transform.append(&clip.transform);
+    if clip.units == usvg::Units::ObjectBoundingBox {
+        let bbox = node_bbox(node, usvg::Transform::default())?;
+        clip_transform.append(&usvg::Transform::new(bbox.width(), 0.0, 0.0, bbox.height(), bbox.x(), bbox.y()));
+    }
+
+    let mut paths = Vec::new();
+    collect_paths(tree, source, &clip_node, &State { transform: clip_transform, ..Default::default() }, &mut paths);
+
+    // A clip path can be clipped itself.
+    let clip_path = match clip.clip_path {
+        Some(ref id) => Some(Arc::new(resolve_clip_path(tree, source, node, id, transform)?)),
+        None => None,
+    };
+
+    Some(ClipPath { paths, clip_path })
+}
+
+/// Resolves the mask with the given `id`, which is referenced by the group `node`.
+/// `transform` is the accumulated transform of the group.
+fn resolve_mask(tree: &usvg::Tree, source: &Source, node: &usvg::Node, id: &str, transform: &usvg::Transform) -> Option<Mask> {
+    let mask_node = tree.defs_by_id(id)?;
+    let mask = match *mask_node.borrow() {
+        usvg::NodeKind::Mask(ref mask) => mask.clone(),
+        _ => return None,
+    };
+
+    let bbox = node_bbox(node, usvg::Transform::default()).and_then(|bbox| bbox.to_rect());
+    let rect = match mask.units {
+        usvg::Units::UserSpaceOnUse => mask.rect,
+        usvg::Units::ObjectBoundingBox => mask.rect.bbox_transform(bbox?),
+    };
+
+    let mut content_transform = *transform;
+    if mask.content_units == usvg::Units::ObjectBoundingBox {
+        let bbox = bbox?;
+        content_transform.append(&usvg::Transform::new(bbox.width(), 0.0, 0.0, bbox.height(), bbox.x(), bbox.y()));
+    }
+
+    let mut paths = Vec::new();
+    collect_paths(tree, source, &mask_node, &State { transform: content_transform, ..Default::default() }, &mut paths);
+
+    // A mask can be masked itself.
+    let nested = match mask.mask {
+        Some(ref id) => Some(Arc::new(resolve_mask(tree, source, node, id, transform)?)),
+        None => None,
+    };
+
+    Some(Mask {
+        paths,
+        rect: lyon_geom::rect(rect.x() as f32, rect.y() as f32, rect.width() as f32, rect.height() as f32),
+        transform: transform.convert(),
+        mask: nested,
+    })
+}
+
+/// Computes the bounding box of all paths below `node`, `transform` is applied to all of them.
+pub(crate) fn node_bbox(node: &usvg::Node, transform: usvg::Transform) -> Option<usvg::PathBbox> {
+    let mut bbox: Option<usvg::PathBbox> = None;
+    for child in node.children() {
+        let mut child_transform = transform;
+        child_transform.append(&child.borrow().transform());
+        let child_bbox = match *child.borrow() {
+            usvg::NodeKind::Path(ref path) => path.data.bbox_with_transform(child_transform, None),
+            usvg::NodeKind::Group(_) => node_bbox(&child, child_transform),
+            _ => None,
+        };
+        bbox = match (bbox, child_bbox) {
+            (Some(bbox), Some(child_bbox)) => Some(bbox.expand(child_bbox)),
+            (bbox, child_bbox) => bbox.or(child_bbox),
+        };
+    }
+    bbox
+}
+
+/// Resolves the paint of a fill or stroke, looking up referenced paint servers in the `tree`.
+/// `transform` is the accumulated transform of the `path`.
+fn resolve_paint(
+    tree: &usvg::Tree,
+    source: &Source,
+    path: &usvg::Path,
+    transform: &usvg::Transform,
+    paint: &usvg::Paint,
+    opacity: usvg::Opacity,
+) -> Paint {
+    match paint {
+        usvg::Paint::Color(c) => Paint::Color(Color::rgba_u8(c.red, c.green, c.blue, opacity.to_u8())),
+        usvg::Paint::Link(id) => {
+            let node = match tree.defs_by_id(id) {
+                Some(node) => node,
+                None => return Paint::default(),
+            };
+            let paint = match *node.borrow() {
+                usvg::NodeKind::LinearGradient(ref gradient) => {
+                    gradient_transform(path, transform, gradient.units, &gradient.transform).map(|transform| {
+                        Paint::LinearGradient(LinearGradient {
+                            start: (&gradient.x1, &gradient.y1).convert(),
+                            end: (&gradient.x2, &gradient.y2).convert(),
+                            stops: (gradient.stops.as_slice(), opacity).convert(),
+                            spread: gradient.spread_method.convert(),
+                            transform,
+                        })
+                    })
+                },
+                usvg::NodeKind::RadialGradient(ref gradient) => {
+                    gradient_transform(path, transform, gradient.units, &gradient.transform).map(|transform| {
+                        Paint::RadialGradient(RadialGradient {
+                            center: (&gradient.cx, &gradient.cy).convert(),
+                            radius: gradient.r.value() as f32,
+                            focal: (&gradient.fx, &gradient.fy).convert(),
+                            stops: (gradient.stops.as_slice(), opacity).convert(),
+                            spread: gradient.spread_method.convert(),
+                            transform,
+                        })
+                    })
+                },
+                usvg::NodeKind::Pattern(ref pattern) => {
+                    resolve_pattern(tree, source, path, transform, &node, pattern, opacity).map(Paint::Pattern)
+                },
+                _ => None,
+            };
+
+            paint.unwrap_or_default()
+        },
+    }
+}
+
+/// Resolves a `pattern` paint server, which is referenced by `path`.
+fn resolve_pattern(
+    tree: &usvg::Tree,
+    source: &Source,
+    path: &usvg::Path,
+    transform: &usvg::Transform,
+    node: &usvg::Node,
+    pattern: &usvg::Pattern,
+    opacity: usvg::Opacity,
+) -> Option<Pattern> {
+    let bbox = path.data.bbox().and_then(|bbox| bbox.to_rect());
+    let rect = match pattern.units {
+        usvg::Units::UserSpaceOnUse => pattern.rect,
+        usvg::Units::ObjectBoundingBox => pattern.rect.bbox_transform(bbox?),
+    };
+
+    let content_transform = if let Some(view_box) = pattern.view_box {
+        usvg::utils::view_box_to_transform(view_box.rect, view_box.aspect, rect.size())
+    } else if pattern.content_units == usvg::Units::ObjectBoundingBox {
+        let bbox = bbox?;
+        usvg::Transform::new_scale(bbox.width(), bbox.height())
+    } else {
+        usvg::Transform::default()
+    };
+
+    let mut tile = Vec::new();
+    collect_paths(tree, source, node, &State::default(), &mut tile);
+
+    Some(Pattern {
+        tile,
+        content_transform: content_transform.convert()
+            .then_translate(lyon_tessellation::math::vector(rect.x() as f32, rect.y() as f32)),
+        rect: lyon_geom::rect(rect.x() as f32, rect.y() as f32, rect.width() as f32, rect.height() as f32),
+        opacity: opacity.value() as f32,
+        // Pattern space into the user space of the path, then apply the same sign correction as `PathConvIter`.
+        transform: pattern.transform.convert().then(&path_sign_correction(transform)),
+    })
+}
+
+/// Computes the transform that maps a point of the tessellated `path` into the coordinate
+/// system of a gradient with the given `units` and `gradientTransform`.
+fn gradient_transform(path: &usvg::Path, transform: &usvg::Transform, units: usvg::Units, gradient_transform: &usvg::Transform) -> Option<Transform2D<f32>> {
+    let to_user_space = match units {
+        usvg::Units::UserSpaceOnUse => Transform2D::identity(),
+        usvg::Units::ObjectBoundingBox => {
+            let bbox = path.data.bbox()?;
+            Transform2D::new(
+                bbox.width() as f32, 0.0,
+                0.0, bbox.height() as f32,
+                bbox.x() as f32, bbox.y() as f32,
+            )
+        },
+    };
+    // The `gradientTransform` is applied inside of the bounding box for `objectBoundingBox`.
+    let to_user_space = gradient_transform.convert().then(&to_user_space);
+
+    // Undo the sign correction of `PathConvIter` to get back into the user space of the path.
+    Some(path_sign_correction(transform).then(&to_user_space.inverse()?))
+}
+
+/// Converts the accumulated transform of a path into the [`Transform`] of its [`PathDescriptor`].
+fn abs_transform(t: &usvg::Transform) -> Transform {
+    Transform::from_matrix(
+        Mat4::from_cols(
+            [t.a.abs() as f32, t.b as f32,       0.0, 0.0].into(),
+            [t.c as f32,       t.d.abs() as f32, 0.0, 0.0].into(),
+            [0.0,              0.0,              1.0, 0.0].into(),
+            [t.e as f32,       t.f as f32,       0.0, 1.0].into()
+        )
+    )
+}
+
+/// Returns the factor by which `t` scales lengths, the geometric mean of both axes.
+fn transform_scale(t: &usvg::Transform) -> f64 {
+    (t.a * t.d - t.b * t.c).abs().sqrt()
+}
+
+/// Decodes the data of a PNG or JPEG image, with the image formats that are enabled in Bevy.
+/// The transform of the returned image is the identity.
+///
+/// `usvg` already reads referenced files and decodes base64 data URIs, but the format is taken
+/// from the MIME type of a data URI, which some exporters get wrong. So the format is determined
+/// from the data itself, if possible.
+fn decode_image(kind: &usvg::ImageKind) -> Option<RasterImage> {
+    let (data, extension) = match kind {
+        usvg::ImageKind::PNG(data) => (data, "png"),
+        usvg::ImageKind::JPEG(data) => (data, "jpeg"),
+        usvg::ImageKind::SVG(_) => return None,
+    };
+    let extension = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "png"
+    } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        "jpeg"
+    } else {
+        extension
+    };
+
+    let image = Image::from_buffer(data, ImageType::Extension(extension), CompressedImageFormats::NONE, true)
+        .map_err(|err| warn!("Failed to decode {} image: {}", extension, err))
+        .ok()?;
+    if image.texture_descriptor.format != TextureFormat::Rgba8UnormSrgb {
+        warn!("Unsupported image format {:?}", image.texture_descriptor.format);
+        return None;
+    }
+
+    Some(RasterImage {
+        width: image.texture_descriptor.size.width,
+        height: image.texture_descriptor.size.height,
+        data: Arc::new(image.data),
+        transform: Transform2D::identity(),
+    })
+}
+
+/// For some reason the transform of some paths has negative scale values.
+/// This is the transform that corrects them to positive values.
+fn path_sign_correction(transform: &usvg::Transform) -> Transform2D<f32> {
+    lyon_geom::Transform::scale(
+        if transform.a < 0.0 { -1.0 } else { 1.0 },
+        if transform.d < 0.0 { -1.0 } else { 1.0 }
+    )
+}
+
+/// Draws the [`Svg`] of an entity with a fixed size, in world units, independent of the size in
+/// its file.
+///
+/// The [`Origin`] refers to this size, and the scale of the `Transform` still applies on top of it.
+/// How the SVG is fitted into the size is decided by the [`SvgScaleMode`] of the entity.
+#[derive(Clone, Component, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct SvgSize {
+    /// Width of the SVG.
+    pub width: f32,
+    /// Height of the SVG.
+    pub height: f32,
+}
+
+impl SvgSize {
+    /// Creates a size of `width` × `height`.
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
+    /// Returns how much the mesh of an SVG of `svg_size` is scaled on both axes to fit into this
+    /// size with `mode`, and how far it is moved to be centered in it, with the y-axis of Bevy.
+    pub(crate) fn layout(&self, svg_size: Vec2, mode: SvgScaleMode) -> (Vec2, Vec2) {
+        let size = Vec2::new(self.width, self.height);
+        let stretched = size / svg_size;
+        // SVGs without a size can't be scaled to one.
+        if !stretched.is_finite() {
+            return (Vec2::ONE, Vec2::ZERO);
+        }
+        let scale = match mode {
+            SvgScaleMode::Stretch => stretched,
+            SvgScaleMode::Fit => Vec2::splat(stretched.min_element()),
+            SvgScaleMode::Fill => Vec2::splat(stretched.max_element()),
+            SvgScaleMode::None => Vec2::ONE,
+        };
+        let offset = (size - svg_size * scale) * 0.5;
+        (scale, Vec2::new(offset.x, -offset.y))
+    }
+}
+
+/// How the SVG of an entity with a [`SvgSize`] is fitted into that size, like
+/// `preserveAspectRatio` does with the view box.
+///
+/// The SVG is centered in the size. Content that sticks out of it is still drawn, it isn't
+/// clipped.
+#[derive(Clone, Component, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect_value(Component, PartialEq)]
+pub enum SvgScaleMode {
+    /// Scales both axes independently to match the size exactly, which distorts the SVG if its
+    /// aspect ratio differs.
+    Stretch,
+    /// Scales the SVG as large as it fits into the size while keeping its aspect ratio, leaving
+    /// empty space on two sides, like `xMidYMid meet`.
+    Fit,
+    /// Scales the SVG as small as it covers the whole size while keeping its aspect ratio, so it
+    /// sticks out on two sides, like `xMidYMid slice`.
+    Fill,
+    /// Keeps the size of the file.
+    None,
+}
+
+impl Default for SvgScaleMode {
+    fn default() -> Self {
+        SvgScaleMode::Stretch
+    }
+}
+
+/// Which way the y-axis of the world points, which decides how SVGs are placed and which way up
+/// they are drawn.
+///
+/// Insert it as resource before adding the [`SvgPlugin`](crate::prelude::SvgPlugin) to change it
+/// for all entities, or add it to a single entity. With [`SvgYAxis::Down`], like with a camera
+/// that is flipped for screen coordinates, the SVG is flipped to stay upright and the [`Origin`]
+/// is placed with the same y-axis as its file.
+#[derive(Clone, Component, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect_value(Component, PartialEq)]
+pub enum SvgYAxis {
+    /// The y-axis points up, like Bevy's.
+    Up,
+    /// The y-axis points down, like the one of SVG files.
+    Down,
+}
+
+impl Default for SvgYAxis {
+    fn default() -> Self {
+        SvgYAxis::Up
+    }
+}
+
+/// Whether the back of the SVG of a [`Svg3dBundle`](crate::prelude::Svg3dBundle) is drawn, seen
+/// from behind it is mirrored. On by default.
+///
+/// Without it, only the side of the mesh that faces the camera along its normals is drawn, like the
+/// front of a playing card with an SVG of its own on the back, or the outside of an extruded solid.
+#[derive(Clone, Component, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgDoubleSided(pub bool);
+
+impl Default for SvgDoubleSided {
+    fn default() -> Self {
+        SvgDoubleSided(true)
+    }
+}
+
+/// Draws the SVG of a 2D entity with a single instanced draw call, together with all other
+/// entities with this component that share the same mesh, like thousands of identical markers on
+/// a map. Each instance keeps its own transform and [`SvgTint`](crate::prelude::SvgTint).
+///
+/// The instances are drawn from back to front among themselves, but all of them at the depth of
+/// the backmost one compared to other sprites and meshes. Entities with runtime overrides that
+/// give them their own mesh, like [`SvgColor`](crate::prelude::SvgColor), are only drawn together
+/// with entities that have the same mesh.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgInstancing;
+
+/// Merges the SVG of a 2D entity into one mesh with the SVGs of all other entities with this
+/// component, which is drawn with a single draw call, like many different small icons in a
+/// toolbar. Each entity keeps its own transform and [`SvgTint`](crate::prelude::SvgTint).
+///
+/// The meshes are merged on the CPU every frame, from back to front among themselves, and drawn
+/// at the depth of the backmost one compared to other sprites and meshes, so this pays off for
+/// many small meshes rather than a few large ones. Entities with [`SvgInstancing`] or
+/// [`SvgSdf`](crate::prelude::SvgSdf) are drawn by those instead.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct SvgBatching;
+
+#[derive(Clone, Component, Copy, Debug, PartialEq, Reflect)]
+#[reflect_value(Component, PartialEq)]
 /// Origin of the coordinate system.
 pub enum Origin {
     /// Bottom left of the image or viewbox.
@@ -100,6 +1268,9 @@ pub enum Origin {
     TopLeft,
     /// Top right of the image or viewbox.
     TopRight,
+    /// A point of the image or viewbox, relative to its size: `(0, 0)` is the top left and
+    /// `(1, 1)` the bottom right, like the tip of an arrow that should point at the position.
+    Custom(Vec2),
 }
 
 impl Default for Origin {
@@ -135,24 +1306,153 @@ impl Origin {
                 0.0,
                 0.0
             ),
+            Origin::Custom(point) => Vec3::new(
+                -scaled_size.x * point.x,
+                scaled_size.y * point.y,
+                0.0
+            ),
         }
     }
 }
 
-#[derive(Debug)]
+/// The paths of an [`Svg`] that changed since its mesh was last tessellated.
+#[derive(Clone, Debug)]
+pub(crate) enum ModifiedPaths {
+    Unchanged,
+    /// The indices of the changed paths.
+    Paths(HashSet<usize>),
+    /// Paths may have been added, removed or reordered as well.
+    All,
+}
+
+impl Default for ModifiedPaths {
+    fn default() -> Self {
+        ModifiedPaths::Unchanged
+    }
+}
+
+impl ModifiedPaths {
+    /// Marks the paths at the `indices` as changed.
+    fn insert(&mut self, indices: Range<usize>) {
+        match self {
+            ModifiedPaths::Unchanged => *self = ModifiedPaths::Paths(indices.collect()),
+            ModifiedPaths::Paths(paths) => paths.extend(indices),
+            ModifiedPaths::All => {},
+        }
+    }
+
+    /// Returns whether one of the paths at the `indices` changed.
+    pub(crate) fn intersects(&self, indices: Range<usize>) -> bool {
+        match self {
+            ModifiedPaths::Unchanged => false,
+            ModifiedPaths::Paths(paths) => indices.into_iter().any(|index| paths.contains(&index)),
+            ModifiedPaths::All => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct PathDescriptor {
+    /// The ID of the shape that the path belongs to, can be empty.
+    pub id: String,
     pub segments: Vec<PathEvent>,
     pub abs_transform: Transform,
-    pub color: Color,
+    /// Accumulated transform of the shape, in the coordinate system of the SVG.
+    pub transform: Transform2D<f32>,
+    pub paint: Paint,
+    /// Name of the CSS custom property, like `--accent`, that the color of the paint comes from.
+    pub paint_variable: Option<String>,
     pub draw_type: DrawType,
+    /// Whether the path is a stroke with `vector-effect="non-scaling-stroke"`, whose width
+    /// doesn't change when the entity is scaled.
+    pub non_scaling_stroke: bool,
+    /// The dash pattern of a stroke, `None` for solid strokes and fills.
+    pub dash: Option<Dash>,
+    /// Length of a stroke along all of its subpaths, in the coordinate system of the path. Zero
+    /// for fills.
+    pub length: f32,
+    /// All ancestor groups of the path, the outermost first.
+    pub groups: Vec<Arc<Group>>,
+}
+
+/// A group, `g` in SVG, and the effects that are applied to its content as a whole.
+///
+/// Paths that share the same [`Group`] in their [`PathDescriptor::groups`] at the same depth
+/// are always stored next to each other.
+#[derive(Debug)]
+pub struct Group {
+    /// The ID of the group, can be empty. Groups that only hold the effects of a single shape
+    /// have the ID of the shape.
+    pub id: String,
+    /// Transform of the group, relative to its parent.
+    pub transform: Transform2D<f32>,
+    /// Accumulated transform of all ancestors of the group.
+    pub parent_transform: Transform2D<f32>,
+    /// Clip path of the group.
+    pub clip_path: Option<Arc<ClipPath>>,
+    /// Mask of the group.
+    pub mask: Option<Arc<Mask>>,
+    /// Filters of the group, applied in order before the clip path and mask.
+    pub filters: Vec<Filter>,
+    /// Opacity of the group, applied to the composited content after all other effects.
+    pub opacity: f32,
+    /// How the group is blended with the content of its parent that is drawn below it,
+    /// `mix-blend-mode` in CSS.
+    pub blend_mode: BlendMode,
 }
 
+/// A clip region, `clipPath` in SVG.
 #[derive(Debug)]
+pub struct ClipPath {
+    /// All paths that make up the clip region, only their geometry is used.
+    pub paths: Vec<PathDescriptor>,
+    /// An additional clip path, which clips this clip region.
+    pub clip_path: Option<Arc<ClipPath>>,
+}
+
+/// A luminance mask, `mask` in SVG.
+///
+/// The luminance and alpha of the mask content are multiplied into the alpha of the masked paths.
+/// Overlapping shapes inside of a mask are not composited with each other.
+#[derive(Debug)]
+pub struct Mask {
+    /// All paths that make up the content of the mask.
+    pub paths: Vec<PathDescriptor>,
+    /// The mask region, nothing outside of it is visible.
+    pub rect: lyon_tessellation::math::Rect,
+    /// Maps the mask region into the coordinate system of the masked paths.
+    pub transform: Transform2D<f32>,
+    /// An additional mask, which masks this mask.
+    pub mask: Option<Arc<Mask>>,
+}
+
+/// The dash pattern of a stroke, `stroke-dasharray` and `stroke-dashoffset` in SVG.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dash {
+    /// Alternating lengths of dashes and gaps, always an even number of lengths.
+    pub array: Vec<f32>,
+    /// Distance into the dash pattern at which the stroke starts.
+    pub offset: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DrawType {
-    Fill,
+    Fill(lyon_tessellation::FillOptions),
     Stroke(lyon_tessellation::StrokeOptions),
 }
 
+impl DrawType {
+    /// Multiplies the width of a stroke with `factor`, fills stay as they are.
+    pub(crate) fn scale_stroke(self, factor: f32) -> DrawType {
+        match self {
+            DrawType::Stroke(options) => DrawType::Stroke(options.with_line_width(options.line_width * factor)),
+            fill => fill,
+        }
+    }
+}
+
 // Taken from https://github.com/nical/lyon/blob/74e6b137fea70d71d3b537babae22c6652f8843e/examples/wgpu_svg/src/main.rs
 struct PathConvIter<'a> {
     iter: std::slice::Iter<'a, usvg::PathSegment>,
@@ -188,6 +1488,7 @@ impl<'l> Iterator for PathConvIter<'l> {
                     });
                 } else {
                     self.first = (x, y).convert();
+                    self.prev = self.first;
                     return_event = Some(PathEvent::Begin { at: self.first });
                 }
             }
@@ -220,9 +1521,10 @@ impl<'l> Iterator for PathConvIter<'l> {
             }
             Some(usvg::PathSegment::ClosePath) => {
                 self.needs_end = false;
+                let last = self.prev;
                 self.prev = self.first;
                 return_event = Some(PathEvent::End {
-                    last: self.prev,
+                    last,
                     first: self.first,
                     close: true,
                 });
@@ -251,32 +1553,32 @@ impl Convert<Point> for (&f64, &f64) {
     }
 }
 
-impl<'a> Convert<PathConvIter<'a>> for &'a usvg::Path {
+impl<'a> Convert<PathConvIter<'a>> for (&'a usvg::Path, usvg::Transform) {
     fn convert(self) -> PathConvIter<'a> {
+        let (path, transform) = self;
         PathConvIter {
-            iter: self.data.iter(),
+            iter: path.data.iter(),
             first: Point::new(0.0, 0.0),
             prev: Point::new(0.0, 0.0),
             deferred: None,
             needs_end: false,
-            // For some reason the local transform of some paths has negative scale values.
-            // Here we correct to positive values.
-            scale: lyon_geom::Transform::scale(
-                if self.transform.a < 0.0 { -1.0 } else { 1.0 },
-                if self.transform.d < 0.0 { -1.0 } else { 1.0 }
-            )
+            scale: path_sign_correction(&transform),
         }
     }
 }
 
-impl Convert<(Color, DrawType)> for &usvg::Stroke {
-    fn convert(self) -> (Color, DrawType) {
-        let color = match self.paint {
-            usvg::Paint::Color(c) =>
-                Color::rgba_u8(c.red, c.green, c.blue, self.opacity.to_u8()),
-            _ => Color::default(),
+impl Convert<DrawType> for &usvg::Fill {
+    fn convert(self) -> DrawType {
+        let fill_rule = match self.rule {
+            usvg::FillRule::NonZero => lyon_tessellation::FillRule::NonZero,
+            usvg::FillRule::EvenOdd => lyon_tessellation::FillRule::EvenOdd,
         };
+        DrawType::Fill(lyon_tessellation::FillOptions::tolerance(FILL_TOLERANCE).with_fill_rule(fill_rule))
+    }
+}
 
+impl Convert<DrawType> for &usvg::Stroke {
+    fn convert(self) -> DrawType {
         let linecap = match self.linecap {
             usvg::LineCap::Butt => lyon_tessellation::LineCap::Butt,
             usvg::LineCap::Square => lyon_tessellation::LineCap::Square,
@@ -288,11 +1590,13 @@ impl Convert<(Color, DrawType)> for &usvg::Stroke {
             usvg::LineJoin::Round => lyon_tessellation::LineJoin::Round,
         };
 
-        let opt = lyon_tessellation::StrokeOptions::tolerance(0.01)
+        let opt = lyon_tessellation::StrokeOptions::tolerance(STROKE_TOLERANCE)
             .with_line_width(self.width.value() as f32)
             .with_line_cap(linecap)
-            .with_line_join(linejoin);
+            .with_line_join(linejoin)
+            // `usvg` already rejects limits below 1, lyon would panic on them.
+            .with_miter_limit((self.miterlimit.value() as f32).max(lyon_tessellation::StrokeOptions::MINIMUM_MITER_LIMIT));
 
-        (color, DrawType::Stroke(opt))
+        DrawType::Stroke(opt)
     }
 }
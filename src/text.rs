@@ -0,0 +1,176 @@
+//! Lays out `text` elements again with the content of a
+//! [`SvgTextOverrides`](crate::prelude::SvgTextOverrides).
+//!
+//! `usvg` turns texts into paths while parsing, so a copy of the document that only draws the
+//! replaced element is parsed again with the new content, and the paths are taken from it.
+
+use std::{collections::{HashMap, HashSet}, path::PathBuf, sync::Mutex};
+
+use bevy::{log::warn, math::Vec2};
+
+use crate::{
+    font::SvgFontDatabase,
+    loader::{self, ParseOptions, SvgDpi, SvgLanguages, SvgParserOptions},
+    source::{self, TextContent},
+    svg::{is_part_of, PathDescriptor, Svg},
+};
+
+
+/// The document of an [`Svg`] and everything it was parsed with, which its `text` elements with
+/// an ID are laid out again from.
+#[derive(Debug)]
+pub(crate) struct TextSource {
+    /// The document with the elements of other files inlined, before it was preprocessed.
+    document: String,
+    style_sheets: Vec<String>,
+    /// Where the content of the `text` elements is, keyed by their ID.
+    contents: HashMap<String, TextContent>,
+    /// The documents that only draw a single `text` element, see [`source::text_document`], and
+    /// where its content is in them. Keyed by the ID of the element, created when it is laid out
+    /// for the first time.
+    documents: Mutex<HashMap<String, (String, TextContent)>>,
+    dpi: SvgDpi,
+    languages: SvgLanguages,
+    parser: SvgParserOptions,
+    default_size: Option<Vec2>,
+    resources_dir: Option<PathBuf>,
+}
+
+impl TextSource {
+    /// Keeps the document `text` that is parsed with the `style_sheets` and `options`. Returns
+    /// `None` if it has no `text` elements with an ID.
+    pub(crate) fn new(text: &str, style_sheets: &[String], options: &ParseOptions<'_>) -> Option<Self> {
+        let contents = source::text_contents(text);
+        if contents.is_empty() {
+            return None;
+        }
+        Some(Self {
+            document: text.to_owned(),
+            style_sheets: style_sheets.to_vec(),
+            contents,
+            documents: Mutex::default(),
+            dpi: options.dpi,
+            languages: options.languages.clone(),
+            parser: options.parser.clone(),
+            default_size: options.default_size,
+            resources_dir: options.resources_dir.clone(),
+        })
+    }
+
+    /// Lays out the `text` element with the ID `id` with the plain text `content`, and returns its
+    /// paths. `None` if there is no such element or the document can't be parsed anymore.
+    fn shape(&self, id: &str, content: &str, fonts: &SvgFontDatabase) -> Option<Vec<PathDescriptor>> {
+        let text_content = match self.contents.get(id) {
+            Some(text_content) => text_content,
+            None => {
+                warn!("There is no `text` element with the ID `{}`, its content can't be replaced.", id);
+                return None;
+            },
+        };
+        let document = {
+            let mut documents = self.documents.lock().unwrap();
+            let (document, text_content) = documents.entry(id.to_owned()).or_insert_with(|| {
+                source::text_document(&self.document, id)
+                    .and_then(|document| {
+                        let text_content = source::text_contents(&document).remove(id)?;
+                        Some((document, text_content))
+                    })
+                    // Laying out the element in the whole document gives the same paths.
+                    .unwrap_or_else(|| (self.document.clone(), text_content.clone()))
+            });
+            source::replace_text_content(document, text_content, content)
+        };
+        let options = ParseOptions {
+            fonts,
+            dpi: self.dpi,
+            languages: &self.languages,
+            parser: &self.parser,
+            tolerance: None,
+            default_size: self.default_size,
+            time_sliced_above: None,
+            fragments: false,
+            resources_dir: self.resources_dir.clone(),
+            progress: None,
+        };
+        match loader::parse_tree(document, &self.style_sheets, &options) {
+            Ok((svg, ..)) => Some(svg.paths.into_iter().filter(|path| is_part_of(path, id)).collect()),
+            Err(err) => {
+                warn!("Could not lay out the text `{}` with its new content: {}", id, err);
+                None
+            },
+        }
+    }
+}
+
+/// The texts of an entity that were laid out with new content, keyed by their ID.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ShapedTexts {
+    /// The content and the paths of every text, `None` if it couldn't be laid out.
+    texts: HashMap<String, (String, Option<Vec<PathDescriptor>>)>,
+}
+
+impl ShapedTexts {
+    /// Returns `svg` with the `text` elements of `contents` replaced by their new content, keyed
+    /// by their ID. Only the texts whose content changed since the last call are laid out again.
+    /// Returns `None` if no text is replaced.
+    pub(crate) fn substitute(&mut self, svg: &Svg, contents: &HashMap<String, String>, fonts: &SvgFontDatabase) -> Option<Svg> {
+        let source = svg.texts.as_ref()?;
+        self.texts.retain(|id, (content, _)| contents.get(id) == Some(content));
+        for (id, content) in contents {
+            if !self.texts.contains_key(id) {
+                self.texts.insert(id.clone(), (content.clone(), source.shape(id, content, fonts)));
+            }
+        }
+
+        let mut paths = Vec::with_capacity(svg.paths.len());
+        let mut replaced = HashSet::new();
+        for path in &svg.paths {
+            let shaped = self.texts.iter().find_map(|(id, (_, shaped))| match shaped {
+                Some(shaped) if is_part_of(path, id) => Some((id, shaped)),
+                _ => None,
+            });
+            match shaped {
+                // The new paths take the place of the first path of the old content.
+                Some((id, shaped)) => if replaced.insert(id) {
+                    paths.extend(shaped.iter().map(|shaped| rebase(shaped, path, id)));
+                },
+                None => paths.push(path.clone()),
+            }
+        }
+        if replaced.is_empty() {
+            return None;
+        }
+
+        Some(Svg {
+            name: svg.name.clone(),
+            size: svg.size,
+            view_box: svg.view_box,
+            content_rect: svg.content_rect,
+            paths,
+            animations: svg.animations.clone(),
+            transitions: svg.transitions.clone(),
+            hidden: svg.hidden.clone(),
+            skipped_features: svg.skipped_features.clone(),
+            dependencies: Vec::new(),
+            tolerance: svg.tolerance,
+            time_sliced: false,
+            texts: None,
+            modified_paths: Default::default(),
+            mesh: Default::default(),
+        })
+    }
+}
+
+/// Returns the path `shaped` of the `text` element `id`, with the groups around the element
+/// taken from `original`, a path of the element in the [`Svg`] it is put into. Groups are only
+/// drawn together if the paths share them.
+fn rebase(shaped: &PathDescriptor, original: &PathDescriptor, id: &str) -> PathDescriptor {
+    let outer = original.groups.iter()
+        .position(|group| group.id == id)
+        .unwrap_or(original.groups.len());
+    let mut path = shaped.clone();
+    if path.groups.len() >= outer {
+        path.groups.splice(..outer, original.groups[..outer].iter().cloned());
+    }
+    path
+}
@@ -0,0 +1,103 @@
+//! Tessellation of huge SVGs over several frames, so that loading them doesn't block a frame
+//! until every path is tessellated.
+
+use bevy::{
+    asset::{AssetEvent, AssetServer, Assets, Handle},
+    ecs::{event::EventReader, system::{Local, Res, ResMut}},
+    render::mesh::Mesh,
+    utils::{Duration, Instant},
+};
+use lyon_tessellation::{FillTessellator, StrokeTessellator};
+
+use crate::{
+    progress::{SvgLoadProgress, SvgLoadStage, SvgProgress},
+    render::{tessellation, vertex_buffer::VertexBuffers},
+    svg::Svg,
+    Convert,
+};
+
+
+/// Decides which SVGs are tessellated over several frames instead of while they are loaded.
+///
+/// The mesh of such an SVG starts out empty and grows every frame, until all paths are
+/// tessellated. Insert this resource before adding the [`SvgPlugin`](crate::prelude::SvgPlugin)
+/// to change it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgTimeSlicing {
+    /// SVGs with more paths than this are tessellated over several frames, `None` turns it off.
+    pub min_paths: Option<usize>,
+    /// How long tessellating may take in each frame, shared by all SVGs.
+    pub frame_budget: Duration,
+}
+
+impl Default for SvgTimeSlicing {
+    fn default() -> Self {
+        Self {
+            min_paths: Some(10_000),
+            frame_budget: Duration::from_millis(4),
+        }
+    }
+}
+
+/// A time-sliced SVG whose mesh isn't complete yet.
+pub(crate) struct TessellationJob {
+    svg: Handle<Svg>,
+    /// Index of the first path that isn't tessellated.
+    next: usize,
+    /// Everything that is already tessellated, with the y-axis of the SVG.
+    buffers: VertexBuffers,
+}
+
+/// Bevy system which tessellates the paths of time-sliced [`Svg`]s, as many as fit into the
+/// [`SvgTimeSlicing::frame_budget`], and updates their meshes and [`SvgLoadProgress`] with what
+/// is done.
+pub(crate) fn tessellate_time_sliced(
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    mut jobs: Local<Vec<TessellationJob>>,
+    time_slicing: Res<SvgTimeSlicing>,
+    svgs: Res<Assets<Svg>>,
+    asset_server: Res<AssetServer>,
+    progress: Res<SvgLoadProgress>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+) {
+    for event in svg_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                jobs.retain(|job| job.svg != *handle);
+                if svgs.get(handle).map_or(false, |svg| svg.time_sliced) {
+                    jobs.push(TessellationJob { svg: handle.clone_weak(), next: 0, buffers: VertexBuffers::new() });
+                }
+            },
+            AssetEvent::Removed { handle } => jobs.retain(|job| job.svg != *handle),
+        }
+    }
+
+    let deadline = Instant::now() + time_slicing.frame_budget;
+    for job in jobs.iter_mut() {
+        if Instant::now() >= deadline {
+            break;
+        }
+        let svg = match svgs.get(&job.svg) {
+            Some(svg) => svg,
+            None => continue,
+        };
+        job.next = tessellation::tessellate_until(svg, &mut fill_tess, &mut stroke_tess, job.next, &mut job.buffers, deadline);
+        if let Some(mesh) = meshes.get_mut(&svg.mesh) {
+            *mesh = tessellation::finish_buffer(job.buffers.clone(), svg.size).convert();
+        }
+        if let Some(path) = asset_server.get_handle_path(&job.svg) {
+            if job.next < svg.paths.len() {
+                progress.set(path.path(), SvgProgress {
+                    stage: SvgLoadStage::Tessellating,
+                    tessellated_paths: job.next,
+                    total_paths: svg.paths.len(),
+                });
+            } else {
+                progress.remove(path.path());
+            }
+        }
+    }
+    jobs.retain(|job| svgs.get(&job.svg).map_or(false, |svg| job.next < svg.paths.len()));
+}
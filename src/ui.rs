@@ -0,0 +1,135 @@
+//! Draws SVGs as nodes of `bevy_ui`, for icons in buttons and menus that are placed by the UI
+//! layout.
+
+use std::collections::HashSet;
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    ecs::{
+        bundle::Bundle,
+        component::Component,
+        event::EventReader,
+        system::{Query, Res, ResMut},
+    },
+    math::Vec2,
+    render::{color::Color, mesh::Mesh, texture::Image, view::Visibility},
+    transform::components::{GlobalTransform, Transform},
+    ui::{CalculatedSize, FocusPolicy, Node, Size, Style, UiColor, UiImage},
+    window::Windows,
+};
+
+use crate::svg::Svg;
+
+
+/// Upper limit for the width and height of the rasterized SVG of a UI node in pixels.
+const MAX_UI_IMAGE_SIZE: f32 = 2048.0;
+
+/// A Bevy [`Bundle`] that draws an SVG as a node of `bevy_ui`.
+///
+/// The SVG is stretched over the size the UI layout gives the node, which is its own size unless
+/// [`Style`] says otherwise. It is rasterized again whenever that size or the SVG changes, so it
+/// stays sharp at every size. Clipping by parent nodes and tinting with [`UiColor`] work like for
+/// images.
+#[allow(missing_docs)]
+#[derive(Bundle, Clone, Debug)]
+pub struct SvgUiBundle {
+    pub svg: Handle<Svg>,
+    pub svg_node: SvgUiNode,
+    pub node: Node,
+    pub style: Style,
+    /// Set to the size of the SVG, which is used by [`Style`] sizes that are `Auto`.
+    pub calculated_size: CalculatedSize,
+    /// The image the SVG is rasterized into, set by the plugin.
+    pub image: UiImage,
+    pub color: UiColor,
+    pub focus_policy: FocusPolicy,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+}
+
+impl Default for SvgUiBundle {
+    /// Creates a default [`SvgUiBundle`].
+    fn default() -> Self {
+        Self {
+            svg: Default::default(),
+            svg_node: Default::default(),
+            node: Default::default(),
+            style: Default::default(),
+            calculated_size: Default::default(),
+            // Nodes whose image isn't loaded aren't drawn, unlike with the default white image.
+            image: UiImage(Handle::default()),
+            color: UiColor(Color::WHITE),
+            focus_policy: Default::default(),
+            transform: Default::default(),
+            global_transform: Default::default(),
+            visibility: Default::default(),
+        }
+    }
+}
+
+/// The image that the SVG of a [`SvgUiBundle`] was last rasterized into.
+#[derive(Component, Clone, Debug, Default)]
+pub struct SvgUiNode {
+    /// Size of the image in pixels.
+    size: (u32, u32),
+    image: Option<Handle<Image>>,
+}
+
+/// Bevy system which rasterizes the SVGs of UI nodes at the size of their node, when either of
+/// them changed.
+pub(crate) fn rasterize_ui_svgs(
+    svgs: Res<Assets<Svg>>,
+    meshes: Res<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    windows: Res<Windows>,
+    mut svg_events: EventReader<AssetEvent<Svg>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut query: Query<(&Handle<Svg>, &Node, &mut SvgUiNode, &mut UiImage, &mut CalculatedSize)>,
+) {
+    let changed_svgs: HashSet<_> = svg_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    let changed_meshes: HashSet<_> = mesh_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.id),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    // The layout is in logical pixels, the image should match the physical pixels of the screen.
+    let scale_factor = windows.get_primary().map_or(1.0, |window| window.scale_factor() as f32);
+
+    for (handle, node, mut svg_node, mut ui_image, mut calculated_size) in query.iter_mut() {
+        let svg = match svgs.get(handle) {
+            Some(svg) => svg,
+            None => continue,
+        };
+        let size = Size::new(svg.size.x, svg.size.y);
+        if calculated_size.size != size {
+            calculated_size.size = size;
+        }
+
+        let pixels = (node.size * scale_factor).round().min(Vec2::splat(MAX_UI_IMAGE_SIZE));
+        let pixels = (pixels.x as u32, pixels.y as u32);
+        let changed = changed_svgs.contains(&handle.id) || changed_meshes.contains(&svg.mesh.id);
+        if svg_node.size == pixels && svg_node.image.is_some() && !changed {
+            continue;
+        }
+        let image = match svg.rasterize(pixels.0, pixels.1, &meshes) {
+            Some(image) => image,
+            None => continue,
+        };
+        let image = match svg_node.image.take() {
+            Some(old) => images.set(old, image),
+            None => images.add(image),
+        };
+        if ui_image.0 != image {
+            ui_image.0 = image.clone();
+        }
+        svg_node.size = pixels;
+        svg_node.image = Some(image);
+    }
+}